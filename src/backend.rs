@@ -0,0 +1,128 @@
+#[cfg(target_os = "linux")]
+mod portal;
+#[cfg(target_os = "linux")]
+mod uinput;
+
+use crate::keyboard::Owner;
+use crate::window::Window;
+use clap::ValueEnum;
+use enigo::{Axis, Button, Coordinate, Direction, Enigo, InputResult, Key, Keyboard, Mouse, Settings};
+
+/// Something that can send keyboard and mouse events
+pub trait Backend: Keyboard + Mouse {}
+
+impl<T: Keyboard + Mouse> Backend for T {}
+
+/// The available backends for sending input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BackendKind {
+    /// The native input APIs of the platform (XTest on Linux)
+    Native,
+    /// A virtual input device created through /dev/uinput (Linux only)
+    Uinput,
+    /// The RemoteDesktop portal of xdg-desktop-portal, which asks for permission first (Linux only)
+    Portal
+}
+
+/// Which backend to create, and where it should send events
+#[derive(Debug, Clone)]
+pub struct BackendConfig {
+    pub kind: BackendKind,
+    /// Send events directly to this window instead of to whatever has focus
+    pub window: Option<Window>,
+    /// Who the keys pressed along with Shift belong to, which is the same for all backends of a script
+    pub owner: Owner
+}
+
+/// Create a new instance of the configured backend
+pub fn create(config: &BackendConfig) -> Result<Box<dyn Backend>, String> {
+    if let Some(window) = &config.window {
+        return create_window_target(config.kind, window, config.owner);
+    }
+
+    match config.kind {
+        BackendKind::Native => {
+            let enigo = Enigo::new(&Settings::default()).map_err(|error| format!("Failed to initialize Enigo: {error}"))?;
+            Ok(Box::new(Native(enigo, config.owner)))
+        }
+        #[cfg(target_os = "linux")]
+        BackendKind::Uinput => Ok(Box::new(uinput::Uinput::new(config.owner)?)),
+        #[cfg(not(target_os = "linux"))]
+        BackendKind::Uinput => Err("The uinput backend is only available on Linux".to_string()),
+        #[cfg(target_os = "linux")]
+        BackendKind::Portal => Ok(Box::new(portal::Portal::new(config.owner)?)),
+        #[cfg(not(target_os = "linux"))]
+        BackendKind::Portal => Err("The portal backend is only available on Linux".to_string())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn create_window_target(kind: BackendKind, window: &Window, owner: Owner) -> Result<Box<dyn Backend>, String> {
+    if kind != BackendKind::Native {
+        return Err("Targeting a window is only supported with the native backend".to_string());
+    }
+
+    Ok(Box::new(crate::window::Target::new(window, owner)?))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn create_window_target(_kind: BackendKind, _window: &Window, _owner: Owner) -> Result<Box<dyn Backend>, String> {
+    Err("Targeting a window is not supported on this platform".to_string())
+}
+
+/// The native input APIs of the platform, through Enigo
+pub struct Native(Enigo, Owner);
+
+impl Keyboard for Native {
+    fn fast_text(&mut self, text: &str) -> InputResult<Option<()>> {
+        self.0.fast_text(text)
+    }
+
+    fn text(&mut self, text: &str) -> InputResult<()> {
+        self.0.text(text)
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> InputResult<()> {
+        crate::keyboard::with_shift(self.1, key, crate::keyboard::needs_shift(key), direction, |key, direction| self.0.key(key, direction))
+    }
+
+    fn raw(&mut self, keycode: u16, direction: Direction) -> InputResult<()> {
+        self.0.raw(keycode, direction)
+    }
+}
+
+impl Mouse for Native {
+    fn button(&mut self, button: Button, direction: Direction) -> InputResult<()> {
+        self.0.button(button, direction)
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> InputResult<()> {
+        // Because of a bug in enigo, we can't just pass the method to the move_mouse() function
+        match coordinate {
+            Coordinate::Abs => self.0.move_mouse(x, y, coordinate),
+            Coordinate::Rel => {
+                // More details on why I'm doing this can be found on the relevant GitHub issue page
+                // https://github.com/enigo-rs/enigo/issues/91
+                // Basically, the relative mouse movement code uses incorrect pixel units.
+                // The workaround for this is to first get the current mouse position,
+                // calculate a new absolute position, and move the mouse there. This probably
+                // introduces some overhead, but it'll just have to be acceptable until
+                // the enigo maintainers push a fix.
+                let current_pos = self.0.location()?;
+                self.0.move_mouse(x + current_pos.0, y + current_pos.1, Coordinate::Abs)
+            }
+        }
+    }
+
+    fn scroll(&mut self, length: i32, axis: Axis) -> InputResult<()> {
+        self.0.scroll(length, axis)
+    }
+
+    fn main_display(&self) -> InputResult<(i32, i32)> {
+        self.0.main_display()
+    }
+
+    fn location(&self) -> InputResult<(i32, i32)> {
+        self.0.location()
+    }
+}
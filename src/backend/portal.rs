@@ -0,0 +1,276 @@
+use crate::dbus::{Connection, Value};
+use crate::keyboard::Owner;
+use crate::monitors;
+use crate::window::keysym;
+use enigo::{Axis, Button, Coordinate, Direction, InputError, InputResult, Key, Keyboard, Mouse};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const REMOTE_DESKTOP: &str = "org.freedesktop.portal.RemoteDesktop";
+const SCREEN_CAST: &str = "org.freedesktop.portal.ScreenCast";
+const REQUEST: &str = "org.freedesktop.portal.Request";
+
+// Values from the RemoteDesktop and ScreenCast portal specifications
+const DEVICE_KEYBOARD: u32 = 1;
+const DEVICE_POINTER: u32 = 2;
+const SOURCE_MONITOR: u32 = 1;
+const PERSIST_UNTIL_REVOKED: u32 = 2;
+
+// Button codes from linux/input-event-codes.h
+const BTN_LEFT: i32 = 0x110;
+const BTN_RIGHT: i32 = 0x111;
+const BTN_MIDDLE: i32 = 0x112;
+const BTN_SIDE: i32 = 0x113;
+const BTN_EXTRA: i32 = 0x114;
+
+/// The remote desktop session, shared between all threads so the user only has to approve it once per run
+static SESSION: OnceLock<Result<Arc<Session>, String>> = OnceLock::new();
+
+/// Used to make the handle tokens of requests unique
+static NEXT_TOKEN: AtomicU32 = AtomicU32::new(0);
+
+struct Session {
+    connection: Mutex<Connection>,
+    handle: String,
+    /// The monitors shared with the session, used for moving the mouse to absolute positions
+    streams: Vec<Stream>,
+    /// The size of the primary monitor
+    main_display: (i32, i32),
+    /// The last known cursor position, as the portal has no way of reading it
+    position: Mutex<Option<(i32, i32)>>
+}
+
+struct Stream {
+    node_id: u32,
+    /// The top left corner and size of the monitor
+    bounds: (i32, i32, i32, i32)
+}
+
+/// The RemoteDesktop portal of xdg-desktop-portal, which asks the user for permission before sending events
+///
+/// This works on Wayland desktops that don't allow other ways of sending input, like GNOME.
+pub struct Portal(Arc<Session>, Owner);
+
+impl Portal {
+    pub fn new(owner: Owner) -> Result<Self, String> {
+        SESSION.get_or_init(|| Session::start().map(Arc::new)).clone().map(|session| Portal(session, owner))
+    }
+}
+
+impl Session {
+    fn start() -> Result<Self, String> {
+        let mut connection = Connection::session()?;
+
+        // Subscribe to the responses of portal requests before making any, so none of them are missed
+        let rule = format!("type='signal',interface='{REQUEST}',member='Response'");
+        connection.call("org.freedesktop.DBus", "/org/freedesktop/DBus", "org.freedesktop.DBus", "AddMatch", &[Value::String(rule)])?;
+
+        let session_token = format!("tatata{}", std::process::id());
+        let results = request(&mut connection, REMOTE_DESKTOP, "CreateSession", vec![], vec![
+            ("session_handle_token", Value::String(session_token))
+        ])?;
+        let handle = results.get("session_handle").and_then(Value::as_str).ok_or("The portal didn't return a session")?.to_string();
+        let session = Value::ObjectPath(handle.clone());
+
+        // Reuse the permission from the last run if there is one, so the user doesn't have to approve it every time
+        let mut options = vec![
+            ("types", Value::Uint32(DEVICE_KEYBOARD | DEVICE_POINTER)),
+            ("persist_mode", Value::Uint32(PERSIST_UNTIL_REVOKED))
+        ];
+        if let Some(token) = restore_token_path().and_then(|path| std::fs::read_to_string(path).ok()) {
+            options.push(("restore_token", Value::String(token.trim().to_string())));
+        }
+        request(&mut connection, REMOTE_DESKTOP, "SelectDevices", vec![session.clone()], options)?;
+
+        // Sharing monitors is only needed for absolute mouse movements, so the session is still usable without it
+        let _ = request(&mut connection, SCREEN_CAST, "SelectSources", vec![session.clone()], vec![
+            ("types", Value::Uint32(SOURCE_MONITOR)),
+            ("multiple", Value::Bool(true))
+        ]);
+
+        let results = request(&mut connection, REMOTE_DESKTOP, "Start", vec![session, Value::String(String::new())], vec![])?;
+        if let Some(token) = results.get("restore_token").and_then(Value::as_str) {
+            save_restore_token(token);
+        }
+
+        let streams = results.get("streams").and_then(Value::as_slice).unwrap_or_default().iter().filter_map(|stream| {
+            let [node_id, properties] = stream.as_slice()? else {
+                return None;
+            };
+            let pair = |key: &str| {
+                let values = properties.get(key)?.as_slice()?;
+                Some((values.first()?.as_i32()?, values.get(1)?.as_i32()?))
+            };
+            let (x, y) = pair("position").unwrap_or((0, 0));
+            let (width, height) = pair("size").unwrap_or((i32::MAX, i32::MAX));
+            Some(Stream { node_id: node_id.as_u32()?, bounds: (x, y, width, height) })
+        }).collect();
+
+        let main_display = monitors::get_monitors().first().map_or((1920, 1080), |monitor| (monitor.width as i32, monitor.height as i32));
+
+        Ok(Session {
+            connection: Mutex::new(connection),
+            handle,
+            streams,
+            main_display,
+            position: Mutex::new(None)
+        })
+    }
+
+    /// Call a method on the RemoteDesktop portal for this session
+    fn notify(&self, method: &str, arguments: Vec<Value>) -> InputResult<()> {
+        let mut body = vec![Value::ObjectPath(self.handle.clone()), Value::dict(vec![])];
+        body.extend(arguments);
+
+        let mut connection = self.connection.lock().unwrap_or_else(|error| error.into_inner());
+        connection.call(PORTAL_DESTINATION, PORTAL_PATH, REMOTE_DESKTOP, method, &body)
+            .map(|_| ())
+            .map_err(|_| InputError::Simulate("failed to send event through the portal"))
+    }
+}
+
+/// Make a portal request and wait for the user to respond to it, returning the results
+fn request(connection: &mut Connection, interface: &str, method: &str, mut arguments: Vec<Value>, mut options: Vec<(&str, Value)>) -> Result<Value, String> {
+    let token = format!("tatata{}_{}", std::process::id(), NEXT_TOKEN.fetch_add(1, Ordering::Relaxed));
+    options.push(("handle_token", Value::String(token)));
+    arguments.push(Value::dict(options));
+
+    let reply = connection.call(PORTAL_DESTINATION, PORTAL_PATH, interface, method, &arguments)?;
+    let handle = reply.first().and_then(Value::as_str).ok_or(format!("The portal didn't return a request for {method}"))?.to_string();
+
+    let response = connection.wait_for_signal(&handle, REQUEST, "Response")?;
+    match response.first().and_then(Value::as_u32) {
+        Some(0) => Ok(response.get(1).cloned().unwrap_or(Value::dict(vec![]))),
+        Some(1) => Err("The request to control the desktop was cancelled".to_string()),
+        _ => Err(format!("The portal failed to handle {method}"))
+    }
+}
+
+/// Where the token for restoring the session in the next run is stored
+fn restore_token_path() -> Option<PathBuf> {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state_dir.join("tatata").join("portal-restore-token"))
+}
+
+fn save_restore_token(token: &str) {
+    let Some(path) = restore_token_path() else {
+        return;
+    };
+
+    let result = path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|_| std::fs::write(&path, token));
+    if let Err(error) = result {
+        log::warn!("Failed to save portal permission to {}, you will be asked again next time: {error}", path.display());
+    }
+}
+
+fn direction_states(direction: Direction) -> &'static [u32] {
+    match direction {
+        Direction::Press => &[1],
+        Direction::Release => &[0],
+        Direction::Click => &[1, 0]
+    }
+}
+
+impl Keyboard for Portal {
+    fn fast_text(&mut self, _text: &str) -> InputResult<Option<()>> {
+        // There is no fast way of entering text, so it will be entered one key at a time
+        Ok(None)
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> InputResult<()> {
+        crate::keyboard::with_shift(self.1, key, crate::keyboard::needs_shift(key), direction, |key, direction| {
+            let keysym = keysym(key).ok_or(InputError::InvalidInput("key is not supported by the portal backend"))?;
+            for &state in direction_states(direction) {
+                self.0.notify("NotifyKeyboardKeysym", vec![Value::Int32(keysym as i32), Value::Uint32(state)])?;
+            }
+            Ok(())
+        })
+    }
+
+    fn raw(&mut self, keycode: u16, direction: Direction) -> InputResult<()> {
+        for &state in direction_states(direction) {
+            self.0.notify("NotifyKeyboardKeycode", vec![Value::Int32(keycode as i32), Value::Uint32(state)])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Mouse for Portal {
+    fn button(&mut self, button: Button, direction: Direction) -> InputResult<()> {
+        let code = match button {
+            Button::Left => BTN_LEFT,
+            Button::Right => BTN_RIGHT,
+            Button::Middle => BTN_MIDDLE,
+            Button::Back => BTN_SIDE,
+            Button::Forward => BTN_EXTRA,
+            // Scroll buttons only do something when pressed
+            Button::ScrollUp if direction != Direction::Release => return self.scroll(-1, Axis::Vertical),
+            Button::ScrollDown if direction != Direction::Release => return self.scroll(1, Axis::Vertical),
+            Button::ScrollLeft if direction != Direction::Release => return self.scroll(-1, Axis::Horizontal),
+            Button::ScrollRight if direction != Direction::Release => return self.scroll(1, Axis::Horizontal),
+            Button::ScrollUp | Button::ScrollDown | Button::ScrollLeft | Button::ScrollRight => return Ok(())
+        };
+
+        for &state in direction_states(direction) {
+            self.0.notify("NotifyPointerButton", vec![Value::Int32(code), Value::Uint32(state)])?;
+        }
+
+        Ok(())
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> InputResult<()> {
+        let mut position = self.0.position.lock().unwrap_or_else(|error| error.into_inner());
+
+        match coordinate {
+            Coordinate::Abs => {
+                // Absolute positions are relative to the monitor they're on
+                if self.0.streams.is_empty() {
+                    return Err(InputError::Simulate("no monitors were shared with the portal, so the mouse can only be moved relatively"));
+                }
+                let stream = self.0.streams.iter()
+                    .find(|stream| {
+                        let (stream_x, stream_y, width, height) = stream.bounds;
+                        (stream_x..stream_x.saturating_add(width)).contains(&x) && (stream_y..stream_y.saturating_add(height)).contains(&y)
+                    })
+                    .ok_or(InputError::InvalidInput("position is outside of the monitors shared with the portal"))?;
+
+                let (stream_x, stream_y, _, _) = stream.bounds;
+                self.0.notify("NotifyPointerMotionAbsolute", vec![
+                    Value::Uint32(stream.node_id),
+                    Value::Double((x - stream_x) as f64),
+                    Value::Double((y - stream_y) as f64)
+                ])?;
+                *position = Some((x, y));
+            }
+            Coordinate::Rel => {
+                self.0.notify("NotifyPointerMotion", vec![Value::Double(x as f64), Value::Double(y as f64)])?;
+                *position = position.map(|(current_x, current_y)| (current_x + x, current_y + y));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn scroll(&mut self, length: i32, axis: Axis) -> InputResult<()> {
+        let axis = match axis {
+            Axis::Vertical => 0,
+            Axis::Horizontal => 1
+        };
+        self.0.notify("NotifyPointerAxisDiscrete", vec![Value::Uint32(axis), Value::Int32(length)])
+    }
+
+    fn main_display(&self) -> InputResult<(i32, i32)> {
+        Ok(self.0.main_display)
+    }
+
+    fn location(&self) -> InputResult<(i32, i32)> {
+        let position = self.0.position.lock().unwrap_or_else(|error| error.into_inner());
+        position.ok_or(InputError::Simulate("the portal backend can't read the cursor position before the mouse has been moved to an absolute position"))
+    }
+}
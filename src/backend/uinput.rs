@@ -0,0 +1,432 @@
+use crate::keyboard::{self, Owner};
+use crate::monitors;
+use enigo::{Axis, Button, Coordinate, Direction, InputError, InputResult, Key, Keyboard, Mouse};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+// Event types and codes from linux/input-event-codes.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0x00;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const KEY_LEFTSHIFT: u16 = 42;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const BTN_SIDE: u16 = 0x113;
+const BTN_EXTRA: u16 = 0x114;
+const BUS_VIRTUAL: u16 = 0x06;
+
+// ioctl requests from linux/uinput.h
+const UI_DEV_CREATE: u64 = 0x5501;
+const UI_DEV_SETUP: u64 = 0x405c5503;
+const UI_ABS_SETUP: u64 = 0x401c5504;
+const UI_SET_EVBIT: u64 = 0x40045564;
+const UI_SET_KEYBIT: u64 = 0x40045565;
+const UI_SET_RELBIT: u64 = 0x40045566;
+const UI_SET_ABSBIT: u64 = 0x40045567;
+
+/// The time to wait after creating the devices, so the display server has time to pick them up
+const DEVICE_SETTLE_TIME: Duration = Duration::from_millis(250);
+
+/// The virtual devices, shared between all threads so they only get created once per run
+static DEVICES: OnceLock<Result<Arc<Devices>, String>> = OnceLock::new();
+
+struct Devices {
+    /// Keyboard and relative mouse device
+    keyboard: Mutex<File>,
+    /// Absolute pointer device, used for moving the mouse to a specific position
+    pointer: Mutex<File>,
+    /// The top left corner and size of the area covered by the absolute pointer
+    bounds: (i32, i32, i32, i32),
+    /// The size of the primary monitor
+    main_display: (i32, i32),
+    /// The last known cursor position, as uinput has no way of reading it
+    position: Mutex<Option<(i32, i32)>>,
+    /// The codes of the keys that are held
+    held_keys: Mutex<Vec<u16>>
+}
+
+/// A virtual input device created through /dev/uinput, which works in applications and sessions that ignore XTest
+pub struct Uinput(Arc<Devices>, Owner);
+
+impl Uinput {
+    pub fn new(owner: Owner) -> Result<Self, String> {
+        DEVICES.get_or_init(|| Devices::create().map(Arc::new)).clone().map(|devices| Uinput(devices, owner))
+    }
+}
+
+impl Devices {
+    fn create() -> Result<Self, String> {
+        // The absolute pointer covers the bounding box of all monitors
+        let monitors = monitors::get_monitors();
+        let min_x = monitors.iter().map(|monitor| monitor.x).min().unwrap_or(0);
+        let min_y = monitors.iter().map(|monitor| monitor.y).min().unwrap_or(0);
+        let max_x = monitors.iter().map(|monitor| monitor.x + monitor.width as i32).max().unwrap_or(1920);
+        let max_y = monitors.iter().map(|monitor| monitor.y + monitor.height as i32).max().unwrap_or(1080);
+        let main_display = monitors.first().map_or((max_x - min_x, max_y - min_y), |monitor| (monitor.width as i32, monitor.height as i32));
+
+        let keyboard = create_device("tatata virtual keyboard", |fd| {
+            ioctl(fd, UI_SET_EVBIT, EV_KEY as u64)?;
+            for code in 1..=248 {
+                ioctl(fd, UI_SET_KEYBIT, code)?;
+            }
+            for button in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE, BTN_SIDE, BTN_EXTRA] {
+                ioctl(fd, UI_SET_KEYBIT, button as u64)?;
+            }
+
+            ioctl(fd, UI_SET_EVBIT, EV_REL as u64)?;
+            for axis in [REL_X, REL_Y, REL_HWHEEL, REL_WHEEL] {
+                ioctl(fd, UI_SET_RELBIT, axis as u64)?;
+            }
+
+            Ok(())
+        }).map_err(|error| diagnose(&error))?;
+
+        let pointer = create_device("tatata virtual pointer", |fd| {
+            ioctl(fd, UI_SET_EVBIT, EV_KEY as u64)?;
+            ioctl(fd, UI_SET_KEYBIT, BTN_LEFT as u64)?;
+
+            ioctl(fd, UI_SET_EVBIT, EV_ABS as u64)?;
+            for (axis, maximum) in [(ABS_X, max_x - min_x - 1), (ABS_Y, max_y - min_y - 1)] {
+                ioctl(fd, UI_SET_ABSBIT, axis as u64)?;
+
+                // SAFETY: uinput_abs_setup is a plain C struct, for which all zeroes is a valid value
+                let mut abs_setup: libc::uinput_abs_setup = unsafe { std::mem::zeroed() };
+                abs_setup.code = axis;
+                abs_setup.absinfo.maximum = maximum;
+                // SAFETY: UI_ABS_SETUP takes a pointer to a uinput_abs_setup struct
+                if unsafe { libc::ioctl(fd, UI_ABS_SETUP as _, &abs_setup) } < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+
+            Ok(())
+        }).map_err(|error| diagnose(&error))?;
+
+        // Give the display server some time to pick up the new devices before sending events
+        std::thread::sleep(DEVICE_SETTLE_TIME);
+
+        Ok(Devices {
+            keyboard: Mutex::new(keyboard),
+            pointer: Mutex::new(pointer),
+            bounds: (min_x, min_y, max_x - min_x, max_y - min_y),
+            main_display,
+            position: Mutex::new(None),
+            held_keys: Mutex::new(Vec::new())
+        })
+    }
+}
+
+/// Open /dev/uinput and create a virtual device, using the given function to enable its capabilities
+fn create_device(name: &str, enable_capabilities: impl FnOnce(i32) -> io::Result<()>) -> io::Result<File> {
+    let file = OpenOptions::new().write(true).custom_flags(libc::O_NONBLOCK).open("/dev/uinput")?;
+    let fd = file.as_raw_fd();
+
+    enable_capabilities(fd)?;
+
+    // SAFETY: uinput_setup is a plain C struct, for which all zeroes is a valid value
+    let mut setup: libc::uinput_setup = unsafe { std::mem::zeroed() };
+    setup.id.bustype = BUS_VIRTUAL;
+    setup.id.vendor = 0x7a7a;
+    setup.id.product = 0x7a7a;
+    for (index, byte) in name.bytes().take(libc::UINPUT_MAX_NAME_SIZE - 1).enumerate() {
+        setup.name[index] = byte as libc::c_char;
+    }
+
+    // SAFETY: UI_DEV_SETUP takes a pointer to a uinput_setup struct, and UI_DEV_CREATE takes no argument
+    unsafe {
+        if libc::ioctl(fd, UI_DEV_SETUP as _, &setup) < 0 || libc::ioctl(fd, UI_DEV_CREATE as _) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(file)
+}
+
+/// Run an ioctl that takes an integer argument
+fn ioctl(fd: i32, request: u64, value: u64) -> io::Result<()> {
+    // SAFETY: All the requests passed to this function take an integer argument
+    if unsafe { libc::ioctl(fd, request as _, value as libc::c_ulong) } < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Explain how to fix common setup problems with /dev/uinput
+fn diagnose(error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::NotFound => format!(
+            "Failed to open /dev/uinput: {error}\n\
+            The uinput kernel module is probably not loaded. Load it by running `sudo modprobe uinput`, and add `uinput` \
+            to /etc/modules-load.d/uinput.conf to load it on boot."
+        ),
+        io::ErrorKind::PermissionDenied => format!(
+            "Failed to open /dev/uinput: {error}\n\
+            Your user is not allowed to create virtual input devices. To allow it, create \
+            /etc/udev/rules.d/99-uinput.rules containing:\n\
+            \n    KERNEL==\"uinput\", GROUP=\"input\", MODE=\"0660\", OPTIONS+=\"static_node=uinput\"\n\n\
+            Then add yourself to the input group by running `sudo usermod -aG input $USER`, reload the rules by running \
+            `sudo udevadm control --reload-rules && sudo udevadm trigger`, and log out and back in."
+        ),
+        _ => format!("Failed to create uinput device: {error}")
+    }
+}
+
+/// Write a batch of events to a device, followed by a synchronization event
+fn emit(device: &Mutex<File>, events: &[(u16, u16, i32)]) -> InputResult<()> {
+    let mut device = device.lock().unwrap_or_else(|error| error.into_inner());
+
+    for &(type_, code, value) in events.iter().chain(&[(EV_SYN, SYN_REPORT, 0)]) {
+        let event = libc::input_event {
+            time: libc::timeval { tv_sec: 0, tv_usec: 0 },
+            type_,
+            code,
+            value
+        };
+
+        // SAFETY: input_event is a plain C struct, and the kernel expects its raw bytes
+        let bytes = unsafe {
+            std::slice::from_raw_parts(&event as *const libc::input_event as *const u8, size_of::<libc::input_event>())
+        };
+        device.write_all(bytes).map_err(|_| InputError::Simulate("failed to write to uinput device"))?;
+    }
+
+    Ok(())
+}
+
+fn direction_values(direction: Direction) -> &'static [i32] {
+    match direction {
+        Direction::Press => &[1],
+        Direction::Release => &[0],
+        Direction::Click => &[1, 0]
+    }
+}
+
+impl Keyboard for Uinput {
+    fn fast_text(&mut self, _text: &str) -> InputResult<Option<()>> {
+        // There is no fast way of entering text, so it will be entered one key at a time
+        Ok(None)
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> InputResult<()> {
+        let (_, shift) = key_code(key).ok_or(InputError::InvalidInput("key is not supported by the uinput backend"))?;
+
+        // Shift is pressed once for all held symbols that need it, like a person holding it down for several keys
+        crate::keyboard::with_shift(self.1, key, shift, direction, |key, direction| {
+            let code = key_code(key).map_or(KEY_LEFTSHIFT, |(code, _)| code);
+
+            // The kernel ignores presses of keys that are already held, so those are sent as repeats like a real keyboard
+            // does
+            let mut held_keys = self.0.held_keys.lock().unwrap_or_else(|error| error.into_inner());
+            if direction == Direction::Press && held_keys.contains(&code) {
+                return emit(&self.0.keyboard, &[(EV_KEY, code, 2)]);
+            }
+            match direction {
+                Direction::Press => held_keys.push(code),
+                Direction::Release | Direction::Click => held_keys.retain(|&held| held != code)
+            }
+            drop(held_keys);
+
+            for &value in direction_values(direction) {
+                emit(&self.0.keyboard, &[(EV_KEY, code, value)])?;
+            }
+            Ok(())
+        })
+    }
+
+    fn raw(&mut self, keycode: u16, direction: Direction) -> InputResult<()> {
+        for &value in direction_values(direction) {
+            emit(&self.0.keyboard, &[(EV_KEY, keycode, value)])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Mouse for Uinput {
+    fn button(&mut self, button: Button, direction: Direction) -> InputResult<()> {
+        let code = match button {
+            Button::Left => BTN_LEFT,
+            Button::Right => BTN_RIGHT,
+            Button::Middle => BTN_MIDDLE,
+            Button::Back => BTN_SIDE,
+            Button::Forward => BTN_EXTRA,
+            // Scroll buttons only do something when pressed
+            Button::ScrollUp if direction != Direction::Release => return self.scroll(-1, Axis::Vertical),
+            Button::ScrollDown if direction != Direction::Release => return self.scroll(1, Axis::Vertical),
+            Button::ScrollLeft if direction != Direction::Release => return self.scroll(-1, Axis::Horizontal),
+            Button::ScrollRight if direction != Direction::Release => return self.scroll(1, Axis::Horizontal),
+            Button::ScrollUp | Button::ScrollDown | Button::ScrollLeft | Button::ScrollRight => return Ok(())
+        };
+
+        for &value in direction_values(direction) {
+            emit(&self.0.keyboard, &[(EV_KEY, code, value)])?;
+        }
+
+        Ok(())
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> InputResult<()> {
+        let mut position = self.0.position.lock().unwrap_or_else(|error| error.into_inner());
+
+        match coordinate {
+            Coordinate::Abs => {
+                let (min_x, min_y, width, height) = self.0.bounds;
+                let abs_x = (x - min_x).clamp(0, width - 1);
+                let abs_y = (y - min_y).clamp(0, height - 1);
+                emit(&self.0.pointer, &[(EV_ABS, ABS_X, abs_x), (EV_ABS, ABS_Y, abs_y)])?;
+                *position = Some((abs_x + min_x, abs_y + min_y));
+            }
+            Coordinate::Rel => {
+                emit(&self.0.keyboard, &[(EV_REL, REL_X, x), (EV_REL, REL_Y, y)])?;
+                *position = position.map(|(current_x, current_y)| (current_x + x, current_y + y));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn scroll(&mut self, length: i32, axis: Axis) -> InputResult<()> {
+        // Positive lengths scroll down/right, while a positive wheel value scrolls up
+        match axis {
+            Axis::Vertical => emit(&self.0.keyboard, &[(EV_REL, REL_WHEEL, -length)]),
+            Axis::Horizontal => emit(&self.0.keyboard, &[(EV_REL, REL_HWHEEL, length)])
+        }
+    }
+
+    fn main_display(&self) -> InputResult<(i32, i32)> {
+        Ok(self.0.main_display)
+    }
+
+    fn location(&self) -> InputResult<(i32, i32)> {
+        let position = self.0.position.lock().unwrap_or_else(|error| error.into_inner());
+        position.ok_or(InputError::Simulate("the uinput backend can't read the cursor position before the mouse has been moved to an absolute position"))
+    }
+}
+
+/// Get the Linux key code for a key, and whether it needs shift to be held on a US keyboard layout
+fn key_code(key: Key) -> Option<(u16, bool)> {
+    let code = match key {
+        Key::Alt => 56,
+        Key::Backspace => 14,
+        Key::CapsLock => 58,
+        Key::Control => 29,
+        Key::Delete => 111,
+        Key::DownArrow => 108,
+        Key::End => 107,
+        Key::Escape => 1,
+        Key::F1 => 59,
+        Key::F2 => 60,
+        Key::F3 => 61,
+        Key::F4 => 62,
+        Key::F5 => 63,
+        Key::F6 => 64,
+        Key::F7 => 65,
+        Key::F8 => 66,
+        Key::F9 => 67,
+        Key::F10 => 68,
+        Key::F11 => 87,
+        Key::F12 => 88,
+        Key::F13 => 183,
+        Key::F14 => 184,
+        Key::F15 => 185,
+        Key::F16 => 186,
+        Key::F17 => 187,
+        Key::F18 => 188,
+        Key::F19 => 189,
+        Key::F20 => 190,
+        Key::Home => 102,
+        Key::Insert => 110,
+        Key::LeftArrow => 105,
+        Key::LMenu => 127,
+        Key::MediaNextTrack => 163,
+        Key::MediaPlayPause => 164,
+        Key::MediaPrevTrack => 165,
+        Key::MediaStop => 166,
+        Key::Meta => 125,
+        Key::Numlock => 69,
+        Key::PageDown => 109,
+        Key::PageUp => 104,
+        Key::Pause => 119,
+        Key::Print => 99,
+        Key::Return => 28,
+        Key::RightArrow => 106,
+        Key::ScrollLock => 70,
+        Key::Shift => 42,
+        Key::Space => 57,
+        Key::Tab => 15,
+        Key::UpArrow => 103,
+        Key::VolumeDown => 114,
+        Key::VolumeMute => 113,
+        Key::VolumeUp => 115,
+        keyboard::BRIGHTNESS_DOWN => 224,
+        keyboard::BRIGHTNESS_UP => 225,
+        Key::Unicode(character) => return unicode_key_code(character),
+        _ => return None
+    };
+
+    Some((code, false))
+}
+
+/// Get the Linux key code for a character on a US keyboard layout, and whether it needs shift to be held
+fn unicode_key_code(character: char) -> Option<(u16, bool)> {
+    const LETTERS: [u16; 26] = [30, 48, 46, 32, 18, 33, 34, 35, 23, 36, 37, 38, 50, 49, 24, 25, 16, 19, 31, 20, 22, 47, 17, 45, 21, 44];
+    const DIGITS: [u16; 10] = [11, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+
+    let key = match character {
+        'a'..='z' => (LETTERS[character as usize - 'a' as usize], false),
+        'A'..='Z' => (LETTERS[character as usize - 'A' as usize], true),
+        '0'..='9' => (DIGITS[character as usize - '0' as usize], false),
+        ')' => (11, true),
+        '!' => (2, true),
+        '@' => (3, true),
+        '#' => (4, true),
+        '$' => (5, true),
+        '%' => (6, true),
+        '^' => (7, true),
+        '&' => (8, true),
+        '*' => (9, true),
+        '(' => (10, true),
+        '-' => (12, false),
+        '_' => (12, true),
+        '=' => (13, false),
+        '+' => (13, true),
+        '[' => (26, false),
+        '{' => (26, true),
+        ']' => (27, false),
+        '}' => (27, true),
+        ';' => (39, false),
+        ':' => (39, true),
+        '\'' => (40, false),
+        '"' => (40, true),
+        '`' => (41, false),
+        '~' => (41, true),
+        '\\' => (43, false),
+        '|' => (43, true),
+        ',' => (51, false),
+        '<' => (51, true),
+        '.' => (52, false),
+        '>' => (52, true),
+        '/' => (53, false),
+        '?' => (53, true),
+        ' ' => (57, false),
+        '\t' => (15, false),
+        '\n' => (28, false),
+        _ => return None
+    };
+
+    Some(key)
+}
@@ -0,0 +1,87 @@
+use enigo::{Button, Key};
+use log::warn;
+
+use crate::control;
+
+/// A key or mouse button that can be held down
+#[derive(Clone, Copy, PartialEq)]
+pub enum Output {
+    Key(Key),
+    Mouse(Button)
+}
+
+impl Output {
+    fn name(self) -> String {
+        match self {
+            Output::Key(key) => format!("key {key:?}"),
+            Output::Mouse(button) => format!("mouse {button:?}")
+        }
+    }
+
+    /// Whether the output stays down until it's released, which scroll buttons don't, as they only scroll when pressed
+    fn can_be_held(self) -> bool {
+        !matches!(self, Output::Mouse(Button::ScrollUp | Button::ScrollDown | Button::ScrollLeft | Button::ScrollRight))
+    }
+}
+
+/// Keeps track of what the script holds down, to warn about presses and releases that don't match up
+///
+/// Keys that are pressed and never released are the most common mistake in hand-written scripts, and would stay held
+/// after the run until they're pressed again, so they're released at the end.
+#[derive(Default)]
+pub struct Balance {
+    /// The held outputs, along with the line that pressed them
+    held: Vec<(Output, i32)>
+}
+
+impl Balance {
+    pub fn press(&mut self, output: Output, line: i32) {
+        if !output.can_be_held() {
+            return;
+        }
+        match self.held.iter().find(|(held, _)| *held == output) {
+            Some((_, pressed_on)) => warn!("Line {line} presses {}, which is already held since line {pressed_on}", output.name()),
+            None => {
+                self.held.push((output, line));
+                control::hold(output.name());
+            }
+        }
+    }
+
+    pub fn release(&mut self, output: Output, line: i32) {
+        if !output.can_be_held() {
+            return;
+        }
+        match self.held.iter().position(|(held, _)| *held == output) {
+            Some(index) => {
+                self.held.remove(index);
+                control::unhold(&output.name());
+            }
+            None => warn!("Line {line} releases {}, which isn't held", output.name())
+        }
+    }
+
+    /// Release every held key, or every held mouse button, like `release` does
+    ///
+    /// Nothing is reported when nothing is held, as releasing everything is a common way to make sure of that.
+    pub fn release_all(&mut self, keys: bool, mouse: bool) {
+        self.held.retain(|(output, _)| {
+            let released = match output {
+                Output::Key(_) => keys,
+                Output::Mouse(_) => mouse
+            };
+            if released {
+                control::unhold(&output.name());
+            }
+            !released
+        });
+    }
+
+    /// Report everything that is still held at the end of the run, which the run then releases
+    pub fn finish(&mut self) {
+        for (output, line) in self.held.drain(..) {
+            warn!("Line {line} presses {}, which is never released, so it's released at the end", output.name());
+            control::unhold(&output.name());
+        }
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+use x11 as platform;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "windows")]
+use win32 as platform;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub struct Blocker;
+
+    impl Blocker {
+        pub fn new() -> Result<Self, String> {
+            Err("Blocking input is not supported on this platform".to_string())
+        }
+    }
+}
+
+use log::warn;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// The blocker while input is blocked, which unblocks it when dropped
+static BLOCKER: Mutex<Option<platform::Blocker>> = Mutex::new(None);
+
+/// Whether input is blocked for the run, even while it's let through for a moment
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Keep the physical keyboard and mouse from reaching applications while the script runs, except for Escape, which aborts
+///
+/// Events sent by TATATA itself still go through.
+pub fn start() -> Result<(), String> {
+    *BLOCKER.lock().unwrap_or_else(|error| error.into_inner()) = Some(platform::Blocker::new()?);
+    ENABLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Let input through again once the run has finished
+pub fn stop() {
+    ENABLED.store(false, Ordering::Relaxed);
+    suspend();
+}
+
+/// Let input through for a while, like when the run is paused or waits for a key to be pressed
+pub fn suspend() {
+    BLOCKER.lock().unwrap_or_else(|error| error.into_inner()).take();
+}
+
+/// Block input again after it was let through, if it was blocked for the run
+pub fn resume() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut blocker = BLOCKER.lock().unwrap_or_else(|error| error.into_inner());
+    if blocker.is_none() {
+        match platform::Blocker::new() {
+            Ok(new) => *blocker = Some(new),
+            Err(error) => warn!("Failed to block input again, so the rest of the run can be interrupted: {error}")
+        }
+    }
+}
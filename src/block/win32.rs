@@ -0,0 +1,94 @@
+use crate::interrupt;
+use log::warn;
+use std::ptr::null_mut;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use windows_sys::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::System::Threading::GetCurrentThreadId;
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::VK_ESCAPE;
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, GetMessageW, PostThreadMessageW, SetWindowsHookExW, UnhookWindowsHookEx, HC_ACTION, KBDLLHOOKSTRUCT,
+    LLKHF_INJECTED, LLMHF_INJECTED, MSG, MSLLHOOKSTRUCT, WH_KEYBOARD_LL, WH_MOUSE_LL, WM_KEYDOWN, WM_QUIT
+};
+
+/// Hooks that drop every keyboard and mouse event that wasn't sent by an application, which are removed when dropped
+pub struct Blocker {
+    thread_id: u32,
+    handle: Option<JoinHandle<()>>
+}
+
+impl Blocker {
+    /// Install the hooks on a separate thread, as low-level hooks are called through the message loop of their thread
+    pub fn new() -> Result<Self, String> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            // SAFETY: The hook functions are valid for the whole lifetime of the process, and the module handle is this one
+            let hooks = unsafe {
+                let module = GetModuleHandleW(null_mut());
+                [SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook), module, 0), SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook), module, 0)]
+            };
+            // SAFETY: GetCurrentThreadId has no safety requirements
+            let _ = sender.send(hooks.iter().all(|hook| !hook.is_null()).then(|| unsafe { GetCurrentThreadId() }));
+
+            if hooks.iter().all(|hook| !hook.is_null()) {
+                // SAFETY: MSG is a plain C struct, for which all zeroes is a valid value
+                let mut message: MSG = unsafe { std::mem::zeroed() };
+                // SAFETY: The message is valid for the duration of the call
+                while unsafe { GetMessageW(&mut message, null_mut(), 0, 0) } > 0 {}
+            }
+            for hook in hooks.into_iter().filter(|hook| !hook.is_null()) {
+                // SAFETY: The hook was installed by this thread
+                unsafe { UnhookWindowsHookEx(hook) };
+            }
+        });
+
+        match receiver.recv() {
+            Ok(Some(thread_id)) => Ok(Blocker { thread_id, handle: Some(handle) }),
+            _ => {
+                let _ = handle.join();
+                Err("Failed to hook the keyboard and mouse".to_string())
+            }
+        }
+    }
+}
+
+impl Drop for Blocker {
+    fn drop(&mut self) {
+        // SAFETY: Posting a message to a thread has no safety requirements
+        if unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, 0, 0) } == 0 {
+            warn!("Failed to stop blocking input");
+            return;
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        // SAFETY: Keyboard hooks are called with a KBDLLHOOKSTRUCT
+        let event = unsafe { &*(lparam as *const KBDLLHOOKSTRUCT) };
+        if event.flags & LLKHF_INJECTED == 0 {
+            if event.vkCode == VK_ESCAPE as u32 && wparam == WM_KEYDOWN as usize && !interrupt::request() {
+                warn!("Escape was pressed, aborting");
+            }
+            return 1;
+        }
+    }
+    // SAFETY: The arguments are the ones the hook was called with
+    unsafe { CallNextHookEx(null_mut(), code, wparam, lparam) }
+}
+
+unsafe extern "system" fn mouse_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        // SAFETY: Mouse hooks are called with an MSLLHOOKSTRUCT
+        let event = unsafe { &*(lparam as *const MSLLHOOKSTRUCT) };
+        if event.flags & LLMHF_INJECTED == 0 {
+            return 1;
+        }
+    }
+    // SAFETY: The arguments are the ones the hook was called with
+    unsafe { CallNextHookEx(null_mut(), code, wparam, lparam) }
+}
@@ -0,0 +1,86 @@
+use crate::interrupt;
+use enigo::Key;
+use log::warn;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::xinput::{ConnectionExt, Device, DeviceType, GrabOwner, XIEventMask};
+use x11rb::protocol::xproto::{GrabMode, GrabStatus};
+use x11rb::protocol::Event;
+use x11rb::CURRENT_TIME;
+
+/// How often the grabbed keyboards are checked for Escape
+const CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Grabs of the physical keyboards and mice, which are released when the connection is closed
+pub struct Blocker {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>
+}
+
+impl Blocker {
+    /// Grab every keyboard and mouse except the ones TATATA sends events through
+    ///
+    /// A grabbed device is detached from the cursor and keyboard focus it normally controls, and its events only reach
+    /// TATATA, while events sent through XTest or the uinput backend go through their own devices.
+    pub fn new() -> Result<Self, String> {
+        let escape = crate::keyboard::code(Key::Escape)?;
+        let (connection, screen) = x11rb::connect(None).map_err(|error| format!("Failed to connect to X server: {error}"))?;
+        let root = connection.setup().roots[screen].root;
+        connection.xinput_xi_query_version(2, 0)
+            .map_err(ReplyError::from)
+            .and_then(|cookie| cookie.reply())
+            .map_err(|error| format!("The X server doesn't support XInput 2: {error}"))?;
+        let devices = connection.xinput_xi_query_device(Device::ALL)
+            .map_err(ReplyError::from)
+            .and_then(|cookie| cookie.reply())
+            .map_err(|error| format!("Failed to list input devices: {error}"))?;
+
+        for device in devices.infos {
+            let name = String::from_utf8_lossy(&device.name);
+            if ![DeviceType::SLAVE_POINTER, DeviceType::SLAVE_KEYBOARD].contains(&device.type_) || name.contains("XTEST") || name.starts_with("tatata") {
+                continue;
+            }
+            let grab = connection.xinput_xi_grab_device(root, CURRENT_TIME, x11rb::NONE, device.deviceid, GrabMode::ASYNC, GrabMode::ASYNC, GrabOwner::NO_OWNER, &[u32::from(XIEventMask::KEY_PRESS)])
+                .map_err(ReplyError::from)
+                .and_then(|cookie| cookie.reply())
+                .map_err(|error| format!("Failed to grab {name}: {error}"))?;
+            if grab.status != GrabStatus::SUCCESS {
+                return Err(format!("{name} is grabbed by another application"));
+            }
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                match connection.poll_for_event() {
+                    Ok(Some(Event::XinputKeyPress(event))) if event.detail == escape => {
+                        warn!("Escape was pressed, aborting");
+                        interrupt::request();
+                    }
+                    Ok(Some(_)) => {}
+                    Ok(None) => thread::sleep(CHECK_INTERVAL),
+                    Err(error) => {
+                        warn!("Lost the connection to the X server, so input isn't blocked anymore: {error}");
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Blocker { stop, handle: Some(handle) })
+    }
+}
+
+impl Drop for Blocker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::inflate::inflate;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The extension of script bundles, which are ZIP archives of a script and the files it uses
+pub const EXTENSION: &str = "tatatapkg";
+
+/// The bundles that have been opened
+static BUNDLES: Mutex<Vec<Bundle>> = Mutex::new(Vec::new());
+
+struct Bundle {
+    path: PathBuf,
+    /// The files in the bundle by their names inside it, like `images/button.png`
+    files: HashMap<String, Vec<u8>>
+}
+
+// The signatures the records of a ZIP archive start with
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+
+/// How many bytes the files of a bundle can take up together once they're decompressed
+const MAX_SIZE: usize = 256 * 1024 * 1024;
+
+/// Open a bundle, and get the path of the script inside it
+///
+/// The files of the bundle are kept in memory, and are read by `read` as if the bundle was a directory, so files the
+/// script uses are found next to it. The script is the only `.tatata` file at the top of the bundle.
+pub fn open(path: &Path) -> Result<PathBuf, String> {
+    let data = fs::read(path).map_err(|error| format!("couldn't read {}: {error}", path.display()))?;
+    let entries = unzip(&data).map_err(|error| format!("couldn't open {}: {error}", path.display()))?;
+
+    let scripts: Vec<&String> = entries.keys().filter(|name| !name.contains('/') && name.ends_with(".tatata")).collect();
+    let script = match scripts.as_slice() {
+        [script] => path.join(script),
+        [] => return Err(format!("{} has no .tatata script at the top", path.display())),
+        _ => return Err(format!("{} has more than one script at the top: {}", path.display(), scripts.iter().map(|name| name.as_str()).collect::<Vec<_>>().join(", ")))
+    };
+
+    BUNDLES.lock().unwrap_or_else(|error| error.into_inner()).push(Bundle { path: path.to_path_buf(), files: entries });
+    Ok(script)
+}
+
+/// Read a file, from an opened bundle if it's inside one
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let bundles = BUNDLES.lock().unwrap_or_else(|error| error.into_inner());
+    for bundle in bundles.iter() {
+        let Ok(relative) = path.strip_prefix(&bundle.path) else {
+            continue;
+        };
+        // Names in ZIP archives are always separated by slashes
+        let name = relative.components().map(|component| component.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        return bundle.files.get(&name).cloned().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{name} isn't in the bundle")));
+    }
+    fs::read(path)
+}
+
+/// Read a text file, from an opened bundle if it's inside one
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"))
+}
+
+/// Get the files of a ZIP archive by their names, leaving out directories
+///
+/// Files can be stored or compressed with DEFLATE, which is what archiving tools use by default. Encrypted files and
+/// ZIP64 archives, which are only needed for files over 4 GB, aren't supported. The files can't take up more than
+/// [`MAX_SIZE`] together, so a small archive can't fill up the memory.
+fn unzip(data: &[u8]) -> Result<HashMap<String, Vec<u8>>, String> {
+    // The end of the central directory is at the end of the archive, followed by a comment of up to 64 KB
+    let end = (0..data.len().saturating_sub(21)).rev().take(u16::MAX as usize + 1)
+        .find(|&offset| u32_at(data, offset) == Some(END_OF_CENTRAL_DIRECTORY))
+        .ok_or("it isn't a ZIP archive")?;
+    let count = u16_at(data, end + 10).ok_or("the archive is cut off")?;
+    let mut offset = u32_at(data, end + 16).ok_or("the archive is cut off")? as usize;
+
+    let mut files = HashMap::new();
+    let mut remaining = MAX_SIZE;
+    for _ in 0..count {
+        if u32_at(data, offset) != Some(CENTRAL_HEADER) {
+            return Err("the archive is corrupted".to_string());
+        }
+        let field = |position: usize| u16_at(data, offset + position).map(usize::from).ok_or("the archive is cut off");
+        let (flags, method, name_length, extra_length, comment_length) = (field(8)?, field(10)?, field(28)?, field(30)?, field(32)?);
+        let size = |position: usize| u32_at(data, offset + position).ok_or("the archive is cut off");
+        let (checksum, compressed_size, size, local_offset) = (size(16)?, size(20)?, size(24)?, size(42)? as usize);
+        let name = data.get(offset + 46..offset + 46 + name_length).ok_or("the archive is cut off")?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        offset += 46 + name_length + extra_length + comment_length;
+
+        if name.ends_with('/') {
+            continue;
+        }
+        if flags & 1 != 0 {
+            return Err(format!("{name} is encrypted"));
+        }
+        if compressed_size == u32::MAX || size == u32::MAX || local_offset == u32::MAX as usize {
+            return Err(format!("{name} is too big"));
+        }
+        let size = size as usize;
+        if size > remaining {
+            return Err(format!("{name} is too big, as the files of a bundle can only take up {} MB", MAX_SIZE / 1024 / 1024));
+        }
+        remaining -= size;
+
+        // The data comes after the local header, whose name and extra field can differ from the central directory
+        if u32_at(data, local_offset) != Some(LOCAL_HEADER) {
+            return Err("the archive is corrupted".to_string());
+        }
+        let local_field = |position: usize| u16_at(data, local_offset + position).map(usize::from).ok_or("the archive is cut off");
+        let start = local_offset + 30 + local_field(26)? + local_field(28)?;
+        let compressed = data.get(start..start + compressed_size as usize).ok_or("the archive is cut off")?;
+        let content = match method {
+            0 => compressed.to_vec(),
+            8 => inflate(compressed, size).map_err(|error| format!("couldn't decompress {name}: {error}"))?,
+            _ => return Err(format!("{name} is compressed in an unsupported way"))
+        };
+        if content.len() != size || crc32(&content) != checksum {
+            return Err(format!("{name} is corrupted"));
+        }
+        files.insert(name, content);
+    }
+    Ok(files)
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/// Calculate the CRC-32 checksum ZIP archives store for every file
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,52 @@
+use crate::backend::Backend;
+use enigo::{Direction, Key};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+use x11 as platform;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "windows")]
+use win32 as platform;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub fn text() -> Result<String, String> {
+        Err("Reading the clipboard is not supported on this platform".to_string())
+    }
+
+    pub fn set_text(_text: &str) -> Result<(), String> {
+        Err("Writing to the clipboard is not supported on this platform".to_string())
+    }
+}
+
+/// How long the application gets to read the clipboard after pasting, before the clipboard is changed again
+const PASTE_DELAY: Duration = Duration::from_millis(150);
+
+/// Get the text on the clipboard, which is empty if the clipboard holds something else, like an image
+pub fn text() -> Result<String, String> {
+    platform::text()
+}
+
+/// Replace what's on the clipboard with text
+pub fn set_text(text: &str) -> Result<(), String> {
+    platform::set_text(text)
+}
+
+/// Paste text into the focused application, by putting it on the clipboard and pressing Ctrl+V
+pub fn paste(backend: &mut dyn Backend, text: &str) -> Result<(), String> {
+    set_text(text)?;
+
+    let modifier = if cfg!(target_os = "macos") { Key::Meta } else { Key::Control };
+    backend.key(modifier, Direction::Press)
+        .and_then(|_| backend.key(Key::Unicode('v'), Direction::Click))
+        .and_then(|_| backend.key(modifier, Direction::Release))
+        .map_err(|error| format!("Failed to press the paste shortcut: {error}"))?;
+
+    thread::sleep(PASTE_DELAY);
+    Ok(())
+}
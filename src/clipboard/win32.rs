@@ -0,0 +1,79 @@
+use std::ptr;
+use windows_sys::Win32::System::DataExchange::{CloseClipboard, EmptyClipboard, GetClipboardData, IsClipboardFormatAvailable, OpenClipboard, SetClipboardData};
+use windows_sys::Win32::System::Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use windows_sys::Win32::System::Ole::CF_UNICODETEXT;
+
+pub fn text() -> Result<String, String> {
+    // SAFETY: the clipboard is closed again before returning, and the data is only read while it's locked
+    unsafe {
+        if OpenClipboard(ptr::null_mut()) == 0 {
+            return Err("Failed to open the clipboard, as another application is using it".to_string());
+        }
+
+        let result = if IsClipboardFormatAvailable(CF_UNICODETEXT as u32) == 0 {
+            Ok(String::new())
+        } else {
+            read_unicode_text()
+        };
+
+        CloseClipboard();
+        result
+    }
+}
+
+/// Read the clipboard as text, which has to be open
+unsafe fn read_unicode_text() -> Result<String, String> {
+    // SAFETY: the clipboard is open, and the handle stays valid until it's closed
+    unsafe {
+        let handle = GetClipboardData(CF_UNICODETEXT as u32);
+        if handle.is_null() {
+            return Err("Failed to read the clipboard".to_string());
+        }
+
+        let data = GlobalLock(handle) as *const u16;
+        if data.is_null() {
+            return Err("Failed to read the clipboard".to_string());
+        }
+
+        // The text ends at the first null character
+        let mut length = 0;
+        while *data.add(length) != 0 {
+            length += 1;
+        }
+        let text = String::from_utf16_lossy(std::slice::from_raw_parts(data, length));
+
+        GlobalUnlock(handle);
+        Ok(text)
+    }
+}
+
+pub fn set_text(text: &str) -> Result<(), String> {
+    let units: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+
+    // SAFETY: the clipboard is closed again before returning, the copy fits in the allocated memory, and the memory is
+    // only freed if the clipboard didn't take it
+    unsafe {
+        if OpenClipboard(ptr::null_mut()) == 0 {
+            return Err("Failed to open the clipboard, as another application is using it".to_string());
+        }
+        EmptyClipboard();
+
+        let handle = GlobalAlloc(GMEM_MOVEABLE, units.len() * size_of::<u16>());
+        let data = if handle.is_null() { ptr::null_mut() } else { GlobalLock(handle) as *mut u16 };
+        let result = if data.is_null() {
+            Err("Failed to allocate memory for the clipboard".to_string())
+        } else {
+            ptr::copy_nonoverlapping(units.as_ptr(), data, units.len());
+            GlobalUnlock(handle);
+            if SetClipboardData(CF_UNICODETEXT as u32, handle).is_null() {
+                GlobalFree(handle);
+                Err("Failed to put the text on the clipboard".to_string())
+            } else {
+                Ok(())
+            }
+        };
+
+        CloseClipboard();
+        result
+    }
+}
@@ -0,0 +1,144 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, CreateWindowAux, EventMask, PropMode, SelectionNotifyEvent, WindowClass, SELECTION_NOTIFY_EVENT};
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, CURRENT_TIME};
+
+/// How long the application owning the clipboard gets to hand over its contents
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+fn intern_atom(connection: &RustConnection, name: &str) -> Result<u32, String> {
+    connection.intern_atom(false, name.as_bytes())
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map(|reply| reply.atom)
+        .map_err(|error| format!("Failed to get X atom {name}: {error}"))
+}
+
+/// Ask the owner of the clipboard for its contents as UTF-8 text
+///
+/// X11 has no clipboard storage, so the text is sent by the application that copied it to a window we create for it.
+pub fn text() -> Result<String, String> {
+    let (connection, screen) = x11rb::connect(None).map_err(|error| format!("Failed to connect to X server: {error}"))?;
+    let root = connection.setup().roots[screen].root;
+    let clipboard = intern_atom(&connection, "CLIPBOARD")?;
+    let utf8_string = intern_atom(&connection, "UTF8_STRING")?;
+    let property = intern_atom(&connection, "TATATA_CLIPBOARD")?;
+
+    // Nothing has been copied, or the application that copied it has closed
+    let owner = connection.get_selection_owner(clipboard)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to get the clipboard owner: {error}"))?
+        .owner;
+    if owner == x11rb::NONE {
+        return Ok(String::new());
+    }
+
+    let window = connection.generate_id().map_err(|error| format!("Failed to create window: {error}"))?;
+    connection.create_window(COPY_DEPTH_FROM_PARENT, window, root, 0, 0, 1, 1, 0, WindowClass::INPUT_ONLY, x11rb::COPY_FROM_PARENT, &CreateWindowAux::new())
+        .and_then(|_| connection.convert_selection(window, clipboard, utf8_string, property, CURRENT_TIME))
+        .and_then(|_| connection.flush())
+        .map_err(|error| format!("Failed to request the clipboard: {error}"))?;
+
+    let result = receive(&connection, window, property);
+    let _ = connection.destroy_window(window);
+    let _ = connection.flush();
+    result
+}
+
+/// Wait for the clipboard owner to store the text in the property, and read it
+fn receive(connection: &RustConnection, window: u32, property: u32) -> Result<String, String> {
+    let start = Instant::now();
+    loop {
+        let event = connection.poll_for_event().map_err(|error| format!("Failed to read the clipboard: {error}"))?;
+        match event {
+            // The owner couldn't convert the contents to text
+            Some(Event::SelectionNotify(event)) if event.requestor == window && event.property == x11rb::NONE => return Ok(String::new()),
+            Some(Event::SelectionNotify(event)) if event.requestor == window => break,
+            Some(_) => continue,
+            None if start.elapsed() >= TIMEOUT => return Err("The application that owns the clipboard didn't respond".to_string()),
+            None => thread::sleep(Duration::from_millis(5))
+        }
+    }
+
+    let reply = connection.get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX / 4)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to read the clipboard: {error}"))?;
+
+    // Large contents are sent in parts, which isn't worth supporting for text that's typed back
+    if reply.type_ == intern_atom(connection, "INCR")? {
+        return Err("The text on the clipboard is too large".to_string());
+    }
+
+    Ok(String::from_utf8_lossy(&reply.value).into_owned())
+}
+
+/// Put text on the clipboard, by owning it and handing the text to applications that paste it
+///
+/// The text is handed out on a separate thread until another application copies something, or until TATATA exits, as X11
+/// has no clipboard storage of its own.
+pub fn set_text(text: &str) -> Result<(), String> {
+    let (connection, screen) = x11rb::connect(None).map_err(|error| format!("Failed to connect to X server: {error}"))?;
+    let root = connection.setup().roots[screen].root;
+    let clipboard = intern_atom(&connection, "CLIPBOARD")?;
+
+    let window = connection.generate_id().map_err(|error| format!("Failed to create window: {error}"))?;
+    connection.create_window(COPY_DEPTH_FROM_PARENT, window, root, 0, 0, 1, 1, 0, WindowClass::INPUT_ONLY, x11rb::COPY_FROM_PARENT, &CreateWindowAux::new())
+        .and_then(|_| connection.set_selection_owner(window, clipboard, CURRENT_TIME))
+        .and_then(|_| connection.flush())
+        .map_err(|error| format!("Failed to take the clipboard: {error}"))?;
+
+    let owner = connection.get_selection_owner(clipboard)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to take the clipboard: {error}"))?
+        .owner;
+    if owner != window {
+        return Err("Failed to take the clipboard, as another application took it first".to_string());
+    }
+
+    let text = text.to_string();
+    let targets = intern_atom(&connection, "TARGETS")?;
+    let utf8_string = intern_atom(&connection, "UTF8_STRING")?;
+    thread::spawn(move || serve(&connection, &text, targets, utf8_string));
+    Ok(())
+}
+
+/// Hand the text to every application that asks for it, until something else is put on the clipboard
+fn serve(connection: &RustConnection, text: &str, targets: u32, utf8_string: u32) {
+    while let Ok(event) = connection.wait_for_event() {
+        let request = match event {
+            Event::SelectionClear(_) => return,
+            Event::SelectionRequest(request) => request,
+            _ => continue
+        };
+
+        // Old applications don't say which property to store the text in, and expect the name of the format to be used
+        let property = if request.property == x11rb::NONE { request.target } else { request.property };
+        let stored = if request.target == targets {
+            connection.change_property32(PropMode::REPLACE, request.requestor, property, AtomEnum::ATOM, &[targets, utf8_string, AtomEnum::STRING.into()]).is_ok()
+        } else if request.target == utf8_string || request.target == u32::from(AtomEnum::STRING) {
+            connection.change_property8(PropMode::REPLACE, request.requestor, property, request.target, text.as_bytes()).is_ok()
+        } else {
+            false
+        };
+
+        let notify = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: request.time,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property: if stored { property } else { x11rb::NONE }
+        };
+        let _ = connection.send_event(false, request.requestor, EventMask::NO_EVENT, notify);
+        let _ = connection.flush();
+    }
+}
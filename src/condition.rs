@@ -0,0 +1,78 @@
+use crate::monitors::{self, Monitor};
+use crate::screen::{self, Color};
+use crate::window;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Something about the screen that a loop checks before every iteration
+#[derive(Debug)]
+pub enum Condition {
+    /// Whether the pixel at a position has a color, or doesn't when `equal` is false
+    Pixel {
+        x: i32,
+        y: i32,
+        color: Color,
+        equal: bool
+    },
+    /// Whether a window with a title containing the text exists
+    Window(String)
+}
+
+impl Condition {
+    /// Parse a condition, like `pixel(100, 200) == #112233` or `window("Notepad")`, where the position of the pixel is
+    /// changed by the `@offset` and `@scale` lines before it
+    pub fn parse(text: &str, variables: &HashMap<String, i32>, transform: &crate::Transform) -> Result<Self, String> {
+        let text = text.trim();
+
+        if let Some(rest) = text.strip_prefix("pixel(") {
+            let (arguments, comparison) = rest.split_once(')').ok_or("missing ) after the position")?;
+            let (x, y) = arguments.split_once(',').ok_or("expected a position like pixel(100, 200)")?;
+            let x = crate::resolve_position(&transform.apply_literal(x.trim(), Some(0)), "X", variables).map_err(|error| format!("invalid X position {:?} ({error})", x.trim()))?;
+            let y = crate::resolve_position(&transform.apply_literal(y.trim(), Some(1)), "Y", variables).map_err(|error| format!("invalid Y position {:?} ({error})", y.trim()))?;
+
+            let comparison = comparison.trim();
+            let (equal, color) = if let Some(color) = comparison.strip_prefix("==") {
+                (true, color)
+            } else if let Some(color) = comparison.strip_prefix("!=") {
+                (false, color)
+            } else {
+                return Err("expected == or != followed by a color after the position".to_string());
+            };
+            let color = Color::parse(color.trim()).map_err(|error| format!("invalid color {:?} ({error})", color.trim()))?;
+
+            return Ok(Condition::Pixel { x, y, color, equal });
+        }
+
+        if let Some(rest) = text.strip_prefix("window(") {
+            let title = rest.strip_suffix(')').ok_or("missing ) after the title")?.trim();
+            let title = title.strip_prefix('"').and_then(|title| title.strip_suffix('"')).ok_or("the title has to be in quotes, like window(\"Notepad\")")?;
+            return Ok(Condition::Window(title.to_string()));
+        }
+
+        Err("expected pixel(<x>, <y>) == <color> or window(\"<title>\")".to_string())
+    }
+
+    /// Check whether the condition is currently true
+    pub fn check(&self, logical_monitors: Option<&[Monitor]>) -> Result<bool, String> {
+        match self {
+            Condition::Pixel { x, y, color, equal } => {
+                // Positions are interpreted the same way as absolute mouse movements
+                let (x, y) = match logical_monitors {
+                    Some(monitors) => monitors::logical_to_physical(monitors, *x, *y),
+                    None => (*x, *y)
+                };
+                Ok((screen::pixel(x, y)? == *color) == *equal)
+            }
+            Condition::Window(title) => Ok(window::find(title)?.is_some())
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::Pixel { x, y, color, equal } => write!(formatter, "pixel({x}, {y}) {} {color}", if *equal { "==" } else { "!=" }),
+            Condition::Window(title) => write!(formatter, "window({title:?})")
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicUsize, Ordering};
+
+/// Whether the run should wait before executing the next line
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the run is paused because the screen is locked, which is separate so unlocking doesn't resume a paused run
+static LOCKED: AtomicBool = AtomicBool::new(false);
+
+/// How far through the script the run is, in percent of its duration
+static PERCENT: AtomicU32 = AtomicU32::new(0);
+
+/// The line that was executed last
+static LINE: AtomicI32 = AtomicI32::new(0);
+
+/// The keys and mouse buttons the scripts hold down, like `key Shift`
+static HELD: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// How many scripts haven't finished or been aborted yet, which is more than one with `--parallel`
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// The exit code a script asked for with `abort`, or 0 if none has
+static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
+
+/// Pause the run if it's running, or resume it if it's paused
+pub fn toggle_pause() {
+    PAUSED.fetch_xor(true, Ordering::Relaxed);
+}
+
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed) || is_locked()
+}
+
+/// Record whether the screen is locked, which pauses the run while it is
+pub fn set_locked(locked: bool) {
+    LOCKED.store(locked, Ordering::Relaxed);
+}
+
+pub fn is_locked() -> bool {
+    LOCKED.load(Ordering::Relaxed)
+}
+
+/// Record that a line has been executed
+pub fn set_progress(percent: u32, line: i32) {
+    PERCENT.store(percent, Ordering::Relaxed);
+    LINE.store(line, Ordering::Relaxed);
+}
+
+/// Get how far through the script the run is in percent, and the line that was executed last
+pub fn progress() -> (u32, i32) {
+    (PERCENT.load(Ordering::Relaxed), LINE.load(Ordering::Relaxed))
+}
+
+/// The name of the last `//! cue:` comment the run reached
+static CUE: Mutex<String> = Mutex::new(String::new());
+
+/// Record that a script has pressed a key or mouse button
+pub fn hold(name: String) {
+    HELD.lock().unwrap_or_else(|error| error.into_inner()).push(name);
+}
+
+/// Record that a script has released a key or mouse button
+pub fn unhold(name: &str) {
+    let mut held = HELD.lock().unwrap_or_else(|error| error.into_inner());
+    if let Some(index) = held.iter().position(|held| held == name) {
+        held.remove(index);
+    }
+}
+
+pub fn held() -> Vec<String> {
+    HELD.lock().unwrap_or_else(|error| error.into_inner()).clone()
+}
+
+/// Record that the run has reached a cue
+pub fn set_cue(name: &str) {
+    name.clone_into(&mut CUE.lock().unwrap_or_else(|error| error.into_inner()));
+}
+
+pub fn cue() -> String {
+    CUE.lock().unwrap_or_else(|error| error.into_inner()).clone()
+}
+
+/// Record how many scripts are about to start
+pub fn start_scripts(count: usize) {
+    RUNNING.store(count, Ordering::Relaxed);
+}
+
+/// Record that a script has finished or been aborted, returning whether it was the last one running
+pub fn finish_script() -> bool {
+    RUNNING.fetch_sub(1, Ordering::Relaxed) <= 1
+}
+
+/// Record the exit code a script stopped with, so the last script running exits with it
+pub fn set_exit_code(code: i32) {
+    EXIT_CODE.store(code, Ordering::Relaxed);
+}
+
+pub fn exit_code() -> Option<i32> {
+    Some(EXIT_CODE.load(Ordering::Relaxed)).filter(|&code| code != 0)
+}
+
+/// Describe the state of the run, like `script.tatata: 42% (line 17, Logging in), paused, holding key Shift`
+pub fn describe(script_name: &str) -> String {
+    let (percent, line) = progress();
+    let cue = cue();
+    let cue = if cue.is_empty() { String::new() } else { format!(", {cue}") };
+    let paused = if is_locked() { ", paused while the screen is locked" } else if is_paused() { ", paused" } else { "" };
+    let held = held();
+    let holding = if held.is_empty() { String::new() } else { format!(", holding {}", held.join(", ")) };
+    format!("{script_name}: {percent}% (line {line}{cue}){paused}{holding}")
+}
@@ -0,0 +1,625 @@
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::io::{Read, Write};
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::ffi::OsStrExt;
+use std::os::fd::AsRawFd;
+use std::os::unix::net::{SocketAddr, UnixStream};
+use std::time::Duration;
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+const SIGNAL: u8 = 4;
+
+// Header field codes from the D-Bus specification
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SENDER: u8 = 7;
+const FIELD_SIGNATURE: u8 = 8;
+
+/// A value that can be sent over D-Bus
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Byte(u8),
+    Bool(bool),
+    Int16(i16),
+    Uint16(u16),
+    Int32(i32),
+    Uint32(u32),
+    Int64(i64),
+    Uint64(u64),
+    Double(f64),
+    String(String),
+    ObjectPath(String),
+    Signature(String),
+    UnixFd(u32),
+    Variant(Box<Value>),
+    /// An array, along with the signature of its elements so empty arrays can be sent
+    Array(String, Vec<Value>),
+    Struct(Vec<Value>),
+    DictEntry(Box<Value>, Box<Value>)
+}
+
+impl Value {
+    /// Create a string to variant dictionary (`a{sv}`), which is how the portals take their options
+    pub fn dict(entries: Vec<(&str, Value)>) -> Value {
+        let entries = entries.into_iter()
+            .map(|(key, value)| Value::DictEntry(Box::new(Value::String(key.to_string())), Box::new(Value::Variant(Box::new(value)))))
+            .collect();
+        Value::Array("{sv}".to_string(), entries)
+    }
+
+    /// Look up a key in a string to variant dictionary
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let Value::Array(_, entries) = self else {
+            return None;
+        };
+
+        entries.iter().find_map(|entry| match entry {
+            Value::DictEntry(entry_key, value) if **entry_key == Value::String(key.to_string()) => Some(value.unwrap_variant()),
+            _ => None
+        })
+    }
+
+    fn unwrap_variant(&self) -> &Value {
+        match self {
+            Value::Variant(value) => value.unwrap_variant(),
+            value => value
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match self.unwrap_variant() {
+            Value::Uint32(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        match self.unwrap_variant() {
+            Value::Int32(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.unwrap_variant() {
+            Value::String(value) | Value::ObjectPath(value) | Value::Signature(value) => Some(value),
+            _ => None
+        }
+    }
+
+    /// Get the fields of a struct, or the elements of an array
+    pub fn as_slice(&self) -> Option<&[Value]> {
+        match self.unwrap_variant() {
+            Value::Struct(values) | Value::Array(_, values) => Some(values),
+            _ => None
+        }
+    }
+
+    fn signature(&self) -> String {
+        match self {
+            Value::Byte(_) => "y".to_string(),
+            Value::Bool(_) => "b".to_string(),
+            Value::Int16(_) => "n".to_string(),
+            Value::Uint16(_) => "q".to_string(),
+            Value::Int32(_) => "i".to_string(),
+            Value::Uint32(_) => "u".to_string(),
+            Value::Int64(_) => "x".to_string(),
+            Value::Uint64(_) => "t".to_string(),
+            Value::Double(_) => "d".to_string(),
+            Value::String(_) => "s".to_string(),
+            Value::ObjectPath(_) => "o".to_string(),
+            Value::Signature(_) => "g".to_string(),
+            Value::UnixFd(_) => "h".to_string(),
+            Value::Variant(_) => "v".to_string(),
+            Value::Array(element, _) => format!("a{element}"),
+            Value::Struct(values) => format!("({})", values.iter().map(Value::signature).collect::<String>()),
+            Value::DictEntry(key, value) => format!("{{{}{}}}", key.signature(), value.signature())
+        }
+    }
+}
+
+/// Get the alignment of a type from the first character of its signature
+fn alignment(signature: &str) -> usize {
+    match signature.as_bytes().first() {
+        Some(b'n' | b'q') => 2,
+        Some(b'b' | b'i' | b'u' | b's' | b'o' | b'a' | b'h') => 4,
+        Some(b'x' | b't' | b'd' | b'(' | b'{') => 8,
+        _ => 1
+    }
+}
+
+/// Split the first complete type off a signature
+fn split_type(signature: &str) -> Result<(&str, &str), String> {
+    let length = match signature.as_bytes().first() {
+        None => return Err("Unexpected end of D-Bus signature".to_string()),
+        Some(b'a') => 1 + split_type(&signature[1..])?.0.len(),
+        Some(&open @ (b'(' | b'{')) => {
+            let close = if open == b'(' { b')' } else { b'}' };
+            let mut depth = 0;
+            let end = signature.bytes().position(|byte| {
+                if byte == open {
+                    depth += 1;
+                } else if byte == close {
+                    depth -= 1;
+                }
+                depth == 0
+            });
+            end.ok_or(format!("Unbalanced D-Bus signature {signature:?}"))? + 1
+        }
+        Some(_) => 1
+    };
+
+    Ok(signature.split_at(length))
+}
+
+/// Serializes values in little endian D-Bus wire format
+#[derive(Default)]
+struct Writer {
+    buffer: Vec<u8>
+}
+
+impl Writer {
+    fn align(&mut self, alignment: usize) {
+        while !self.buffer.len().is_multiple_of(alignment) {
+            self.buffer.push(0);
+        }
+    }
+
+    fn put(&mut self, alignment: usize, bytes: &[u8]) {
+        self.align(alignment);
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn write(&mut self, value: &Value) {
+        match value {
+            Value::Byte(value) => self.put(1, &[*value]),
+            Value::Bool(value) => self.put(4, &(*value as u32).to_le_bytes()),
+            Value::Int16(value) => self.put(2, &value.to_le_bytes()),
+            Value::Uint16(value) => self.put(2, &value.to_le_bytes()),
+            Value::Int32(value) => self.put(4, &value.to_le_bytes()),
+            Value::Uint32(value) | Value::UnixFd(value) => self.put(4, &value.to_le_bytes()),
+            Value::Int64(value) => self.put(8, &value.to_le_bytes()),
+            Value::Uint64(value) => self.put(8, &value.to_le_bytes()),
+            Value::Double(value) => self.put(8, &value.to_le_bytes()),
+            Value::String(value) | Value::ObjectPath(value) => {
+                self.put(4, &(value.len() as u32).to_le_bytes());
+                self.buffer.extend_from_slice(value.as_bytes());
+                self.buffer.push(0);
+            }
+            Value::Signature(value) => {
+                self.buffer.push(value.len() as u8);
+                self.buffer.extend_from_slice(value.as_bytes());
+                self.buffer.push(0);
+            }
+            Value::Variant(value) => {
+                self.write(&Value::Signature(value.signature()));
+                self.write(value);
+            }
+            Value::Array(element, values) => {
+                // The length doesn't include the padding before the first element, so it's filled in afterwards
+                self.put(4, &[0; 4]);
+                let length_position = self.buffer.len() - 4;
+                self.align(alignment(element));
+                let start = self.buffer.len();
+                for value in values {
+                    self.write(value);
+                }
+                let length = (self.buffer.len() - start) as u32;
+                self.buffer[length_position..length_position + 4].copy_from_slice(&length.to_le_bytes());
+            }
+            Value::Struct(values) => {
+                self.align(8);
+                for value in values {
+                    self.write(value);
+                }
+            }
+            Value::DictEntry(key, value) => {
+                self.align(8);
+                self.write(key);
+                self.write(value);
+            }
+        }
+    }
+}
+
+/// Deserializes values in little endian D-Bus wire format
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, alignment: usize, length: usize) -> Result<&'a [u8], String> {
+        let start = self.position.next_multiple_of(alignment);
+        let bytes = self.data.get(start..start + length).ok_or("Malformed D-Bus message")?;
+        self.position = start + length;
+        Ok(bytes)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N, N)?.try_into().unwrap_or([0; N]))
+    }
+
+    fn read_string(&mut self, length: usize) -> Result<String, String> {
+        let bytes = self.take(1, length + 1)?;
+        Ok(String::from_utf8_lossy(&bytes[..length]).into_owned())
+    }
+
+    /// Read a single complete type
+    fn read(&mut self, signature: &str) -> Result<Value, String> {
+        let value = match signature.as_bytes().first() {
+            Some(b'y') => Value::Byte(self.take(1, 1)?[0]),
+            Some(b'b') => Value::Bool(u32::from_le_bytes(self.take_array()?) != 0),
+            Some(b'n') => Value::Int16(i16::from_le_bytes(self.take_array()?)),
+            Some(b'q') => Value::Uint16(u16::from_le_bytes(self.take_array()?)),
+            Some(b'i') => Value::Int32(i32::from_le_bytes(self.take_array()?)),
+            Some(b'u') => Value::Uint32(u32::from_le_bytes(self.take_array()?)),
+            Some(b'h') => Value::UnixFd(u32::from_le_bytes(self.take_array()?)),
+            Some(b'x') => Value::Int64(i64::from_le_bytes(self.take_array()?)),
+            Some(b't') => Value::Uint64(u64::from_le_bytes(self.take_array()?)),
+            Some(b'd') => Value::Double(f64::from_le_bytes(self.take_array()?)),
+            Some(b's') => {
+                let length = u32::from_le_bytes(self.take_array()?) as usize;
+                Value::String(self.read_string(length)?)
+            }
+            Some(b'o') => {
+                let length = u32::from_le_bytes(self.take_array()?) as usize;
+                Value::ObjectPath(self.read_string(length)?)
+            }
+            Some(b'g') => {
+                let length = self.take(1, 1)?[0] as usize;
+                Value::Signature(self.read_string(length)?)
+            }
+            Some(b'v') => {
+                let length = self.take(1, 1)?[0] as usize;
+                let signature = self.read_string(length)?;
+                Value::Variant(Box::new(self.read(&signature)?))
+            }
+            Some(b'a') => {
+                let element = split_type(&signature[1..])?.0;
+                let length = u32::from_le_bytes(self.take_array()?) as usize;
+                self.take(alignment(element), 0)?;
+                let end = self.position + length;
+                let mut values = Vec::new();
+                while self.position < end {
+                    values.push(self.read(element)?);
+                }
+                Value::Array(element.to_string(), values)
+            }
+            Some(b'(') => {
+                self.take(8, 0)?;
+                let mut fields = &signature[1..signature.len() - 1];
+                let mut values = Vec::new();
+                while !fields.is_empty() {
+                    let (field, rest) = split_type(fields)?;
+                    values.push(self.read(field)?);
+                    fields = rest;
+                }
+                Value::Struct(values)
+            }
+            Some(b'{') => {
+                self.take(8, 0)?;
+                let (key, value) = split_type(&signature[1..signature.len() - 1])?;
+                Value::DictEntry(Box::new(self.read(key)?), Box::new(self.read(value)?))
+            }
+            _ => return Err(format!("Unsupported D-Bus signature {signature:?}"))
+        };
+
+        Ok(value)
+    }
+}
+
+/// A message received from the bus
+struct Message {
+    kind: u8,
+    serial: u32,
+    reply_serial: Option<u32>,
+    sender: Option<String>,
+    path: Option<String>,
+    interface: Option<String>,
+    member: Option<String>,
+    error_name: Option<String>,
+    body: Vec<Value>
+}
+
+/// A method called on an object exported by this connection
+pub struct MethodCall {
+    serial: u32,
+    sender: Option<String>,
+    pub path: String,
+    pub interface: String,
+    pub member: String,
+    pub body: Vec<Value>
+}
+
+/// A connection to the D-Bus session bus
+pub struct Connection {
+    stream: UnixStream,
+    serial: u32,
+    /// Signals and method calls received while waiting for a method to return
+    queued: VecDeque<Message>
+}
+
+impl Connection {
+    /// Connect and authenticate to the session bus
+    pub fn session() -> Result<Self, String> {
+        let address = session_address()?;
+        let mut stream = UnixStream::connect_addr(&address).map_err(|error| format!("Failed to connect to the D-Bus session bus: {error}"))?;
+
+        // Authenticate as the user running this process
+        // SAFETY: getuid has no safety requirements
+        let uid = unsafe { libc::getuid() };
+        let hex_uid: String = uid.to_string().bytes().map(|byte| format!("{byte:02x}")).collect();
+        stream.write_all(format!("\0AUTH EXTERNAL {hex_uid}\r\n").as_bytes())
+            .map_err(|error| format!("Failed to authenticate to the D-Bus session bus: {error}"))?;
+        let response = read_line(&mut stream).map_err(|error| format!("Failed to authenticate to the D-Bus session bus: {error}"))?;
+        if !response.starts_with("OK") {
+            return Err(format!("The D-Bus session bus rejected authentication: {response}"));
+        }
+        stream.write_all(b"BEGIN\r\n").map_err(|error| format!("Failed to authenticate to the D-Bus session bus: {error}"))?;
+
+        // The bus doesn't accept any other messages before Hello
+        let mut connection = Connection { stream, serial: 0, queued: VecDeque::new() };
+        connection.call("org.freedesktop.DBus", "/org/freedesktop/DBus", "org.freedesktop.DBus", "Hello", &[])?;
+
+        Ok(connection)
+    }
+
+    /// Call a method and wait for it to return
+    pub fn call(&mut self, destination: &str, path: &str, interface: &str, member: &str, body: &[Value]) -> Result<Vec<Value>, String> {
+        let serial = self.send(METHOD_CALL, vec![
+            (FIELD_PATH, Value::ObjectPath(path.to_string())),
+            (FIELD_INTERFACE, Value::String(interface.to_string())),
+            (FIELD_MEMBER, Value::String(member.to_string())),
+            (FIELD_DESTINATION, Value::String(destination.to_string()))
+        ], body).map_err(|error| format!("Failed to call {interface}.{member}: {error}"))?;
+
+        loop {
+            let message = self.receive().map_err(|error| format!("Failed to call {interface}.{member}: {error}"))?;
+            match message.kind {
+                METHOD_RETURN if message.reply_serial == Some(serial) => return Ok(message.body),
+                ERROR if message.reply_serial == Some(serial) => {
+                    let name = message.error_name.unwrap_or_default();
+                    let description = message.body.first().and_then(Value::as_str).unwrap_or_default().to_string();
+                    return Err(format!("{interface}.{member} failed: {name}: {description}"));
+                }
+                SIGNAL | METHOD_CALL => self.queued.push_back(message),
+                _ => {}
+            }
+        }
+    }
+
+    /// Wait for a signal to be emitted by an object, and return its arguments
+    ///
+    /// The signal has to be subscribed to with an `AddMatch` call first.
+    pub fn wait_for_signal(&mut self, path: &str, interface: &str, member: &str) -> Result<Vec<Value>, String> {
+        let matches = |message: &Message| {
+            message.kind == SIGNAL && message.path.as_deref() == Some(path) && message.interface.as_deref() == Some(interface) && message.member.as_deref() == Some(member)
+        };
+
+        if let Some(index) = self.queued.iter().position(matches) {
+            return Ok(self.queued.remove(index).map(|message| message.body).unwrap_or_default());
+        }
+
+        loop {
+            let message = self.receive().map_err(|error| format!("Failed to wait for {interface}.{member}: {error}"))?;
+            if matches(&message) {
+                return Ok(message.body);
+            } else if message.kind == METHOD_CALL {
+                self.queued.push_back(message);
+            }
+        }
+    }
+
+    /// Wait up to the given time for a method to be called on one of the objects exported by this connection
+    pub fn next_method_call(&mut self, timeout: Duration) -> Result<Option<MethodCall>, String> {
+        let message = match self.queued.iter().position(|message| message.kind == METHOD_CALL) {
+            Some(index) => self.queued.remove(index),
+            None => {
+                // Only start reading once a message has arrived, so the timeout can't cut a message in half
+                let mut poll = libc::pollfd { fd: self.stream.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+                // SAFETY: poll is given a single valid pollfd
+                let ready = unsafe { libc::poll(&mut poll, 1, timeout.as_millis() as libc::c_int) };
+                if ready < 0 {
+                    return Err(format!("Failed to wait for D-Bus messages: {}", std::io::Error::last_os_error()));
+                }
+                if ready == 0 {
+                    return Ok(None);
+                }
+
+                let message = self.receive()?;
+                if message.kind == SIGNAL {
+                    self.queued.push_back(message);
+                    return Ok(None);
+                }
+                Some(message)
+            }
+        };
+
+        Ok(message.filter(|message| message.kind == METHOD_CALL).map(|message| MethodCall {
+            serial: message.serial,
+            sender: message.sender,
+            path: message.path.unwrap_or_default(),
+            interface: message.interface.unwrap_or_default(),
+            member: message.member.unwrap_or_default(),
+            body: message.body
+        }))
+    }
+
+    /// Return from a method call
+    pub fn reply(&mut self, call: &MethodCall, body: &[Value]) -> Result<(), String> {
+        let mut fields = vec![(FIELD_REPLY_SERIAL, Value::Uint32(call.serial))];
+        if let Some(sender) = &call.sender {
+            fields.push((FIELD_DESTINATION, Value::String(sender.clone())));
+        }
+
+        self.send(METHOD_RETURN, fields, body).map(|_| ()).map_err(|error| format!("Failed to reply to {}.{}: {error}", call.interface, call.member))
+    }
+
+    /// Fail a method call with the given error name, like `org.freedesktop.DBus.Error.UnknownMethod`
+    pub fn reply_error(&mut self, call: &MethodCall, name: &str, description: &str) -> Result<(), String> {
+        let mut fields = vec![(FIELD_REPLY_SERIAL, Value::Uint32(call.serial)), (FIELD_ERROR_NAME, Value::String(name.to_string()))];
+        if let Some(sender) = &call.sender {
+            fields.push((FIELD_DESTINATION, Value::String(sender.clone())));
+        }
+
+        self.send(ERROR, fields, &[Value::String(description.to_string())]).map(|_| ())
+            .map_err(|error| format!("Failed to reply to {}.{}: {error}", call.interface, call.member))
+    }
+
+    /// Emit a signal from an object exported by this connection
+    pub fn emit(&mut self, path: &str, interface: &str, member: &str, body: &[Value]) -> Result<(), String> {
+        self.send(SIGNAL, vec![
+            (FIELD_PATH, Value::ObjectPath(path.to_string())),
+            (FIELD_INTERFACE, Value::String(interface.to_string())),
+            (FIELD_MEMBER, Value::String(member.to_string()))
+        ], body).map(|_| ()).map_err(|error| format!("Failed to emit {interface}.{member}: {error}"))
+    }
+
+    fn send(&mut self, kind: u8, fields: Vec<(u8, Value)>, body: &[Value]) -> Result<u32, String> {
+        self.serial += 1;
+
+        let mut body_writer = Writer::default();
+        for value in body {
+            body_writer.write(value);
+        }
+
+        let field = |code: u8, value: Value| Value::Struct(vec![Value::Byte(code), Value::Variant(Box::new(value))]);
+        let mut fields: Vec<Value> = fields.into_iter().map(|(code, value)| field(code, value)).collect();
+        if !body.is_empty() {
+            fields.push(field(FIELD_SIGNATURE, Value::Signature(body.iter().map(Value::signature).collect())));
+        }
+
+        let mut writer = Writer::default();
+        writer.buffer.extend_from_slice(&[b'l', kind, 0, 1]);
+        writer.buffer.extend_from_slice(&(body_writer.buffer.len() as u32).to_le_bytes());
+        writer.buffer.extend_from_slice(&self.serial.to_le_bytes());
+        writer.write(&Value::Array("(yv)".to_string(), fields));
+        writer.align(8);
+        writer.buffer.extend_from_slice(&body_writer.buffer);
+
+        self.stream.write_all(&writer.buffer).map_err(|error| error.to_string())?;
+        Ok(self.serial)
+    }
+
+    fn receive(&mut self) -> Result<Message, String> {
+        let mut header = vec![0; 16];
+        self.stream.read_exact(&mut header).map_err(|error| error.to_string())?;
+        if header[0] != b'l' {
+            return Err("Big endian D-Bus messages are not supported".to_string());
+        }
+
+        let body_length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let fields_length = u32::from_le_bytes([header[12], header[13], header[14], header[15]]) as usize;
+        let header_length = (16 + fields_length).next_multiple_of(8);
+        header.resize(header_length + body_length, 0);
+        self.stream.read_exact(&mut header[16..]).map_err(|error| error.to_string())?;
+
+        let mut reader = Reader { data: &header[..header_length], position: 12 };
+        let fields = reader.read("a(yv)")?;
+        let mut message = Message {
+            kind: header[1],
+            serial: u32::from_le_bytes([header[8], header[9], header[10], header[11]]),
+            reply_serial: None,
+            sender: None,
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            body: Vec::new()
+        };
+        let mut signature = String::new();
+        for field in fields.as_slice().unwrap_or_default() {
+            let [Value::Byte(code), value] = field.as_slice().unwrap_or_default() else {
+                continue;
+            };
+            let text = value.as_str().map(str::to_string);
+            match *code {
+                FIELD_PATH => message.path = text,
+                FIELD_INTERFACE => message.interface = text,
+                FIELD_MEMBER => message.member = text,
+                FIELD_ERROR_NAME => message.error_name = text,
+                FIELD_REPLY_SERIAL => message.reply_serial = value.as_u32(),
+                FIELD_SENDER => message.sender = text,
+                FIELD_SIGNATURE => signature = text.unwrap_or_default(),
+                _ => {}
+            }
+        }
+
+        let mut reader = Reader { data: &header[header_length..], position: 0 };
+        let mut remaining = signature.as_str();
+        while !remaining.is_empty() {
+            let (type_, rest) = split_type(remaining)?;
+            message.body.push(reader.read(type_)?);
+            remaining = rest;
+        }
+
+        Ok(message)
+    }
+}
+
+/// Find the socket address of the session bus
+fn session_address() -> Result<SocketAddr, String> {
+    let Ok(address) = std::env::var("DBUS_SESSION_BUS_ADDRESS") else {
+        // Without an address, the bus is usually at a well-known path in the runtime directory
+        let runtime_dir = std::env::var("XDG_RUNTIME_DIR").map_err(|_| "No D-Bus session bus found, is DBUS_SESSION_BUS_ADDRESS set?".to_string())?;
+        return SocketAddr::from_pathname(format!("{runtime_dir}/bus")).map_err(|error| error.to_string());
+    };
+
+    // The address is a list of transports like `unix:path=/run/user/1000/bus`, of which only unix sockets are supported
+    for transport in address.split(';') {
+        let Some(parameters) = transport.strip_prefix("unix:") else {
+            continue;
+        };
+
+        for parameter in parameters.split(',') {
+            if let Some(path) = parameter.strip_prefix("path=") {
+                return SocketAddr::from_pathname(OsStr::from_bytes(&unescape(path))).map_err(|error| error.to_string());
+            }
+            if let Some(name) = parameter.strip_prefix("abstract=") {
+                return SocketAddr::from_abstract_name(unescape(name)).map_err(|error| error.to_string());
+            }
+        }
+    }
+
+    Err(format!("Unsupported D-Bus session bus address {address:?}"))
+}
+
+/// Decode the percent-escaped bytes in a D-Bus address value
+fn unescape(value: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut input = value.bytes();
+
+    while let Some(byte) = input.next() {
+        if byte == b'%' {
+            let hex: Vec<u8> = input.by_ref().take(2).collect();
+            let decoded = std::str::from_utf8(&hex).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            bytes.push(decoded.unwrap_or(b'%'));
+        } else {
+            bytes.push(byte);
+        }
+    }
+
+    bytes
+}
+
+/// Read a line of the authentication protocol, without the trailing `\r\n`
+fn read_line(stream: &mut UnixStream) -> std::io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+
+    while !line.ends_with(b"\r\n") {
+        stream.read_exact(&mut byte)?;
+        line.push(byte[0]);
+    }
+
+    line.truncate(line.len() - 2);
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
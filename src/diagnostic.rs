@@ -0,0 +1,298 @@
+use clap::ValueEnum;
+use std::cell::Cell;
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::ops::Range;
+use std::process;
+use std::sync::OnceLock;
+
+/// How errors are printed
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// Readable text, with the line of the script the error is on
+    Human,
+    /// One JSON object per line on stderr, for tools wrapping TATATA
+    Json
+}
+
+static FORMAT: OnceLock<ErrorFormat> = OnceLock::new();
+
+pub fn set_format(format: ErrorFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn format() -> ErrorFormat {
+    FORMAT.get().copied().unwrap_or(ErrorFormat::Human)
+}
+
+/// What went wrong, which decides the exit code of TATATA
+///
+/// Invalid command line arguments exit with code 2, which is left to clap.
+#[derive(Clone, Copy)]
+pub enum ErrorKind {
+    /// The run couldn't start, like when the script file or the window to target doesn't exist
+    Setup,
+    /// The script is invalid
+    Parse,
+    /// Events couldn't be sent, either when starting the backend or while running the script
+    Injection,
+    /// The script stopped itself with `abort`, like when the screen doesn't look as expected
+    Assertion,
+    /// The run was aborted by the user
+    Abort
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Setup => 1,
+            ErrorKind::Parse => 3,
+            ErrorKind::Injection => 4,
+            ErrorKind::Assertion => 5,
+            ErrorKind::Abort => 130
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Setup => "setup",
+            ErrorKind::Parse => "parse",
+            ErrorKind::Injection => "injection",
+            ErrorKind::Assertion => "assertion",
+            ErrorKind::Abort => "abort"
+        }
+    }
+}
+
+/// Print an error that isn't about a line of the script, and stop TATATA with the exit code of its kind
+pub fn fail(kind: ErrorKind, message: impl Display) -> ! {
+    match format() {
+        ErrorFormat::Human => log::error!("{message}"),
+        ErrorFormat::Json => eprintln!("{{\"level\":\"error\",\"kind\":\"{}\",\"message\":{}}}", kind.name(), json_string(&message.to_string()))
+    }
+    process::exit(kind.exit_code());
+}
+
+/// A script being run, kept around so errors can show the line they happened on
+pub struct Source {
+    name: String,
+    content: String
+}
+
+thread_local! {
+    /// The script that errors on this thread are about, as scripts run with `--parallel` each run on their own thread
+    static SOURCE: Cell<Option<&'static Source>> = const { Cell::new(None) };
+}
+
+/// Keep the content of a script for showing in errors on this thread, and get it back to parse it
+///
+/// Parsing the returned content lets [`Diagnostic::at`] find where a token is on its line, as it points into the same string.
+pub fn set_source(name: String, content: String) -> &'static str {
+    // Scripts are needed until TATATA exits, so they're never freed
+    let source: &'static Source = Box::leak(Box::new(Source { name, content }));
+    SOURCE.set(Some(source));
+    &source.content
+}
+
+/// Get the script that errors on this thread are about, to show errors about it on another thread
+pub fn source() -> Option<&'static Source> {
+    SOURCE.get()
+}
+
+/// Make errors on this thread about a script
+pub fn use_source(source: Option<&'static Source>) {
+    SOURCE.set(source);
+}
+
+/// An error on a line of the script, shown along with the line itself
+pub struct Diagnostic {
+    kind: ErrorKind,
+    line: i32,
+    message: String,
+    /// The part of the line the error is about, in bytes
+    span: Option<Range<usize>>,
+    /// Text shown next to the highlighted part, like a suggestion for fixing it
+    label: Option<String>
+}
+
+impl Diagnostic {
+    pub fn new(line: i32, message: impl Into<String>) -> Self {
+        Diagnostic { kind: ErrorKind::Parse, line, message: message.into(), span: None, label: None }
+    }
+
+    /// Set what went wrong, as errors are about parsing the script unless told otherwise
+    pub fn kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Highlight a token on the line
+    pub fn at(self, token: &str) -> Self {
+        self.spanning(token, token)
+    }
+
+    /// Highlight everything from the start of one token to the end of another
+    pub fn spanning(mut self, first: &str, last: &str) -> Self {
+        if let Some(line) = source_line(self.line) {
+            self.span = match (offset_in(line, first), offset_in(line, last)) {
+                (Some(start), Some(end)) => Some(start..end + last.len()),
+                // The token isn't part of the line itself, like when a block comment was cut out of the line
+                _ => line.find(first).map(|start| start..start + first.len())
+            };
+        }
+
+        self
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Suggest the candidate closest to what was written, if any of them are close enough to be a likely typo
+    pub fn suggest<'a>(self, written: &str, candidates: impl IntoIterator<Item = &'a str>) -> Self {
+        match closest(written, candidates) {
+            Some(candidate) => self.label(format!("did you mean {candidate:?}?")),
+            None => self
+        }
+    }
+
+    /// Print the error
+    pub fn report(&self) {
+        match format() {
+            ErrorFormat::Human => log::error!("{}", self.render(use_color())),
+            ErrorFormat::Json => eprintln!("{}", self.to_json())
+        }
+    }
+
+    /// Print the error and stop TATATA with the exit code of its kind
+    pub fn exit(&self) -> ! {
+        self.report();
+        process::exit(self.exit_code());
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.kind.exit_code()
+    }
+
+    fn to_json(&self) -> String {
+        let mut fields = vec![
+            ("level", json_string("error")),
+            ("kind", json_string(self.kind.name())),
+            ("message", json_string(&self.message))
+        ];
+        if let Some(source) = SOURCE.get() {
+            fields.push(("file", json_string(&source.name)));
+        }
+        fields.push(("line", self.line.to_string()));
+        if let (Some(line), Some(span)) = (source_line(self.line), &self.span) {
+            fields.push(("column", (line[..span.start].chars().count() + 1).to_string()));
+        }
+        if let Some(label) = &self.label {
+            fields.push(("label", json_string(label)));
+        }
+
+        let fields: Vec<String> = fields.into_iter().map(|(key, value)| format!("\"{key}\":{value}")).collect();
+        format!("{{{}}}", fields.join(","))
+    }
+
+    fn render(&self, color: bool) -> String {
+        let paint = |code: &str, text: &str| if color { format!("\x1b[{code}m{text}\x1b[0m") } else { text.to_string() };
+
+        let mut output = format!("{}{}", paint("1;31", "error"), paint("1", &format!(": {}", self.message)));
+        let Some(source) = SOURCE.get() else {
+            return output;
+        };
+        let Some(line) = source_line(self.line) else {
+            output.push_str(&format!("\n{} {}:{}", paint("1;34", "-->"), source.name, self.line));
+            return output;
+        };
+
+        // Highlight the whole line if there's nothing more specific to point at
+        let span = self.span.clone().unwrap_or_else(|| {
+            let start = line.len() - line.trim_start().len();
+            start..line.trim_end().len().max(start)
+        });
+        let column = line[..span.start].chars().count() + 1;
+
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let bar = paint("1;34", "|");
+
+        // Keep tabs in the indentation of the carets, so they line up with the line above
+        let indent: String = line[..span.start].chars().map(|character| if character == '\t' { '\t' } else { ' ' }).collect();
+        let carets = "^".repeat(line[span.clone()].chars().count().max(1));
+        let label = self.label.as_deref().map_or(String::new(), |label| format!(" {label}"));
+
+        output.push_str(&format!("\n{gutter}{} {}:{}:{column}", paint("1;34", "-->"), source.name, self.line));
+        output.push_str(&format!("\n{gutter} {bar}"));
+        output.push_str(&format!("\n{} {bar} {line}", paint("1;34", &number)));
+        output.push_str(&format!("\n{gutter} {bar} {indent}{}", paint("1;31", &format!("{carets}{label}"))));
+        output
+    }
+}
+
+/// Get a line of the script being run, counting from 1
+pub fn source_line(line: i32) -> Option<&'static str> {
+    let index = usize::try_from(line).ok()?.checked_sub(1)?;
+    SOURCE.get()?.content.lines().nth(index)
+}
+
+/// Find where a token starts in a line, if the token points into the line
+fn offset_in(line: &str, token: &str) -> Option<usize> {
+    let start = (token.as_ptr() as usize).checked_sub(line.as_ptr() as usize)?;
+    (start + token.len() <= line.len()).then_some(start)
+}
+
+/// Colors are only used when printing to a terminal, and can be turned off with the `NO_COLOR` environment variable
+fn use_color() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Find the candidate with the fewest edits from what was written, ignoring candidates that are too different
+fn closest<'a>(written: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let written = written.to_lowercase();
+    let max_distance = (written.chars().count() / 3).max(1);
+
+    candidates.into_iter()
+        .map(|candidate| (edit_distance(&written, &candidate.to_lowercase()), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Count the insertions, deletions and substitutions needed to turn one string into another
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Quote and escape a string for JSON
+pub fn json_string(text: &str) -> String {
+    let mut output = String::with_capacity(text.len() + 2);
+    output.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            character if character.is_control() => output.push_str(&format!("\\u{:04x}", character as u32)),
+            character => output.push(character)
+        }
+    }
+    output.push('"');
+    output
+}
@@ -0,0 +1,212 @@
+use crate::random;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// A calculation with whole numbers and variables, like `$SCREEN_W/2 - 100`
+#[derive(Debug, Clone)]
+pub enum Expression {
+    Number(i32),
+    Variable(String),
+    Negate(Box<Expression>),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+    /// A number picked at random every time the expression is calculated, from `rand(<lowest>..<highest>)`
+    Random(Box<Expression>, Box<Expression>)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide
+}
+
+/// The characters that can join two numbers in an expression
+pub const OPERATORS: [char; 4] = ['+', '-', '*', '/'];
+
+impl Expression {
+    /// Parse an expression, where `*` and `/` go before `+` and `-`, and parentheses go before both
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser { text, characters: text.char_indices().peekable() };
+        let expression = parser.sum()?;
+        match parser.next_token() {
+            None => Ok(expression),
+            Some((index, _)) => Err(format!("unexpected {:?}", &text[index..]))
+        }
+    }
+
+    /// Calculate the value of the expression, rounding divisions towards zero
+    pub fn evaluate(&self, variables: &HashMap<String, i32>) -> Result<i32, String> {
+        match self {
+            Expression::Number(value) => Ok(*value),
+            Expression::Variable(name) => variables.get(name).copied().ok_or_else(|| format!("unknown variable ${name}")),
+            Expression::Negate(expression) => expression.evaluate(variables)?.checked_neg().ok_or("the result is too large".to_string()),
+            Expression::Binary(left, operator, right) => {
+                let (left, right) = (left.evaluate(variables)?, right.evaluate(variables)?);
+                let result = match operator {
+                    Operator::Add => left.checked_add(right),
+                    Operator::Subtract => left.checked_sub(right),
+                    Operator::Multiply => left.checked_mul(right),
+                    Operator::Divide if right == 0 => return Err("division by zero".to_string()),
+                    Operator::Divide => left.checked_div(right)
+                };
+                result.ok_or("the result is too large".to_string())
+            }
+            Expression::Random(lowest, highest) => {
+                let (lowest, highest) = (lowest.evaluate(variables)?, highest.evaluate(variables)?);
+                if highest < lowest {
+                    return Err(format!("rand({lowest}..{highest}) has no numbers, as {highest} is lower than {lowest}"));
+                }
+                let count = (i64::from(highest) - i64::from(lowest) + 1) as usize;
+                Ok((i64::from(lowest) + random::below(count) as i64) as i32)
+            }
+        }
+    }
+
+    /// Whether the expression picks a random number, so it can't be calculated once and kept
+    pub fn is_random(&self) -> bool {
+        match self {
+            Expression::Number(_) | Expression::Variable(_) => false,
+            Expression::Negate(expression) => expression.is_random(),
+            Expression::Binary(left, _, right) => left.is_random() || right.is_random(),
+            Expression::Random(..) => true
+        }
+    }
+
+    /// Get the names of the variables used in the expression
+    pub fn variables(&self) -> Vec<&str> {
+        match self {
+            Expression::Number(_) => Vec::new(),
+            Expression::Variable(name) => vec![name.as_str()],
+            Expression::Negate(expression) => expression.variables(),
+            Expression::Binary(left, _, right) | Expression::Random(left, right) => [left.variables(), right.variables()].concat()
+        }
+    }
+
+    /// Replace the variables that have a value with their value, leaving the rest to be evaluated later
+    pub fn substitute(self, variables: &HashMap<String, i32>) -> Self {
+        match self {
+            Expression::Variable(name) => match variables.get(&name) {
+                Some(value) => Expression::Number(*value),
+                None => Expression::Variable(name)
+            },
+            Expression::Negate(expression) => Expression::Negate(Box::new(expression.substitute(variables))),
+            Expression::Binary(left, operator, right) => Expression::Binary(Box::new(left.substitute(variables)), operator, Box::new(right.substitute(variables))),
+            Expression::Random(lowest, highest) => Expression::Random(Box::new(lowest.substitute(variables)), Box::new(highest.substitute(variables))),
+            number => number
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    characters: Peekable<CharIndices<'a>>
+}
+
+impl Parser<'_> {
+    /// Skip whitespace, and look at the character after it
+    fn next_token(&mut self) -> Option<(usize, char)> {
+        while self.characters.next_if(|(_, character)| character.is_whitespace()).is_some() {}
+        self.characters.peek().copied()
+    }
+
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut expression = self.product()?;
+        loop {
+            let operator = match self.next_token() {
+                Some((_, '+')) => Operator::Add,
+                Some((_, '-')) => Operator::Subtract,
+                _ => return Ok(expression)
+            };
+            self.characters.next();
+            expression = Expression::Binary(Box::new(expression), operator, Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut expression = self.factor()?;
+        loop {
+            let operator = match self.next_token() {
+                Some((_, '*')) => Operator::Multiply,
+                Some((_, '/')) => Operator::Divide,
+                _ => return Ok(expression)
+            };
+            self.characters.next();
+            expression = Expression::Binary(Box::new(expression), operator, Box::new(self.factor()?));
+        }
+    }
+
+    fn factor(&mut self) -> Result<Expression, String> {
+        match self.next_token() {
+            Some((_, '-')) => {
+                self.characters.next();
+                Ok(Expression::Negate(Box::new(self.factor()?)))
+            }
+            Some((_, '+')) => {
+                self.characters.next();
+                self.factor()
+            }
+            Some((_, '(')) => {
+                self.characters.next();
+                let expression = self.sum()?;
+                match self.next_token() {
+                    Some((_, ')')) => {
+                        self.characters.next();
+                        Ok(expression)
+                    }
+                    _ => Err("missing )".to_string())
+                }
+            }
+            Some((_, '$')) => {
+                self.characters.next();
+                let name = self.word();
+                if name.is_empty() {
+                    return Err("missing variable name after $".to_string());
+                }
+                Ok(Expression::Variable(name.to_string()))
+            }
+            Some((_, character)) if character.is_ascii_digit() => {
+                let digits = self.word();
+                digits.parse().map(Expression::Number).map_err(|error| format!("invalid number {digits:?} ({error})"))
+            }
+            Some((index, character)) if character.is_ascii_alphabetic() => {
+                let text = self.text;
+                if self.word() != "rand" {
+                    return Err(format!("expected a number or a variable, found {:?}", &text[index..]));
+                }
+                self.random()
+            }
+            Some((index, _)) => Err(format!("expected a number or a variable, found {:?}", &self.text[index..])),
+            None => Err("expected a number or a variable".to_string())
+        }
+    }
+
+    /// Parse the range after `rand`, like `(100..200)`
+    fn random(&mut self) -> Result<Expression, String> {
+        if !matches!(self.next_token(), Some((_, '('))) {
+            return Err("expected a range in parentheses after rand, like rand(100..200)".to_string());
+        }
+        self.characters.next();
+        let lowest = self.sum()?;
+        if !matches!(self.next_token(), Some((_, '.'))) || self.characters.next().is_none() || self.characters.next_if(|(_, character)| *character == '.').is_none() {
+            return Err("expected `..` between the lowest and highest number of rand".to_string());
+        }
+        let highest = self.sum()?;
+        match self.next_token() {
+            Some((_, ')')) => {
+                self.characters.next();
+                Ok(Expression::Random(Box::new(lowest), Box::new(highest)))
+            }
+            _ => Err("missing )".to_string())
+        }
+    }
+
+    /// Read letters, digits and underscores, which make up both numbers and variable names
+    fn word(&mut self) -> &str {
+        let start = self.characters.peek().map_or(self.text.len(), |&(index, _)| index);
+        while self.characters.next_if(|(_, character)| character.is_ascii_alphanumeric() || *character == '_').is_some() {}
+        let end = self.characters.peek().map_or(self.text.len(), |&(index, _)| index);
+        &self.text[start..end]
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+/// A frame of an animation, as indices into the palette, row by row from the top left
+pub struct Frame {
+    pub pixels: Vec<u8>,
+    /// How long the frame is shown, in hundredths of a second
+    pub delay: u16
+}
+
+/// The largest code LZW compression in GIF files can use
+const MAX_CODE: u16 = 4095;
+
+/// Write an animated GIF that loops forever
+///
+/// Every frame covers the whole image, and uses the palette of the whole file.
+pub fn encode(width: u16, height: u16, palette: &[[u8; 3]; 256], frames: &[Frame]) -> Vec<u8> {
+    let mut data = b"GIF89a".to_vec();
+    data.extend(width.to_le_bytes());
+    data.extend(height.to_le_bytes());
+    // A global palette of 256 colors with 8 bits per channel, no background color and square pixels
+    data.extend([0xf7, 0, 0]);
+    data.extend(palette.iter().flatten());
+    // The Netscape extension makes the animation loop, 0 meaning forever
+    data.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+    for frame in frames {
+        // A graphic control extension with the delay, leaving the frame in place for the next one to replace
+        data.extend([0x21, 0xf9, 4, 0x04]);
+        data.extend(frame.delay.to_le_bytes());
+        data.extend([0, 0]);
+
+        data.push(0x2c);
+        data.extend([0, 0, 0, 0]);
+        data.extend(width.to_le_bytes());
+        data.extend(height.to_le_bytes());
+        data.push(0);
+
+        data.push(8);
+        for block in compress(&frame.pixels).chunks(255) {
+            data.push(block.len() as u8);
+            data.extend(block);
+        }
+        data.push(0);
+    }
+
+    data.push(0x3b);
+    data
+}
+
+/// Compress palette indices with the variant of LZW GIF files use, starting with 9 bit codes
+fn compress(pixels: &[u8]) -> Vec<u8> {
+    let clear: u16 = 256;
+    let end: u16 = 257;
+    let mut writer = BitWriter::default();
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut size = 9;
+
+    writer.write(clear, size);
+    let Some((&first, rest)) = pixels.split_first() else {
+        writer.write(end, size);
+        return writer.finish();
+    };
+
+    let mut prefix = u16::from(first);
+    for &pixel in rest {
+        if let Some(&code) = table.get(&(prefix, pixel)) {
+            prefix = code;
+            continue;
+        }
+
+        writer.write(prefix, size);
+        // The code size grows once the decoder, which adds every code a step later, can see a code that needs it
+        if next_code > (1 << size) - 1 && size < 12 {
+            size += 1;
+        }
+        if next_code > MAX_CODE {
+            writer.write(clear, size);
+            table.clear();
+            next_code = end + 1;
+            size = 9;
+        } else {
+            table.insert((prefix, pixel), next_code);
+            next_code += 1;
+        }
+        prefix = u16::from(pixel);
+    }
+
+    writer.write(prefix, size);
+    if next_code > (1 << size) - 1 && size < 12 {
+        size += 1;
+    }
+    writer.write(end, size);
+    writer.finish()
+}
+
+/// Packs codes into bytes starting from the lowest bit, like GIF files store them
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= u32::from(code) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
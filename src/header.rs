@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+/// What the comments at the top of a script say about it, like `// @title Daily report`
+#[derive(Debug, Default)]
+pub struct Header {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// The size of the screen the script was written for, in pixels
+    pub resolution: Option<(u32, u32)>,
+    /// How long the script takes according to its author, in milliseconds
+    pub duration: Option<u64>
+}
+
+impl Header {
+    /// Print every field of the header, or that there are none
+    pub fn print(&self) {
+        if self.title.is_none() && self.author.is_none() && self.resolution.is_none() && self.duration.is_none() {
+            println!("The script has no header");
+            return;
+        }
+
+        if let Some(title) = &self.title {
+            println!("Title: {title}");
+        }
+        if let Some(author) = &self.author {
+            println!("Author: {author}");
+        }
+        if let Some((width, height)) = self.resolution {
+            println!("Resolution: {width}x{height}");
+        }
+        if let Some(duration) = self.duration {
+            println!("Duration: {}", crate::notification::format_duration(Duration::from_millis(duration)));
+        }
+    }
+}
+
+/// Get the `// @name value` comments at the top of a script, along with the lines they're on
+///
+/// The header ends at the first line that isn't empty or a comment.
+pub fn comments(content: &str) -> impl Iterator<Item = (i32, &str, &str)> {
+    content.lines().zip(1..)
+        .map(|(line, index)| (index, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map_while(|(index, line)| line.strip_prefix("//").map(|comment| (index, comment.trim())))
+        .filter_map(|(index, comment)| {
+            let field = comment.strip_prefix('@')?;
+            let (name, value) = field.split_once(char::is_whitespace).unwrap_or((field, ""));
+            Some((index, name, value.trim()))
+        })
+}
+
+/// Read the header of a script, or the line, the part of it and the reason a field is invalid
+///
+/// Fields that aren't part of the header, like `@hotkey`, are left to whatever reads them.
+pub fn read(content: &str) -> Result<Header, (i32, &str, String)> {
+    let mut header = Header::default();
+    for (line, name, value) in comments(content) {
+        if matches!(name, "title" | "author" | "resolution" | "duration") && value.is_empty() {
+            return Err((line, name, format!("The @{name} header has no value")));
+        }
+
+        match name {
+            "title" => header.title = Some(value.to_string()),
+            "author" => header.author = Some(value.to_string()),
+            "resolution" => {
+                let resolution = value.split_once('x')
+                    .and_then(|(width, height)| Some((width.trim().parse().ok()?, height.trim().parse().ok()?)))
+                    .filter(|&(width, height)| width > 0 && height > 0);
+                match resolution {
+                    Some(resolution) => header.resolution = Some(resolution),
+                    None => return Err((line, value, format!("Invalid resolution {value:?} (expected a width and a height like 1920x1080)")))
+                }
+            }
+            "duration" => {
+                let duration = if value.contains(':') { crate::parse_clock_time(value) } else { crate::parse_time(value) };
+                match duration {
+                    Ok(duration) => header.duration = Some(duration),
+                    Err(error) => return Err((line, value, format!("Invalid duration {value:?} ({error})")))
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(header)
+}
@@ -0,0 +1,179 @@
+/// The lengths the length codes from 257 stand for, and how many extra bits they're followed by
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// The distances the distance codes stand for, and how many extra bits they're followed by
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// The order the lengths of the code length codes are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompress a DEFLATE stream, like the data of a PNG image or a file in a ZIP archive
+///
+/// A few bytes of DEFLATE data can stand for gigabytes, so this stops as soon as the output would get bigger than
+/// `limit` bytes.
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let mut reader = BitReader { data, position: 0 };
+    let mut output = Output { bytes: Vec::new(), limit };
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                // Stored blocks start at the next byte, with their length and its complement
+                reader.position = reader.position.div_ceil(8) * 8;
+                let length = reader.bits(16)? as usize;
+                if reader.bits(16)? as usize != !length & 0xffff {
+                    return Err("the compressed data is corrupted".to_string());
+                }
+                let start = reader.position / 8;
+                output.reserve(length)?;
+                output.bytes.extend_from_slice(reader.data.get(start..start + length).ok_or("the compressed data is cut off")?);
+                reader.position += length * 8;
+            }
+            1 => {
+                let mut lengths = [8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                inflate_block(&mut reader, &mut output, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            _ => return Err("the compressed data is corrupted".to_string())
+        }
+        if last {
+            return Ok(output.bytes);
+        }
+    }
+}
+
+fn read_dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_length_lengths = [0; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or("the compressed data is corrupted")?, reader.bits(2)? + 3),
+            17 => (0, reader.bits(3)? + 3),
+            _ => (0, reader.bits(7)? + 11)
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err("the compressed data is corrupted".to_string());
+    }
+    Ok((Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..])))
+}
+
+fn inflate_block(reader: &mut BitReader, output: &mut Output, literals: &Huffman, distances: &Huffman) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => {
+                output.reserve(1)?;
+                output.bytes.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                let length = *LENGTH_BASE.get(index).ok_or("the compressed data is corrupted")? as usize + reader.bits(LENGTH_EXTRA[index])? as usize;
+                let index = distances.decode(reader)? as usize;
+                let distance = *DISTANCE_BASE.get(index).ok_or("the compressed data is corrupted")? as usize + reader.bits(DISTANCE_EXTRA[index])? as usize;
+                if distance > output.bytes.len() {
+                    return Err("the compressed data is corrupted".to_string());
+                }
+
+                // The copied bytes can overlap the ones being written, which repeats them
+                output.reserve(length)?;
+                let start = output.bytes.len() - distance;
+                for index in start..start + length {
+                    output.bytes.push(output.bytes[index]);
+                }
+            }
+        }
+    }
+}
+
+/// The decompressed bytes, which can't grow past the limit
+struct Output {
+    bytes: Vec<u8>,
+    limit: usize
+}
+
+impl Output {
+    /// Check that `length` more bytes still fit, before writing them
+    fn reserve(&self, length: usize) -> Result<(), String> {
+        if length > self.limit - self.bytes.len() {
+            return Err("the decompressed data is bigger than expected".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Reads the bits of DEFLATE data, starting from the lowest bit of every byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// The number of bits read so far
+    position: usize
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u8) -> Result<u32, String> {
+        let mut value = 0;
+        for bit in 0..count {
+            let byte = self.data.get(self.position / 8).ok_or("the compressed data is cut off")?;
+            value |= ((byte >> (self.position % 8)) as u32 & 1) << bit;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// A canonical Huffman code, as DEFLATE describes codes only by the length of the code of every symbol
+struct Huffman {
+    /// How many symbols have codes of every length
+    counts: [u16; 16],
+    /// The symbols with codes, ordered by the length of their code
+    symbols: Vec<u16>
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16).filter(|&symbol| lengths[symbol as usize] > 0).collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        // Codes of the same length are consecutive numbers, so each length covers a range of codes
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("the compressed data is corrupted".to_string())
+    }
+}
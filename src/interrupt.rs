@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the user has asked to abort the run
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Catch Ctrl+C, so the run can be aborted cleanly instead of exiting immediately
+///
+/// Pressing Ctrl+C a second time exits immediately, in case the run is stuck.
+pub fn install() {
+    platform::install();
+}
+
+/// Whether the run has been asked to abort, either through Ctrl+C or something like the tray icon
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
+
+/// Ask for the run to be aborted, returning whether it already was
+pub fn request() -> bool {
+    REQUESTED.swap(true, Ordering::Relaxed)
+}
+
+#[cfg(unix)]
+mod platform {
+    extern "C" fn handle(_signal: libc::c_int) {
+        if super::request() {
+            // SAFETY: _exit is async-signal-safe
+            unsafe { libc::_exit(130) };
+        }
+    }
+
+    pub fn install() {
+        // SAFETY: The handler only touches an atomic and calls async-signal-safe functions
+        unsafe {
+            libc::signal(libc::SIGINT, handle as *const () as libc::sighandler_t);
+            libc::signal(libc::SIGTERM, handle as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows_sys::core::BOOL;
+    use windows_sys::Win32::System::Console::{SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_C_EVENT};
+
+    unsafe extern "system" fn handle(event: u32) -> BOOL {
+        if event != CTRL_C_EVENT && event != CTRL_BREAK_EVENT {
+            return 0;
+        }
+
+        // Returning false lets the default handler exit the process
+        if super::request() { 0 } else { 1 }
+    }
+
+    pub fn install() {
+        // SAFETY: The handler is a valid function for the whole lifetime of the process
+        unsafe { SetConsoleCtrlHandler(Some(handle), 1) };
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    pub fn install() {}
+}
@@ -0,0 +1,181 @@
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+use x11 as platform;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "windows")]
+use win32 as platform;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub fn code(_key: enigo::Key) -> Result<u32, String> {
+        Err("Reading the keyboard is not supported on this platform".to_string())
+    }
+
+    pub fn held_keys() -> Result<Vec<u32>, String> {
+        Err("Reading the keyboard is not supported on this platform".to_string())
+    }
+
+    /// The keyboard layout can't be looked up here, so symbols are pressed as the key they're on
+    pub fn needs_shift(_key: enigo::Key) -> bool {
+        false
+    }
+}
+
+use enigo::{Direction, Key};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Everything Shift is held down for, by who holds it: keys that need Shift, and Shift itself when a script holds it
+///
+/// There's only one Shift for the whole system, so this is shared by every backend in the process, like the ones of
+/// scripts running in parallel and the threads of `holdkey`.
+static SHIFT_HOLDERS: Mutex<Vec<(Owner, Key)>> = Mutex::new(Vec::new());
+
+/// How often the keyboard is checked while waiting for a key
+pub const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The keys for changing the brightness of the screen, which enigo only names on macOS, so they're sent as their X
+/// keysyms on Linux
+#[cfg(target_os = "linux")]
+pub const BRIGHTNESS_UP: Key = Key::Other(0x1008_ff02);
+#[cfg(target_os = "linux")]
+pub const BRIGHTNESS_DOWN: Key = Key::Other(0x1008_ff03);
+#[cfg(target_os = "macos")]
+pub const BRIGHTNESS_UP: Key = Key::BrightnessUp;
+#[cfg(target_os = "macos")]
+pub const BRIGHTNESS_DOWN: Key = Key::BrightnessDown;
+
+/// Get the code of a key on the current keyboard layout, which is what `held_keys` returns when it's held
+pub fn code(key: Key) -> Result<u32, String> {
+    platform::code(key)
+}
+
+/// Get the codes of all keys on the physical keyboard that are held down
+pub fn held_keys() -> Result<Vec<u32>, String> {
+    platform::held_keys()
+}
+
+/// Whether a symbol has to be pressed along with Shift, like `!`, because the platform only presses the key it's on
+pub fn needs_shift(key: Key) -> bool {
+    platform::needs_shift(key)
+}
+
+/// Something that holds Shift down, which is every script, so Shift is only released once none of them need it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Owner(usize);
+
+impl Owner {
+    /// Get an owner that's different from all others
+    pub fn next() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        Owner(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Press or release a key with `send`, along with Shift when `shifted` is set or the key is an uppercase letter
+///
+/// Shift is pressed with the first key that needs it and released after the last one, so releasing one of several
+/// symbols doesn't release Shift for the others. Holding Shift with `keydown shift` counts as needing it too, so a
+/// script releasing Shift doesn't release it under the symbols another one still holds. All threads of a script, like
+/// the ones of `holdkey`, are the same owner.
+pub fn with_shift<E>(owner: Owner, key: Key, shifted: bool, direction: Direction, mut send: impl FnMut(Key, Direction) -> Result<(), E>) -> Result<(), E> {
+    // Uppercase letters are the key of the lowercase letter with Shift, on every layout
+    let (key, shifted) = match key {
+        Key::Unicode(character) if character.is_ascii_uppercase() => (Key::Unicode(character.to_ascii_lowercase()), true),
+        _ => (key, shifted || key == Key::Shift)
+    };
+    if !shifted {
+        return send(key, direction);
+    }
+
+    let (press_shift, send_key, release_shift) = {
+        let mut holders = SHIFT_HOLDERS.lock().unwrap_or_else(|error| error.into_inner());
+        let idle = holders.is_empty();
+        let holds = |holders: &Vec<(Owner, Key)>| holders.contains(&(owner, key));
+        let held_by_others = |holders: &Vec<(Owner, Key)>| holders.iter().any(|&(holder, held)| held == key && holder != owner);
+        if key == Key::Shift {
+            // Shift itself is only sent when nothing else holds it down
+            let send = match direction {
+                Direction::Press => {
+                    if !holds(&holders) {
+                        holders.push((owner, key));
+                    }
+                    idle
+                }
+                Direction::Release => {
+                    holders.retain(|holder| *holder != (owner, key));
+                    holders.is_empty()
+                }
+                Direction::Click => idle
+            };
+            (false, send, false)
+        } else {
+            match direction {
+                // Pressing a key that's already held is a repeat, which Shift is already down for
+                Direction::Press => {
+                    if !holds(&holders) {
+                        holders.push((owner, key));
+                    }
+                    (idle, true, false)
+                }
+                // A key that another script holds as well stays down until that one releases it
+                Direction::Release => {
+                    let held = holds(&holders);
+                    holders.retain(|holder| *holder != (owner, key));
+                    (false, !held_by_others(&holders), held && holders.is_empty())
+                }
+                Direction::Click => (idle, true, idle)
+            }
+        }
+    };
+
+    if press_shift {
+        send(Key::Shift, Direction::Press)?;
+    }
+    if send_key {
+        send(key, direction)?;
+    }
+    if release_shift {
+        send(Key::Shift, Direction::Release)?;
+    }
+    Ok(())
+}
+
+/// Wait until a key on the physical keyboard is pressed and released again, or any key if none is given
+///
+/// Keys that are already held when waiting starts, like keys held by the script, only count once they're pressed again.
+/// Returns `false` if `should_stop` returns `true` before the key is pressed.
+pub fn wait_for_press(key: Option<Key>, should_stop: impl Fn() -> bool) -> Result<bool, String> {
+    let code = key.map(platform::code).transpose()?;
+    let mut ignored = platform::held_keys()?;
+
+    let pressed = loop {
+        if should_stop() {
+            return Ok(false);
+        }
+
+        let held = platform::held_keys()?;
+        let pressed: Vec<u32> = held.iter().copied().filter(|held| !ignored.contains(held) && code.is_none_or(|code| *held == code)).collect();
+        if !pressed.is_empty() {
+            break pressed;
+        }
+
+        ignored.retain(|ignored| held.contains(ignored));
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    // Wait for the key to be released, so it isn't mixed into the keys the script presses next
+    while platform::held_keys()?.iter().any(|held| pressed.contains(held)) {
+        if should_stop() {
+            return Ok(false);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    Ok(true)
+}
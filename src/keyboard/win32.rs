@@ -0,0 +1,29 @@
+use crate::window::virtual_key;
+use enigo::Key;
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VkKeyScanW};
+
+/// Get the virtual key code of a key
+pub fn code(key: Key) -> Result<u32, String> {
+    virtual_key(key).map(u32::from).ok_or(format!("{key:?} isn't on this keyboard layout"))
+}
+
+/// Get the virtual key codes of all keys that are held down
+pub fn held_keys() -> Result<Vec<u32>, String> {
+    // Virtual keys below 0x08 are mouse buttons
+    // SAFETY: GetAsyncKeyState has no safety requirements
+    let held = (0x08..=0xfe).filter(|&key| unsafe { GetAsyncKeyState(key) } as u16 & 0x8000 != 0).map(|key| key as u32).collect();
+    Ok(held)
+}
+
+/// Whether a character is typed with Shift on the current keyboard layout, as keys are pressed by their virtual key
+pub fn needs_shift(key: Key) -> bool {
+    let Key::Unicode(character) = key else {
+        return false;
+    };
+    let mut units = [0u16; 2];
+    character.encode_utf16(&mut units);
+    // The high byte is the shift state, or -1 along with the low byte if the character can't be typed on the layout
+    // SAFETY: VkKeyScanW has no safety requirements
+    let result = unsafe { VkKeyScanW(units[0]) };
+    result != -1 && result >> 8 & 1 != 0
+}
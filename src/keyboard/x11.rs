@@ -0,0 +1,52 @@
+use crate::window::keysym;
+use enigo::Key;
+use std::sync::OnceLock;
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::xproto::ConnectionExt;
+use x11rb::rust_connection::RustConnection;
+
+/// The connection to the X server, kept open as the keyboard is checked many times in a row
+static CONNECTION: OnceLock<Result<RustConnection, String>> = OnceLock::new();
+
+fn connection() -> Result<&'static RustConnection, String> {
+    CONNECTION
+        .get_or_init(|| x11rb::connect(None).map(|(connection, _)| connection).map_err(|error| format!("Failed to connect to X server: {error}")))
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+/// Get the key code of a key on the current keyboard layout
+pub fn code(key: Key) -> Result<u32, String> {
+    let connection = connection()?;
+    let min_keycode = connection.setup().min_keycode;
+    let count = connection.setup().max_keycode - min_keycode + 1;
+    let mapping = connection.get_keyboard_mapping(min_keycode, count)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to get keyboard mapping: {error}"))?;
+
+    let keysym = keysym(key).ok_or(format!("{key:?} can't be read from the keyboard"))?;
+    let index = mapping.keysyms.iter().position(|&candidate| candidate == keysym).ok_or(format!("{key:?} isn't on this keyboard layout"))?;
+    Ok(u32::from(min_keycode) + (index / mapping.keysyms_per_keycode as usize) as u32)
+}
+
+/// Get the key codes of all keys that are held down
+///
+/// This only works on X11, as Wayland doesn't let applications read the keyboard when they don't have focus.
+pub fn held_keys() -> Result<Vec<u32>, String> {
+    let keymap = connection()?.query_keymap()
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to read the keyboard: {error}"))?;
+
+    // Every key code has a bit, starting from the lowest bit of the first byte
+    let held = (0..256).filter(|&code| keymap.keys[code / 8] & (1 << (code % 8)) != 0).map(|code| code as u32).collect();
+    Ok(held)
+}
+
+/// XTest and the portal find the key and Shift for a symbol themselves, so Shift is only added for uppercase letters,
+/// which every backend does
+pub fn needs_shift(_key: Key) -> bool {
+    false
+}
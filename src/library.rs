@@ -0,0 +1,171 @@
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+use x11 as platform;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "windows")]
+use win32 as platform;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub fn listen(_hotkeys: &[super::Hotkey], _on_press: &mut dyn FnMut(usize)) -> Result<(), String> {
+        Err("Global hotkeys are not supported on this platform".to_string())
+    }
+}
+
+use crate::header;
+use enigo::Key;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+
+/// How long to wait for the hotkey to be released before running the script anyway
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+const RELEASE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// A key combination, like `ctrl+alt+1`
+#[derive(Clone)]
+pub struct Hotkey {
+    pub control: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+    pub key: Key,
+    /// The combination as it was written in the script
+    text: String
+}
+
+impl Hotkey {
+    /// Whether two hotkeys are the same combination, even if they're written differently
+    fn same_combination(&self, other: &Hotkey) -> bool {
+        (self.control, self.alt, self.shift, self.meta, self.key) == (other.control, other.alt, other.shift, other.meta, other.key)
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.text)
+    }
+}
+
+/// A script in the library, along with the hotkey that runs it
+struct Binding {
+    hotkey: Hotkey,
+    path: PathBuf,
+    /// The process running the script, if it has been started
+    child: Option<Child>
+}
+
+/// Bind every script in a directory with a `@hotkey` header to its hotkey, and run it when the hotkey is pressed
+///
+/// This keeps running until the process is stopped. The arguments are passed to TATATA for every script it runs.
+pub fn run(directory: &Path, arguments: &[String]) -> Result<(), String> {
+    let mut bindings = scan(directory)?;
+    if bindings.is_empty() {
+        return Err(format!("No scripts with a @hotkey header found in {}", directory.display()));
+    }
+
+    for binding in &bindings {
+        log::info!("{}: {}", binding.hotkey, binding.path.display());
+    }
+
+    let hotkeys: Vec<Hotkey> = bindings.iter().map(|binding| binding.hotkey.clone()).collect();
+    platform::listen(&hotkeys, &mut |index| launch(&mut bindings[index], arguments))
+}
+
+/// Find all scripts in a directory with a `@hotkey` header
+fn scan(directory: &Path) -> Result<Vec<Binding>, String> {
+    let entries = std::fs::read_dir(directory).map_err(|error| format!("Couldn't read {}: {error}", directory.display()))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "tatata"))
+        .collect();
+    paths.sort();
+
+    let mut bindings: Vec<Binding> = Vec::new();
+    for path in paths {
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) => {
+                log::warn!("Skipping {}: {error}", path.display());
+                continue;
+            }
+        };
+
+        let hotkey = match read_header(&content) {
+            Some(Ok(hotkey)) => hotkey,
+            Some(Err(error)) => {
+                log::warn!("Skipping {}: Invalid @hotkey ({error})", path.display());
+                continue;
+            }
+            None => continue
+        };
+
+        if let Some(existing) = bindings.iter().find(|binding| binding.hotkey.same_combination(&hotkey)) {
+            log::warn!("Skipping {}: {hotkey} is already bound to {}", path.display(), existing.path.display());
+            continue;
+        }
+
+        bindings.push(Binding { hotkey, path, child: None });
+    }
+
+    Ok(bindings)
+}
+
+/// Find the `// @hotkey` comment in the comments at the top of a script
+fn read_header(content: &str) -> Option<Result<Hotkey, String>> {
+    header::comments(content).find(|(_, name, _)| *name == "hotkey").map(|(_, _, combination)| parse_hotkey(combination))
+}
+
+/// Parse a key combination, like `ctrl+alt+1`
+fn parse_hotkey(text: &str) -> Result<Hotkey, String> {
+    let (modifiers, key) = text.rsplit_once('+').unwrap_or(("", text));
+    if key.is_empty() {
+        return Err("no key given".to_string());
+    }
+
+    let key = crate::parse_key(key.trim())?;
+    if matches!(key, Key::Control | Key::Alt | Key::Shift | Key::Meta) {
+        return Err("the last key can't be a modifier".to_string());
+    }
+
+    let mut hotkey = Hotkey { control: false, alt: false, shift: false, meta: false, key, text: text.to_lowercase() };
+    for modifier in modifiers.split('+').map(str::trim).filter(|modifier| !modifier.is_empty()) {
+        match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => hotkey.control = true,
+            "alt" => hotkey.alt = true,
+            "shift" => hotkey.shift = true,
+            "super" => hotkey.meta = true,
+            _ => return Err(format!("unknown modifier {modifier:?}"))
+        }
+    }
+
+    Ok(hotkey)
+}
+
+/// Run the script of a binding in a new TATATA process, unless it's already running
+fn launch(binding: &mut Binding, arguments: &[String]) {
+    if let Some(child) = &mut binding.child {
+        if matches!(child.try_wait(), Ok(None)) {
+            log::info!("{} is already running", binding.path.display());
+            return;
+        }
+    }
+
+    // Running the script in a separate process keeps the library running even if the script fails to parse
+    let executable = match std::env::current_exe() {
+        Ok(executable) => executable,
+        Err(error) => {
+            log::error!("Couldn't find the TATATA executable: {error}");
+            return;
+        }
+    };
+
+    log::info!("Running {}", binding.path.display());
+    match Command::new(executable).args(arguments).arg(&binding.path).spawn() {
+        Ok(child) => binding.child = Some(child),
+        Err(error) => log::error!("Failed to run {}: {error}", binding.path.display())
+    }
+}
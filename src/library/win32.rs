@@ -0,0 +1,54 @@
+use super::Hotkey;
+use crate::window::virtual_key;
+use std::ptr::null_mut;
+use std::time::{Duration, Instant};
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, RegisterHotKey, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{GetMessageW, MSG, WM_HOTKEY};
+
+/// Register the hotkeys with Windows, and call the function with the index of every hotkey that is pressed
+pub fn listen(hotkeys: &[Hotkey], on_press: &mut dyn FnMut(usize)) -> Result<(), String> {
+    for (index, hotkey) in hotkeys.iter().enumerate() {
+        let key = virtual_key(hotkey.key).ok_or(format!("{hotkey} can't be bound on this keyboard layout"))?;
+
+        let mut modifiers = MOD_NOREPEAT;
+        for (enabled, modifier) in [(hotkey.control, MOD_CONTROL), (hotkey.alt, MOD_ALT), (hotkey.shift, MOD_SHIFT), (hotkey.meta, MOD_WIN)] {
+            if enabled {
+                modifiers |= modifier;
+            }
+        }
+
+        // The ID of each hotkey is its index, offset by one as IDs have to be positive
+        // SAFETY: Registering a hotkey without a window has no safety requirements
+        if unsafe { RegisterHotKey(null_mut(), index as i32 + 1, modifiers, key as u32) } == 0 {
+            return Err(format!("{hotkey} is already bound by another application"));
+        }
+    }
+
+    // SAFETY: MSG is a plain C struct, for which all zeroes is a valid value
+    let mut message: MSG = unsafe { std::mem::zeroed() };
+    // SAFETY: The message is valid for the duration of the call
+    while unsafe { GetMessageW(&mut message, null_mut(), 0, 0) } > 0 {
+        if message.message == WM_HOTKEY {
+            wait_for_release();
+            on_press(message.wParam - 1);
+        }
+    }
+
+    Ok(())
+}
+
+/// Wait until no keys are held, so the held hotkey doesn't get mixed into the keys the script presses
+fn wait_for_release() {
+    let start = Instant::now();
+    while start.elapsed() < super::RELEASE_TIMEOUT {
+        // Virtual keys below 0x08 are mouse buttons
+        // SAFETY: GetAsyncKeyState has no safety requirements
+        let held = (0x08..=0xfe).any(|key| unsafe { GetAsyncKeyState(key) } as u16 & 0x8000 != 0);
+        if !held {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,81 @@
+use super::Hotkey;
+use crate::window::keysym;
+use std::time::{Duration, Instant};
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::xproto::{ConnectionExt, GrabMode, ModMask};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+/// Grab the hotkeys on the root window, and call the function with the index of every hotkey that is pressed
+///
+/// This only works on X11, as Wayland doesn't let applications grab keys.
+pub fn listen(hotkeys: &[Hotkey], on_press: &mut dyn FnMut(usize)) -> Result<(), String> {
+    let (connection, screen) = x11rb::connect(None).map_err(|error| format!("Failed to connect to X server: {error}"))?;
+    let root = connection.setup().roots[screen].root;
+
+    // Get the keyboard mapping, used to find the key code for each key
+    let min_keycode = connection.setup().min_keycode;
+    let count = connection.setup().max_keycode - min_keycode + 1;
+    let mapping = connection.get_keyboard_mapping(min_keycode, count)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to get keyboard mapping: {error}"))?;
+    let keycode = |keysym: u32| {
+        let index = mapping.keysyms.iter().position(|&candidate| candidate == keysym)?;
+        Some(min_keycode + (index / mapping.keysyms_per_keycode as usize) as u8)
+    };
+
+    let mut grabs = Vec::new();
+    for (index, hotkey) in hotkeys.iter().enumerate() {
+        let keycode = keysym(hotkey.key).and_then(keycode).ok_or(format!("{hotkey} can't be bound on this keyboard layout"))?;
+        let modifiers = modifier_mask(hotkey);
+
+        // Caps Lock and Num Lock count as modifiers, so the key is grabbed with every combination of them as well
+        for locks in [ModMask::from(0u16), ModMask::LOCK, ModMask::M2, ModMask::LOCK | ModMask::M2] {
+            connection.grab_key(false, root, modifiers | locks, keycode, GrabMode::ASYNC, GrabMode::ASYNC)
+                .map_err(ReplyError::from)
+                .and_then(|cookie| cookie.check())
+                .map_err(|_| format!("{hotkey} is already bound by another application"))?;
+        }
+
+        grabs.push((keycode, modifiers, index));
+    }
+
+    let relevant = ModMask::CONTROL | ModMask::SHIFT | ModMask::M1 | ModMask::M4;
+    loop {
+        let event = connection.wait_for_event().map_err(|error| format!("Lost connection to X server: {error}"))?;
+        let Event::KeyPress(event) = event else {
+            continue;
+        };
+
+        let state = ModMask::from(u16::from(event.state)) & relevant;
+        if let Some(&(_, _, index)) = grabs.iter().find(|&&(keycode, modifiers, _)| keycode == event.detail && modifiers == state) {
+            wait_for_release(&connection);
+            on_press(index);
+        }
+    }
+}
+
+fn modifier_mask(hotkey: &Hotkey) -> ModMask {
+    let mut mask = ModMask::from(0u16);
+    for (enabled, modifier) in [(hotkey.control, ModMask::CONTROL), (hotkey.alt, ModMask::M1), (hotkey.shift, ModMask::SHIFT), (hotkey.meta, ModMask::M4)] {
+        if enabled {
+            mask |= modifier;
+        }
+    }
+
+    mask
+}
+
+/// Wait until no keys are held, so the held hotkey doesn't get mixed into the keys the script presses
+fn wait_for_release(connection: &RustConnection) {
+    let start = Instant::now();
+    while start.elapsed() < super::RELEASE_TIMEOUT {
+        let keymap = connection.query_keymap().map_err(ReplyError::from).and_then(|cookie| cookie.reply());
+        match keymap {
+            Ok(keymap) if keymap.keys.iter().any(|&byte| byte != 0) => std::thread::sleep(Duration::from_millis(10)),
+            _ => return
+        }
+    }
+}
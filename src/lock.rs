@@ -0,0 +1,90 @@
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "windows")]
+use win32 as platform;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub struct Detector;
+
+    impl Detector {
+        pub fn new() -> Result<Self, String> {
+            Err("Detecting a locked screen is not supported on this platform".to_string())
+        }
+
+        pub fn is_locked(&mut self) -> Result<bool, String> {
+            Ok(false)
+        }
+    }
+}
+
+use crate::{control, interrupt};
+use clap::ValueEnum;
+use log::{info, warn};
+use std::thread;
+use std::time::Duration;
+
+/// How often the screen is checked for being locked
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What happens to the run when the screen is locked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OnLock {
+    /// Pause the run until the screen is unlocked
+    Pause,
+    /// Abort the run, running its teardown
+    Abort,
+    /// Keep running, sending input to the lock screen
+    Ignore
+}
+
+/// Check whether the screen gets locked on a separate thread, pausing or aborting the run when it does
+///
+/// The screen counts as locked when the lock screen or screensaver is shown, when another user is switched to, and when
+/// the display is asleep.
+pub fn watch(on_lock: OnLock) {
+    if on_lock == OnLock::Ignore {
+        return;
+    }
+    let mut detector = match platform::Detector::new() {
+        Ok(detector) => detector,
+        Err(error) => {
+            warn!("Can't tell when the screen is locked, so the run continues while it is: {error}");
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        let mut was_locked = false;
+        loop {
+            let locked = match detector.is_locked() {
+                Ok(locked) => locked,
+                Err(error) => {
+                    warn!("Stopped checking whether the screen is locked: {error}");
+                    control::set_locked(false);
+                    return;
+                }
+            };
+
+            if locked != was_locked {
+                was_locked = locked;
+                match (locked, on_lock) {
+                    (true, OnLock::Abort) => {
+                        warn!("The screen was locked, aborting");
+                        interrupt::request();
+                        return;
+                    }
+                    (true, _) => warn!("The screen was locked, pausing until it's unlocked"),
+                    (false, _) => info!("The screen was unlocked, resuming")
+                }
+                control::set_locked(locked);
+            }
+            thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
@@ -0,0 +1,73 @@
+use crate::dbus::{Connection, Value};
+use x11rb::errors::ReplyError;
+use x11rb::protocol::dpms::{ConnectionExt, DPMSMode};
+use x11rb::rust_connection::RustConnection;
+
+/// The services that tell whether the screensaver or lock screen is active, as GNOME has its own
+const SCREENSAVERS: &[(&str, &str)] = &[
+    ("org.freedesktop.ScreenSaver", "/org/freedesktop/ScreenSaver"),
+    ("org.gnome.ScreenSaver", "/org/gnome/ScreenSaver")
+];
+
+/// Asks the screensaver of the desktop whether it's active, and the X server whether the display is asleep
+///
+/// Switching to another user shows the lock screen of the desktop, so it counts as the screensaver being active.
+pub struct Detector {
+    /// The session bus and the screensaver service on it that answered, if any did
+    screensaver: Option<(Connection, &'static str, &'static str)>,
+    /// The X server, if it supports DPMS
+    display: Option<RustConnection>
+}
+
+impl Detector {
+    pub fn new() -> Result<Self, String> {
+        let mut detector = Detector { screensaver: None, display: None };
+
+        let mut errors = Vec::new();
+        match Connection::session() {
+            Ok(mut connection) => match SCREENSAVERS.iter().find(|(name, path)| screensaver_active(&mut connection, name, path).is_ok()) {
+                Some(&(name, path)) => detector.screensaver = Some((connection, name, path)),
+                None => errors.push("no screensaver service is running".to_string())
+            },
+            Err(error) => errors.push(error)
+        }
+        match x11rb::connect(None) {
+            Ok((connection, _)) => match display_asleep(&connection) {
+                Ok(_) => detector.display = Some(connection),
+                Err(error) => errors.push(error)
+            },
+            Err(error) => errors.push(format!("Failed to connect to X server: {error}"))
+        }
+
+        if detector.screensaver.is_none() && detector.display.is_none() {
+            return Err(errors.join(", "));
+        }
+        Ok(detector)
+    }
+
+    pub fn is_locked(&mut self) -> Result<bool, String> {
+        if let Some((connection, name, path)) = &mut self.screensaver {
+            if screensaver_active(connection, name, path)? {
+                return Ok(true);
+            }
+        }
+        match &self.display {
+            Some(connection) => display_asleep(connection),
+            None => Ok(false)
+        }
+    }
+}
+
+fn screensaver_active(connection: &mut Connection, name: &str, path: &str) -> Result<bool, String> {
+    match connection.call(name, path, name, "GetActive", &[])?.first() {
+        Some(Value::Bool(active)) => Ok(*active),
+        _ => Err(format!("{name}.GetActive didn't return whether it's active"))
+    }
+}
+
+/// Check whether DPMS has put the display into standby, suspend or off
+fn display_asleep(connection: &RustConnection) -> Result<bool, String> {
+    let info = connection.dpms_info().map_err(ReplyError::from).and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to get the power state of the display: {error}"))?;
+    Ok(info.state && info.power_level != DPMSMode::ON)
+}
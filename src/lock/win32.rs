@@ -0,0 +1,25 @@
+use windows_sys::Win32::System::StationsAndDesktops::{CloseDesktop, OpenInputDesktop, SwitchDesktop, DESKTOP_SWITCHDESKTOP};
+
+/// Checks whether the desktop that receives input is the one of this session
+///
+/// The lock screen and other users' sessions run on other desktops, which can't be switched to while they're shown.
+pub struct Detector;
+
+impl Detector {
+    pub fn new() -> Result<Self, String> {
+        Ok(Detector)
+    }
+
+    pub fn is_locked(&mut self) -> Result<bool, String> {
+        // SAFETY: The desktop is closed right after checking it
+        unsafe {
+            let desktop = OpenInputDesktop(0, 0, DESKTOP_SWITCHDESKTOP);
+            if desktop.is_null() {
+                return Ok(true);
+            }
+            let switchable = SwitchDesktop(desktop) != 0;
+            CloseDesktop(desktop);
+            Ok(!switchable)
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::diagnostic::{self, ErrorFormat};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+use std::thread;
+
+/// Prints messages from TATATA itself, ignoring messages from dependencies like enigo
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+/// The status line at the bottom of the terminal, which is kept below the messages printed while it's shown
+static STATUS: Mutex<String> = Mutex::new(String::new());
+
+/// Clears the line the cursor is on, for replacing the status line
+const CLEAR_LINE: &str = "\r\x1b[K";
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target().starts_with(env!("CARGO_CRATE_NAME")) && metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // Scripts run with `--parallel` run on threads named after them, so their messages say which script they're from
+        let message = match thread::current().name() {
+            Some(name) if name != "main" => format!("[{name}] {}", record.args()),
+            _ => record.args().to_string()
+        };
+
+        // The status line is moved below the message, instead of the message being written over it
+        let status = STATUS.lock().unwrap_or_else(|error| error.into_inner());
+        if !status.is_empty() {
+            print!("{CLEAR_LINE}");
+        }
+
+        // Errors and warnings go to stderr, so they aren't mixed into the output of a dry run when it's redirected
+        match record.level() {
+            Level::Error | Level::Warn if diagnostic::format() == ErrorFormat::Json => {
+                let level = if record.level() == Level::Error { "error" } else { "warning" };
+                eprintln!("{{\"level\":\"{level}\",\"message\":{}}}", diagnostic::json_string(&message));
+            }
+            Level::Error | Level::Warn => eprintln!("{message}"),
+            Level::Info | Level::Debug => println!("{message}"),
+            Level::Trace => println!("[trace] {message}")
+        }
+
+        if !status.is_empty() {
+            print!("{status}");
+            let _ = io::stdout().flush();
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Start printing messages up to the given level
+pub fn init(level: LevelFilter) {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
+
+/// Get the level to log at from the number of `-v` flags, and whether `-q` was passed
+pub fn level(verbosity: u8, quiet: bool) -> LevelFilter {
+    match (quiet, verbosity) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace
+    }
+}
+
+/// Show a line below the messages that's replaced every time it changes, like the time until the next action
+///
+/// Nothing is shown when the output isn't a terminal, so redirected output doesn't fill up with every update.
+pub fn set_status(text: String) {
+    if !io::stdout().is_terminal() {
+        return;
+    }
+    let mut status = STATUS.lock().unwrap_or_else(|error| error.into_inner());
+    print!("{CLEAR_LINE}{text}");
+    let _ = io::stdout().flush();
+    *status = text;
+}
+
+/// Remove the status line, if one is shown
+pub fn clear_status() {
+    let mut status = STATUS.lock().unwrap_or_else(|error| error.into_inner());
+    if !status.is_empty() {
+        print!("{CLEAR_LINE}");
+        let _ = io::stdout().flush();
+        status.clear();
+    }
+}
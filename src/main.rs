@@ -1,533 +1,5132 @@
-use clap::Parser;
-use enigo::{Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse, Settings};
-use std::path::PathBuf;
-use std::{process, thread};
+mod backend;
+mod balance;
+mod block;
+mod bundle;
+mod clipboard;
+mod condition;
+mod control;
+mod diagnostic;
+mod expression;
+#[cfg(target_os = "linux")]
+mod dbus;
+mod gif;
+mod header;
+mod inflate;
+mod interrupt;
+mod keyboard;
+mod library;
+mod lock;
+mod logging;
+mod monitors;
+mod motion;
+mod mouse;
+mod notification;
+mod ocr;
+mod png;
+mod processes;
+mod prompt;
+mod render;
+mod random;
+mod remote;
+mod rendezvous;
+mod repeat;
+mod report;
+mod screen;
+mod selftest;
+mod sha256;
+mod speech;
+mod stdlib;
+mod suite;
+mod template;
+mod trace;
+mod tray;
+mod typing;
+mod window;
+
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use condition::Condition;
+use backend::{Backend, BackendConfig, BackendKind};
+use balance::{Balance, Output};
+use diagnostic::{Diagnostic, ErrorFormat, ErrorKind};
+use expression::Expression;
+use header::Header;
+use enigo::{Axis, Button, Coordinate, Direction, Key};
+use lock::OnLock;
+use log::{debug, error, info, trace, warn};
+use monitors::Monitor;
+use processes::Process;
+use random::Pattern;
+use repeat::{KeyRepeat, RepeatRate};
+use report::{Outcome, Report};
+use screen::Color;
+use template::Template;
+use typing::Keystroke;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Barrier};
+use std::{fs, process, thread};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// The names of all actions, for suggesting corrections to misspelled ones
+const ACTION_NAMES: &[&str] = &["mousemove", "mousetrace", "mousedown", "mouseup", "click", "doubleclick", "tripleclick", "scroll", "keydown", "keyup", "keypress", "holdkey", "release", "text", "say", "log", "turbo", "waitkey", "wait", "getmouse", "movewindow", "resizewindow", "maximize", "minimize", "closewindow", "killprocess", "waitprocess", "waitwindow", "waitpixel", "waitimage", "exec", "abort", "typerandom", "getclipboard", "waittext", "readtext", "clickimage", "probe"];
+
+/// The highest rate `turbo` and repeating `keydown` actions can press at, as each press needs time to be handled
+const MAX_TAP_RATE: u32 = 500;
+
+/// The time between the clicks of `doubleclick` and `tripleclick` in milliseconds, well within the double click time of
+/// every platform
+const DOUBLE_CLICK_DELAY: u64 = 50;
+
+/// The names of the special keys accepted by `keydown` and `keyup`, for suggesting corrections to misspelled ones
+const KEY_NAMES: &[&str] = &[
+    "alt", "backspace", "capslock", "control", "delete", "down", "end", "enter", "escape", "f1", "f2", "f3", "f4", "f5", "f6",
+    "f7", "f8", "f9", "f10", "f11", "f12", "f13", "f14", "f15", "f16", "f17", "f18", "f19", "f20", "home",
+    #[cfg(not(target_os = "macos"))]
+    "insert",
+    "left", "pagedown", "pageup", "right", "shift", "space", "super", "tab", "up", "playpause", "nexttrack", "prevtrack",
+    "volumeup", "volumedown", "mute",
+    #[cfg(not(target_os = "macos"))]
+    "printscreen",
+    #[cfg(not(target_os = "macos"))]
+    "scrolllock",
+    #[cfg(not(target_os = "macos"))]
+    "pause",
+    #[cfg(not(target_os = "macos"))]
+    "contextmenu",
+    #[cfg(not(target_os = "macos"))]
+    "numlock",
+    #[cfg(not(target_os = "windows"))]
+    "brightnessup",
+    #[cfg(not(target_os = "windows"))]
+    "brightnessdown"
+];
+
+/// The names of the mouse buttons accepted by `mousedown` and `mouseup`, for suggesting corrections to misspelled ones
+const BUTTON_NAMES: &[&str] = &[
+    "left", "right", "middle",
+    #[cfg(not(target_os = "macos"))]
+    "back",
+    #[cfg(not(target_os = "macos"))]
+    "forward",
+    "scrollup", "scrolldown", "scrollleft", "scrollright"
+];
+
+/// The newest version of the TATATA language this version of TATATA understands, which scripts can require with
+/// `@tatata`, and which goes up whenever the meaning of an existing script changes
+const LANGUAGE_VERSION: u32 = 1;
+
+/// The longest time to wait between checking whether the run has been paused or aborted
+const CONTROL_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The shortest time between checking the condition of a loop, so loops without lines don't keep a CPU core busy
+const LOOP_CHECK_INTERVAL: u64 = 10;
+
+/// How many of the longest gaps `--list-gaps` prints
+const MAX_LISTED_GAPS: usize = 10;
+
+/// How often to check whether something that's being waited for has happened, as listing processes or reading text is
+/// fairly slow
+const WAIT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long the wait for a line has to be for the time until it to be shown with `--verbose`, so short waits don't flicker
+const NEXT_LINE_STATUS_WAIT: Duration = Duration::from_secs(1);
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Arguments {
-    /// The TATATA file to execute
-    file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The TATATA file to execute, or the files to execute at the same time with `--parallel`
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Run all given files at the same time, each on its own schedule, aborting them together
+    #[arg(long, default_value_t = false, conflicts_with = "report")]
+    parallel: bool,
 
     /// Print output to stdout instead of sending events
     #[arg(short, long, default_value_t = false)]
     dry_run: bool,
 
-    /// Log all actions to stdout
+    /// Log all actions to stdout, or also the timing decisions of the scheduler when given twice (`-vv`)
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Only print errors
+    #[arg(short, long, default_value_t = false)]
+    quiet: bool,
+
+    /// How to print errors, `json` printing one JSON object per line to stderr for tools wrapping TATATA
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+
+    /// Set the value of a variable the script asks for with `@prompt`, instead of asking when the script starts
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = prompt::parse_assignment)]
+    variables: Vec<(String, i32)>,
+
+    /// Only run the tagged lines with one of these tags, like `setup,core`, along with the lines without tags
+    #[arg(long, value_name = "TAGS", value_delimiter = ',')]
+    tags: Vec<String>,
+
+    /// Skip the lines with one of these tags, like `optional`
+    #[arg(long, value_name = "TAGS", value_delimiter = ',')]
+    skip_tags: Vec<String>,
+
+    /// Interpret coordinates as logical pixels, scaled by the scale factor of each monitor
+    #[arg(short, long, default_value_t = false)]
+    logical: bool,
+
+    /// The backend to use for sending events
+    #[arg(short, long, value_enum, default_value_t = BackendKind::Native)]
+    backend: BackendKind,
+
+    /// The display to send events to, e.g. `:99` for an X server or `wayland-1` for a Wayland compositor (Linux only)
+    #[arg(long)]
+    display: Option<String>,
+
+    /// Send events directly to the first window whose title contains this text, without focusing it (Linux/Windows only)
+    #[arg(short, long)]
+    window: Option<String>,
+
+    /// Speak progress milestones and errors through the text-to-speech engine of the platform
+    #[arg(short, long, default_value_t = false)]
+    announce: bool,
+
+    /// Show a desktop notification when the script finishes, fails or is aborted
+    #[arg(short, long, default_value_t = false)]
+    notify: bool,
+
+    /// Show a desktop notification when the script reaches a `//! cue:` comment
+    #[arg(long, default_value_t = false)]
+    notify_cues: bool,
+
+    /// Shorten every idle period between lines that is longer than this many milliseconds down to this length
+    #[arg(long, value_name = "MILLISECONDS")]
+    max_gap: Option<u64>,
+
+    /// Leave at least this many milliseconds between actions, delaying the rest of the script when they're closer together
+    #[arg(long, value_name = "MILLISECONDS")]
+    min_delay: Option<u64>,
+
+    /// Make the random choices of `choose` and `typerandom` from this seed, so runs with the same seed make the same choices
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Run every line right after the line before it, ignoring timestamps except the ones marked with `!`
+    #[arg(long, default_value_t = false, conflicts_with = "max_gap")]
+    no_wait: bool,
+
+    /// List the longest idle periods between lines of the script, instead of running it
+    #[arg(long, default_value_t = false)]
+    list_gaps: bool,
+
+    /// Write an HTML report of the run to this file, with when every line ran and which actions failed
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// What to do when the screen is locked, the user is switched, or the display goes to sleep during the run
+    #[arg(long, value_enum, default_value_t = OnLock::Pause)]
+    on_lock: OnLock,
+
+    /// Keep the physical keyboard and mouse from interfering while the script runs, pressing Escape aborts the run
+    #[arg(long, default_value_t = false)]
+    block_input: bool,
+
+    /// Wait for this many instances on other machines to join with `--sync` before starting, all at the same moment
+    #[arg(long, value_name = "COUNT", requires = "sync_listen")]
+    sync_peers: Option<usize>,
+
+    /// The address and port to wait for the instances of `--sync-peers` on, like `0.0.0.0:7778`
+    #[arg(long, value_name = "ADDRESS", requires = "sync_peers", conflicts_with = "sync")]
+    sync_listen: Option<String>,
+
+    /// Start at the same moment as the instance listening on this address and port with `--sync-listen`
+    #[arg(long, value_name = "ADDRESS")]
+    sync: Option<String>,
+
+    /// Show a tray icon with the progress of the script, for pausing, resuming or aborting it (Linux/Windows only)
     #[arg(short, long, default_value_t = false)]
-    verbose: bool
+    tray: bool
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List all connected monitors and their geometry
+    Monitors {
+        /// The display to list monitors of (Linux only)
+        #[arg(long)]
+        display: Option<String>
+    },
+
+    /// Wait for a click anywhere on the screen, and print where it was and the color of the pixel there (Linux/Windows only)
+    Pick {
+        /// Also print a `mousemove` line to the position, written in this form
+        #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "absolute", value_name = "FORM")]
+        mousemove: Option<PositionForm>,
+
+        /// The display to pick from (Linux only)
+        #[arg(long)]
+        display: Option<String>
+    },
+
+    /// Print the name `keydown` and `keyup` use for every key pressed on the keyboard, until stopped with Ctrl+C (Linux/Windows only)
+    Keys {
+        /// The display to read the keyboard of (Linux only)
+        #[arg(long)]
+        display: Option<String>
+    },
+
+    /// Print the header of a script or bundle, like its title and the size of screen it was written for
+    Info {
+        /// The script or bundle
+        file: PathBuf
+    },
+
+    /// Draw the mouse movements, clicks and typed text of a script into an animated GIF, without sending any events
+    Render {
+        /// The script to render
+        file: PathBuf,
+
+        /// The GIF file to write
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+
+        /// A PNG screenshot of the screen to draw over, which also sets the size of the screen
+        #[arg(long, value_name = "PNG")]
+        background: Option<PathBuf>,
+
+        /// How much to scale the screen by in the preview, like 0.5 for half its size
+        #[arg(long, default_value_t = 0.5)]
+        scale: f64,
+
+        /// Frames per second of the preview
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=100))]
+        fps: u32,
+
+        /// Set a variable the script prompts for, instead of asking for it
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = prompt::parse_assignment)]
+        variables: Vec<(String, i32)>,
+
+        /// The display whose monitors set the size of the screen without a screenshot (Linux only)
+        #[arg(long)]
+        display: Option<String>
+    },
+
+    /// Check that events can be sent to the display
+    SelfTest {
+        /// The backend to test
+        #[arg(short, long, value_enum, default_value_t = BackendKind::Native)]
+        backend: BackendKind,
+
+        /// The display to test (Linux only)
+        #[arg(long)]
+        display: Option<String>
+    },
+
+    /// Bind the scripts in a directory to the global hotkeys in their `// @hotkey` headers, and run them when pressed (Linux/Windows only)
+    Library {
+        /// The directory containing the scripts
+        directory: PathBuf,
+
+        /// The display to bind hotkeys on and run scripts on (Linux only)
+        #[arg(long)]
+        display: Option<String>,
+
+        /// Options to run every script with, given after `--`, like `-- --backend uinput --notify`
+        #[arg(last = true)]
+        options: Vec<String>
+    },
+
+    /// Run every script in a directory and its subdirectories as a test, and report which of them failed
+    Test {
+        /// The directory containing the scripts
+        directory: PathBuf,
+
+        /// The display to run the scripts on (Linux only)
+        #[arg(long)]
+        display: Option<String>,
+
+        /// Options to run every script with, given after `--`, like `-- --backend uinput --logical`
+        #[arg(last = true)]
+        options: Vec<String>
+    },
+
+    /// Run scripts sent by `tatata run --remote` from other machines, one at a time
+    Agent {
+        /// The address and port to listen on, like `0.0.0.0:7777` to accept connections from anywhere
+        #[arg(long, value_name = "ADDRESS")]
+        listen: String,
+
+        /// The token clients have to know to run scripts, which can also be set in the TATATA_TOKEN environment variable
+        #[arg(long)]
+        token: Option<String>,
+
+        /// The display to run the scripts on (Linux only)
+        #[arg(long)]
+        display: Option<String>
+    },
+
+    /// Run a script or bundle on another machine through its agent, printing its output here
+    Run {
+        /// The script or bundle to run
+        file: PathBuf,
+
+        /// The address and port of the agent, like `testrig:7777`
+        #[arg(long, value_name = "ADDRESS")]
+        remote: String,
+
+        /// The token of the agent, which can also be set in the TATATA_TOKEN environment variable
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Options to run the script with, given after `--`, like `-- --dry-run --var NAME=VALUE`
+        #[arg(last = true)]
+        options: Vec<String>
+    }
+}
+
+/// A parsed script, with the lines of its `@setup` and `@teardown` sections kept apart from the main lines
+struct Script {
+    header: Header,
+    setup: Vec<QueueItem>,
+    queue: Vec<QueueItem>,
+    teardown: Vec<QueueItem>,
+    /// Whether the script has `@offset` or `@scale` lines, which adapt its positions to another screen
+    transformed: bool
 }
 
 #[derive(Debug)]
 struct QueueItem {
     time: u64,
-    actions: Vec<Action>
+    line: i32,
+    /// Whether the time since the line before it is kept with `--no-wait`, from a `!` after its timestamp
+    mandatory: bool,
+    /// How many milliseconds the line can run before or after its time, from a `~` after its timestamp
+    jitter: u64,
+    step: Step
+}
+
+impl QueueItem {
+    /// The time to run the line at this time it's reached, moved by a random part of its jitter
+    fn scheduled_time(&self) -> u64 {
+        if self.jitter == 0 {
+            return self.time;
+        }
+        (self.time + random::below(self.jitter as usize * 2 + 1) as u64).saturating_sub(self.jitter)
+    }
 }
 
+/// What happens at a point in time of the script
 #[derive(Debug)]
-enum Action {
-    MouseMove {
-        x: i32,
-        y: i32,
-        time: u64,
-        method: Coordinate
-    },
-    MouseDown(Button),
-    MouseUp(Button),
-    KeyDown(Key),
-    KeyUp(Key),
-    Release(OutputType),
-    Text(String)
+enum Step {
+    Actions(Vec<Action>),
+    Loop(Loop),
+    /// Branches of lines, one of which is picked at random every time the line is reached
+    Choose(Vec<Vec<QueueItem>>),
+    Retry(Retry),
+    If(Conditional),
+    /// The lines of tracks that run at the same time, merged into one timeline from the start of the block
+    Parallel(Vec<QueueItem>)
+}
+
+impl Step {
+    /// How long the actions of the line keep inputting after they start, like a `mousemove` over time
+    fn duration(&self) -> u64 {
+        let Step::Actions(actions) = self else {
+            return 0;
+        };
+
+        // Actions after a `wait` start that much later than the line
+        let mut waited = 0;
+        actions.iter().map(|action| waited + match action {
+            Action::Wait(duration) => {
+                waited += duration;
+                0
+            }
+            Action::MouseMove { time, .. } | Action::MousePath { time, .. } | Action::Scroll { time, .. } => *time,
+            Action::MouseTrace { points, speed, .. } => points.last().map_or(0, |point| (point.time as f64 / speed).round() as u64),
+            Action::Turbo { duration, .. } | Action::HoldKey { duration, .. } => *duration,
+            // The length of text variables isn't known before running, so only the text written in the script counts
+            Action::Text { text, typing } => typing.delay * text.iter().map(|part| match part {
+                TextPart::Literal(literal) => literal.chars().count() as u64,
+                TextPart::Variable(_) => 0
+            }).sum::<u64>(),
+            Action::TypeRandom { pattern: Pattern::Characters(_), length: Number::Fixed(length), typing } => typing.delay * (*length).max(0) as u64,
+            _ => 0
+        }).max().unwrap_or(0)
+    }
+
+    /// The lines of the block, or of every branch of a choice
+    fn bodies(&self) -> Vec<&Vec<QueueItem>> {
+        match self {
+            Step::Actions(_) => Vec::new(),
+            Step::Loop(repeat) => vec![&repeat.body],
+            Step::Choose(branches) => branches.iter().collect(),
+            Step::Retry(retry) => vec![&retry.body],
+            Step::If(conditional) => vec![&conditional.then, &conditional.otherwise],
+            Step::Parallel(body) => vec![body]
+        }
+    }
+
+    fn bodies_mut(&mut self) -> Vec<&mut Vec<QueueItem>> {
+        match self {
+            Step::Actions(_) => Vec::new(),
+            Step::Loop(repeat) => vec![&mut repeat.body],
+            Step::Choose(branches) => branches.iter_mut().collect(),
+            Step::Retry(retry) => vec![&mut retry.body],
+            Step::If(conditional) => vec![&mut conditional.then, &mut conditional.otherwise],
+            Step::Parallel(body) => vec![body]
+        }
+    }
 }
 
+/// Lines that are repeated for as long as a condition is true, or until it becomes true
 #[derive(Debug)]
-enum OutputType {
-    Mouse,
-    Key,
-    Both
+struct Loop {
+    condition: Condition,
+    /// Whether the loop stops when the condition becomes true, instead of when it becomes false
+    until: bool,
+    /// The lines of the loop, timed from the start of each iteration
+    body: Vec<QueueItem>
 }
 
-fn main() {
-    // Get arguments
-    let args = Arguments::parse();
-    let queue = parse_file(args.file);
-    let dry_run = args.dry_run;
-    let verbose = args.verbose;
+/// Lines that only run when a condition is true, and lines that only run when it isn't
+#[derive(Debug)]
+struct Conditional {
+    condition: Condition,
+    /// The lines that run when the condition is true, timed from the start of the block
+    then: Vec<QueueItem>,
+    /// The lines after `} else {`, which run when the condition is false
+    otherwise: Vec<QueueItem>
+}
 
-    // Create Enigo object
-    let mut enigo = Enigo::new(&Settings::default()).unwrap_or_else(|error| {
-        println!("Failed to initialize Enigo: {error}");
-        process::exit(1);
-    });
+/// Lines that are run again when an action in them fails, like a wait that runs out of time
+#[derive(Debug)]
+struct Retry {
+    /// How many times the lines are run at most, including the first time
+    attempts: u32,
+    /// How long to wait before the first retry, in milliseconds
+    delay: u64,
+    /// What the delay is multiplied by after every retry
+    backoff: f64,
+    /// The lines of the block, timed from the start of each attempt
+    body: Vec<QueueItem>
+}
+
+/// Lines defined with `macro`, which are parsed in place of every `call` line with the parameters replaced
+struct Macro {
+    line: i32,
+    parameters: Vec<String>,
+    /// The lines of the macro as they're written, along with their line numbers
+    body: Vec<(i32, Cow<'static, str>)>
+}
 
-    // Store thread handles created during execution
-    let mut threads: Vec<JoinHandle<()>> = Vec::new();
+/// A position defined with `point`, which actions that take positions can use by its name, like `click left @submit`
+struct NamedPoint {
+    line: i32,
+    x: String,
+    y: String
+}
 
-    // Store held outputs while running
-    let mut held_mouse: Vec<Button> = Vec::new();
-    let mut held_key: Vec<Key> = Vec::new();
+/// A block that has been opened but not closed yet while parsing, with the line it starts on, and whether its
+/// tags leave it out of the script
+enum OpenBlock {
+    Loop {
+        line: i32,
+        skip: bool,
+        condition: Condition,
+        until: bool,
+        body: Vec<QueueItem>
+    },
+    Choose {
+        line: i32,
+        skip: bool,
+        /// The branches so far, the last of which is the one lines are added to
+        branches: Vec<Vec<QueueItem>>
+    },
+    Retry {
+        line: i32,
+        skip: bool,
+        attempts: u32,
+        delay: u64,
+        backoff: f64,
+        body: Vec<QueueItem>
+    },
+    If {
+        line: i32,
+        skip: bool,
+        condition: Condition,
+        then: Vec<QueueItem>,
+        /// The lines after `} else {`, once the line has been reached
+        otherwise: Option<Vec<QueueItem>>
+    },
+    Parallel {
+        line: i32,
+        skip: bool,
+        /// The tracks so far, the last of which is the one lines are added to
+        tracks: Vec<Vec<QueueItem>>
+    },
+    /// A `@setup` or `@teardown` section, which can only be at the top level of the script
+    Section {
+        line: i32,
+        teardown: bool,
+        body: Vec<QueueItem>
+    }
+}
 
-    // Execute queue
-    let start_time = std::time::Instant::now();
-    for entry in queue {
-        // Wait until correct timestamp
-        if start_time.elapsed() < std::time::Duration::from_millis(entry.time) {
-            spin_sleep::sleep(std::time::Duration::from_millis(entry.time) - start_time.elapsed());
+impl OpenBlock {
+    /// The lines that new lines are added to
+    fn body(&mut self) -> &mut Vec<QueueItem> {
+        match self {
+            OpenBlock::Loop { body, .. } | OpenBlock::Retry { body, .. } | OpenBlock::Section { body, .. } => body,
+            OpenBlock::Choose { branches, .. } => branches.last_mut().expect("a choice always has a branch"),
+            OpenBlock::Parallel { tracks, .. } => tracks.last_mut().expect("a parallel block always has a track"),
+            OpenBlock::If { then, otherwise, .. } => otherwise.as_mut().unwrap_or(then)
         }
+    }
 
-        // Execute actions
-        for action in entry.actions {
-            if let Some(handle) = execute_action(&mut enigo, entry.time, action, !dry_run, dry_run || verbose, (&mut held_mouse, &mut held_key)) {
-                threads.push(handle);
-            }
+    fn line(&self) -> i32 {
+        match self {
+            OpenBlock::Loop { line, .. } | OpenBlock::Choose { line, .. } | OpenBlock::Retry { line, .. } | OpenBlock::If { line, .. } | OpenBlock::Parallel { line, .. } | OpenBlock::Section { line, .. } => *line
         }
     }
 
-    // Wait for all threads to finish execution
-    for handle in threads {
-        match handle.join() {
-            Ok(_) => {
-                if verbose {
-                    println!("Joined thread handle");
-                }
-            }
-            Err(error) => println!("Failed to join thread: {error:?}")
+    fn name(&self) -> &'static str {
+        match self {
+            OpenBlock::Loop { .. } => "loop",
+            OpenBlock::Choose { .. } => "choice",
+            OpenBlock::Retry { .. } => "retry block",
+            OpenBlock::If { .. } => "if block",
+            OpenBlock::Parallel { .. } => "parallel block",
+            OpenBlock::Section { teardown: false, .. } => "setup",
+            OpenBlock::Section { teardown: true, .. } => "teardown"
         }
     }
 }
 
-fn parse_file(file_path: PathBuf) -> Vec<QueueItem> {
-    // Check if file exists
-    if !file_path.exists() {
-        println!("File does not exist: {}", file_path.display());
-        process::exit(1);
-    }
+#[derive(Debug, Clone)]
+enum Action {
+    MouseMove {
+        x: Number,
+        y: Number,
+        time: u64,
+        method: Coordinate,
+        /// Whether the mouse moves like a hand moves it over the time, instead of in a straight line at an even speed
+        human: bool
+    },
+    /// Move the mouse through several points, with the time spread over the parts of the path by how long they are
+    MousePath {
+        points: Vec<(Number, Number)>,
+        time: u64,
+        method: Coordinate,
+        human: bool
+    },
+    /// Move the mouse along a recorded path, with the time between points divided by the speed
+    MouseTrace {
+        file: String,
+        points: Arc<Vec<trace::Point>>,
+        speed: f64
+    },
+    MouseDown(Button),
+    MouseUp(Button),
+    /// Scroll by a number of wheel notches, down or right when positive, spread over a time in milliseconds
+    Scroll {
+        amount: Number,
+        axis: Axis,
+        time: u64
+    },
+    KeyDown(Key, Option<RepeatRate>),
+    KeyUp(Key),
+    Release(OutputType),
+    /// Type text, one character at a time when the typing has a delay
+    Text {
+        text: Vec<TextPart>,
+        typing: Typing
+    },
+    Say(Vec<TextPart>),
+    /// Print a message, to follow a run without printing every action
+    Log(Vec<TextPart>),
+    /// Announce which part of the script is running, from a `//! cue:` comment before the line
+    Cue(String),
+    Turbo {
+        input: TurboInput,
+        rate: u32,
+        duration: u64
+    },
+    /// Hold a key for a number of milliseconds
+    HoldKey {
+        key: Key,
+        duration: u64,
+        repeat: Option<RepeatRate>
+    },
+    /// Wait for the operator to press a key, or any key if none is given
+    WaitKey(Option<Key>),
+    /// Pause between the actions of a line for a number of milliseconds
+    Wait(u64),
+    /// Store the position of the mouse in two variables
+    GetMouse(String, String),
+    /// Move the first window whose title contains the text
+    MoveWindow {
+        title: String,
+        x: Number,
+        y: Number
+    },
+    /// Resize the first window whose title contains the text
+    ResizeWindow {
+        title: String,
+        width: Number,
+        height: Number
+    },
+    /// Maximize, minimize or close the first window whose title contains the text
+    WindowCommand(window::Command, String),
+    KillProcess(Process),
+    /// Wait for a process to start, or to exit, optionally giving up after a timeout in milliseconds
+    WaitProcess {
+        process: Process,
+        exit: bool,
+        timeout: Option<u64>
+    },
+    /// Wait for a window whose title contains the text to open, or to have the keyboard focus, optionally giving up
+    /// after a timeout in milliseconds
+    WaitWindow {
+        title: String,
+        focus: bool,
+        timeout: Option<u64>
+    },
+    /// Wait for a pixel on the screen to be a color, or close to it, optionally giving up after a timeout in milliseconds
+    WaitPixel {
+        x: Number,
+        y: Number,
+        color: Color,
+        tolerance: u8,
+        timeout: Option<u64>
+    },
+    /// Type random text, like `text`
+    TypeRandom {
+        pattern: Pattern,
+        length: Number,
+        typing: Typing
+    },
+    /// Store the text on the clipboard in a text variable
+    GetClipboard(String),
+    /// Wait for text to appear in a region of the screen, or anywhere on it, optionally giving up after a timeout
+    WaitText {
+        text: String,
+        region: Option<[Number; 4]>,
+        timeout: Option<u64>
+    },
+    /// Run a shell command, optionally waiting for it to finish
+    Exec {
+        command: String,
+        wait: bool
+    },
+    /// Stop the script early, exiting with a code and printing a message if there is one
+    Abort {
+        code: i32,
+        message: Vec<TextPart>
+    },
+    /// Wait for an image to show up on the screen, optionally giving up after a timeout in milliseconds
+    WaitImage {
+        file: String,
+        template: Arc<Template>,
+        timeout: Option<u64>
+    },
+    /// Click the center of an image on the screen, optionally waiting for it to show up until a timeout
+    ClickImage {
+        file: String,
+        template: Arc<Template>,
+        button: Button,
+        timeout: Option<u64>
+    },
+    /// Store the text in a region of the screen in a text variable
+    ReadText {
+        region: [Number; 4],
+        name: String
+    },
+    /// Log the color of a pixel and the position of the mouse, to help writing conditions
+    Probe(Number, Number)
+}
 
-    // Validate file name (https://github.com/soni801/tatata/issues/1)
-    let file_name = file_path.to_str().unwrap_or_else(|| {
-        println!("Invalid file name: {}", file_path.display());
-        process::exit(1);
-    });
-    if !file_name.ends_with(".tatata") {
-        println!("Not a TATATA file: {}", file_path.display());
-        process::exit(1);
-    }
+/// Which tagged lines and blocks to run, from `--tags` and `--skip-tags`
+struct TagFilter {
+    only: Vec<String>,
+    skip: Vec<String>
+}
 
-    // Try to open file
-    let file_content = std::fs::read_to_string(file_path).unwrap_or_else(|error| {
-        println!("Couldn't open input file for execution: {error}");
-        process::exit(1);
-    });
+impl TagFilter {
+    /// Lines without tags always run, and tagged lines run unless `--tags` leaves them out or `--skip-tags` skips them
+    fn allows(&self, tags: &[&str]) -> bool {
+        if tags.is_empty() {
+            return true;
+        }
 
-    // Create empty queue
-    let mut queue: Vec<QueueItem> = Vec::new();
+        let listed = |list: &[String]| tags.iter().any(|tag| list.iter().any(|listed| listed == tag));
+        (self.only.is_empty() || listed(&self.only)) && !listed(&self.skip)
+    }
+}
 
-    // Parse file
-    let mut line_index = 0;
-    let mut in_comment = false;
-    for mut line in file_content.lines() {
-        line_index += 1;
+/// A number in an action, which is either known when the script is parsed or calculated from variables set while it runs
+#[derive(Debug, Clone)]
+enum Number {
+    Fixed(i32),
+    Expression(Expression)
+}
 
-        // Check if the line closes a multi-line comment
-        if in_comment {
-            if line.contains("*/") {
-                in_comment = false;
-                line = line.split("*/").collect::<Vec<&str>>()[1];
-            } else {
-                continue;
+impl Number {
+    fn resolve(&self, variables: &HashMap<String, i32>) -> Result<i32, String> {
+        match self {
+            Number::Fixed(value) => Ok(*value),
+            Number::Expression(expression) => {
+                if let Some(name) = expression.variables().into_iter().find(|name| !variables.contains_key(*name)) {
+                    return Err(format!("${name} hasn't been set yet"));
+                }
+                expression.evaluate(variables)
             }
         }
+    }
+}
 
-        // Check if the line opens a multi-line comment
-        let mut line_without_comment: String;
-        if line.contains("/*") {
-            line_without_comment = line.split("/*").next().unwrap().to_string();
+/// How the absolute positions of the lines after `@offset` and `@scale` lines are changed, which is scaled first
+#[derive(Debug, Clone, Copy)]
+struct Transform {
+    offset: (i32, i32),
+    scale: f64
+}
 
-            // Check if the multi-line comment closes on the same line
-            if line.contains("*/") {
-                let after_comment = line.split("*/").collect::<Vec<&str>>()[1];
-                line_without_comment.push_str(after_comment);
-            } else {
-                in_comment = true;
-            }
+impl Default for Transform {
+    fn default() -> Self {
+        Transform { offset: (0, 0), scale: 1.0 }
+    }
+}
 
-            line = line_without_comment.as_str();
-        }
+impl Transform {
+    fn is_identity(&self) -> bool {
+        self.offset == (0, 0) && self.scale == 1.0
+    }
 
-        // Check if the line contains a single-line comment
-        line = if line.contains("//") {
-            line.split("//").next().unwrap()
-        } else {
-            line
-        };
+    /// How far positions on an axis are moved, where 0 is X and 1 is Y, and sizes without an axis aren't moved
+    fn offset(&self, axis: Option<usize>) -> i32 {
+        axis.map_or(0, |axis| if axis == 0 { self.offset.0 } else { self.offset.1 })
+    }
 
-        // Skip if line is empty or only whitespace
-        if line.trim().is_empty() {
-            continue;
-        }
+    /// Change a position on an axis, or only scale a size when there's no axis
+    fn apply(&self, value: i32, axis: Option<usize>) -> i32 {
+        (f64::from(value) * self.scale).round() as i32 + self.offset(axis)
+    }
 
-        // Get line data
-        let line_decoded: Vec<&str> = line.split(">").collect();
-        if line_decoded.len() != 2 {
-            println!("Line {line_index}: Incorrectly formatted line: {line:?}");
-            process::exit(1);
+    /// Change a position written as numbers, like `960` or `900 + 60`, or only scale a size when there's no axis
+    ///
+    /// Positions on a monitor, percentages and positions calculated from variables are already positions on this
+    /// screen, so they're left as they are. Random positions keep the scale to a thousandth.
+    fn apply_literal<'a>(&self, argument: &'a str, axis: Option<usize>) -> Cow<'a, str> {
+        if self.is_identity() || argument.contains(['%', ':']) {
+            return Cow::Borrowed(argument);
         }
-
-        // Decode line
-        let line_timestamp_text = line_decoded[0];
-        let line_actions_text = line_decoded[1];
-
-        // Get previous timestamp
-        let previous_timestamp = if let Some(previous_action) = queue.last() {
-            previous_action.time
-        } else {
-            0
+        let Ok(expression) = Expression::parse(argument) else {
+            return Cow::Borrowed(argument);
         };
+        if !expression.variables().is_empty() {
+            return Cow::Borrowed(argument);
+        }
+        if expression.is_random() {
+            return Cow::Owned(format!("({argument})*{}/1000{:+}", (self.scale * 1000.0).round() as i32, self.offset(axis)));
+        }
+        match expression.evaluate(&HashMap::new()) {
+            Ok(value) => Cow::Owned(self.apply(value, axis).to_string()),
+            Err(_) => Cow::Borrowed(argument)
+        }
+    }
+}
 
-        // Parse the timestamp of this line
-        let line_timestamp: u64 = if line_timestamp_text.starts_with("+") {
-            // Relative timestamp
-            let parsed_time: u64 = line_timestamp_text[1..].parse().unwrap_or_else(|error| {
-                println!("Line {line_index}: Incorrectly formatted timestamp: {line_timestamp_text:?} ({error})");
-                process::exit(1);
-            });
+/// A piece of the text in a `text` or `say` action, where text variables are filled in while the script runs
+#[derive(Debug, Clone)]
+enum TextPart {
+    Literal(String),
+    Variable(String)
+}
 
-            previous_timestamp + parsed_time
-        } else {
-            // Absolute timestamp
-            let parsed_time: u64 = line_timestamp_text.parse().unwrap_or_else(|error| {
-                println!("Line {line_index}: Incorrectly formatted timestamp: {line_timestamp_text:?} ({error})");
-                process::exit(1);
-            });
+/// The kind of value in a variable that's set while the script runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VariableKind {
+    Number,
+    Text
+}
 
-            // Reject lines that have a timestamp lower than the previous line
-            if parsed_time > 0 && parsed_time <= previous_timestamp {
-                println!("Line {line_index}: Timestamp must be greater than previous action line");
-                process::exit(1);
-            }
+/// How `text` actions type, set by an `@typing_delay` line
+#[derive(Debug, Clone, Copy, Default)]
+struct Typing {
+    /// The time between characters in milliseconds, or 0 to type all at once
+    delay: u64,
+    /// Whether the time between characters varies, with pauses between words and the occasional corrected typo
+    human: bool
+}
 
-            parsed_time
-        };
+/// What actions use for the arguments they leave out, set by the `@defaults` sections before them
+#[derive(Debug, Clone, Copy, Default)]
+struct Defaults {
+    /// How `text` actions type, which an `@typing_delay` line sets too
+    typing: Typing,
+    /// The method of `mousemove` and click positions written without `abs` or `rel`
+    method: Option<Coordinate>,
+    /// Whether the mouse moves like a person when moving over time
+    human: bool,
+    /// The button of `mousedown`, `mouseup` and clicks written without one
+    button: Option<Button>,
+    /// How the positions written as numbers are changed, set by `@offset` and `@scale` lines
+    transform: Transform
+}
 
-        // Parse actions
-        let actions = parse_actions_string(line_actions_text, line_index);
+/// The key or mouse button repeated by a `turbo` action
+#[derive(Debug, Clone, Copy)]
+enum TurboInput {
+    Key(Key),
+    Button(Button)
+}
 
-        // Add actions to queue
-        queue.push(QueueItem {
-            time: line_timestamp,
-            actions
-        });
-    }
+/// How a position picked with `tatata pick` is written in a script
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PositionForm {
+    /// The position on the whole screen, like `1234 567`
+    Absolute,
+    /// The position from the top left corner of its monitor, like `$MONITOR2_X+314 $MONITOR2_Y+87`
+    Monitor,
+    /// The position as a fraction of the size of its monitor, like `$MONITOR2_X+$MONITOR2_W*245/1000 ...`
+    Percent
+}
 
-    // Return populated queue
-    queue
+/// Settings that stay the same for the whole execution of a script
+#[derive(Clone)]
+struct ExecutionOptions {
+    should_execute: bool,
+    backend_config: BackendConfig,
+    logical_monitors: Option<Vec<Monitor>>,
+    /// The shortest time between two actions, set by `--min-delay`
+    min_delay: Option<Duration>
 }
 
-fn parse_actions_string(string: &str, line_index: i32) -> Vec<Action> {
-    // Split into individual action strings
-    let action_strings: Vec<&str> = string.split(";").collect();
-    if action_strings.len() == 1 && action_strings[0].is_empty() {
-        println!("Line {line_index}: Need at least one action");
-        process::exit(1);
-    }
+#[derive(Debug, Clone)]
+enum OutputType {
+    Mouse,
+    Key,
+    Both
+}
+
+fn main() {
+    // Get arguments
+    let args = Arguments::parse();
+
+    // Dry runs always print the actions, as that's their whole point
+    let level = logging::level(args.verbose, args.quiet);
+    logging::init(if args.dry_run { level.max(log::LevelFilter::Debug) } else { level });
+    diagnostic::set_format(args.error_format);
+
+    // Run subcommand if specified
+    if let Some(command) = args.command {
+        match command {
+            Command::Monitors { display } => {
+                select_display(display);
+                list_monitors();
+            }
+            Command::Pick { mousemove, display } => {
+                select_display(display);
+                pick(mousemove);
+            }
+            Command::Keys { display } => {
+                select_display(display);
+                if let Err(error) = list_keys() {
+                    diagnostic::fail(ErrorKind::Setup, error);
+                }
+            }
+            Command::Info { file } => {
+                if let Err(error) = print_header(&file) {
+                    diagnostic::fail(ErrorKind::Setup, error);
+                }
+            }
+            Command::Render { file, output, background, scale, fps, variables, display } => {
+                select_display(display);
+                if let Err(error) = render(file, &output, background, scale, fps, variables) {
+                    diagnostic::fail(ErrorKind::Setup, error);
+                }
+            }
+            Command::SelfTest { backend, display } => {
+                select_display(display);
+                if !selftest::run(backend) {
+                    process::exit(ErrorKind::Injection.exit_code());
+                }
+            }
+            Command::Library { directory, display, options } => {
+                select_display(display);
+                if let Err(error) = library::run(&directory, &options) {
+                    diagnostic::fail(ErrorKind::Setup, error);
+                }
+            }
+            Command::Test { directory, display, options } => {
+                select_display(display);
+                match suite::run(&directory, &options) {
+                    Ok(0) => {}
+                    Ok(code) => process::exit(code),
+                    Err(error) => diagnostic::fail(ErrorKind::Setup, error)
+                }
+            }
+            Command::Agent { listen, token, display } => {
+                select_display(display);
+                let result = remote::token(token).and_then(|token| remote::serve(&listen, &token));
+                if let Err(error) = result {
+                    diagnostic::fail(ErrorKind::Setup, error);
+                }
+            }
+            Command::Run { file, remote, token, options } => {
+                match remote::token(token).and_then(|token| remote::run_remote(&remote, &token, &file, &options)) {
+                    Ok(0) => {}
+                    Ok(code) => process::exit(code),
+                    Err(error) => diagnostic::fail(ErrorKind::Setup, error)
+                }
+            }
+        }
+        return;
+    }
+
+    select_display(args.display);
+
+    let dry_run = args.dry_run;
+    let notify = args.notify && !dry_run;
+    if args.announce && !dry_run {
+        speech::enable_announcements();
+    }
+
+    if args.files.len() > 1 && !args.parallel {
+        Arguments::command().error(clap::error::ErrorKind::TooManyValues, "pass --parallel to run more than one script at once").exit();
+    }
+
+    // Query monitors and expose their geometry as script variables
+    let monitors = monitors::get_monitors();
+    let mut variables = monitors::get_variables(&monitors);
+
+    // Find the window to send events to
+    let window = args.window.map(|title| match window::find(&title) {
+        Ok(Some(window)) => {
+            debug!("Targeting window {:?} ({})", window.title, window.id);
+            window
+        }
+        Ok(None) => diagnostic::fail(ErrorKind::Setup, format!("Couldn't find a window with a title containing {title:?}")),
+        Err(error) => diagnostic::fail(ErrorKind::Setup, format!("Failed to list windows: {error}"))
+    });
+
+    // Positions are relative to the window when targeting one, so its size is needed for positions like `50%`
+    if let Some(window) = &window {
+        let (width, height) = window::size(window).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Setup, error));
+        variables.insert("WINDOW_W".to_string(), width as i32);
+        variables.insert("WINDOW_H".to_string(), height as i32);
+    }
+    variables.extend(args.variables);
+
+    // Parse the scripts, asking for the variables they prompt for that weren't given, which are shared between them
+    let tag_filter = TagFilter { only: args.tags, skip: args.skip_tags };
+    let mut scripts: Vec<(String, Option<&'static diagnostic::Source>, Script)> = args.files.into_iter().map(|file| {
+        let script_name = file.file_name().unwrap_or(file.as_os_str()).to_string_lossy().into_owned();
+        let script = parse_file(file, &mut variables, &tag_filter);
+        (script_name, diagnostic::source(), script)
+    }).collect();
+    let parallel = scripts.len() > 1;
+
+    if args.list_gaps {
+        for (script_name, _, script) in &scripts {
+            if parallel {
+                println!("{script_name}:");
+            }
+            list_gaps(script);
+        }
+        return;
+    }
+    for (script_name, _, script) in &scripts {
+        check_header(script_name, &script.header, script.transformed, &monitors, dry_run);
+    }
+    for (_, _, script) in &mut scripts {
+        if let Some(max_gap) = args.max_gap {
+            for lines in [&mut script.setup, &mut script.queue, &mut script.teardown] {
+                compress_gaps(lines, max_gap);
+            }
+        }
+        if args.no_wait {
+            for lines in [&mut script.setup, &mut script.queue, &mut script.teardown] {
+                remove_waits(lines);
+            }
+        }
+    }
+    if let Some(seed) = args.seed {
+        random::set_seed(seed);
+    }
+
+    // Keep monitor scale factors if coordinates should be interpreted as logical pixels
+    let logical_monitors: Option<Vec<Monitor>> = if args.logical {
+        for monitor in &monitors {
+            debug!("Monitor {:?}: {}x{} at {}, {} (scale {})", monitor.name, monitor.width, monitor.height, monitor.x, monitor.y, monitor.scale_factor);
+        }
+        Some(monitors)
+    } else {
+        None
+    };
+
+    // Coordinates are relative to the window when targeting one, so they can't be scaled by monitor
+    if window.is_some() && logical_monitors.is_some() {
+        warn!("--logical is ignored when targeting a window");
+    }
+    let logical_monitors = if window.is_some() { None } else { logical_monitors };
+
+    let options = ExecutionOptions {
+        should_execute: !dry_run,
+        backend_config: BackendConfig { kind: args.backend, window, owner: keyboard::Owner::next() },
+        logical_monitors,
+        min_delay: args.min_delay.map(Duration::from_millis)
+    };
+    let run_name = scripts.iter().map(|(script_name, _, _)| script_name.as_str()).collect::<Vec<_>>().join(", ");
+
+    // Every script gets its own backend, as backends can't be shared between threads, and holds Shift for itself
+    let create_runner = |script_name: String, teardown: Vec<QueueItem>, report: Option<Report>| {
+        let mut options = options.clone();
+        options.backend_config.owner = keyboard::Owner::next();
+        let backend = backend::create(&options.backend_config).unwrap_or_else(|error| {
+            speech::announce("Failed to start, could not create the input backend");
+            if notify {
+                notification::send("TATATA run failed", &format!("Couldn't start {script_name}: {error}"));
+            }
+            speech::wait();
+            diagnostic::fail(ErrorKind::Injection, error);
+        });
+
+        Runner {
+            backend,
+            options,
+            script_name,
+            notify,
+            notify_cues: args.notify_cues && !dry_run,
+            show_next: args.verbose > 0,
+            run_start: Instant::now(),
+            threads: Vec::new(),
+            held_mouse: Vec::new(),
+            held_key: Vec::new(),
+            key_repeats: Vec::new(),
+            variables: HashMap::new(),
+            texts: HashMap::new(),
+            teardown,
+            in_teardown: false,
+            report,
+            balance: Balance::default(),
+            retry_failures: None,
+            last_action: None,
+            failure_count: 0,
+            first_failure: None
+        }
+    };
+
+    // The progress of the run is the progress of its longest script
+    let longest = scripts.iter().enumerate().max_by_key(|(_, (_, _, script))| script.queue.last().map_or(0, |entry| entry.time)).map_or(0, |(index, _)| index);
+
+    // A single script runs on this thread, and is ready to start before anything is announced
+    let single = (!parallel).then(|| {
+        let (script_name, _, mut script) = scripts.remove(0);
+        let runner = create_runner(script_name, std::mem::take(&mut script.teardown), args.report.map(Report::new));
+        (runner, script)
+    });
+
+    // Catch Ctrl+C, so an aborted run can be reported, and cleaned up by the teardowns
+    if notify || single.as_ref().is_some_and(|(runner, _)| runner.report.is_some() || !runner.teardown.is_empty())
+        || scripts.iter().any(|(_, _, script)| !script.teardown.is_empty()) {
+        interrupt::install();
+    }
+
+    // Instances on other machines start at the same moment, once every one of them is ready
+    let synchronized = match (&args.sync_listen, args.sync_peers, &args.sync) {
+        (Some(address), Some(peers), _) => rendezvous::coordinate(address, peers),
+        (_, _, Some(address)) => rendezvous::join(address),
+        _ => Ok(())
+    };
+    if let Err(error) = synchronized {
+        diagnostic::fail(ErrorKind::Setup, format!("Failed to synchronize the start: {error}"));
+    }
+
+    speech::announce("Starting script");
+    if args.tray && !dry_run {
+        if let Err(error) = tray::start(&run_name) {
+            warn!("Failed to show tray icon: {error}");
+        }
+    }
+    if !dry_run {
+        lock::watch(args.on_lock);
+    }
+    if args.block_input && !dry_run {
+        if let Err(error) = block::start() {
+            diagnostic::fail(ErrorKind::Setup, format!("Failed to block input: {error}"));
+        }
+        info!("Blocking the keyboard and mouse, press Escape to abort");
+    }
+
+    let run_start = Instant::now();
+    control::start_scripts(scripts.len());
+    let results: Vec<ScriptResult> = if let Some((runner, script)) = single {
+        vec![run_script(runner, &script, true)]
+    } else {
+        // The scripts start once all of them are ready, so their timestamps line up
+        let ready = Barrier::new(scripts.len());
+        thread::scope(|scope| {
+            let handles: Vec<_> = scripts.into_iter().enumerate().map(|(index, (script_name, source, mut script))| {
+                let (create_runner, ready) = (&create_runner, &ready);
+                thread::Builder::new().name(script_name.clone()).spawn_scoped(scope, move || {
+                    diagnostic::use_source(source);
+                    let runner = create_runner(script_name, std::mem::take(&mut script.teardown), None);
+                    ready.wait();
+                    run_script(runner, &script, index == longest)
+                }).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Setup, format!("Failed to start a thread for the script: {error}")))
+            }).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))).collect()
+        })
+    };
+
+    block::stop();
+    let failure_count: u32 = results.iter().map(|result| result.failure_count).sum();
+    if notify {
+        let duration = notification::format_duration(run_start.elapsed());
+        match results.iter().find_map(|result| result.first_failure.as_ref().map(|failure| (&result.script_name, failure))) {
+            Some((script_name, (line, error))) => {
+                let location = if parallel { format!("line {line} of {script_name}") } else { format!("line {line}") };
+                notification::send("TATATA run failed", &format!("{run_name} finished in {duration} with {failure_count} failed action(s), the first on {location}: {error}"));
+            }
+            None => notification::send("TATATA run finished", &format!("{run_name} finished in {duration}"))
+        }
+    }
+
+    tray::stop();
+
+    // Let any remaining speech finish before exiting
+    speech::announce("Script finished");
+    speech::wait();
+
+    // The errors have already been reported as they happened
+    if failure_count > 0 {
+        process::exit(ErrorKind::Injection.exit_code());
+    }
+}
+
+/// How a script that wasn't aborted went
+struct ScriptResult {
+    script_name: String,
+    /// The number of failed actions, along with the line and error of the first one
+    failure_count: u32,
+    first_failure: Option<(i32, String)>
+}
+
+/// Run the setup, lines and teardown of a script
+///
+/// Only one script reports its progress to the tray icon and announcements, as scripts run with `--parallel` run at
+/// the same time.
+fn run_script(mut runner: Runner, script: &Script, track_progress: bool) -> ScriptResult {
+    // The status line can only show what one of the scripts is waiting for
+    runner.show_next &= track_progress;
+
+    // Announce every quarter of the script as it passes
+    let queue = &script.queue;
+    let duration = queue.last().map_or(0, |entry| entry.time);
+    let mut next_milestone = 1;
+
+    // Execute the setup, and the queue unless the setup failed, as the lines after it rely on it
+    if !script.setup.is_empty() {
+        debug!("Running setup");
+        runner.report_section("Setup");
+        runner.run_section(&script.setup);
+    }
+    if runner.failure_count > 0 {
+        warn!("Skipping the script, as the setup failed");
+    } else {
+        runner.report_section("Script");
+        let mut start_time = Instant::now();
+        for entry in queue {
+            runner.wait_until(&mut start_time, entry.line, entry.scheduled_time());
+            runner.execute(entry, &mut start_time, entry.time);
+
+            if !track_progress {
+                continue;
+            }
+            control::set_progress((entry.time * 100).checked_div(duration).unwrap_or(100) as u32, entry.line);
+            while next_milestone < 4 && duration > 0 && entry.time >= duration * next_milestone / 4 {
+                speech::announce(&format!("{} percent done", next_milestone * 25));
+                next_milestone += 1;
+            }
+        }
+    }
+    runner.run_teardown();
+
+    // Nothing stays held after the script, as that would leave the keyboard or mouse stuck until they're pressed again
+    runner.balance.finish();
+    runner.release_held();
+
+    // Wait for all threads to finish execution
+    trace!("Joining {} thread handle(s)", runner.threads.len());
+    for handle in runner.threads.drain(..) {
+        match handle.join() {
+            Ok(_) => trace!("Joined thread handle"),
+            Err(error) => error!("Failed to join thread: {error:?}")
+        }
+    }
+
+    runner.write_report(Outcome::Finished);
+    control::finish_script();
+    ScriptResult { script_name: runner.script_name, failure_count: runner.failure_count, first_failure: runner.first_failure }
+}
+
+/// The state of a script while it's running
+struct Runner {
+    backend: Box<dyn Backend>,
+    options: ExecutionOptions,
+    script_name: String,
+    notify: bool,
+    /// Whether reaching a cue shows a desktop notification
+    notify_cues: bool,
+    /// Whether long waits show the line they wait for and the time until it, which `--verbose` turns on
+    show_next: bool,
+    /// When the run started, for reporting how long it took
+    run_start: Instant,
+    /// Thread handles created during execution
+    threads: Vec<JoinHandle<()>>,
+    /// Outputs that are held down
+    held_mouse: Vec<Button>,
+    held_key: Vec<Key>,
+    key_repeats: Vec<KeyRepeat>,
+    /// Variables set while running, like by `getmouse`
+    variables: HashMap<String, i32>,
+    /// Text variables set while running, like by `getclipboard`
+    texts: HashMap<String, String>,
+    /// The lines of the `@teardown` section, which are taken out when they run so they only run once
+    teardown: Vec<QueueItem>,
+    /// Whether the teardown is running, which finishes even when the run has been aborted
+    in_teardown: bool,
+    /// What happened so far, if a report should be written
+    report: Option<Report>,
+    /// What the script holds down, to warn about keys and mouse buttons that are never released
+    balance: Balance,
+    /// The failures in an attempt of a `retry` block that can still be retried, which are kept instead of reported
+    retry_failures: Option<Vec<(i32, String)>>,
+    /// When the last action was sent, for keeping actions apart with `--min-delay`
+    last_action: Option<Instant>,
+    /// The number of failed actions, along with the line and error of the first one
+    failure_count: u32,
+    first_failure: Option<(i32, String)>
+}
+
+impl Runner {
+    /// Wait until a time in a schedule, aborting the run if requested
+    ///
+    /// The time is in milliseconds after the start time, and the start time is moved forward by any time spent paused, so
+    /// the script continues where it left off.
+    fn wait_until(&mut self, start_time: &mut Instant, line: i32, time: u64) {
+        // Wait in short steps, so pausing and aborting take effect without waiting for the next line
+        let time = Duration::from_millis(time);
+        trace!("Waiting {}ms for line {line}", time.saturating_sub(start_time.elapsed()).as_millis());
+        let next = (self.show_next && time.saturating_sub(start_time.elapsed()) >= NEXT_LINE_STATUS_WAIT).then(|| describe_line(line));
+        while !self.interrupted() && start_time.elapsed() < time {
+            if control::is_paused() {
+                trace!("Paused before line {line}");
+                let pause_start = Instant::now();
+                block::suspend();
+                while control::is_paused() && !self.interrupted() {
+                    thread::sleep(CONTROL_CHECK_INTERVAL);
+                }
+                block::resume();
+                *start_time += pause_start.elapsed();
+                trace!("Resumed after {}ms, shifting the schedule", pause_start.elapsed().as_millis());
+                continue;
+            }
+
+            if let Some(next) = &next {
+                logging::set_status(format!("next: {next} in {:.1}s", time.saturating_sub(start_time.elapsed()).as_secs_f64()));
+            }
+            spin_sleep::sleep(time.saturating_sub(start_time.elapsed()).min(CONTROL_CHECK_INTERVAL));
+        }
+        if next.is_some() {
+            logging::clear_status();
+        }
+        trace!("Woke up for line {line} {}us after its timestamp", start_time.elapsed().saturating_sub(time).as_micros());
+
+        if self.interrupted() {
+            self.abort(line);
+        }
+    }
+
+    /// Wait until the minimum delay has passed since the last action, if there is one
+    ///
+    /// The start time is moved forward by the time waited, so the lines after it are delayed as well.
+    fn space_out(&mut self, start_time: &mut Instant) {
+        if let (Some(min_delay), Some(last_action)) = (self.options.min_delay, self.last_action) {
+            let remaining = min_delay.saturating_sub(last_action.elapsed());
+            if !remaining.is_zero() {
+                trace!("Waiting {}us to keep actions {}ms apart", remaining.as_micros(), min_delay.as_millis());
+                spin_sleep::sleep(remaining);
+                *start_time += remaining;
+            }
+        }
+        self.last_action = Some(Instant::now());
+    }
+
+    /// Execute the actions or the block of a line
+    ///
+    /// Blocks take no time in the schedule, so the start time is moved forward by the time they ran for.
+    fn execute(&mut self, entry: &QueueItem, start_time: &mut Instant, mut current_time: u64) {
+        match &entry.step {
+            Step::Actions(actions) => {
+                if let Some(report) = &mut self.report {
+                    report.line(entry.line, current_time, start_time.elapsed().saturating_sub(Duration::from_millis(entry.time)));
+                }
+
+                for action in actions {
+                    match action {
+                        Action::KeyDown(key, _) => self.balance.press(Output::Key(*key), entry.line),
+                        Action::KeyUp(key) => self.balance.release(Output::Key(*key), entry.line),
+                        Action::MouseDown(button) => self.balance.press(Output::Mouse(*button), entry.line),
+                        Action::MouseUp(button) => self.balance.release(Output::Mouse(*button), entry.line),
+                        Action::Release(output_type) => self.balance.release_all(!matches!(output_type, OutputType::Mouse), !matches!(output_type, OutputType::Key)),
+                        _ => {}
+                    }
+
+                    match action {
+                        Action::WaitKey(key) => {
+                            self.wait_for_key(entry.line, *key, start_time, current_time);
+                            continue;
+                        }
+                        Action::Cue(name) => {
+                            self.cue(name);
+                            continue;
+                        }
+                        Action::Wait(duration) => {
+                            debug!("At {current_time}ms: Wait {duration}ms");
+                            let wait_start = Instant::now();
+                            let mut paused_start = wait_start;
+                            self.wait_until(&mut paused_start, entry.line, *duration);
+                            // The lines after it keep their timestamps, unless the run was paused while waiting
+                            *start_time += paused_start - wait_start;
+                            current_time += duration;
+                            continue;
+                        }
+                        Action::WaitProcess { process, exit, timeout } => {
+                            let description = format!("for {process} to {}", if *exit { "exit" } else { "start" });
+                            self.wait_for(entry.line, &description, *timeout, start_time, current_time, || {
+                                processes::exists(process).map(|exists| exists != *exit)
+                            });
+                            continue;
+                        }
+                        Action::WaitWindow { title, focus, timeout } => {
+                            let description = format!("for a window titled {title:?} to {}", if *focus { "have focus" } else { "open" });
+                            self.wait_for(entry.line, &description, *timeout, start_time, current_time, || {
+                                if *focus {
+                                    let title = title.to_lowercase();
+                                    window::active().map(|window| window.is_some_and(|window| window.title.to_lowercase().contains(&title)))
+                                } else {
+                                    window::find(title).map(|window| window.is_some())
+                                }
+                            });
+                            continue;
+                        }
+                        Action::WaitPixel { x, y, color, tolerance, timeout } => {
+                            match x.resolve(&self.variables).and_then(|x| Ok((x, y.resolve(&self.variables)?))) {
+                                Ok((x, y)) => {
+                                    // Positions are interpreted the same way as absolute mouse movements
+                                    let (physical_x, physical_y) = match self.options.logical_monitors.as_deref() {
+                                        Some(monitors) => monitors::logical_to_physical(monitors, x, y),
+                                        None => (x, y)
+                                    };
+                                    self.wait_for(entry.line, &format!("for the pixel at {x}, {y} to be {color}"), *timeout, start_time, current_time, || {
+                                        Ok(screen::pixel(physical_x, physical_y)?.is_close_to(*color, *tolerance))
+                                    });
+                                }
+                                Err(error) => self.fail(entry.line, format!("At {current_time}ms: Failed to wait for a pixel: {error}"))
+                            }
+                            continue;
+                        }
+                        Action::WaitText { text, region, timeout } => {
+                            let description = format!("for {text:?} to appear on the screen");
+                            let logical_monitors = self.options.logical_monitors.as_deref();
+                            match region.as_ref().map(|region| resolve_region(region, &self.variables, logical_monitors)).transpose() {
+                                Ok(region) => self.wait_for(entry.line, &description, *timeout, start_time, current_time, || {
+                                    Ok(ocr::contains(&ocr::read(region)?, text))
+                                }),
+                                Err(error) => self.fail(entry.line, format!("At {current_time}ms: Failed to wait {description}: {error}"))
+                            }
+                            continue;
+                        }
+                        Action::Exec { command, wait } => {
+                            self.exec(entry.line, command, *wait, start_time, current_time);
+                            continue;
+                        }
+                        Action::Abort { code, message } => {
+                            let message = match resolve_text(message, &self.texts) {
+                                Ok(message) if message.is_empty() => "Stopped by the script".to_string(),
+                                Ok(message) => message,
+                                Err(error) => format!("Stopped by the script, without a message as it failed: {error}")
+                            };
+
+                            // A dry run goes on, as whether a script stops usually depends on what's on the screen
+                            if !self.options.should_execute {
+                                debug!("At {current_time}ms: Abort with exit code {code}: {message}");
+                                continue;
+                            }
+                            self.stop(entry.line, &message, *code);
+                        }
+                        Action::WaitImage { file, template, timeout } => {
+                            let targets_window = self.options.backend_config.window.is_some();
+                            self.wait_for(entry.line, &format!("for {file} to appear on the screen"), *timeout, start_time, current_time, || {
+                                find_image(template, targets_window).map(|position| position.is_some())
+                            });
+                            continue;
+                        }
+                        Action::ClickImage { file, template, button, timeout } => {
+                            // Without a timeout, the image has to be on the screen already
+                            let mut position = None;
+                            let targets_window = self.options.backend_config.window.is_some();
+                            self.wait_for(entry.line, &format!("for {file} to appear on the screen"), Some(timeout.unwrap_or(0)), start_time, current_time, || {
+                                position = find_image(template, targets_window)?;
+                                Ok(position.is_some())
+                            });
+
+                            match position {
+                                Some((x, y)) => {
+                                    self.space_out(start_time);
+                                    debug!("At {current_time}ms: Click {button:?} on {file} at {x}, {y}");
+                                    if let Err(error) = self.backend.move_mouse(x, y, Coordinate::Abs).and_then(|_| self.backend.button(*button, Direction::Click)) {
+                                        self.fail(entry.line, format!("At {current_time}ms: Failed to click on {file}: {error}"));
+                                    }
+                                }
+                                None if !self.options.should_execute => debug!("At {current_time}ms: Click {button:?} on {file}"),
+                                // Not finding the image has been reported by waiting for it
+                                None => {}
+                            }
+                            continue;
+                        }
+                        _ => {}
+                    }
+
+                    self.space_out(start_time);
+                    let held_outputs = (&mut self.held_mouse, &mut self.held_key, &mut self.key_repeats);
+                    match execute_action(self.backend.as_mut(), &self.options, current_time, action.clone(), held_outputs, &mut self.variables, &mut self.texts) {
+                        Ok(Some(handle)) => {
+                            trace!("Started thread for an action on line {}", entry.line);
+                            self.threads.push(handle);
+                        }
+                        Ok(None) => {}
+                        Err(error) => self.fail(entry.line, error)
+                    }
+                }
+            }
+            Step::Loop(repeat) => {
+                let loop_start = Instant::now();
+                self.run_loop(entry.line, repeat, current_time);
+                *start_time += loop_start.elapsed();
+            }
+            Step::Retry(retry) => {
+                let retry_start = Instant::now();
+                self.run_retry(entry.line, retry, current_time);
+                *start_time += retry_start.elapsed();
+            }
+            Step::If(conditional) => {
+                let if_start = Instant::now();
+                self.run_if(entry.line, conditional, current_time);
+                *start_time += if_start.elapsed();
+            }
+            Step::Choose(branches) => {
+                let choice_start = Instant::now();
+                let index = random::below(branches.len());
+                debug!("At {current_time}ms: Choosing branch {} of {}", index + 1, branches.len());
+
+                // Lines in the branch are timed from the start of the choice
+                let mut branch_start = Instant::now();
+                for branch_entry in &branches[index] {
+                    self.wait_until(&mut branch_start, branch_entry.line, branch_entry.scheduled_time());
+                    self.execute(branch_entry, &mut branch_start, current_time + branch_entry.time);
+                }
+                *start_time += choice_start.elapsed();
+            }
+            Step::Parallel(body) => {
+                let parallel_start = Instant::now();
+                debug!("At {current_time}ms: Run tracks at the same time");
+
+                // The lines of every track are already in the order they run in, timed from the start of the block
+                let mut block_start = Instant::now();
+                for body_entry in body {
+                    self.wait_until(&mut block_start, body_entry.line, body_entry.scheduled_time());
+                    self.execute(body_entry, &mut block_start, current_time + body_entry.time);
+                }
+                *start_time += parallel_start.elapsed();
+            }
+        }
+    }
+
+    fn run_loop(&mut self, line: i32, repeat: &Loop, current_time: u64) {
+        let keyword = if repeat.until { "until" } else { "while" };
+
+        // The condition can't be relied on without sending events, so a dry run shows the lines of the loop once
+        if !self.options.should_execute {
+            debug!("At {current_time}ms: Loop {keyword} {}, showing its lines once", repeat.condition);
+            let mut start_time = Instant::now();
+            for entry in &repeat.body {
+                self.execute(entry, &mut start_time, current_time + entry.time);
+            }
+            return;
+        }
+
+        debug!("At {current_time}ms: Loop {keyword} {}", repeat.condition);
+        let mut iterations = 0;
+        loop {
+            match repeat.condition.check(self.options.logical_monitors.as_deref()) {
+                Ok(result) if result == repeat.until => break,
+                Ok(_) => {}
+                Err(error) => {
+                    self.fail(line, format!("Failed to check loop condition: {error}"));
+                    break;
+                }
+            }
+
+            // Lines in the loop are timed from the start of the iteration
+            let mut start_time = Instant::now();
+            for entry in &repeat.body {
+                self.wait_until(&mut start_time, entry.line, entry.scheduled_time());
+                self.execute(entry, &mut start_time, self.run_start.elapsed().as_millis() as u64);
+            }
+            self.wait_until(&mut start_time, line, LOOP_CHECK_INTERVAL);
+            iterations += 1;
+        }
+        trace!("Loop on line {line} ended after {iterations} iteration(s)");
+    }
+
+    fn run_if(&mut self, line: i32, conditional: &Conditional, current_time: u64) {
+        // The condition can't be relied on without sending events, so a dry run shows the lines for when it's true
+        let lines = if !self.options.should_execute {
+            debug!("At {current_time}ms: If {}, showing the lines for when it's true", conditional.condition);
+            &conditional.then
+        } else {
+            match conditional.condition.check(self.options.logical_monitors.as_deref()) {
+                Ok(result) => {
+                    debug!("At {current_time}ms: If {}, which is {result}", conditional.condition);
+                    if result { &conditional.then } else { &conditional.otherwise }
+                }
+                Err(error) => {
+                    self.fail(line, format!("Failed to check condition: {error}"));
+                    return;
+                }
+            }
+        };
+
+        // Lines in the block are timed from the start of the block
+        let mut start_time = Instant::now();
+        for entry in lines {
+            self.wait_until(&mut start_time, entry.line, entry.scheduled_time());
+            self.execute(entry, &mut start_time, current_time + entry.time);
+        }
+    }
+
+    fn run_retry(&mut self, line: i32, retry: &Retry, current_time: u64) {
+        // Nothing fails without sending events, so a dry run shows the lines of the block once
+        if !self.options.should_execute {
+            debug!("At {current_time}ms: Retry up to {} time(s), showing its lines once", retry.attempts);
+            let mut start_time = Instant::now();
+            for entry in &retry.body {
+                self.execute(entry, &mut start_time, current_time + entry.time);
+            }
+            return;
+        }
+
+        debug!("At {current_time}ms: Retry up to {} time(s)", retry.attempts);
+        let mut delay = retry.delay as f64;
+        for attempt in 1..=retry.attempts {
+            // The failures of the last attempt are reported like any other, or kept by a retry block around this one
+            let outer_failures = (attempt < retry.attempts).then(|| self.retry_failures.replace(Vec::new()));
+
+            // Lines in the block are timed from the start of the attempt
+            let mut start_time = Instant::now();
+            for entry in &retry.body {
+                self.wait_until(&mut start_time, entry.line, entry.scheduled_time());
+                self.execute(entry, &mut start_time, self.run_start.elapsed().as_millis() as u64);
+            }
+
+            let Some(outer_failures) = outer_failures else {
+                break;
+            };
+            let failures = std::mem::replace(&mut self.retry_failures, outer_failures).unwrap_or_default();
+            if failures.is_empty() {
+                trace!("Retry block on line {line} succeeded on attempt {attempt}");
+                return;
+            }
+
+            for (failed_line, error) in failures {
+                warn!("Attempt {attempt} of {} failed on line {failed_line}: {error}", retry.attempts);
+            }
+            debug!("Retrying the block on line {line} in {}ms", delay.round());
+            self.wait_until(&mut Instant::now(), line, delay.round() as u64);
+            delay *= retry.backoff;
+        }
+        trace!("Retry block on line {line} ran out of attempts");
+    }
+
+    /// Announce that the script has reached a cue, in the log, the tray icon, and with `--announce` and `--notify-cues`
+    fn cue(&self, name: &str) {
+        info!("Cue: {name}");
+        control::set_cue(name);
+        if self.options.should_execute {
+            speech::announce(name);
+        }
+        if self.notify_cues {
+            notification::send(&format!("TATATA: {name}"), &format!("{} reached {name}", self.script_name));
+        }
+    }
+
+    /// Wait until the operator presses a key
+    ///
+    /// Like pausing, this moves the start time forward by the time spent waiting, so the script continues where it left off.
+    fn wait_for_key(&mut self, line: i32, key: Option<Key>, start_time: &mut Instant, current_time: u64) {
+        let name = key.map_or("any key".to_string(), |key| format!("{key:?}"));
+
+        // There's nobody to wait for in a dry run
+        if !self.options.should_execute {
+            debug!("At {current_time}ms: Wait for {name} to be pressed");
+            return;
+        }
+
+        info!("Waiting for {name} to be pressed");
+        speech::announce(&format!("Press {name} to continue"));
+        let wait_start = Instant::now();
+        // The key has to come from the physical keyboard, so it's let through while waiting
+        block::suspend();
+        let result = keyboard::wait_for_press(key, || self.interrupted());
+        block::resume();
+        *start_time += wait_start.elapsed();
+        trace!("Waited {}ms for {name}, shifting the schedule", wait_start.elapsed().as_millis());
+
+        match result {
+            Ok(true) => {}
+            Ok(false) => self.abort(line),
+            Err(error) => self.fail(line, format!("Failed to wait for {name}: {error}"))
+        }
+    }
+
+    /// Wait until a check passes, like a process having started, or the timeout runs out
+    ///
+    /// Like pausing, this moves the start time forward by the time spent waiting, so the script continues where it left off.
+    fn wait_for(&mut self, line: i32, description: &str, timeout: Option<u64>, start_time: &mut Instant, current_time: u64, mut check: impl FnMut() -> Result<bool, String>) {
+        // What would be running or on the screen can't be known without running the script
+        if !self.options.should_execute {
+            debug!("At {current_time}ms: Wait {description}");
+            return;
+        }
+
+        debug!("At {current_time}ms: Waiting {description}");
+        let wait_start = Instant::now();
+        let timeout = timeout.map(Duration::from_millis);
+        let result = loop {
+            match check() {
+                Ok(true) => break Ok(()),
+                Ok(false) => {}
+                Err(error) => break Err(error)
+            }
+            if timeout.is_some_and(|timeout| wait_start.elapsed() >= timeout) {
+                break Err(format!("gave up after {}ms", wait_start.elapsed().as_millis()));
+            }
+            if self.interrupted() {
+                self.abort(line);
+            }
+            thread::sleep(WAIT_CHECK_INTERVAL);
+        };
+        *start_time += wait_start.elapsed();
+        trace!("Waited {}ms {description}, shifting the schedule", wait_start.elapsed().as_millis());
+
+        if let Err(error) = result {
+            self.fail(line, format!("At {current_time}ms: Failed to wait {description}: {error}"));
+        }
+    }
+
+    /// Run a shell command, and if it should be waited for, move the rest of the script back by the time it ran for
+    ///
+    /// A command that isn't waited for runs on after the script has finished.
+    fn exec(&mut self, line: i32, command: &str, wait: bool, start_time: &mut Instant, current_time: u64) {
+        if !self.options.should_execute {
+            debug!("At {current_time}ms: Run {command:?}{}", if wait { " and wait for it to finish" } else { "" });
+            return;
+        }
+
+        debug!("At {current_time}ms: Running {command:?}");
+        let mut child = match shell(command).spawn() {
+            Ok(child) => child,
+            Err(error) => {
+                self.fail(line, format!("At {current_time}ms: Failed to run {command:?}: {error}"));
+                return;
+            }
+        };
+        if !wait {
+            // Collect the exit status whenever it finishes, so it doesn't linger as a zombie process
+            thread::spawn(move || child.wait());
+            return;
+        }
+
+        let mut status = None;
+        self.wait_for(line, &format!("for {command:?} to finish"), None, start_time, current_time, || {
+            status = child.try_wait().map_err(|error| error.to_string())?;
+            Ok(status.is_some())
+        });
+        if let Some(status) = status.filter(|status| !status.success()) {
+            self.fail(line, format!("At {current_time}ms: {command:?} exited with {status}"));
+        }
+    }
+
+    /// Run the lines of a `@setup` or `@teardown` section, which are timed from the start of the section
+    fn run_section(&mut self, lines: &[QueueItem]) {
+        let mut start_time = Instant::now();
+        for entry in lines {
+            self.wait_until(&mut start_time, entry.line, entry.scheduled_time());
+            self.execute(entry, &mut start_time, entry.time);
+        }
+    }
+
+    /// Run the `@teardown` section, if it hasn't run yet
+    fn run_teardown(&mut self) {
+        let teardown = std::mem::take(&mut self.teardown);
+        if teardown.is_empty() {
+            return;
+        }
+
+        debug!("Running teardown");
+        self.report_section("Teardown");
+        self.in_teardown = true;
+        self.run_section(&teardown);
+        self.in_teardown = false;
+    }
+
+    /// Whether the run should stop, which is never the case during the teardown, as it's what cleans up after stopping
+    fn interrupted(&self) -> bool {
+        !self.in_teardown && interrupt::requested()
+    }
+
+    /// Release every held mouse button and key
+    fn release_held(&mut self) {
+        repeat::stop(&mut self.key_repeats, None);
+        for button in self.held_mouse.drain(..) {
+            let _ = self.backend.button(button, Direction::Release);
+        }
+        for key in self.held_key.drain(..) {
+            let _ = self.backend.key(key, Direction::Release);
+        }
+    }
+
+    fn report_section(&mut self, name: &'static str) {
+        if let Some(report) = &mut self.report {
+            report.section(name);
+        }
+    }
+
+    fn write_report(&self, outcome: Outcome) {
+        if let Some(report) = &self.report {
+            report.write(&self.script_name, outcome, self.run_start.elapsed());
+        }
+    }
+
+    /// Report a failed action, and keep running the script
+    fn fail(&mut self, line: i32, error: String) {
+        if let Some(failures) = &mut self.retry_failures {
+            failures.push((line, error));
+            return;
+        }
+
+        Diagnostic::new(line, &error).kind(ErrorKind::Injection).report();
+        if let Some(report) = &mut self.report {
+            report.failure(line, &error);
+        }
+        self.failure_count += 1;
+        self.first_failure.get_or_insert((line, error));
+    }
+
+    /// Stop the run before a line, releasing everything that is held down and running the teardown
+    fn abort(&mut self, line: i32) -> ! {
+        let diagnostic = Diagnostic::new(line, "Aborted").kind(ErrorKind::Abort).label("the run was stopped before this line");
+        let notification = ("TATATA run aborted", format!("{} was aborted before line {line}", self.script_name));
+        self.finish_early(Outcome::Aborted(line), &diagnostic, notification, "Script aborted");
+    }
+
+    /// Stop the run on an `abort` line, exiting with its exit code once the teardown has run
+    fn stop(&mut self, line: i32, message: &str, code: i32) -> ! {
+        // Other scripts run with `--parallel` are aborted, and the last of them to stop exits with this code
+        control::set_exit_code(code);
+        interrupt::request();
+        let diagnostic = Diagnostic::new(line, message).kind(ErrorKind::Assertion).label(format!("the script stopped here with exit code {code}"));
+        let notification = ("TATATA run stopped", format!("{} stopped itself on line {line}", self.script_name));
+        self.finish_early(Outcome::Stopped(line), &diagnostic, notification, "Script stopped");
+    }
+
+    /// Release everything that is held down, run the teardown and exit, or wait for the other scripts to stop
+    fn finish_early(&mut self, outcome: Outcome, diagnostic: &Diagnostic, notification: (&str, String), announcement: &str) -> ! {
+        // Don't leave anything held down after exiting, including by the teardown
+        self.release_held();
+
+        // An interrupted retry block won't be retried, so failures in the teardown are reported as usual
+        self.retry_failures = None;
+        self.run_teardown();
+        self.release_held();
+
+        if self.notify {
+            let (title, body) = notification;
+            notification::send(title, &format!("{body} after {}", notification::format_duration(self.run_start.elapsed())));
+        }
+        self.write_report(outcome);
+
+        // Scripts run with `--parallel` are aborted together, and the last of them to stop exits
+        diagnostic.report();
+        if !control::finish_script() {
+            loop {
+                thread::park();
+            }
+        }
+        speech::announce(announcement);
+        tray::stop();
+        speech::wait();
+        process::exit(control::exit_code().unwrap_or(diagnostic.exit_code()));
+    }
+}
+
+/// Find the window an action is about, which has to exist by the time the action runs
+fn find_window(title: &str) -> Result<window::Window, String> {
+    window::find(title)?.ok_or_else(|| format!("couldn't find a window with a title containing {title:?}"))
+}
+
+/// Find the center of the first place an image is on the screen, in physical pixels
+fn find_image(template: &Template, targets_window: bool) -> Result<Option<(i32, i32)>, String> {
+    // The whole screen is searched, so the positions would be wrong in a window targeted with --window
+    if targets_window {
+        return Err("images can't be clicked when targeting a window".to_string());
+    }
+
+    let (x, y, width, height) = screen::bounds()?;
+    let screen = screen::capture(x, y, width, height)?;
+    Ok(template.find(&screen).map(|(left, top)| (x + (left + template.width / 2) as i32, y + (top + template.height / 2) as i32)))
+}
+
+/// Make all display connections use the given display instead of the default one
+fn select_display(display: Option<String>) {
+    let Some(display) = display else {
+        return;
+    };
+
+    if cfg!(all(unix, not(target_os = "macos"))) {
+        // X display names contain a colon (like `:99`), while Wayland display names are socket names (like `wayland-1`)
+        if display.contains(':') {
+            std::env::set_var("DISPLAY", display);
+            std::env::remove_var("WAYLAND_DISPLAY");
+        } else {
+            std::env::set_var("WAYLAND_DISPLAY", display);
+        }
+    } else {
+        warn!("Selecting a display is only supported on Linux, ignoring --display {display:?}");
+    }
+}
+
+fn list_monitors() {
+    let monitors = monitors::get_monitors();
+    if monitors.is_empty() {
+        println!("No monitors found");
+        return;
+    }
+
+    for (index, monitor) in monitors.iter().enumerate() {
+        let number = index + 1;
+        let prefix = format!("MONITOR{number}");
+        let primary = if monitor.is_primary { " (primary)" } else { "" };
+        println!("Monitor {number}: {:?}{primary}", monitor.name);
+        println!("  Position: {}, {} (${prefix}_X, ${prefix}_Y)", monitor.x, monitor.y);
+        println!("  Size: {}x{} (${prefix}_W, ${prefix}_H)", monitor.width, monitor.height);
+        println!("  Scale factor: {}", monitor.scale_factor);
+    }
+}
+
+/// Wait for a click, and print the position of the click in every form a script can use, along with the color there
+fn pick(mousemove: Option<PositionForm>) {
+    let monitors = monitors::get_monitors();
+    println!("Click anywhere to pick a position");
+    let (x, y) = mouse::wait_for_click().unwrap_or_else(|error| diagnostic::fail(ErrorKind::Setup, error));
+
+    let absolute = (x.to_string(), y.to_string());
+    println!("Position: {x}, {y}");
+
+    // Positions on a monitor keep working when the monitors are arranged differently, or have a different resolution
+    let mut relative = None;
+    let mut percent = None;
+    if let Some((index, monitor)) = monitors.iter().enumerate().find(|(_, monitor)| monitor.contains_physical(x, y)) {
+        let prefix = format!("$MONITOR{}", index + 1);
+        let (offset_x, offset_y) = (x - monitor.x, y - monitor.y);
+        let position = (format!("{prefix}_X+{offset_x}"), format!("{prefix}_Y+{offset_y}"));
+        println!("Monitor {}: {offset_x}, {offset_y} ({}, {})", index + 1, position.0, position.1);
+        relative = Some(position);
+
+        let (per_mille_x, per_mille_y) = (offset_x as i64 * 1000 / monitor.width as i64, offset_y as i64 * 1000 / monitor.height as i64);
+        let position = (format!("{prefix}_X+{prefix}_W*{per_mille_x}/1000"), format!("{prefix}_Y+{prefix}_H*{per_mille_y}/1000"));
+        println!("Percent of monitor {}: {:.1}%, {:.1}% ({}, {})", index + 1, per_mille_x as f64 / 10.0, per_mille_y as f64 / 10.0, position.0, position.1);
+        percent = Some(position);
+    }
+
+    match screen::pixel(x, y) {
+        Ok(color) => println!("Color: {color}"),
+        Err(error) => warn!("Failed to read the color: {error}")
+    }
+
+    if let Some(form) = mousemove {
+        let position = match form {
+            PositionForm::Absolute => Some(absolute),
+            PositionForm::Monitor => relative,
+            PositionForm::Percent => percent
+        };
+        match position {
+            Some((x, y)) => println!("0 > mousemove abs {x} {y}"),
+            None => warn!("The position isn't on any monitor, so it can't be written as a position on a monitor")
+        }
+    }
+}
+
+/// Print the name of every key pressed on the keyboard, so special keys don't have to be guessed
+fn list_keys() -> Result<(), String> {
+    // Every name a key can be written as, by the code of the key on this keyboard layout
+    let characters = ('a'..='z').chain('0'..='9').chain("`-=[]\\;',./".chars()).map(String::from);
+    let mut names: HashMap<u32, String> = HashMap::new();
+    for name in KEY_NAMES.iter().map(|name| name.to_string()).chain(characters) {
+        if let Ok(code) = parse_key(&name).and_then(keyboard::code) {
+            names.entry(code).or_insert(name);
+        }
+    }
+
+    println!("Press keys to print their names, and Ctrl+C to stop");
+    let mut held = keyboard::held_keys()?;
+    loop {
+        let now_held = keyboard::held_keys()?;
+        let pressed: Vec<u32> = now_held.iter().copied().filter(|code| !held.contains(code)).collect();
+
+        // Some keys are held under more than one code, like Shift under both Shift and Left Shift on Windows
+        let known: Vec<&String> = pressed.iter().filter_map(|code| names.get(code)).collect();
+        match (known.as_slice(), pressed.as_slice()) {
+            (_, []) => {}
+            ([], _) => println!("Unsupported (key code {})", pressed.iter().map(u32::to_string).collect::<Vec<_>>().join(", ")),
+            (known, _) => {
+                for name in known {
+                    println!("{name}");
+                }
+            }
+        }
+
+        held = now_held;
+        thread::sleep(keyboard::POLL_INTERVAL);
+    }
+}
+
+/// Simulate a script without sending any events, and write what it does into an animated GIF
+///
+/// Lines run like in a dry run, so the lines of a loop run once, and a choice picks a random branch.
+fn render(file: PathBuf, output: &Path, background: Option<PathBuf>, scale: f64, fps: u32, given: Vec<(String, i32)>) -> Result<(), String> {
+    if !(scale > 0.0 && scale <= 1.0) {
+        return Err(format!("The scale has to be above 0 and at most 1, not {scale}"));
+    }
+    let background = background.map(|path| png::load(&path)).transpose()?;
+
+    // Without a display, the script runs on a single monitor the size of the screenshot, or a common screen size
+    let mut monitors = monitors::get_monitors();
+    let size = match (&background, monitors.first()) {
+        (Some(background), _) => (background.width, background.height),
+        (None, Some(primary)) => (primary.width, primary.height),
+        (None, None) => (1920, 1080)
+    };
+    if monitors.is_empty() {
+        monitors.push(Monitor { name: "preview".to_string(), x: 0, y: 0, width: size.0, height: size.1, scale_factor: 1.0, is_primary: true });
+    }
+    let mut variables = monitors::get_variables(&monitors);
+    variables.extend(given);
+    let script = parse_file(file, &mut variables, &TagFilter { only: Vec::new(), skip: Vec::new() });
+
+    // The sections run one after the other, each starting when the last input of the one before it has ended
+    let mut events = Vec::new();
+    let mut offset = 0;
+    for lines in [&script.setup, &script.queue, &script.teardown] {
+        render_events(lines, offset, &mut events);
+        offset += lines.iter().map(|entry| entry.time + entry.step.duration()).max().unwrap_or(0);
+    }
+
+    let gif = render::render(&events, render::Options { background, size, scale, fps })?;
+    fs::write(output, gif).map_err(|error| format!("Couldn't write {}: {error}", output.display()))?;
+    info!("Wrote the preview to {}", output.display());
+    Ok(())
+}
+
+/// Collect the events of lines that show up in a preview, at their time in the script
+///
+/// Values that are only known while running, like variables set by `getmouse`, can't be simulated, so the actions using
+/// them are left out.
+fn render_events(lines: &[QueueItem], offset: u64, events: &mut Vec<(u64, render::Event)>) {
+    let no_variables = HashMap::new();
+    for entry in lines {
+        let mut time = offset + entry.time;
+        let actions = match &entry.step {
+            Step::Actions(actions) => actions,
+            Step::Loop(repeat) => {
+                render_events(&repeat.body, time, events);
+                continue;
+            }
+            Step::Retry(retry) => {
+                render_events(&retry.body, time, events);
+                continue;
+            }
+            Step::If(conditional) => {
+                render_events(&conditional.then, time, events);
+                continue;
+            }
+            Step::Choose(branches) => {
+                render_events(&branches[random::below(branches.len())], time, events);
+                continue;
+            }
+            Step::Parallel(body) => {
+                render_events(body, time, events);
+                continue;
+            }
+        };
+
+        for action in actions {
+            let event = match action {
+                Action::MouseMove { x, y, time: duration, method, .. } => match (x.resolve(&no_variables), y.resolve(&no_variables)) {
+                    (Ok(x), Ok(y)) => render::Event::Move { x, y, relative: *method == Coordinate::Rel, duration: *duration },
+                    _ => continue
+                },
+                Action::MousePath { points, time: duration, method, .. } => match points.iter().map(|(x, y)| Ok((x.resolve(&no_variables)?, y.resolve(&no_variables)?))).collect::<Result<_, String>>() {
+                    Ok(points) => render::Event::Path { points, relative: *method == Coordinate::Rel, duration: *duration },
+                    _ => continue
+                },
+                Action::MouseTrace { points, speed, .. } => render::Event::Trace { points: Arc::clone(points), speed: *speed },
+                Action::MouseDown(button) => render::Event::MouseDown(*button),
+                Action::MouseUp(button) => render::Event::MouseUp(*button),
+                Action::KeyDown(key, _) => render::Event::KeyDown(key_name(*key)),
+                Action::KeyUp(key) => render::Event::KeyUp(key_name(*key)),
+                Action::HoldKey { key, duration, .. } => {
+                    events.push((time + duration, render::Event::KeyUp(key_name(*key))));
+                    render::Event::KeyDown(key_name(*key))
+                }
+                Action::Wait(duration) => {
+                    time += duration;
+                    continue;
+                }
+                Action::Text { text, typing } => match resolve_text(text, &HashMap::new()) {
+                    Ok(text) => render::Event::Text { text, delay: typing.delay },
+                    Err(_) => continue
+                },
+                Action::TypeRandom { pattern, length, typing } => match length.resolve(&no_variables) {
+                    Ok(length) => render::Event::Text { text: pattern.generate(length.max(0) as usize), delay: typing.delay },
+                    Err(_) => continue
+                },
+                _ => continue
+            };
+            events.push((time, event));
+        }
+    }
+}
+
+/// Get the name `keydown` uses for a key
+fn key_name(key: Key) -> String {
+    match key {
+        Key::Unicode(character) => character.to_string(),
+        _ => KEY_NAMES.iter().find(|name| parse_key(name) == Ok(key)).map_or(format!("{key:?}").to_lowercase(), |name| name.to_string())
+    }
+}
+
+/// Get what a line of the script does as it's written, without its timestamp, like `keydown ctrl`
+fn describe_line(line: i32) -> String {
+    let text = diagnostic::source_line(line).unwrap_or_default();
+    text.split_once('>').map_or(text, |(_, actions)| actions).trim().to_string()
+}
+
+/// Print the header of a script, or of the script in a bundle
+fn print_header(file: &Path) -> Result<(), String> {
+    let path = if file.extension().is_some_and(|extension| extension == bundle::EXTENSION) { bundle::open(file)? } else { file.to_path_buf() };
+    let content = bundle::read_to_string(&path).map_err(|error| format!("Couldn't read {}: {error}", path.display()))?;
+    let content = diagnostic::set_source(path.display().to_string(), content);
+    let header = header::read(content).unwrap_or_else(|(line, token, error)| {
+        Diagnostic::new(line, error).at(token).exit();
+    });
+    header.print();
+    Ok(())
+}
+
+/// Log the title of a script from its header, and check that the screen is the size the script was written for
+///
+/// A different size fails the run, as the positions in the script would be off, except in a dry run or when the script
+/// adapts its positions with `@offset` and `@scale` lines.
+fn check_header(script_name: &str, header: &Header, transformed: bool, monitors: &[Monitor], dry_run: bool) {
+    match (&header.title, &header.author) {
+        (Some(title), Some(author)) => info!("{script_name}: {title} by {author}"),
+        (Some(title), None) => info!("{script_name}: {title}"),
+        _ => {}
+    }
+
+    let Some((width, height)) = header.resolution else {
+        return;
+    };
+    let Some(screen) = monitors.first() else {
+        warn!("Couldn't check that the screen is {width}x{height}, which {script_name} was written for");
+        return;
+    };
+    if (screen.width, screen.height) != (width, height) {
+        let message = format!("{script_name} was written for a {width}x{height} screen, but the screen is {}x{}", screen.width, screen.height);
+        if transformed {
+            info!("{message}, which its `@offset` and `@scale` lines adapt its positions to");
+        } else if dry_run {
+            warn!("{message}");
+        } else {
+            diagnostic::fail(ErrorKind::Setup, message);
+        }
+    }
+}
+
+/// Print the longest idle periods between lines, longest first
+fn list_gaps(script: &Script) {
+    let mut gaps = Vec::new();
+    for lines in [&script.setup, &script.queue, &script.teardown] {
+        find_gaps(lines, &mut gaps);
+    }
+    if gaps.is_empty() {
+        println!("No gaps found");
+        return;
+    }
+
+    gaps.sort_by_key(|&(line, length)| (std::cmp::Reverse(length), line));
+    let total: u64 = gaps.iter().map(|(_, length)| length).sum();
+    println!("{} gap(s), {} in total", gaps.len(), notification::format_duration(Duration::from_millis(total)));
+    for (line, length) in gaps.iter().take(MAX_LISTED_GAPS) {
+        println!("  {length}ms before line {line}");
+    }
+}
+
+/// Find the idle periods before every line, counted from when the actions of the line before it are done, and including
+/// the lines inside blocks
+fn find_gaps(lines: &[QueueItem], gaps: &mut Vec<(i32, u64)>) {
+    let mut busy_until = 0;
+    for entry in lines {
+        let gap = entry.time.saturating_sub(busy_until);
+        if gap > 0 {
+            gaps.push((entry.line, gap));
+        }
+        busy_until = busy_until.max(entry.time + entry.step.duration());
+
+        for body in entry.step.bodies() {
+            find_gaps(body, gaps);
+        }
+    }
+}
+
+/// Shorten the idle periods before lines down to the given length, moving the lines after them forward
+fn compress_gaps(lines: &mut [QueueItem], max_gap: u64) {
+    let mut busy_until = 0;
+    let mut removed = 0;
+    for entry in lines {
+        let gap = entry.time.saturating_sub(busy_until);
+        busy_until = busy_until.max(entry.time + entry.step.duration());
+        if gap > max_gap {
+            info!("Shortening the {gap}ms gap before line {} to {max_gap}ms", entry.line);
+            removed += gap - max_gap;
+        }
+        entry.time -= removed;
+
+        for body in entry.step.bodies_mut() {
+            compress_gaps(body, max_gap);
+        }
+    }
+}
+
+/// Move every line to right after the line before it, keeping only the time before the lines marked with `!`
+fn remove_waits(lines: &mut [QueueItem]) {
+    let (mut previous_written, mut previous) = (0, 0);
+    for entry in lines {
+        let delay = if entry.mandatory { entry.time - previous_written } else { 0 };
+        previous_written = entry.time;
+        previous += delay;
+        entry.time = previous;
+
+        for body in entry.step.bodies_mut() {
+            remove_waits(body);
+        }
+    }
+}
+
+fn parse_file(file_path: PathBuf, variables: &mut HashMap<String, i32>, tag_filter: &TagFilter) -> Script {
+    // Check if file exists
+    if !file_path.exists() {
+        diagnostic::fail(ErrorKind::Setup, format!("File does not exist: {}", file_path.display()));
+    }
+
+    // A bundle is run by running the script inside it, which finds the other files of the bundle next to it
+    let file_path = if file_path.extension().is_some_and(|extension| extension == bundle::EXTENSION) {
+        bundle::open(&file_path).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Setup, error))
+    } else {
+        file_path
+    };
+
+    // Every tag in the script and the scripts it includes, to warn about tags given with --tags or --skip-tags that
+    // don't match anything
+    let mut seen_tags = HashSet::new();
+    let (script, _) = parse_script(&file_path, variables, tag_filter, &mut seen_tags, &[]);
+    for tag in tag_filter.only.iter().chain(&tag_filter.skip).filter(|tag| !seen_tags.contains(*tag)) {
+        warn!("No lines are tagged #{tag}");
+    }
+
+    script
+}
+
+/// Make a line of an included script and the lines in its blocks belong to the `@include` line, which errors and
+/// reports point to
+fn relocate(item: &mut QueueItem, line: i32) {
+    item.line = line;
+    for body in item.step.bodies_mut() {
+        for inner in body {
+            relocate(inner, line);
+        }
+    }
+}
+
+/// Replace the parameters of a macro in one of its lines, like `$x`, with the arguments it's called with
+fn substitute_parameters(line: &str, parameters: &[String], arguments: &[&str]) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let length = after.find(|character: char| !character.is_ascii_alphanumeric() && character != '_').unwrap_or(after.len());
+        match parameters.iter().position(|parameter| *parameter == after[..length]) {
+            Some(index) => result.push_str(arguments[index]),
+            None => result.push_str(&rest[start..start + 1 + length])
+        }
+        rest = &after[length..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Parse the lines of a script, along with the variables its lines set
+///
+/// Scripts included with `@include` are parsed the same way, with the scripts that include them in `included_from`.
+fn parse_script(
+    file_path: &Path,
+    variables: &mut HashMap<String, i32>,
+    tag_filter: &TagFilter,
+    seen_tags: &mut HashSet<String>,
+    included_from: &[PathBuf]
+) -> (Script, HashMap<String, VariableKind>) {
+    // Validate file name (https://github.com/soni801/tatata/issues/1)
+    let file_name = file_path.to_str().unwrap_or_else(|| {
+        diagnostic::fail(ErrorKind::Setup, format!("Invalid file name: {}", file_path.display()));
+    });
+    if !file_name.ends_with(".tatata") {
+        diagnostic::fail(ErrorKind::Setup, format!("Not a TATATA file: {}", file_path.display()));
+    }
+
+    // Try to open file
+    let file_content = bundle::read_to_string(file_path).unwrap_or_else(|error| {
+        diagnostic::fail(ErrorKind::Setup, format!("Couldn't open input file for execution: {error}"));
+    });
+    let file_content = diagnostic::set_source(file_name.to_string(), file_content);
+    let directory = file_path.parent().unwrap_or(Path::new(""));
+
+    // The comments at the top of the script describe it, like the size of the screen it was written for
+    let header = header::read(file_content).unwrap_or_else(|(line, token, error)| {
+        Diagnostic::new(line, error).at(token).exit();
+    });
+
+    // Create empty queue
+    let mut queue: Vec<QueueItem> = Vec::new();
+
+    // The lines of the `@setup` and `@teardown` sections, along with the lines they start on
+    let mut setup: Option<(i32, Vec<QueueItem>)> = None;
+    let mut teardown: Option<(i32, Vec<QueueItem>)> = None;
+
+    // Blocks that have been opened but not closed yet, innermost last
+    let mut open_blocks: Vec<OpenBlock> = Vec::new();
+
+    // Variables that are set while running, which can be used by the lines after the one setting them
+    let mut assigned_variables: HashMap<String, VariableKind> = HashMap::new();
+
+    // How fast the lines after an `@speed` line run, where 0.5 is half speed
+    let mut speed: f64 = 1.0;
+
+    // Whether there are `@offset` or `@scale` lines, which change the positions of the lines after them
+    let mut transformed = false;
+
+    // What the actions of the lines after `@defaults` sections use for the arguments they leave out, along with how
+    // `text` actions type, set by an `@typing_delay` line
+    let mut defaults = Defaults::default();
+
+    // The `//! cue:` comments since the last line that runs, along with their lines, which are announced by the next one
+    let mut cues: Vec<(i32, String)> = Vec::new();
+
+    // Whether the macros of the standard library can be used, which they can after an `@include std` line
+    let mut includes_std = false;
+
+    // The timestamp of the last line outside of blocks as it's written, before it's scaled by the speed
+    let mut written_timestamp: u64 = 0;
+
+    // The first line that isn't empty or a comment, which is where the language version has to be given if it is
+    let mut first_line: Option<i32> = None;
+    let mut version_line: Option<i32> = None;
+
+    // Macros that have been defined so far, by their names
+    let mut macros: HashMap<String, Macro> = HashMap::new();
+
+    // The positions defined with `point`, by their names
+    let mut points: HashMap<String, NamedPoint> = HashMap::new();
+
+    // Parse file, taking the lines that are left from the end, where the lines of a called macro are put in place of the
+    // `call` line along with whether they come from a macro
+    let mut lines: Vec<(i32, Cow<'static, str>, bool)> = file_content.lines().zip(1..).map(|(line, index)| (index, Cow::Borrowed(line), false)).collect();
+    lines.reverse();
+    let mut in_comment = false;
+    while let Some((line_index, line_text, in_macro)) = lines.pop() {
+        let mut line: &str = &line_text;
+        // Check if the line closes a multi-line comment
+        if in_comment {
+            if line.contains("*/") {
+                in_comment = false;
+                line = line.split("*/").collect::<Vec<&str>>()[1];
+            } else {
+                continue;
+            }
+        }
+
+        // Check if the line is a cue, like `//! cue: Logging in`
+        if let Some(annotation) = line.trim().strip_prefix("//!") {
+            if let Some(name) = annotation.trim().strip_prefix("cue:") {
+                let name = name.trim();
+                if name.is_empty() {
+                    Diagnostic::new(line_index, "The cue has no name")
+                        .at(line.trim())
+                        .label("expected the name of the part of the script after `cue:`, like `//! cue: Logging in`")
+                        .exit();
+                }
+                cues.push((line_index, name.to_string()));
+            }
+            continue;
+        }
+
+        // Check if the line opens a multi-line comment
+        let mut line_without_comment: String;
+        if line.contains("/*") {
+            line_without_comment = line.split("/*").next().unwrap().to_string();
+
+            // Check if the multi-line comment closes on the same line
+            if line.contains("*/") {
+                let after_comment = line.split("*/").collect::<Vec<&str>>()[1];
+                line_without_comment.push_str(after_comment);
+            } else {
+                in_comment = true;
+            }
+
+            line = line_without_comment.as_str();
+        }
+
+        // Check if the line contains a single-line comment
+        line = if line.contains("//") {
+            line.split("//").next().unwrap()
+        } else {
+            line
+        };
+
+        // Skip if line is empty or only whitespace
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // Take the tags at the start of the line, like `#setup +100>text hello`
+        let mut tags: Vec<&str> = Vec::new();
+        line = line.trim();
+        while let Some(rest) = line.strip_prefix('#') {
+            let tag = &rest[..rest.find(char::is_whitespace).unwrap_or(rest.len())];
+            if tag.is_empty() || !tag.chars().all(|character| character.is_ascii_alphanumeric() || character == '_' || character == '-') {
+                Diagnostic::new(line_index, format!("Invalid tag {:?}", &line[..tag.len() + 1]))
+                    .at(&line[..tag.len() + 1])
+                    .label("tags are a # followed by letters, digits, underscores and dashes, like #setup")
+                    .exit();
+            }
+            tags.push(tag);
+            seen_tags.insert(tag.to_string());
+            line = rest[tag.len()..].trim_start();
+        }
+        let skip = !tag_filter.allows(&tags);
+
+        let trimmed = line.trim();
+        if !tags.is_empty() && (trimmed.is_empty() || trimmed.starts_with('@') || trimmed.starts_with('}') || trimmed.starts_with("macro ") || trimmed.starts_with("point ")) {
+            Diagnostic::new(line_index, "Only action lines, calls and blocks can be tagged")
+                .at(if trimmed.is_empty() { tags[tags.len() - 1] } else { trimmed })
+                .label("tags go before the timestamp of a line, before `call`, or before `while`, `until`, `choose`, `parallel`, `retry` or `ifpixel` to tag a whole block")
+                .exit();
+        }
+
+        // Check if the line says which version of the language the script is written for, like `@tatata 1`, which has to
+        // come before every other line so it's known before anything is parsed
+        if let Some(argument) = trimmed.strip_prefix("@tatata") {
+            let argument = argument.trim();
+            if let Some(version_line) = version_line {
+                Diagnostic::new(line_index, "The language version is given twice")
+                    .at(trimmed)
+                    .label(format!("it's already given on line {version_line}"))
+                    .exit();
+            }
+            if let Some(first_line) = first_line {
+                Diagnostic::new(line_index, "The language version must be given before any other line")
+                    .at(trimmed)
+                    .label(format!("move this line above line {first_line}"))
+                    .exit();
+            }
+            let version: u32 = argument.parse().ok().filter(|&version| version > 0).unwrap_or_else(|| {
+                Diagnostic::new(line_index, format!("Invalid language version {argument:?}"))
+                    .at(if argument.is_empty() { trimmed } else { argument })
+                    .label(format!("expected a whole number above 0, like `@tatata {LANGUAGE_VERSION}`"))
+                    .exit();
+            });
+            if version > LANGUAGE_VERSION {
+                Diagnostic::new(line_index, format!("The script is written for version {version} of the TATATA language, which this version of TATATA doesn't understand"))
+                    .at(argument)
+                    .label(format!("this version of TATATA understands up to version {LANGUAGE_VERSION}, so update it to run this script"))
+                    .exit();
+            }
+            version_line = Some(line_index);
+            continue;
+        }
+        first_line = first_line.or(Some(line_index));
+
+        // Check if the line asks for a variable, like `@prompt target_x "X coordinate of the button"`
+        if let Some(arguments) = trimmed.strip_prefix("@prompt") {
+            let arguments = arguments.trim();
+            let (name, description) = arguments.split_once(char::is_whitespace).unwrap_or((arguments, ""));
+            if !prompt::is_valid_name(name) {
+                Diagnostic::new(line_index, format!("Invalid variable name {name:?}"))
+                    .at(if name.is_empty() { trimmed } else { name })
+                    .label("names can only contain letters, digits and underscores, and can't start with a digit")
+                    .exit();
+            }
+            let description = description.trim();
+            let description = if description.is_empty() {
+                name
+            } else {
+                description.strip_prefix('"').and_then(|description| description.strip_suffix('"')).unwrap_or_else(|| {
+                    Diagnostic::new(line_index, "Incorrectly formatted prompt").at(description).label("the description has to be in quotes").exit();
+                })
+            };
+
+            // Every variable is asked for once before the script starts, so asking in a block would be misleading
+            if let Some(block) = open_blocks.last() {
+                Diagnostic::new(line_index, format!("Prompts can't be inside a {}", block.name()))
+                    .at(trimmed)
+                    .label("move this line before the block")
+                    .exit();
+            }
+
+            if !variables.contains_key(name) {
+                let value = prompt::ask(name, description).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, error).kind(ErrorKind::Setup).at(name).exit();
+                });
+                variables.insert(name.to_string(), value);
+            }
+            continue;
+        }
+
+        // Check if the line changes the speed of the lines after it, like `@speed 0.5`
+        if let Some(argument) = trimmed.strip_prefix("@speed") {
+            let argument = argument.trim();
+            speed = argument.parse().ok().filter(|speed: &f64| speed.is_finite() && *speed > 0.0).unwrap_or_else(|| {
+                Diagnostic::new(line_index, format!("Invalid speed {argument:?}"))
+                    .at(if argument.is_empty() { trimmed } else { argument })
+                    .label("expected a number above 0, like 0.5 for half speed or 2 for double speed")
+                    .exit();
+            });
+            continue;
+        }
+
+        // Check if the line moves the absolute positions of the lines after it, like `@offset 0 30`
+        if let Some(argument) = trimmed.strip_prefix("@offset") {
+            let arguments: Vec<&str> = argument.split_whitespace().collect();
+            let offset = match arguments.as_slice() {
+                [x, y] => x.parse().ok().zip(y.parse().ok()),
+                _ => None
+            };
+            transformed = true;
+            defaults.transform.offset = offset.unwrap_or_else(|| {
+                Diagnostic::new(line_index, format!("Invalid offset {:?}", argument.trim()))
+                    .at(if arguments.is_empty() { trimmed } else { argument.trim() })
+                    .label("expected how many pixels to move positions right and down, like `@offset 0 30`")
+                    .exit();
+            });
+            continue;
+        }
+
+        // Check if the line scales the absolute positions of the lines after it, like `@scale 1.25`
+        if let Some(argument) = trimmed.strip_prefix("@scale") {
+            let argument = argument.trim();
+            transformed = true;
+            defaults.transform.scale = argument.parse().ok().filter(|scale: &f64| scale.is_finite() && *scale > 0.0).unwrap_or_else(|| {
+                Diagnostic::new(line_index, format!("Invalid scale {argument:?}"))
+                    .at(if argument.is_empty() { trimmed } else { argument })
+                    .label("expected a number above 0, like 1.25 for a screen with 25% more pixels")
+                    .exit();
+            });
+            continue;
+        }
+
+        // Check if the line sets how the lines after it type, like `@typing_delay 25` or `@typing_delay 120 human`
+        if let Some(argument) = trimmed.strip_prefix("@typing_delay") {
+            defaults.typing = parse_typing(argument, line_index, trimmed);
+            continue;
+        }
+
+        // Check if the line opens a section of defaults for the lines after it, like `@defaults {`
+        if let Some(header) = trimmed.strip_prefix("@defaults") {
+            if header.trim() != "{" {
+                Diagnostic::new(line_index, "Incorrectly formatted section").at(trimmed).label("expected `@defaults {`").exit();
+            }
+
+            // Each line of the section sets one default, like `button = right`, and the ones it doesn't set are kept
+            loop {
+                let Some((setting_index, setting_line, _)) = lines.pop() else {
+                    Diagnostic::new(line_index, "The defaults section is never closed")
+                        .at(trimmed)
+                        .label("expected a line with only `}` after the defaults")
+                        .exit();
+                };
+                let setting_line = setting_line.trim();
+                if setting_line == "}" {
+                    break;
+                }
+                if setting_line.is_empty() || setting_line.starts_with("//") {
+                    continue;
+                }
+                let Some((name, value)) = setting_line.split_once('=') else {
+                    Diagnostic::new(setting_index, "Incorrectly formatted default")
+                        .at(setting_line)
+                        .label("expected `<setting> = <value>`, like `button = right`")
+                        .exit();
+                };
+                let (name, value) = (name.trim(), value.trim());
+                let at_value = if value.is_empty() { setting_line } else { value };
+                match name {
+                    "method" => defaults.method = match value {
+                        "abs" => Some(Coordinate::Abs),
+                        "rel" => Some(Coordinate::Rel),
+                        "none" => None,
+                        _ => {
+                            Diagnostic::new(setting_index, format!("Invalid method {value:?}"))
+                                .at(at_value)
+                                .label("expected `abs`, `rel`, or `none` to always write the method")
+                                .suggest(value, ["abs", "rel", "none"])
+                                .exit();
+                        }
+                    },
+                    "movement" => defaults.human = match value {
+                        "human" => true,
+                        "straight" => false,
+                        _ => {
+                            Diagnostic::new(setting_index, format!("Unknown movement {value:?}"))
+                                .at(at_value)
+                                .label("expected `human` or `straight`")
+                                .suggest(value, ["human", "straight"])
+                                .exit();
+                        }
+                    },
+                    "typing_delay" => defaults.typing = parse_typing(value, setting_index, setting_line),
+                    "button" if value == "none" => defaults.button = None,
+                    "button" => defaults.button = Some(parse_button(value).unwrap_or_else(|error| {
+                        Diagnostic::new(setting_index, format!("Invalid button {value:?} ({error})"))
+                            .at(at_value)
+                            .suggest(value, BUTTON_NAMES.iter().copied())
+                            .exit();
+                    })),
+                    _ => {
+                        Diagnostic::new(setting_index, format!("Unknown default {name:?}"))
+                            .at(if name.is_empty() { setting_line } else { name })
+                            .label("expected `method`, `movement`, `typing_delay` or `button`")
+                            .suggest(name, ["method", "movement", "typing_delay", "button"])
+                            .exit();
+                    }
+                }
+            }
+            continue;
+        }
+
+        // Check if the line includes the standard library for the lines after it, or the lines of another script
+        if let Some(argument) = trimmed.strip_prefix("@include") {
+            let argument = argument.trim();
+            if argument == "std" {
+                includes_std = true;
+                continue;
+            }
+            if !argument.ends_with(".tatata") {
+                Diagnostic::new(line_index, format!("Unknown library {argument:?}"))
+                    .at(if argument.is_empty() { trimmed } else { argument })
+                    .label("the standard library is included with `@include std`, and other scripts with `@include <file>.tatata`")
+                    .exit();
+            }
+
+            let included_path = directory.join(argument);
+            if included_from.contains(&included_path) || included_path == file_path {
+                Diagnostic::new(line_index, format!("{argument} includes itself"))
+                    .at(argument)
+                    .label("a script can't be included by the scripts it includes")
+                    .exit();
+            }
+            if let Err(error) = bundle::read(&included_path) {
+                Diagnostic::new(line_index, format!("Couldn't open {argument}: {error}")).at(argument).exit();
+            }
+
+            // The included script reports its own mistakes, after which errors are about this script again
+            let source = diagnostic::source();
+            let included_from = [included_from, &[file_path.to_path_buf()]].concat();
+            let (included, included_variables) = parse_script(&included_path, variables, tag_filter, seen_tags, &included_from);
+            diagnostic::use_source(source);
+            if !included.setup.is_empty() || !included.teardown.is_empty() {
+                Diagnostic::new(line_index, format!("{argument} has a setup or teardown section"))
+                    .at(argument)
+                    .label("only the script that's run can have `@setup` and `@teardown` sections")
+                    .exit();
+            }
+            assigned_variables.extend(included_variables);
+
+            // The included lines run as if they were written here, starting at the line before this one, and the lines
+            // after this one are timed from the last of them
+            let target = open_blocks.last_mut().map_or(&mut queue, OpenBlock::body);
+            let start = target.last().map_or(0, |item| item.time);
+            for mut item in included.queue {
+                item.time += start;
+                relocate(&mut item, line_index);
+                target.push(item);
+            }
+            continue;
+        }
+
+        // Check if the line opens a section for some platforms only, like `@if macos {`
+        if let Some(header) = trimmed.strip_prefix("@if ") {
+            let Some(header) = header.strip_suffix('{') else {
+                Diagnostic::new(line_index, "The platform section is missing its opening brace")
+                    .at(header.trim())
+                    .label("expected a `{` at the end of the line")
+                    .exit();
+            };
+            let platforms: Vec<&str> = header.split_whitespace().collect();
+            if platforms.is_empty() {
+                Diagnostic::new(line_index, "The platform section has no platforms")
+                    .at(trimmed)
+                    .label("expected one or more of `windows`, `linux` and `macos`, like `@if windows linux {`")
+                    .exit();
+            }
+            for platform in &platforms {
+                if !matches!(*platform, "windows" | "linux" | "macos") {
+                    Diagnostic::new(line_index, format!("Unknown platform {platform:?}"))
+                        .at(platform)
+                        .label("expected `windows`, `linux` or `macos`")
+                        .suggest(platform, ["windows", "linux", "macos"])
+                        .exit();
+                }
+            }
+            let current = if cfg!(target_os = "windows") { "windows" } else if cfg!(target_os = "macos") { "macos" } else { "linux" };
+            let matched = platforms.contains(&current);
+
+            // Take the lines up to the closing brace, splitting them at `} else {`, and parse the lines for this platform as
+            // if they were written in place of the section, so the lines for other platforms don't have to parse here
+            let mut branches = (Vec::new(), Vec::new());
+            let mut in_else = false;
+            let mut depth = 1;
+            loop {
+                let Some(body_line) = lines.pop() else {
+                    Diagnostic::new(line_index, "The platform section is never closed")
+                        .at(trimmed)
+                        .label("expected a line with only `}` after the lines of the section")
+                        .exit();
+                };
+                let body_trimmed = body_line.1.trim();
+                if body_trimmed == "}" {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                } else if depth == 1 && body_trimmed.starts_with('}') && body_trimmed.trim_start_matches('}').trim() == "else {" {
+                    if in_else {
+                        Diagnostic::new(body_line.0, "The platform section has more than one `else`")
+                            .at(body_trimmed)
+                            .label(format!("the section starts on line {line_index}"))
+                            .exit();
+                    }
+                    in_else = true;
+                    continue;
+                } else if body_trimmed.ends_with('{') && !body_trimmed.starts_with('}') && !body_trimmed.contains('>') {
+                    depth += 1;
+                }
+                if in_else { &mut branches.1 } else { &mut branches.0 }.push(body_line);
+            }
+
+            let chosen = if matched { branches.0 } else { branches.1 };
+            lines.extend(chosen.into_iter().rev());
+            continue;
+        }
+
+        // Check if the line opens a setup or teardown section, like `@setup {`
+        if trimmed.starts_with("@setup") || trimmed.starts_with("@teardown") {
+            let words: Vec<&str> = trimmed.split_whitespace().collect();
+            let teardown_section = words[0] == "@teardown";
+            if words.len() != 2 || words[1] != "{" || !matches!(words[0], "@setup" | "@teardown") {
+                Diagnostic::new(line_index, "Incorrectly formatted section")
+                    .at(trimmed)
+                    .label(if teardown_section { "expected `@teardown {`" } else { "expected `@setup {`" })
+                    .exit();
+            }
+
+            // The sections run before and after all other lines, so they can't be part of anything else
+            let name = if teardown_section { "teardown" } else { "setup" };
+            if let Some(block) = open_blocks.last() {
+                Diagnostic::new(line_index, format!("A {name} can't be inside a {}", block.name()))
+                    .at(trimmed)
+                    .label(format!("the {} starts on line {}", block.name(), block.line()))
+                    .exit();
+            }
+            if let Some((line, _)) = if teardown_section { &teardown } else { &setup } {
+                Diagnostic::new(line_index, format!("The script already has a {name}"))
+                    .at(trimmed)
+                    .label(format!("the {name} starts on line {line}"))
+                    .exit();
+            }
+
+            open_blocks.push(OpenBlock::Section { line: line_index, teardown: teardown_section, body: Vec::new() });
+            continue;
+        }
+
+        // Check if the line defines a named point, like `point submit = 1520 830`
+        if let Some(definition) = trimmed.strip_prefix("point ") {
+            let Some((name, position)) = definition.split_once('=') else {
+                Diagnostic::new(line_index, "Incorrectly formatted point")
+                    .at(trimmed)
+                    .label("expected `point <name> = <x> <y>`, like `point submit = 1520 830`")
+                    .exit();
+            };
+            let name = name.trim();
+            if !prompt::is_valid_name(name) {
+                Diagnostic::new(line_index, format!("Invalid point name {name:?}"))
+                    .at(if name.is_empty() { trimmed } else { name })
+                    .label("names can only contain letters, digits and underscores, and can't start with a digit")
+                    .exit();
+            }
+            if let Some(existing) = points.get(name) {
+                Diagnostic::new(line_index, format!("The point {name} is already defined"))
+                    .at(name)
+                    .label(format!("it's defined on line {}", existing.line))
+                    .exit();
+            }
+
+            // The position is checked here, so mistakes in it are shown on this line instead of where it's used
+            let coordinates = split_expressions(position.trim());
+            if coordinates.len() != 2 {
+                Diagnostic::new(line_index, format!("Expected an X and a Y position, found {} argument(s)", coordinates.len()))
+                    .at(if coordinates.is_empty() { trimmed } else { position.trim() })
+                    .label("expected `point <name> = <x> <y>`, like `point submit = 1520 830`")
+                    .exit();
+            }
+            for (axis, coordinate) in [("X", coordinates[0]), ("Y", coordinates[1])] {
+                if let Err(error) = parse_position(coordinate, axis, variables, &assigned_variables) {
+                    Diagnostic::new(line_index, format!("Invalid {axis} position {coordinate:?} ({error})")).at(coordinate).exit();
+                }
+            }
+
+            points.insert(name.to_string(), NamedPoint { line: line_index, x: coordinates[0].to_string(), y: coordinates[1].to_string() });
+            continue;
+        }
+
+        // Check if the line defines a macro, like `macro open_menu(x, y) {`, whose lines are only parsed where it's called
+        if let Some(header) = trimmed.strip_prefix("macro ") {
+            let signature = header.strip_suffix('{')
+                .and_then(|signature| signature.trim().strip_suffix(')'))
+                .and_then(|signature| signature.split_once('('));
+            let Some((name, parameters)) = signature else {
+                Diagnostic::new(line_index, "Incorrectly formatted macro")
+                    .at(trimmed)
+                    .label("expected `macro <name>(<parameters>) {`, like `macro open_menu(x, y) {`")
+                    .exit();
+            };
+            let name = name.trim();
+            if !prompt::is_valid_name(name) {
+                Diagnostic::new(line_index, format!("Invalid macro name {name:?}"))
+                    .at(if name.is_empty() { trimmed } else { name })
+                    .label("names can only contain letters, digits and underscores, and can't start with a digit")
+                    .exit();
+            }
+            let parameters: Vec<&str> = parameters.split(',').map(str::trim).filter(|parameter| !parameter.is_empty()).collect();
+            for (index, parameter) in parameters.iter().enumerate() {
+                if !prompt::is_valid_name(parameter) {
+                    Diagnostic::new(line_index, format!("Invalid parameter name {parameter:?}"))
+                        .at(parameter)
+                        .label("names can only contain letters, digits and underscores, and can't start with a digit")
+                        .exit();
+                }
+                if parameters[..index].contains(parameter) {
+                    Diagnostic::new(line_index, format!("The macro has more than one parameter named {parameter}")).at(parameter).exit();
+                }
+            }
+            if let Some(block) = open_blocks.last() {
+                Diagnostic::new(line_index, format!("A macro can't be inside a {}", block.name()))
+                    .at(trimmed)
+                    .label(format!("the {} starts on line {}", block.name(), block.line()))
+                    .exit();
+            }
+            if let Some(existing) = macros.get(name) {
+                Diagnostic::new(line_index, format!("The macro {name} is already defined"))
+                    .at(name)
+                    .label(format!("it's defined on line {}", existing.line))
+                    .exit();
+            }
+
+            // Take the lines up to the closing brace of the macro, skipping over the blocks in it
+            let mut body = Vec::new();
+            let mut depth = 1;
+            loop {
+                let Some((body_index, body_line, _)) = lines.pop() else {
+                    Diagnostic::new(line_index, "The macro is never closed")
+                        .at(trimmed)
+                        .label("expected a line with only `}` after the lines of the macro")
+                        .exit();
+                };
+                let body_trimmed = body_line.trim();
+                if body_trimmed == "}" {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                } else if body_trimmed.ends_with('{') && !body_trimmed.starts_with('}') && !body_trimmed.contains('>') {
+                    depth += 1;
+                }
+
+                // Macros can only call the macros before them, so they can't end up calling themselves
+                let words: Vec<&str> = body_trimmed.split_whitespace().skip_while(|word| word.starts_with('#')).collect();
+                if words.first() == Some(&"macro") {
+                    Diagnostic::new(body_index, "A macro can't be defined inside another macro")
+                        .at(body_trimmed)
+                        .label(format!("the macro {name} starts on line {line_index}"))
+                        .exit();
+                }
+                if let ["call", called, ..] = words.as_slice() {
+                    if !macros.contains_key(*called) {
+                        Diagnostic::new(body_index, format!("Unknown macro {called:?}"))
+                            .at(called)
+                            .label("macros can only call the macros defined before them")
+                            .exit();
+                    }
+                }
+                body.push((body_index, body_line));
+            }
+
+            let parameters = parameters.into_iter().map(str::to_string).collect();
+            macros.insert(name.to_string(), Macro { line: line_index, parameters, body });
+            continue;
+        }
+
+        // Check if the line opens a loop, like `while pixel(100, 200) == #112233 {`
+        let loop_header = trimmed.strip_prefix("while ").map(|header| (header, false))
+            .or_else(|| trimmed.strip_prefix("until ").map(|header| (header, true)));
+        if let Some((header, until)) = loop_header {
+            let condition_text = header.strip_suffix('{').unwrap_or_else(|| {
+                Diagnostic::new(line_index, "Loop is missing its opening brace").at(header).label("expected a `{` at the end of the line").exit();
+            });
+            let condition = Condition::parse(condition_text, variables, &defaults.transform).unwrap_or_else(|error| {
+                Diagnostic::new(line_index, format!("Invalid loop condition ({error})")).at(condition_text.trim()).exit();
+            });
+
+            open_blocks.push(OpenBlock::Loop { line: line_index, skip, condition, until, body: Vec::new() });
+            continue;
+        }
+
+        let words: Vec<&str> = trimmed.split_whitespace().collect();
+
+        // Check if the line calls a macro, like `call open_menu 100 200`, whose lines are parsed in place of this one
+        if words.first() == Some(&"call") && !trimmed.contains('>') {
+            let name = words.get(1).copied().unwrap_or_default();
+            let Some(called) = macros.get(name) else {
+                Diagnostic::new(line_index, format!("Unknown macro {name:?}"))
+                    .at(if name.is_empty() { trimmed } else { name })
+                    .label("macros are defined with `macro <name>(<parameters>) {` before the lines that call them")
+                    .exit();
+            };
+            let arguments = &words[2..];
+            if arguments.len() != called.parameters.len() {
+                let expected: Vec<String> = called.parameters.iter().map(|parameter| format!(" <{parameter}>")).collect();
+                Diagnostic::new(line_index, format!("The macro {name} takes {} argument(s), but got {}", called.parameters.len(), arguments.len()))
+                    .at(trimmed)
+                    .label(format!("expected `call {name}{}`", expected.concat()))
+                    .exit();
+            }
+            if skip {
+                continue;
+            }
+
+            for (body_index, body_line) in called.body.iter().rev() {
+                lines.push((*body_index, Cow::Owned(substitute_parameters(body_line, &called.parameters, arguments)), true));
+            }
+            continue;
+        }
+
+        // Check if the line opens a retry block, like `retry 3 500 2 {`
+        if words.first() == Some(&"retry") && !trimmed.contains('>') {
+            if words.last() != Some(&"{") || !(3..=5).contains(&words.len()) {
+                Diagnostic::new(line_index, "Incorrectly formatted retry block")
+                    .at(trimmed)
+                    .label("expected `retry <attempts> {`, optionally with a delay and a backoff before the `{`")
+                    .exit();
+            }
+            let arguments = &words[1..words.len() - 1];
+            let attempts = arguments[0].parse().ok().filter(|attempts: &u32| *attempts > 0).unwrap_or_else(|| {
+                Diagnostic::new(line_index, format!("Invalid number of attempts {:?}", arguments[0])).at(arguments[0]).label("expected a whole number above 0").exit();
+            });
+            let delay = arguments.get(1).map_or(0, |word| word.parse().unwrap_or_else(|error| {
+                Diagnostic::new(line_index, format!("Invalid retry delay ({error})")).at(word).label("expected a number of milliseconds").exit();
+            }));
+            let backoff = arguments.get(2).map_or(1.0, |word| word.parse().ok().filter(|backoff: &f64| backoff.is_finite() && *backoff > 0.0).unwrap_or_else(|| {
+                Diagnostic::new(line_index, format!("Invalid retry backoff {word:?}")).at(word).label("expected a number above 0, like 2 to double the delay after every retry").exit();
+            }));
+
+            open_blocks.push(OpenBlock::Retry { line: line_index, skip, attempts, delay, backoff, body: Vec::new() });
+            continue;
+        }
+
+        // Check if the line opens an if block, like `ifpixel 100 200 #112233 {`, or starts its else branch with `} else {`
+        if words.first() == Some(&"ifpixel") && !trimmed.contains('>') {
+            let expanded = expand_points(trimmed, &points, line_index);
+            let words: Vec<&str> = expanded.split_whitespace().collect();
+            if words.len() != 5 || words[4] != "{" {
+                Diagnostic::new(line_index, "Incorrectly formatted if block").at(trimmed).label("expected `ifpixel <x> <y> <color> {`").exit();
+            }
+            let [x, y] = [("X", words[1], 0), ("Y", words[2], 1)].map(|(axis, word, index)| {
+                resolve_position(&defaults.transform.apply_literal(word, Some(index)), axis, variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("Invalid position {word:?} ({error})")).at(word).exit();
+                })
+            });
+            let color = Color::parse(words[3]).unwrap_or_else(|error| {
+                Diagnostic::new(line_index, format!("Invalid color {:?} ({error})", words[3])).at(words[3]).label("expected a hex code like #112233").exit();
+            });
+
+            let condition = Condition::Pixel { x, y, color, equal: true };
+            open_blocks.push(OpenBlock::If { line: line_index, skip, condition, then: Vec::new(), otherwise: None });
+            continue;
+        }
+        if words == ["}", "else", "{"] {
+            match open_blocks.last_mut() {
+                Some(OpenBlock::If { otherwise: otherwise @ None, .. }) => *otherwise = Some(Vec::new()),
+                Some(OpenBlock::If { line, .. }) => {
+                    Diagnostic::new(line_index, "The if block already has an else branch").at(trimmed).label(format!("the if block starts on line {line}")).exit();
+                }
+                Some(block) => {
+                    Diagnostic::new(line_index, format!("Only an if block can have an else branch, but the innermost block is a {}", block.name()))
+                        .at(trimmed)
+                        .label(format!("the {} starts on line {}", block.name(), block.line()))
+                        .exit();
+                }
+                None => Diagnostic::new(line_index, "Else branch without an if block to add it to").at(trimmed).label("start the if block with `ifpixel <x> <y> <color> {`").exit()
+            }
+            continue;
+        }
+
+        // Check if the line opens a choice, like `choose {`, or starts another branch of it with `} or {`
+        if words == ["choose", "{"] {
+            open_blocks.push(OpenBlock::Choose { line: line_index, skip, branches: vec![Vec::new()] });
+            continue;
+        }
+        if trimmed.starts_with("choose") && !trimmed.contains('>') {
+            Diagnostic::new(line_index, "Incorrectly formatted choice").at(trimmed).label("expected `choose {`").exit();
+        }
+
+        // Check if the line opens a parallel block, like `parallel {`, or starts another track of it with `} and {`
+        if words == ["parallel", "{"] {
+            open_blocks.push(OpenBlock::Parallel { line: line_index, skip, tracks: vec![Vec::new()] });
+            continue;
+        }
+        if trimmed.starts_with("parallel") && !trimmed.contains('>') {
+            Diagnostic::new(line_index, "Incorrectly formatted parallel block").at(trimmed).label("expected `parallel {`").exit();
+        }
+        if words == ["}", "and", "{"] {
+            match open_blocks.last_mut() {
+                Some(OpenBlock::Parallel { tracks, .. }) => tracks.push(Vec::new()),
+                Some(block) => {
+                    Diagnostic::new(line_index, format!("Only a parallel block can have more tracks, but the innermost block is a {}", block.name()))
+                        .at(trimmed)
+                        .label(format!("the {} starts on line {}", block.name(), block.line()))
+                        .exit();
+                }
+                None => Diagnostic::new(line_index, "Track without a parallel block to add it to").at(trimmed).label("start the parallel block with `parallel {`").exit()
+            }
+            continue;
+        }
+        if words == ["}", "or", "{"] {
+            match open_blocks.last_mut() {
+                Some(OpenBlock::Choose { branches, .. }) => branches.push(Vec::new()),
+                Some(block) => {
+                    Diagnostic::new(line_index, format!("Only a choice can have more branches, but the innermost block is a {}", block.name()))
+                        .at(trimmed)
+                        .label(format!("the {} starts on line {}", block.name(), block.line()))
+                        .exit();
+                }
+                None => Diagnostic::new(line_index, "Branch without a choice to add it to").at(trimmed).label("start the choice with `choose {`").exit()
+            }
+            continue;
+        }
+
+        // Check if the line closes a block
+        if trimmed == "}" {
+            let Some(block) = open_blocks.pop() else {
+                Diagnostic::new(line_index, "Closing brace without a block to close").at(trimmed).exit();
+            };
+            let (line, step) = match block {
+                OpenBlock::Section { line, teardown: false, body } => {
+                    setup = Some((line, body));
+                    continue;
+                }
+                OpenBlock::Section { line, teardown: true, body } => {
+                    teardown = Some((line, body));
+                    continue;
+                }
+                OpenBlock::Loop { skip: true, .. } | OpenBlock::Choose { skip: true, .. } | OpenBlock::Retry { skip: true, .. } | OpenBlock::If { skip: true, .. } | OpenBlock::Parallel { skip: true, .. } => continue,
+                OpenBlock::Loop { line, condition, until, body, .. } => (line, Step::Loop(Loop { condition, until, body })),
+                OpenBlock::Choose { line, branches, .. } => (line, Step::Choose(branches)),
+                OpenBlock::Retry { line, attempts, delay, backoff, body, .. } => (line, Step::Retry(Retry { attempts, delay, backoff, body })),
+                OpenBlock::If { line, condition, then, otherwise, .. } => (line, Step::If(Conditional { condition, then, otherwise: otherwise.unwrap_or_default() })),
+                OpenBlock::Parallel { line, tracks, .. } => {
+                    // Lines at the same time run in the order of their tracks
+                    let mut body: Vec<QueueItem> = tracks.into_iter().flatten().collect();
+                    body.sort_by_key(|entry| entry.time);
+                    (line, Step::Parallel(body))
+                }
+            };
+
+            // Blocks take as long as they need to, which would hold up the other tracks of a parallel block
+            if let Some(OpenBlock::Parallel { line: parallel_line, .. }) = open_blocks.last() {
+                Diagnostic::new(line, "Blocks can't be inside a parallel block")
+                    .label(format!("the parallel block starts on line {parallel_line}, and its tracks can only have action lines"))
+                    .exit();
+            }
+
+            // The block starts right after the line before it, and the lines after it are timed from when it ends
+            let parent = open_blocks.last_mut().map_or(&mut queue, OpenBlock::body);
+            parent.push(QueueItem {
+                time: parent.last().map_or(0, |previous| previous.time),
+                line,
+                mandatory: false,
+                jitter: 0,
+                step
+            });
+            continue;
+        }
+
+        // Get line data, where the arrows between the points of a path, like `mousemove abs 100 100 -> 300 120`, aren't
+        // separators
+        let line_decoded = line.split_once('>').filter(|(_, actions)| !actions.replace("->", "").contains('>'));
+        let Some((line_timestamp_text, line_actions_text)) = line_decoded else {
+            Diagnostic::new(line_index, "Incorrectly formatted line")
+                .label("expected a timestamp and actions, separated by a single \">\"")
+                .exit();
+        };
+
+        // Decode line
+        let line_timestamp_text = line_timestamp_text.trim();
+
+        // A `!` after the timestamp marks the time before the line as needed, so it's kept even with `--no-wait`
+        let (line_timestamp_text, mandatory) = match line_timestamp_text.strip_suffix('!') {
+            Some(text) => (text.trim_end(), true),
+            None => (line_timestamp_text, false)
+        };
+
+        // A `~` followed by a time after the timestamp, like `+100~20`, lets the line run that much
+        // earlier or later, picked at random every time it runs
+        let (line_timestamp_text, jitter) = match line_timestamp_text.split_once('~') {
+            Some((text, jitter_text)) => {
+                let jitter = parse_time(jitter_text.trim()).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("Incorrectly formatted jitter ({error})"))
+                        .at(jitter_text.trim())
+                        .label("expected a number of milliseconds after the `~`, like `+100~20`")
+                        .exit();
+                });
+                (text.trim_end(), jitter)
+            }
+            None => (line_timestamp_text, 0)
+        };
+
+        // An `x` followed by a count after a relative timestamp, like `+100x20`, runs the line that many times, each the
+        // time of the timestamp after the one before
+        let (line_timestamp_text, repeats) = match line_timestamp_text.split_once('x') {
+            Some((text, count_text)) => {
+                let repeats: u64 = count_text.trim().parse().ok().filter(|&repeats| repeats > 0).unwrap_or_else(|| {
+                    Diagnostic::new(line_index, format!("Invalid repeat count {:?}", count_text.trim()))
+                        .at(count_text.trim())
+                        .label("expected how many times to run the line after the `x`, like `+100x20`")
+                        .exit();
+                });
+                let text = text.trim_end();
+                if !text.starts_with('+') {
+                    Diagnostic::new(line_index, "Only lines with relative timestamps can be repeated")
+                        .at(text)
+                        .label(format!("use +{text}x{repeats} to run this line {repeats} times, {text}ms apart"))
+                        .exit();
+                }
+                (text, repeats)
+            }
+            None => (line_timestamp_text, 1)
+        };
+
+        // Lines in a block are added to it instead of the queue
+        let block_name = open_blocks.last().map(OpenBlock::name).or(in_macro.then_some("macro"));
+        let target = open_blocks.last_mut().map_or(&mut queue, OpenBlock::body);
+
+        // Get previous timestamp
+        let previous_timestamp = if let Some(previous_action) = target.last() {
+            previous_action.time
+        } else {
+            0
+        };
+
+        // Skipped lines are still checked for mistakes, but leave the timing as if they weren't there
+        let written_before = written_timestamp;
+
+        // Parse the timestamp of this line, where the time since the previous line is stretched or shrunk by the speed
+        let scale = |time: u64| (time as f64 / speed).round() as u64;
+        let line_timestamp: u64 = if let Some(relative_text) = line_timestamp_text.strip_prefix("+") {
+            // Relative timestamp
+            let parsed_time = parse_time(relative_text).unwrap_or_else(|error| {
+                Diagnostic::new(line_index, format!("Incorrectly formatted timestamp ({error})")).at(line_timestamp_text).exit();
+            });
+
+            if block_name.is_none() {
+                written_timestamp += parsed_time * repeats;
+            }
+            previous_timestamp + scale(parsed_time)
+        } else {
+            // Absolute timestamp
+            let parsed_time = if line_timestamp_text.contains(':') { parse_clock_time(line_timestamp_text) } else { parse_time(line_timestamp_text) };
+            let parsed_time = parsed_time.unwrap_or_else(|error| {
+                Diagnostic::new(line_index, format!("Incorrectly formatted timestamp ({error})")).at(line_timestamp_text).exit();
+            });
+
+            // Blocks run for as long as they need to, so there's no fixed point in time for the lines in them
+            if let Some(block_name) = block_name {
+                Diagnostic::new(line_index, format!("Lines in a {block_name} must have relative timestamps"))
+                    .at(line_timestamp_text)
+                    .label(format!("use +{parsed_time} to run this {parsed_time}ms after the previous line of the {block_name}"))
+                    .exit();
+            }
+
+            // Reject lines that have a timestamp lower than the previous line
+            if parsed_time > 0 && parsed_time <= written_timestamp {
+                Diagnostic::new(line_index, "Timestamp must be greater than previous action line")
+                    .at(line_timestamp_text)
+                    .label(format!("the previous action line is at {written_timestamp}ms"))
+                    .exit();
+            }
+
+            let time = previous_timestamp + scale(parsed_time.saturating_sub(written_timestamp));
+            written_timestamp = parsed_time;
+            time
+        };
+
+        // Take the lines of a heredoc at the end of the line, like `text <<EOF`, which are typed as they're written
+        let heredoc = line_actions_text.rsplit(';').next()
+            .and_then(|action| action.trim().strip_prefix("text"))
+            .and_then(|text| text.trim().strip_prefix("<<"));
+        let (line_actions_text, heredoc) = match heredoc {
+            Some(marker) => {
+                if marker.is_empty() || !marker.chars().all(|character| character.is_ascii_alphanumeric() || character == '_') {
+                    Diagnostic::new(line_index, format!("Invalid heredoc marker {marker:?}"))
+                        .at(if marker.is_empty() { line_actions_text.trim() } else { marker })
+                        .label("expected letters, digits and underscores after `<<`, like `text <<EOF`")
+                        .exit();
+                }
+
+                let mut text_lines = Vec::new();
+                let closing_line = loop {
+                    let Some((_, text_line, _)) = lines.pop() else {
+                        Diagnostic::new(line_index, "The heredoc is never closed")
+                            .at(marker)
+                            .label(format!("expected a line with only `{marker}` after the text"))
+                            .exit();
+                    };
+                    if text_line.trim() == marker {
+                        break text_line;
+                    }
+                    text_lines.push(text_line);
+                };
+
+                // The indentation of the closing line is left out of every line, so the text can be indented in blocks
+                let indentation = &closing_line[..closing_line.len() - closing_line.trim_start().len()];
+                let text: Vec<&str> = text_lines.iter().map(|text_line| text_line.strip_prefix(indentation).unwrap_or(text_line.trim_start())).collect();
+                let other_actions = &line_actions_text[..line_actions_text.rfind(';').unwrap_or(0)];
+                (other_actions, Some(text.join("\n")))
+            }
+            None => (line_actions_text, None)
+        };
+
+        // Put the positions of named points in place of their names, like `@submit`, keeping the text of the line when
+        // there are none, so mistakes are still shown where they are on the line
+        let expanded: Vec<Cow<str>> = line_actions_text.split(';').map(|action| expand_points(action, &points, line_index)).collect();
+        let expanded = match expanded.iter().all(|action| matches!(action, Cow::Borrowed(_))) {
+            true => Cow::Borrowed(line_actions_text),
+            false => Cow::Owned(expanded.join(";"))
+        };
+        let line_actions_text: &str = &expanded;
+
+        // Parse actions
+        let mut actions = match (line_actions_text.trim().is_empty(), &heredoc) {
+            (true, Some(_)) => Vec::new(),
+            _ => parse_actions_string(line_actions_text, line_index, variables, &mut assigned_variables, defaults, directory, includes_std)
+        };
+        if let Some(text) = heredoc {
+            actions.push(Action::Text { text: parse_text(&text, &assigned_variables), typing: defaults.typing });
+        }
+        if skip {
+            written_timestamp = written_before;
+            continue;
+        }
+        let repeated_actions = actions.clone();
+        actions.splice(0..0, cues.drain(..).map(|(_, name)| Action::Cue(name)));
+
+        // Add actions to queue
+        target.push(QueueItem {
+            time: line_timestamp,
+            line: line_index,
+            mandatory,
+            jitter,
+            step: Step::Actions(actions)
+        });
+
+        // Repeats of the line are spaced like the line itself is after the line before it, and only the first one is cued
+        let interval = line_timestamp - previous_timestamp;
+        for repeat in 1..repeats {
+            target.push(QueueItem {
+                time: line_timestamp + interval * repeat,
+                line: line_index,
+                mandatory,
+                jitter,
+                step: Step::Actions(repeated_actions.clone())
+            });
+        }
+    }
+
+    if let Some(block) = open_blocks.last() {
+        Diagnostic::new(block.line(), format!("The {} is never closed", block.name()))
+            .label(format!("expected a line with only `}}` after the lines of the {}", block.name()))
+            .exit();
+    }
+
+    if let Some((line, name)) = cues.first() {
+        Diagnostic::new(*line, "The cue isn't followed by a line to run")
+            .at(name)
+            .label("cues are announced when the line after them runs")
+            .exit();
+    }
+
+    // Return populated queue
+    let script = Script {
+        header,
+        setup: setup.map(|(_, lines)| lines).unwrap_or_default(),
+        queue,
+        teardown: teardown.map(|(_, lines)| lines).unwrap_or_default(),
+        transformed
+    };
+    (script, assigned_variables)
+}
+
+/// Parse the actions of a line, where files like recorded mouse paths are relative to the directory of the script
+fn parse_actions_string(string: &str, line_index: i32, variables: &HashMap<String, i32>, assigned_variables: &mut HashMap<String, VariableKind>, defaults: Defaults, directory: &Path, includes_std: bool) -> Vec<Action> {
+    let typing = defaults.typing;
+
+    // Split into individual action strings
+    let action_strings: Vec<&str> = string.split(";").collect();
+    if action_strings.len() == 1 && action_strings[0].is_empty() {
+        Diagnostic::new(line_index, "Need at least one action").at(string).exit();
+    }
+
+    // Store actions as Vec<Action>
+    let mut actions: Vec<Action> = Vec::new();
+    for mut action in action_strings {
+        // Ignore empty actions
+        action = action.trim();
+        if action.is_empty() {
+            continue;
+        }
+
+        // An offset before an action, like `+50 keyup a`, waits that long after the action before it
+        if let Some(offset) = action.strip_prefix('+') {
+            let (offset_text, rest) = offset.split_once(char::is_whitespace).unwrap_or((offset, ""));
+            let offset = parse_time(offset_text).unwrap_or_else(|error| {
+                Diagnostic::new(line_index, format!("Incorrectly formatted action offset ({error})"))
+                    .at(action.split_whitespace().next().unwrap())
+                    .label("expected a time after the `+`, like `+50 keyup a`")
+                    .exit();
+            });
+            action = rest.trim();
+            if action.is_empty() {
+                Diagnostic::new(line_index, "No action after the offset").at(offset_text).label("expected an action to run after the offset, like `+50 keyup a`").exit();
+            }
+            actions.push(Action::Wait(offset));
+        }
+
+        // Split into segments, where the arguments of actions that take numbers can be expressions with spaces in them
+        let action_name = action.split_whitespace().next().unwrap();
+        let segments: Vec<&str> = match action_name {
+            "mousemove" | "click" | "doubleclick" | "tripleclick" | "probe" | "waitpixel" => split_expressions(action),
+            _ => action.split_whitespace().collect()
+        };
+
+        // Macros of the standard library stand for other actions, like `std.copy` pressing Ctrl+C
+        if action_name.starts_with("std.") {
+            if !includes_std {
+                Diagnostic::new(line_index, format!("{action_name} is part of the standard library, which isn't included"))
+                    .at(action_name)
+                    .label("add a line containing `@include std` before this line")
+                    .exit();
+            }
+            let expansion = stdlib::expand(action_name).unwrap_or_else(|| {
+                Diagnostic::new(line_index, format!("The standard library has no macro {action_name:?}"))
+                    .at(action_name)
+                    .suggest(action_name, stdlib::NAMES.iter().copied())
+                    .exit();
+            });
+            if segments.len() > 1 {
+                Diagnostic::new(line_index, format!("{action_name}: Macros don't take arguments"))
+                    .spanning(segments[1], segments[segments.len() - 1])
+                    .exit();
+            }
+            actions.extend(parse_actions_string(&expansion, line_index, variables, assigned_variables, defaults, directory, includes_std));
+            continue;
+        }
+
+        // Add Action to actions
+        match action_name {
+            "mousemove" => {
+                // The method can be left out when an `@defaults` section sets one, like `mousemove 500 400`
+                let segments = match defaults.method {
+                    Some(method) if segments.len() > 1 && !matches!(segments[1], "abs" | "rel") => {
+                        [&segments[..1], &[if matches!(method, Coordinate::Abs) { "abs" } else { "rel" }], &segments[1..]].concat()
+                    }
+                    _ => segments
+                };
+
+                // Points to pass through on the way to the last one are separated by arrows, like
+                // `mousemove abs 100 100 -> 300 120 -> 500 400 900`, and the time and movement come after the last one
+                let parts: Vec<&[&str]> = segments.split(|segment| *segment == "->").collect();
+                let (segments, through) = match parts.as_slice() {
+                    [_] => (segments, Vec::new()),
+                    [first, middle @ .., last] => {
+                        let malformed = (first.len() != 4).then_some(*first)
+                            .or_else(|| middle.iter().find(|part| part.len() != 2).copied())
+                            .or_else(|| (last.len() < 2).then_some(*last));
+                        if let Some(part) = malformed {
+                            let diagnostic = Diagnostic::new(line_index, format!("{action_name}: Every point of the path needs an X and a Y position"));
+                            match part {
+                                [] => diagnostic.at(action),
+                                _ => diagnostic.spanning(part[0], part[part.len() - 1])
+                            }.label("expected the points to be separated by `->`, like `mousemove abs 100 100 -> 300 120`").exit();
+                        }
+                        let through: Vec<&[&str]> = [&first[2..]].into_iter().chain(middle.iter().copied()).collect();
+                        ([&first[..2], last].concat(), through)
+                    }
+                    [] => unreachable!("splitting always gives at least one part")
+                };
+
+                // The time can be written after `over`, like `mousemove abs 500 400 over 900`
+                let segments = match segments.get(4) {
+                    Some(&"over") if segments.len() > 5 => [&segments[..4], &segments[5..]].concat(),
+                    Some(&"over") => Diagnostic::new(line_index, format!("{action_name}: No time after `over`")).at(segments[4]).label("expected a number of milliseconds").exit(),
+                    _ => segments
+                };
+
+                // Validate arguments
+                if segments.len() < 4 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too few arguments! (min. 3 arguments)"))
+                        .at(action)
+                        .label("expected a method, an X and a Y position, and optionally a time")
+                        .exit();
+                }
+                if segments.len() > 6 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 5 arguments)"))
+                        .spanning(segments[6], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Parse method
+                let method = match segments[1] {
+                    "abs" => Coordinate::Abs,
+                    "rel" => Coordinate::Rel,
+                    _ => {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid method {:?}", segments[1]))
+                            .at(segments[1])
+                            .suggest(segments[1], ["abs", "rel"])
+                            .exit();
+                    }
+                };
+
+                // Parse X position
+                let variable_names: Vec<String> = variables.keys().chain(number_variables(assigned_variables)).map(|name| format!("${name}")).collect();
+                let parse = |segment: &str, axis: &str| match method {
+                    Coordinate::Abs => parse_position(&defaults.transform.apply_literal(segment, Some(usize::from(axis == "Y"))), axis, variables, assigned_variables),
+                    Coordinate::Rel => parse_number(segment, variables, assigned_variables)
+                };
+                let x = parse(segments[2], "X").unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid X position {:?} ({error})", segments[2]))
+                        .at(segments[2])
+                        .suggest(segments[2], variable_names.iter().map(String::as_str))
+                        .exit();
+                });
+
+                // Parse Y position
+                let y = parse(segments[3], "Y").unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid Y position {:?} ({error})", segments[3]))
+                        .at(segments[3])
+                        .suggest(segments[3], variable_names.iter().map(String::as_str))
+                        .exit();
+                });
+
+                // Parse time
+                let time: u64 = if segments.len() > 4 {
+                    parse_constant(segments[4], variables).unwrap_or_else(|error| {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid time {:?} ({error})", segments[4]))
+                            .at(segments[4])
+                            .exit();
+                    })
+                } else {
+                    0
+                };
+
+                // Parse how the mouse moves, which only matters when it moves over time
+                let human = match segments.get(5) {
+                    None => defaults.human && time >= 2,
+                    Some(&"straight") => false,
+                    Some(&"human") if time >= 2 => true,
+                    Some(&"human") => {
+                        Diagnostic::new(line_index, format!("{action_name}: Human movement needs a time"))
+                            .at(segments[4])
+                            .label("the mouse jumps straight to the position when the time is below 2ms")
+                            .exit();
+                    }
+                    Some(mode) => {
+                        Diagnostic::new(line_index, format!("{action_name}: Unknown movement {mode:?}"))
+                            .at(mode)
+                            .suggest(mode, ["human", "straight"])
+                            .exit();
+                    }
+                };
+
+                // Parse the points the mouse passes through on the way
+                let mut points: Vec<(Number, Number)> = through.iter().map(|point| {
+                    let [x, y] = [("X", point[0]), ("Y", point[1])].map(|(axis, segment)| parse(segment, axis).unwrap_or_else(|error| {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid {axis} position {segment:?} ({error})"))
+                            .at(segment)
+                            .suggest(segment, variable_names.iter().map(String::as_str))
+                            .exit();
+                    }));
+                    (x, y)
+                }).collect();
+
+                // Add to actions
+                if points.is_empty() {
+                    actions.push(Action::MouseMove { x, y, time, method, human });
+                } else {
+                    points.push((x, y));
+                    actions.push(Action::MousePath { points, time, method, human });
+                }
+            }
+            "mousetrace" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No file provided"))
+                        .at(action)
+                        .label("expected a .csv or .json file with the recorded path, and optionally a speed")
+                        .exit();
+                }
+                if segments.len() > 3 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 2 arguments)"))
+                        .spanning(segments[3], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Load the path now, so a missing or broken file is found before anything is sent
+                let points = trace::load(&directory.join(segments[1])).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid mouse path ({error})")).kind(ErrorKind::Setup).at(segments[1]).exit();
+                });
+                let speed = segments.get(2).map_or(1.0, |segment| segment.parse().ok().filter(|speed: &f64| speed.is_finite() && *speed > 0.0).unwrap_or_else(|| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid speed {segment:?}"))
+                        .at(segment)
+                        .label("expected a number above 0, like 0.5 for half speed or 2 for double speed")
+                        .exit();
+                }));
+
+                // Add to actions
+                actions.push(Action::MouseTrace { file: segments[1].to_string(), points: Arc::new(points), speed });
+            }
+            "mousedown" | "mouseup" => {
+                // Validate arguments
+                if segments.len() > 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 1 argument)"))
+                        .spanning(segments[2], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Parse button, which can be left out when an `@defaults` section sets one
+                let button = match (segments.get(1), defaults.button) {
+                    (Some(name), _) => parse_button(name).unwrap_or_else(|error| {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid button {name:?} ({error})"))
+                            .at(name)
+                            .suggest(name, BUTTON_NAMES.iter().copied())
+                            .exit();
+                    }),
+                    (None, Some(button)) => button,
+                    (None, None) => {
+                        Diagnostic::new(line_index, format!("{action_name}: No argument provided"))
+                            .at(action)
+                            .label("expected a button, or an `@defaults` section that sets one")
+                            .exit();
+                    }
+                };
+
+                // Add to actions
+                match action_name {
+                    "mousedown" => actions.push(Action::MouseDown(button)),
+                    "mouseup" => actions.push(Action::MouseUp(button)),
+                    _ => unreachable!("Mouse action must be mousedown or mouseup")
+                }
+            }
+            "click" | "doubleclick" | "tripleclick" => {
+                // Double and triple clicks can have the time between clicks after the button
+                let clicks = match action_name {
+                    "click" => 1,
+                    "doubleclick" => 2,
+                    _ => 3
+                };
+                let example = if clicks == 1 { format!("`{action_name} 1 abs 500 500`") } else { format!("`{action_name} 1 abs 500 500` or `{action_name} 1 80 abs 500 500`") };
+
+                // The arguments after the button are an optional delay and position, where the method can be left out of
+                // the position too, so a delay is told apart by what comes after it
+                let has_delay = |arguments: &[&str]| clicks > 1 && match arguments.len() {
+                    1 | 4 => true,
+                    3 => defaults.method.is_some() && !matches!(arguments[0], "abs" | "rel"),
+                    _ => false
+                };
+                let expected_length = |position: &[&str]| {
+                    if position.first().is_some_and(|segment| matches!(*segment, "abs" | "rel")) || defaults.method.is_none() { 3 } else { 2 }
+                };
+                let fits = |arguments: &[&str]| {
+                    let position = &arguments[usize::from(has_delay(arguments))..];
+                    position.is_empty() || position.len() == expected_length(position)
+                };
+
+                // The button can be left out when an `@defaults` section sets one, so a number is only taken as the button
+                // when the arguments after it can't be read the other way
+                let has_button = match segments.get(1) {
+                    _ if defaults.button.is_none() => true,
+                    Some(first) if parse_button(first).is_ok() => {
+                        let numbered = first.parse::<u8>().is_ok();
+                        match (fits(&segments[2..]), fits(&segments[1..])) {
+                            (true, true) if numbered => {
+                                Diagnostic::new(line_index, format!("{action_name}: {first:?} could be the button or the first argument after it"))
+                                    .at(first)
+                                    .label("write the button by its name, like `left`, or the method before the position, like `rel 3 10`")
+                                    .exit();
+                            }
+                            (false, true) if numbered => false,
+                            _ => true
+                        }
+                    }
+                    _ => false
+                };
+                if has_button && segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No button provided"))
+                        .at(action)
+                        .label(format!("expected a button, and optionally a method and an X and a Y position to click at, like {example}"))
+                        .exit();
+                }
+                let arguments = &segments[1 + usize::from(has_button)..];
+                let has_delay = has_delay(arguments);
+                let position = &arguments[usize::from(has_delay)..];
+                let has_method = position.first().is_some_and(|segment| matches!(*segment, "abs" | "rel"));
+                let expected_length = expected_length(position);
+                if !position.is_empty() && position.len() != expected_length {
+                    let what = if expected_length == 3 { "a method and an X and a Y position" } else { "an X and a Y position" };
+                    Diagnostic::new(line_index, format!("{action_name}: Expected {what}, but got {} argument(s)", position.len()))
+                        .spanning(position[0], position[position.len() - 1])
+                        .label(format!("expected {what} after the button, like {example}"))
+                        .exit();
+                }
+
+                // Parse button
+                let button = match defaults.button {
+                    Some(button) if !has_button => button,
+                    _ => parse_button(segments[1]).unwrap_or_else(|error| {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid button {:?} ({error})", segments[1]))
+                            .at(segments[1])
+                            .suggest(segments[1], BUTTON_NAMES.iter().copied())
+                            .exit();
+                    })
+                };
+
+                // Parse the time between clicks, which is short enough to count as a double click everywhere by default
+                let delay = if has_delay {
+                    parse_constant(arguments[0], variables).unwrap_or_else(|error| {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid delay {:?} ({error})", arguments[0])).at(arguments[0]).exit();
+                    })
+                } else {
+                    DOUBLE_CLICK_DELAY
+                };
+
+                // Parse the position to move to first, like in `mousemove`
+                if !position.is_empty() {
+                    let (method, coordinates) = match defaults.method {
+                        Some(method) if !has_method => (method, position),
+                        _ => (match position[0] {
+                            "abs" => Coordinate::Abs,
+                            "rel" => Coordinate::Rel,
+                            _ => {
+                                Diagnostic::new(line_index, format!("{action_name}: Invalid method {:?}", position[0]))
+                                    .at(position[0])
+                                    .suggest(position[0], ["abs", "rel"])
+                                    .exit();
+                            }
+                        }, &position[1..])
+                    };
+                    let variable_names: Vec<String> = variables.keys().chain(number_variables(assigned_variables)).map(|name| format!("${name}")).collect();
+                    let [x, y] = [("X", coordinates[0]), ("Y", coordinates[1])].map(|(axis, segment)| match method {
+                        Coordinate::Abs => parse_position(&defaults.transform.apply_literal(segment, Some(usize::from(axis == "Y"))), axis, variables, assigned_variables),
+                        Coordinate::Rel => parse_number(segment, variables, assigned_variables)
+                    }.unwrap_or_else(|error| {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid {axis} position {segment:?} ({error})"))
+                            .at(segment)
+                            .suggest(segment, variable_names.iter().map(String::as_str))
+                            .exit();
+                    }));
+                    actions.push(Action::MouseMove { x, y, time: 0, method, human: false });
+                }
+
+                // Add to actions
+                for click in 0..clicks {
+                    if click > 0 {
+                        actions.push(Action::Wait(delay));
+                    }
+                    actions.push(Action::MouseDown(button));
+                    actions.push(Action::MouseUp(button));
+                }
+            }
+            "scroll" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No amount provided"))
+                        .at(action)
+                        .label("expected a number of notches, positive to scroll down and negative to scroll up, like `scroll 3`")
+                        .exit();
+                }
+                if segments.len() > 4 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 3 arguments)"))
+                        .spanning(segments[4], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Parse amount
+                let amount = parse_number(segments[1], variables, assigned_variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid amount {:?} ({error})", segments[1])).at(segments[1]).exit();
+                });
+
+                // Parse time, which can be left out before the direction
+                let (time, direction) = match segments.get(2) {
+                    Some(&("vertical" | "horizontal")) if segments.len() == 3 => (0, segments.get(2)),
+                    Some(time) => {
+                        let time = parse_constant(time, variables).unwrap_or_else(|error| {
+                            Diagnostic::new(line_index, format!("{action_name}: Invalid time {time:?} ({error})")).at(time).exit();
+                        });
+                        (time, segments.get(3))
+                    }
+                    None => (0, None)
+                };
+
+                // Parse direction
+                let axis = match direction {
+                    None | Some(&"vertical") => Axis::Vertical,
+                    Some(&"horizontal") => Axis::Horizontal,
+                    Some(axis) => {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid direction {axis:?}"))
+                            .at(axis)
+                            .suggest(axis, ["vertical", "horizontal"])
+                            .exit();
+                    }
+                };
+
+                // Add to actions
+                actions.push(Action::Scroll { amount, axis, time });
+            }
+            "keydown" | "keyup" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No argument provided")).at(action).exit();
+                }
+                if segments.len() > 2 && action_name == "keyup" {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 1 argument)"))
+                        .spanning(segments[2], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Parse key
+                let key = parse_key(segments[1]).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid key {:?} ({error})", segments[1]))
+                        .at(segments[1])
+                        .suggest(segments[1], KEY_NAMES.iter().copied())
+                        .exit();
+                });
+
+                // Add to actions
+                match action_name {
+                    "keydown" => actions.push(Action::KeyDown(key, parse_repeat(&segments, line_index, variables))),
+                    "keyup" => actions.push(Action::KeyUp(key)),
+                    _ => unreachable!("Key action must be keydown or keyup")
+                }
+            }
+            "keypress" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No keys provided"))
+                        .at(action)
+                        .label("expected keys joined with +, like `keypress ctrl+shift+s`")
+                        .exit();
+                }
+                if segments.len() > 3 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 2 arguments)"))
+                        .spanning(segments[3], segments[segments.len() - 1])
+                        .label("keys are joined with + without spaces, like `keypress ctrl+shift+s`")
+                        .exit();
+                }
+
+                // Split the keys at every +, where a + at the end is the + key itself, like in `ctrl++`
+                let chord = segments[1];
+                let (held, last) = match chord.strip_suffix("++") {
+                    Some(held) => (held, "+"),
+                    None => chord.rsplit_once('+').unwrap_or(("", chord))
+                };
+                let names: Vec<&str> = if held.is_empty() { vec![last] } else { held.split('+').chain([last]).collect() };
+                let mut keys: Vec<Key> = Vec::new();
+                for name in names {
+                    let key = parse_key(name).unwrap_or_else(|error| {
+                        if name.is_empty() {
+                            Diagnostic::new(line_index, format!("{action_name}: Missing key in {chord:?}")).at(chord).label("expected a key between every +").exit();
+                        }
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid key {name:?} ({error})"))
+                            .at(name)
+                            .suggest(name, KEY_NAMES.iter().copied())
+                            .exit();
+                    });
+                    keys.push(key);
+                }
+
+                // Parse how long to hold the keys, which is no time at all by default
+                let hold = segments.get(2).map_or(0, |hold| parse_constant(hold, variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid hold time {hold:?} ({error})"))
+                        .at(hold)
+                        .label("expected a number of milliseconds to hold the keys for, like `keypress space 100`")
+                        .exit();
+                }));
+
+                // Add to actions, releasing the keys in reverse order
+                actions.extend(keys.iter().map(|key| Action::KeyDown(*key, None)));
+                if hold > 0 {
+                    actions.push(Action::Wait(hold));
+                }
+                actions.extend(keys.iter().rev().map(|key| Action::KeyUp(*key)));
+            }
+            "holdkey" => {
+                // Validate arguments
+                if segments.len() < 3 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too few arguments! (min. 2 arguments)"))
+                        .at(action)
+                        .label("expected a key and how many milliseconds to hold it for, like `holdkey w 3000`")
+                        .exit();
+                }
+                if segments.len() > 6 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 5 arguments)"))
+                        .spanning(segments[6], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Parse key
+                let key = parse_key(segments[1]).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid key {:?} ({error})", segments[1]))
+                        .at(segments[1])
+                        .suggest(segments[1], KEY_NAMES.iter().copied())
+                        .exit();
+                });
+
+                // Parse duration
+                let duration = parse_constant(segments[2], variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid duration {:?} ({error})", segments[2])).at(segments[2]).exit();
+                });
+
+                // Parse repeating, written like in `keydown` after the duration
+                let repeat_segments: Vec<&str> = segments[..2].iter().chain(&segments[3..]).copied().collect();
+                let repeat = parse_repeat(&repeat_segments, line_index, variables);
+
+                // Add to actions
+                actions.push(Action::HoldKey { key, duration, repeat });
+            }
+            "release" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No argument provided")).at(action).exit();
+                }
+                if segments.len() > 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 1 argument)"))
+                        .spanning(segments[2], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Add to actions
+                match segments[1].to_lowercase().as_str() {
+                    "mouse" => actions.push(Action::Release(OutputType::Mouse)),
+                    "key" => actions.push(Action::Release(OutputType::Key)),
+                    "both" => actions.push(Action::Release(OutputType::Both)),
+                    _ => {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid argument {:?}", segments[1]))
+                            .at(segments[1])
+                            .suggest(segments[1], ["mouse", "key", "both"])
+                            .exit();
+                    }
+                }
+            }
+            "text" => {
+                // Make sure text is provided
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No text provided")).at(action).exit();
+                }
+
+                // A number of milliseconds before the text, like `text 50ms Hello`, types it one character at a time instead
+                // of like the `@typing_delay` line before it says
+                let (typing, words) = match segments[1].strip_suffix("ms").and_then(|delay| delay.parse().ok()) {
+                    Some(delay) => (Typing { delay, human: typing.human && delay > 0 }, &segments[2..]),
+                    None => (typing, &segments[1..])
+                };
+                if words.is_empty() {
+                    Diagnostic::new(line_index, format!("{action_name}: No text provided"))
+                        .at(action)
+                        .label(format!("expected the text to type after the typing delay, like `text {} Hello`", segments[1]))
+                        .exit();
+                }
 
-    // Store actions as Vec<Action>
-    let mut actions: Vec<Action> = Vec::new();
-    for mut action in action_strings {
-        // Ignore empty actions
-        action = action.trim();
-        if action.is_empty() {
-            continue;
-        }
+                // Add to actions
+                actions.push(Action::Text { text: parse_text(&words.join(" "), assigned_variables), typing });
+            }
+            "turbo" => {
+                // Validate arguments
+                if segments.len() < 4 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too few arguments! (min. 3 arguments)"))
+                        .at(action)
+                        .label("expected a key or mouse button, a rate in Hz, and a duration in milliseconds")
+                        .exit();
+                }
+                if segments.len() > 4 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 3 arguments)"))
+                        .spanning(segments[4], segments[segments.len() - 1])
+                        .exit();
+                }
 
-        // Split into segments
-        let segments: Vec<&str> = action.split_whitespace().collect();
-        let action_name = segments[0];
+                // Parse the input to repeat, where mouse buttons are prefixed to tell them apart from number keys
+                let input = match segments[1].strip_prefix("mouse") {
+                    Some(number) => parse_button(number).map(TurboInput::Button),
+                    None => parse_key(segments[1]).map(TurboInput::Key)
+                };
+                let input = input.unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid key or button {:?} ({error})", segments[1]))
+                        .at(segments[1])
+                        .suggest(segments[1], KEY_NAMES.iter().copied())
+                        .exit();
+                });
 
-        // Add Action to actions
-        match action_name {
-            "mousemove" => {
+                // Parse rate
+                let rate: u32 = parse_constant(segments[2], variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid rate {:?} ({error})", segments[2]))
+                        .at(segments[2])
+                        .exit();
+                });
+                if rate == 0 || rate > MAX_TAP_RATE {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid rate {:?}", segments[2]))
+                        .at(segments[2])
+                        .label(format!("the rate must be between 1 and {MAX_TAP_RATE} Hz"))
+                        .exit();
+                }
+
+                // Parse duration
+                let duration: u64 = parse_constant(segments[3], variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid duration {:?} ({error})", segments[3]))
+                        .at(segments[3])
+                        .exit();
+                });
+
+                // Add to actions
+                actions.push(Action::Turbo { input, rate, duration });
+            }
+            "say" => {
+                // Make sure text is provided
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No text provided")).at(action).exit();
+                }
+
+                // Add to actions
+                actions.push(Action::Say(parse_text(&segments[1..].join(" "), assigned_variables)));
+            }
+            "log" => {
+                // The message can be quoted, like `log "Reached the checkout page"`
+                let message = action[action_name.len()..].trim();
+                let message = message.strip_prefix('"').and_then(|message| message.strip_suffix('"')).unwrap_or(message);
+                if message.is_empty() {
+                    Diagnostic::new(line_index, format!("{action_name}: No message provided")).at(action).exit();
+                }
+
+                // Add to actions
+                actions.push(Action::Log(parse_text(message, assigned_variables)));
+            }
+            "waitkey" => {
+                // Validate arguments
+                if segments.len() > 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 1 argument)"))
+                        .spanning(segments[2], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Parse key, where no key means any key
+                let key = segments.get(1).map(|segment| parse_key(segment).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid key {segment:?} ({error})"))
+                        .at(segment)
+                        .suggest(segment, KEY_NAMES.iter().copied())
+                        .exit();
+                }));
+
+                // Add to actions
+                actions.push(Action::WaitKey(key));
+            }
+            "wait" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No duration provided")).at(action).label("expected a number of milliseconds, like `wait 200`").exit();
+                }
+                if segments.len() > 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 1 argument)"))
+                        .spanning(segments[2], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Parse duration
+                let duration = parse_constant(segments[1], variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid duration {:?} ({error})", segments[1])).at(segments[1]).exit();
+                });
+
+                // Add to actions
+                actions.push(Action::Wait(duration));
+            }
+            "probe" => {
+                // Validate arguments
+                if segments.len() != 3 {
+                    let diagnostic = Diagnostic::new(line_index, format!("{action_name}: Expected 2 arguments, found {}", segments.len() - 1));
+                    match segments.len() {
+                        1 => diagnostic.at(action),
+                        2 => diagnostic.at(segments[1]),
+                        _ => diagnostic.spanning(segments[3], segments[segments.len() - 1])
+                    }
+                        .label("expected the X and Y position of the pixel")
+                        .exit();
+                }
+
+                // Parse position
+                let variable_names: Vec<String> = variables.keys().chain(number_variables(assigned_variables)).map(|name| format!("${name}")).collect();
+                let [x, y] = [("X", segments[1]), ("Y", segments[2])].map(|(axis, segment)| {
+                    parse_position(&defaults.transform.apply_literal(segment, Some(usize::from(axis == "Y"))), axis, variables, assigned_variables).unwrap_or_else(|error| {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid {axis} position {segment:?} ({error})"))
+                            .at(segment)
+                            .suggest(segment, variable_names.iter().map(String::as_str))
+                            .exit();
+                    })
+                });
+
+                // Add to actions
+                actions.push(Action::Probe(x, y));
+            }
+            "getmouse" => {
+                // Validate arguments
+                if segments.len() < 3 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too few arguments! (min. 2 arguments)"))
+                        .at(action)
+                        .label("expected the names of the variables to store the X and Y position in")
+                        .exit();
+                }
+                if segments.len() > 3 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 2 arguments)"))
+                        .spanning(segments[3], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Parse variable names, which are written without a $ as they're being set rather than read
+                for name in &segments[1..] {
+                    let written = name.strip_prefix('$').unwrap_or(name);
+                    if !prompt::is_valid_name(written) {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid variable name {name:?}"))
+                            .at(name)
+                            .label("names can only contain letters, digits and underscores, and can't start with a digit")
+                            .exit();
+                    }
+                    if variables.contains_key(written) {
+                        Diagnostic::new(line_index, format!("{action_name}: ${written} can't be changed"))
+                            .at(name)
+                            .label("it's set before the script starts, so pick another name")
+                            .exit();
+                    }
+                    if assigned_variables.get(written) == Some(&VariableKind::Text) {
+                        Diagnostic::new(line_index, format!("{action_name}: ${written} already holds text"))
+                            .at(name)
+                            .label("it's set by getclipboard, so pick another name")
+                            .exit();
+                    }
+                }
+                let x_name = segments[1].strip_prefix('$').unwrap_or(segments[1]).to_string();
+                let y_name = segments[2].strip_prefix('$').unwrap_or(segments[2]).to_string();
+                assigned_variables.insert(x_name.clone(), VariableKind::Number);
+                assigned_variables.insert(y_name.clone(), VariableKind::Number);
+
+                // Add to actions
+                actions.push(Action::GetMouse(x_name, y_name));
+            }
+            "movewindow" | "resizewindow" => {
+                // Validate arguments
+                if segments.len() < 4 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too few arguments! (min. 3 arguments)"))
+                        .at(action)
+                        .label(match action_name {
+                            "movewindow" => "expected a window title, and an X and a Y position",
+                            _ => "expected a window title, and a width and a height"
+                        })
+                        .exit();
+                }
+
+                // The title is everything before the last two arguments, so it can contain spaces, and can be quoted
+                let first_number = segments[segments.len() - 2];
+                let title_end = first_number.as_ptr() as usize - action.as_ptr() as usize;
+                let title = action[segments[0].len()..title_end].trim();
+                let title = title.strip_prefix('"').and_then(|title| title.strip_suffix('"')).unwrap_or(title).to_string();
+                if title.is_empty() {
+                    Diagnostic::new(line_index, format!("{action_name}: No window title provided")).at(action).exit();
+                }
+
+                // Parse the position or size
+                let variable_names: Vec<String> = variables.keys().chain(number_variables(assigned_variables)).map(|name| format!("${name}")).collect();
+                let names = match action_name {
+                    "movewindow" => ["X position", "Y position"],
+                    _ => ["width", "height"]
+                };
+                let [first, second] = [0, 1].map(|index| {
+                    let segment = segments[segments.len() - 2 + index];
+                    parse_number(segment, variables, assigned_variables).unwrap_or_else(|error| {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid {} {segment:?} ({error})", names[index]))
+                            .at(segment)
+                            .suggest(segment, variable_names.iter().map(String::as_str))
+                            .exit();
+                    })
+                });
+
+                // Add to actions
+                match action_name {
+                    "movewindow" => actions.push(Action::MoveWindow { title, x: first, y: second }),
+                    "resizewindow" => actions.push(Action::ResizeWindow { title, width: first, height: second }),
+                    _ => unreachable!("Window action must be movewindow or resizewindow")
+                }
+            }
+            "maximize" | "minimize" | "closewindow" => {
+                // The title is the rest of the action, so it can contain spaces, and can be quoted
+                let title = action[action_name.len()..].trim();
+                let title = title.strip_prefix('"').and_then(|title| title.strip_suffix('"')).unwrap_or(title).to_string();
+                if title.is_empty() {
+                    Diagnostic::new(line_index, format!("{action_name}: No window title provided")).at(action).exit();
+                }
+
+                // Add to actions
+                let command = match action_name {
+                    "maximize" => window::Command::Maximize,
+                    "minimize" => window::Command::Minimize,
+                    "closewindow" => window::Command::Close,
+                    _ => unreachable!("Window command must be maximize, minimize or closewindow")
+                };
+                actions.push(Action::WindowCommand(command, title));
+            }
+            "killprocess" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No argument provided"))
+                        .at(action)
+                        .label("expected the name or ID of the process")
+                        .exit();
+                }
+                if segments.len() > 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 1 argument)"))
+                        .spanning(segments[2], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Add to actions
+                actions.push(Action::KillProcess(Process::parse(segments[1])));
+            }
+            "waitprocess" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No argument provided"))
+                        .at(action)
+                        .label("expected the name or ID of the process")
+                        .exit();
+                }
+
+                // Parse whether to wait for the process to exit instead of starting, and the timeout
+                let mut arguments = &segments[2..];
+                let exit = arguments.first() == Some(&"exit");
+                if exit {
+                    arguments = &arguments[1..];
+                }
+                let timeout = arguments.first().map(|segment| parse_constant(segment, variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid timeout {segment:?} ({error})"))
+                        .at(segment)
+                        .label("expected `exit` or a timeout in milliseconds")
+                        .exit();
+                }));
+                if arguments.len() > 1 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 3 arguments)"))
+                        .spanning(arguments[1], arguments[arguments.len() - 1])
+                        .exit();
+                }
+
+                // Add to actions
+                actions.push(Action::WaitProcess { process: Process::parse(segments[1]), exit, timeout });
+            }
+            "waitwindow" => {
+                // The title can be quoted to contain spaces, like `waitwindow "Untitled - Notepad" 5000`
+                let rest = action[action_name.len()..].trim();
+                let (title, rest) = match rest.strip_prefix('"') {
+                    Some(quoted) => quoted.split_once('"').unwrap_or_else(|| {
+                        Diagnostic::new(line_index, format!("{action_name}: The window title is never closed"))
+                            .at(rest)
+                            .label("expected a `\"` after the title")
+                            .exit();
+                    }),
+                    None => rest.split_once(char::is_whitespace).unwrap_or((rest, ""))
+                };
+                if title.is_empty() {
+                    Diagnostic::new(line_index, format!("{action_name}: No window title provided"))
+                        .at(action)
+                        .label("expected the title of the window, or a part of it, like `waitwindow Notepad`")
+                        .exit();
+                }
+
+                // Parse whether to wait for the window to have focus instead of opening, and the timeout
+                let arguments: Vec<&str> = rest.split_whitespace().collect();
+                let mut arguments = arguments.as_slice();
+                let focus = arguments.first() == Some(&"focus");
+                if focus {
+                    arguments = &arguments[1..];
+                }
+                let timeout = arguments.first().map(|segment| parse_constant(segment, variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid timeout {segment:?} ({error})"))
+                        .at(segment)
+                        .label("expected `focus` or a timeout in milliseconds")
+                        .exit();
+                }));
+                if arguments.len() > 1 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 3 arguments)"))
+                        .spanning(arguments[1], arguments[arguments.len() - 1])
+                        .exit();
+                }
+
+                // Add to actions
+                actions.push(Action::WaitWindow { title: title.to_string(), focus, timeout });
+            }
+            "waitpixel" => {
                 // Validate arguments
                 if segments.len() < 4 {
-                    println!("Line {line_index} ({action_name}): Too few arguments! (min. 3 arguments)");
-                    process::exit(1);
+                    Diagnostic::new(line_index, format!("{action_name}: Too few arguments! (min. 3 arguments)"))
+                        .at(action)
+                        .label("expected the X and Y position of the pixel and its color, like `waitpixel 100 200 #112233`")
+                        .exit();
+                }
+                if segments.len() > 6 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 5 arguments)"))
+                        .spanning(segments[6], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Parse position
+                let variable_names: Vec<String> = variables.keys().chain(number_variables(assigned_variables)).map(|name| format!("${name}")).collect();
+                let [x, y] = [("X", segments[1]), ("Y", segments[2])].map(|(axis, segment)| {
+                    parse_position(&defaults.transform.apply_literal(segment, Some(usize::from(axis == "Y"))), axis, variables, assigned_variables).unwrap_or_else(|error| {
+                        Diagnostic::new(line_index, format!("{action_name}: Invalid {axis} position {segment:?} ({error})"))
+                            .at(segment)
+                            .suggest(segment, variable_names.iter().map(String::as_str))
+                            .exit();
+                    })
+                });
+
+                // Parse color
+                let color = Color::parse(segments[3]).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid color {:?} ({error})", segments[3]))
+                        .at(segments[3])
+                        .label("expected a hex code like #112233")
+                        .exit();
+                });
+
+                // Parse how far each channel of the color can be off, and the timeout
+                let tolerance = segments.get(4).map_or(0, |segment| parse_constant(segment, variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid tolerance {segment:?} ({error})"))
+                        .at(segment)
+                        .label("expected how far each of red, green and blue can be off, from 0 to 255")
+                        .exit();
+                }));
+                let timeout = segments.get(5).map(|segment| parse_constant(segment, variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid timeout {segment:?} ({error})"))
+                        .at(segment)
+                        .label("expected a timeout in milliseconds")
+                        .exit();
+                }));
+
+                // Add to actions
+                actions.push(Action::WaitPixel { x, y, color, tolerance, timeout });
+            }
+            "typerandom" => {
+                // Validate arguments
+                if segments.len() != 3 {
+                    let diagnostic = Diagnostic::new(line_index, format!("{action_name}: Expected 2 arguments, found {}", segments.len() - 1));
+                    match segments.len() {
+                        1 => diagnostic.at(action),
+                        2 => diagnostic.at(segments[1]),
+                        _ => diagnostic.spanning(segments[3], segments[segments.len() - 1])
+                    }
+                        .label("expected a pattern, like alphanumeric or lorem, and a length")
+                        .exit();
+                }
+
+                // Parse pattern and length
+                let pattern = Pattern::parse(segments[1]).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid pattern {:?} ({error})", segments[1]))
+                        .at(segments[1])
+                        .suggest(segments[1], ["letters", "lowercase", "uppercase", "digits", "alphanumeric", "lorem"])
+                        .exit();
+                });
+                let length = parse_number(segments[2], variables, assigned_variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid length {:?} ({error})", segments[2])).at(segments[2]).exit();
+                });
+
+                // Add to actions
+                actions.push(Action::TypeRandom { pattern, length, typing });
+            }
+            "getclipboard" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No argument provided"))
+                        .at(action)
+                        .label("expected the name of the variable to store the text in")
+                        .exit();
+                }
+                if segments.len() > 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 1 argument)"))
+                        .spanning(segments[2], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Add to actions
+                let name = parse_text_variable(segments[1], line_index, action_name, variables, assigned_variables);
+                actions.push(Action::GetClipboard(name));
+            }
+            "waittext" => {
+                // The text can be quoted so it can contain spaces, and is followed by the numbers
+                let rest = action[action_name.len()..].trim_start();
+                let (text, numbers): (&str, Vec<&str>) = match rest.strip_prefix('"') {
+                    Some(quoted) => {
+                        let end = quoted.find('"').unwrap_or_else(|| {
+                            Diagnostic::new(line_index, format!("{action_name}: Missing closing quote"))
+                                .at(rest)
+                                .label("expected the text to end with \"")
+                                .exit();
+                        });
+                        (&quoted[..end], quoted[end + 1..].split_whitespace().collect())
+                    }
+                    None => (segments.get(1).copied().unwrap_or_default(), segments.iter().skip(2).copied().collect())
+                };
+                if text.trim().is_empty() {
+                    Diagnostic::new(line_index, format!("{action_name}: No text provided")).at(action).exit();
+                }
+
+                // Parse the region and the timeout, which are both optional
+                let (region, timeout) = match numbers.len() {
+                    0 => (None, None),
+                    1 => (None, Some(numbers[0])),
+                    4 => (Some(&numbers[..4]), None),
+                    5 => (Some(&numbers[..4]), Some(numbers[4])),
+                    _ => {
+                        Diagnostic::new(line_index, format!("{action_name}: Wrong number of arguments after the text"))
+                            .spanning(numbers[0], numbers[numbers.len() - 1])
+                            .label("expected a timeout, a region as X, Y, width and height, or a region and a timeout")
+                            .exit();
+                    }
+                };
+                let region = region.map(|region| parse_region(region, line_index, action_name, variables, assigned_variables, &defaults.transform));
+                let timeout = timeout.map(|segment| parse_constant(segment, variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid timeout {segment:?} ({error})"))
+                        .at(segment)
+                        .label("expected a timeout in milliseconds")
+                        .exit();
+                }));
+
+                // Add to actions
+                actions.push(Action::WaitText { text: text.to_string(), region, timeout });
+            }
+            "exec" => {
+                // The command can be quoted to contain spaces, like `exec "firefox example.com" wait`
+                let rest = action[action_name.len()..].trim();
+                let (command, rest) = match rest.strip_prefix('"') {
+                    Some(quoted) => quoted.split_once('"').unwrap_or_else(|| {
+                        Diagnostic::new(line_index, format!("{action_name}: The command is never closed"))
+                            .at(rest)
+                            .label("expected a `\"` after the command")
+                            .exit();
+                    }),
+                    None => rest.split_once(char::is_whitespace).unwrap_or((rest, ""))
+                };
+                if command.trim().is_empty() {
+                    Diagnostic::new(line_index, format!("{action_name}: No command provided"))
+                        .at(action)
+                        .label("expected a command to run, like `exec \"notepad notes.txt\"`")
+                        .exit();
+                }
+
+                // Parse whether to wait for the command to finish
+                let rest = rest.trim();
+                if !rest.is_empty() && rest != "wait" {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid argument {rest:?}"))
+                        .at(rest)
+                        .label("expected `wait` to wait for the command to finish, or nothing. Commands with spaces have to be quoted")
+                        .exit();
+                }
+
+                // Add to actions
+                actions.push(Action::Exec { command: command.to_string(), wait: rest == "wait" });
+            }
+            "abort" => {
+                // The exit code is optional, and so is the message after it, like `abort 7 "The login failed"`
+                let rest = action[action_name.len()..].trim();
+                let (code, message) = match rest.split_once(char::is_whitespace).unwrap_or((rest, "")) {
+                    (code, message) if !code.is_empty() && code.chars().all(|character| character.is_ascii_digit()) => {
+                        match code.parse::<i32>().ok().filter(|code| (1..=255).contains(code)) {
+                            Some(parsed) => (parsed, message.trim()),
+                            None => Diagnostic::new(line_index, format!("{action_name}: Invalid exit code {code:?}"))
+                                .at(code)
+                                .label("expected an exit code from 1 to 255")
+                                .exit()
+                        }
+                    }
+                    _ => (ErrorKind::Assertion.exit_code(), rest)
+                };
+                let message = message.strip_prefix('"').and_then(|message| message.strip_suffix('"')).unwrap_or(message);
+
+                // Add to actions
+                actions.push(Action::Abort { code, message: parse_text(message, assigned_variables) });
+            }
+            "waitimage" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No file provided"))
+                        .at(action)
+                        .label("expected a .png file with the image to wait for, and optionally a timeout")
+                        .exit();
+                }
+                if segments.len() > 3 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 2 arguments)"))
+                        .spanning(segments[3], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Load the image now, so a missing or broken file is found before anything is sent
+                let template = Template::load(&directory.join(segments[1])).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid image ({error})")).kind(ErrorKind::Setup).at(segments[1]).exit();
+                });
+                let timeout = segments.get(2).map(|segment| parse_constant(segment, variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid timeout {segment:?} ({error})"))
+                        .at(segment)
+                        .label("expected a timeout in milliseconds")
+                        .exit();
+                }));
+
+                // Add to actions
+                actions.push(Action::WaitImage { file: segments[1].to_string(), template: Arc::new(template), timeout });
+            }
+            "clickimage" => {
+                // Validate arguments
+                if segments.len() < 2 {
+                    Diagnostic::new(line_index, format!("{action_name}: No file provided"))
+                        .at(action)
+                        .label("expected a .png file with the image to click, and optionally a button and a timeout")
+                        .exit();
                 }
-                if segments.len() > 5 {
-                    println!("Line {line_index} ({action_name}): Too many arguments provided (max. 4 arguments)");
-                    process::exit(1);
+                if segments.len() > 4 {
+                    Diagnostic::new(line_index, format!("{action_name}: Too many arguments provided (max. 3 arguments)"))
+                        .spanning(segments[4], segments[segments.len() - 1])
+                        .exit();
+                }
+
+                // Load the image now, so a missing or broken file is found before anything is sent
+                let template = Template::load(&directory.join(segments[1])).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid image ({error})")).kind(ErrorKind::Setup).at(segments[1]).exit();
+                });
+                let button = segments.get(2).map_or(Ok(Button::Left), |segment| parse_button(segment)).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid button {:?} ({error})", segments[2]))
+                        .at(segments[2])
+                        .suggest(segments[2], BUTTON_NAMES.iter().copied())
+                        .exit();
+                });
+                let timeout = segments.get(3).map(|segment| parse_constant(segment, variables).unwrap_or_else(|error| {
+                    Diagnostic::new(line_index, format!("{action_name}: Invalid timeout {segment:?} ({error})"))
+                        .at(segment)
+                        .label("expected a timeout in milliseconds")
+                        .exit();
+                }));
+
+                // Add to actions
+                actions.push(Action::ClickImage { file: segments[1].to_string(), template: Arc::new(template), button, timeout });
+            }
+            "readtext" => {
+                // Validate arguments
+                if segments.len() != 6 {
+                    let diagnostic = Diagnostic::new(line_index, format!("{action_name}: Expected 5 arguments, found {}", segments.len() - 1));
+                    match segments.len() {
+                        1 => diagnostic.at(action),
+                        2..6 => diagnostic.spanning(segments[1], segments[segments.len() - 1]),
+                        _ => diagnostic.spanning(segments[6], segments[segments.len() - 1])
+                    }
+                        .label("expected a region as X, Y, width and height, and the name of the variable to store the text in")
+                        .exit();
                 }
 
-                // Parse method
-                let method = match segments[1] {
-                    "abs" => Coordinate::Abs,
-                    "rel" => Coordinate::Rel,
-                    _ => {
-                        println!("Line {line_index} ({action_name}): Invalid method {:?}", segments[1]);
-                        process::exit(1);
-                    }
-                };
+                // Add to actions
+                let region = parse_region(&segments[1..5], line_index, action_name, variables, assigned_variables, &defaults.transform);
+                let name = parse_text_variable(segments[5], line_index, action_name, variables, assigned_variables);
+                actions.push(Action::ReadText { region, name });
+            }
+            _ => {
+                Diagnostic::new(line_index, format!("Invalid action: {action_name:?}"))
+                    .at(action_name)
+                    .suggest(action_name, ACTION_NAMES.iter().copied())
+                    .exit();
+            }
+        }
+    }
+
+    // Return populated actions list
+    actions
+}
+
+/// Parse the optional `repeat <rate> [delay]` arguments of `keydown`
+fn parse_repeat(segments: &[&str], line_index: i32, variables: &HashMap<String, i32>) -> Option<RepeatRate> {
+    if segments.len() < 3 {
+        return None;
+    }
+
+    // Validate arguments
+    if segments[2] != "repeat" {
+        Diagnostic::new(line_index, format!("{}: Invalid argument {:?}", segments[0], segments[2]))
+            .at(segments[2])
+            .label("expected `repeat`")
+            .exit();
+    }
+    if segments.len() < 4 {
+        Diagnostic::new(line_index, format!("{}: No repeat rate provided", segments[0])).at(segments[2]).exit();
+    }
+    if segments.len() > 5 {
+        Diagnostic::new(line_index, format!("{}: Too many arguments provided (max. 4 arguments)", segments[0]))
+            .spanning(segments[5], segments[segments.len() - 1])
+            .exit();
+    }
+
+    // Parse rate
+    let rate: u32 = parse_constant(segments[3], variables).unwrap_or_else(|error| {
+        Diagnostic::new(line_index, format!("{}: Invalid repeat rate {:?} ({error})", segments[0], segments[3])).at(segments[3]).exit();
+    });
+    if rate == 0 || rate > MAX_TAP_RATE {
+        Diagnostic::new(line_index, format!("{}: Invalid repeat rate {:?}", segments[0], segments[3]))
+            .at(segments[3])
+            .label(format!("the rate must be between 1 and {MAX_TAP_RATE} Hz"))
+            .exit();
+    }
+
+    // Parse delay
+    let delay: u64 = match segments.get(4) {
+        Some(delay) => parse_constant(delay, variables).unwrap_or_else(|error| {
+            Diagnostic::new(line_index, format!("{}: Invalid repeat delay {delay:?} ({error})", segments[0])).at(delay).exit();
+        }),
+        None => repeat::DEFAULT_DELAY
+    };
+
+    Some(RepeatRate { rate, delay })
+}
+
+/// Parse a time in a timestamp, which is in milliseconds unless it ends in a unit, like `1.5s` or `2m`
+fn parse_time(text: &str) -> Result<u64, String> {
+    let (number, unit) = if let Some(number) = text.strip_suffix("ms") {
+        (number, 1.0)
+    } else if let Some(number) = text.strip_suffix('s') {
+        (number, 1000.0)
+    } else if let Some(number) = text.strip_suffix('m') {
+        (number, 60_000.0)
+    } else {
+        return text.parse().map_err(|error: std::num::ParseIntError| error.to_string());
+    };
+
+    // Fractions are only allowed with a unit, and the time is rounded to whole milliseconds
+    let number = Some(number)
+        .filter(|number| number.chars().all(|character| character.is_ascii_digit() || character == '.'))
+        .and_then(|number| number.parse::<f64>().ok())
+        .ok_or("expected a number before the unit, like 1.5s")?;
+    Ok((number * unit).round() as u64)
+}
+
+/// Parse a time written like a media player shows it, as minutes and seconds like `01:23.450` or with hours in front like
+/// `1:02:03`, into milliseconds
+fn parse_clock_time(text: &str) -> Result<u64, String> {
+    let (clock, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let parts: Vec<&str> = clock.split(':').collect();
+    if parts.len() > 3 || parts.iter().chain([&fraction]).any(|part| !part.chars().all(|character| character.is_ascii_digit())) {
+        return Err("expected minutes and seconds like 01:23.450, or hours, minutes and seconds like 1:02:03".to_string());
+    }
+
+    // Every part after the first counts up to 60, like on a clock
+    let mut seconds: u64 = 0;
+    for (index, part) in parts.iter().enumerate() {
+        let value: u64 = part.parse().map_err(|_| "expected a number on both sides of every \":\"".to_string())?;
+        if index > 0 && value >= 60 {
+            return Err(format!("{value} is too many minutes or seconds, as they go up to 59"));
+        }
+        seconds = seconds * 60 + value;
+    }
+
+    // The fraction of a second is rounded to whole milliseconds
+    let milliseconds = if fraction.is_empty() { 0.0 } else { format!("0.{fraction}").parse::<f64>().map_err(|error| error.to_string())? * 1000.0 };
+    Ok(seconds * 1000 + milliseconds.round() as u64)
+}
+
+/// Get a command that runs a line with the shell, which is `sh` on Linux and macOS and `cmd` on Windows
+fn shell(command: &str) -> process::Command {
+    let (program, flag) = if cfg!(target_os = "windows") { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut shell = process::Command::new(program);
+    shell.args([flag, command]);
+    shell
+}
+
+/// Parse how text is typed, like `25` or `120 human`, as used by `@typing_delay` lines and `@defaults` sections
+fn parse_typing(argument: &str, line_index: i32, line: &str) -> Typing {
+    let mut arguments = argument.split_whitespace();
+    let delay_text = arguments.next().unwrap_or("");
+    let delay: u64 = delay_text.parse().unwrap_or_else(|_| {
+        Diagnostic::new(line_index, format!("Invalid typing delay {delay_text:?}"))
+            .at(if delay_text.is_empty() { line } else { delay_text })
+            .label("expected a number of milliseconds, like 25, or 0 to type text all at once")
+            .exit();
+    });
+    let human = match arguments.next() {
+        None => false,
+        Some("human") if delay > 0 => true,
+        Some("human") => {
+            Diagnostic::new(line_index, "Human typing needs a typing delay".to_string())
+                .at(delay_text)
+                .label("expected the average time between characters, like 120")
+                .exit();
+        }
+        Some(mode) => {
+            Diagnostic::new(line_index, format!("Unknown typing mode {mode:?}"))
+                .at(mode)
+                .suggest(mode, ["human"])
+                .exit();
+        }
+    };
+    if let Some(extra) = arguments.next() {
+        Diagnostic::new(line_index, format!("Unexpected {extra:?} after the typing mode")).at(extra).exit();
+    }
+    Typing { delay, human }
+}
+
+/// Parse the number or name of a mouse button, as used by `mousedown` and `mouseup`
+fn parse_button(name: &str) -> Result<Button, String> {
+    let button = match name.to_lowercase().as_str() {
+        "1" | "left" => Button::Left,
+        "2" | "right" => Button::Right,
+        "3" | "middle" => Button::Middle,
+        #[cfg(not(target_os = "macos"))]
+        "4" | "back" => Button::Back,
+        #[cfg(not(target_os = "macos"))]
+        "5" | "forward" => Button::Forward,
+        "scrollup" => Button::ScrollUp,
+        "scrolldown" => Button::ScrollDown,
+        "scrollleft" => Button::ScrollLeft,
+        "scrollright" => Button::ScrollRight,
+        _ if name.parse::<u8>().is_ok() => return Err("not a mouse button".to_string()),
+        _ => return Err("expected a number from 1 to 5 or the name of a button".to_string())
+    };
+
+    Ok(button)
+}
+
+/// Parse the name of a key, as used by `keydown` and `keyup`
+fn parse_key(name: &str) -> Result<Key, String> {
+    let key = match name.to_lowercase().as_str() {
+        "alt" => Key::Alt,
+        "backspace" => Key::Backspace,
+        "capslock" => Key::CapsLock,
+        "control" | "ctrl" => Key::Control,
+        "delete" => Key::Delete,
+        "down" => Key::DownArrow,
+        "end" => Key::End,
+        "enter" => Key::Return,
+        "escape" => Key::Escape,
+        "f1" => Key::F1,
+        "f2" => Key::F2,
+        "f3" => Key::F3,
+        "f4" => Key::F4,
+        "f5" => Key::F5,
+        "f6" => Key::F6,
+        "f7" => Key::F7,
+        "f8" => Key::F8,
+        "f9" => Key::F9,
+        "f10" => Key::F10,
+        "f11" => Key::F11,
+        "f12" => Key::F12,
+        "f13" => Key::F13,
+        "f14" => Key::F14,
+        "f15" => Key::F15,
+        "f16" => Key::F16,
+        "f17" => Key::F17,
+        "f18" => Key::F18,
+        "f19" => Key::F19,
+        "f20" => Key::F20,
+        "home" => Key::Home,
+        #[cfg(not(target_os = "macos"))]
+        "insert" => Key::Insert,
+        "left" => Key::LeftArrow,
+        "pagedown" => Key::PageDown,
+        "pageup" => Key::PageUp,
+        "right" => Key::RightArrow,
+        "shift" => Key::Shift,
+        "space" => Key::Space,
+        "super" => Key::Meta,
+        "tab" => Key::Tab,
+        "up" => Key::UpArrow,
+        "playpause" => Key::MediaPlayPause,
+        "nexttrack" => Key::MediaNextTrack,
+        "prevtrack" => Key::MediaPrevTrack,
+        "volumeup" => Key::VolumeUp,
+        "volumedown" => Key::VolumeDown,
+        "mute" => Key::VolumeMute,
+        #[cfg(target_os = "linux")]
+        "printscreen" => Key::Print,
+        #[cfg(target_os = "windows")]
+        "printscreen" => Key::Snapshot,
+        #[cfg(target_os = "linux")]
+        "scrolllock" => Key::ScrollLock,
+        #[cfg(target_os = "windows")]
+        "scrolllock" => Key::Scroll,
+        #[cfg(not(target_os = "macos"))]
+        "pause" => Key::Pause,
+        #[cfg(target_os = "linux")]
+        "contextmenu" => Key::LMenu,
+        #[cfg(target_os = "windows")]
+        "contextmenu" => Key::Apps,
+        #[cfg(not(target_os = "macos"))]
+        "numlock" => Key::Numlock,
+        #[cfg(not(target_os = "windows"))]
+        "brightnessup" => keyboard::BRIGHTNESS_UP,
+        #[cfg(not(target_os = "windows"))]
+        "brightnessdown" => keyboard::BRIGHTNESS_DOWN,
+        _ => {
+            // Parse non-special keys
+            let key: char = name.parse().map_err(|error: std::char::ParseCharError| error.to_string())?;
+
+            // Disallow non-standard keys, uppercase letters are pressed along with Shift
+            match key {
+                'a'..='z' | 'A'..='Z' => Key::Unicode(key),
+                '0'..='9' => Key::Unicode(key),
+                '`' => Key::Unicode(key),
+                '-' => Key::Unicode(key),
+                '=' => Key::Unicode(key),
+                '[' => Key::Unicode(key),
+                ']' => Key::Unicode(key),
+                '\\' => Key::Unicode(key),
+                ';' => Key::Unicode(key),
+                '\'' => Key::Unicode(key),
+                ',' => Key::Unicode(key),
+                '.' => Key::Unicode(key),
+                '/' => Key::Unicode(key),
+                // Symbols typed with Shift, which is held along with them
+                '~' | '!' | '@' | '#' | '$' | '%' | '^' | '&' | '*' | '(' | ')' | '_' | '+' | '{' | '}' | '|' | ':' | '"' | '<' | '>' | '?' => Key::Unicode(key),
+                _ => return Err("not a key on a standard keyboard".to_string())
+            }
+        }
+    };
+
+    Ok(key)
+}
+
+/// Split an action into its name and arguments at whitespace, except around operators and inside parentheses
+///
+/// This keeps expressions like `$SCREEN_W/2 - 100` in one argument. A minus sign at the start of an argument is part of a
+/// negative number instead, so `mousemove rel 0 -50` still has two numbers.
+fn split_expressions(action: &str) -> Vec<&str> {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut start: Option<usize> = None;
+    for word in action.split_whitespace() {
+        let offset = word.as_ptr() as usize - action.as_ptr() as usize;
+        let joins = start.is_some_and(|start| {
+            let previous = &action[start..offset];
+            let depth = previous.matches('(').count() as i32 - previous.matches(')').count() as i32;
+            depth > 0 || previous.trim_end().ends_with(expression::OPERATORS) || expression::OPERATORS.iter().any(|operator| word == operator.to_string()) || word.starts_with(['*', '/'])
+        });
+
+        if !joins {
+            if let Some(start) = start {
+                segments.push(action[start..offset].trim_end());
+            }
+            start = Some(offset);
+        }
+    }
+    if let Some(start) = start {
+        segments.push(&action[start..]);
+    }
+
+    segments
+}
+
+/// Parse a numeric argument, which is an expression of numbers and `$VARIABLES` that are set before the script starts or
+/// while it runs
+///
+/// Expressions that only use variables set before the script starts are calculated right away.
+fn parse_number(argument: &str, variables: &HashMap<String, i32>, assigned_variables: &HashMap<String, VariableKind>) -> Result<Number, String> {
+    let expression = Expression::parse(argument)?;
+    for name in expression.variables().into_iter().filter(|name| !variables.contains_key(*name)) {
+        match assigned_variables.get(name) {
+            Some(VariableKind::Number) => {}
+            Some(VariableKind::Text) => return Err(format!("${name} holds text, not a number")),
+            None => return Err(format!("unknown variable ${name}"))
+        }
+    }
+
+    let expression = expression.substitute(variables);
+    if expression.variables().is_empty() && !expression.is_random() {
+        expression.evaluate(variables).map(Number::Fixed)
+    } else if expression.variables().is_empty() {
+        // Mistakes like an empty range are found right away, even though the number is picked while the script runs
+        expression.evaluate(variables).map(|_| Number::Expression(expression))
+    } else {
+        Ok(Number::Expression(expression))
+    }
+}
+
+/// Parse a numeric argument that has to be known before the script starts, like a duration or a timeout, which can be an
+/// expression of numbers and the variables that are set before the script starts
+fn parse_constant<T: TryFrom<i32>>(argument: &str, variables: &HashMap<String, i32>) -> Result<T, String> {
+    let expression = Expression::parse(argument)?;
+    if let Some(name) = expression.variables().into_iter().find(|name| !variables.contains_key(*name)) {
+        return Err(format!("${name} isn't known before the script starts"));
+    }
+    let value = expression.evaluate(variables)?;
+    T::try_from(value).map_err(|_| if value < 0 { format!("{value} is negative") } else { format!("{value} is too large") })
+}
 
-                // Parse X position
-                let x: i32 = segments[2].parse().unwrap_or_else(|error| {
-                    println!("Line {line_index} ({action_name}): Invalid X position {:?} ({error})", segments[2]);
-                    process::exit(1);
-                });
+/// Parse the X position, Y position, width and height of a region of the screen, exiting if any of them is invalid
+///
+/// The position is changed by the `@offset` and `@scale` lines before it, and the size is only scaled.
+fn parse_region(segments: &[&str], line_index: i32, action_name: &str, variables: &HashMap<String, i32>, assigned_variables: &HashMap<String, VariableKind>, transform: &Transform) -> [Number; 4] {
+    let variable_names: Vec<String> = variables.keys().chain(number_variables(assigned_variables)).map(|name| format!("${name}")).collect();
+    let names = ["X position", "Y position", "width", "height"];
+    [0, 1, 2, 3].map(|index| {
+        parse_number(&transform.apply_literal(segments[index], (index < 2).then_some(index)), variables, assigned_variables).unwrap_or_else(|error| {
+            Diagnostic::new(line_index, format!("{action_name}: Invalid {} {:?} ({error})", names[index], segments[index]))
+                .at(segments[index])
+                .suggest(segments[index], variable_names.iter().map(String::as_str))
+                .exit();
+        })
+    })
+}
 
-                // Parse Y position
-                let y: i32 = segments[3].parse().unwrap_or_else(|error| {
-                    println!("Line {line_index} ({action_name}): Invalid Y position {:?} ({error})", segments[3]);
-                    process::exit(1);
-                });
+/// Parse the name of a text variable that an action stores text in, exiting if it's invalid
+///
+/// The name is written without a `$`, as it's being set rather than read.
+fn parse_text_variable(segment: &str, line_index: i32, action_name: &str, variables: &HashMap<String, i32>, assigned_variables: &mut HashMap<String, VariableKind>) -> String {
+    let name = segment.strip_prefix('$').unwrap_or(segment);
+    if !prompt::is_valid_name(name) {
+        Diagnostic::new(line_index, format!("{action_name}: Invalid variable name {segment:?}"))
+            .at(segment)
+            .label("names can only contain letters, digits and underscores, and can't start with a digit")
+            .exit();
+    }
+    if variables.contains_key(name) || assigned_variables.get(name) == Some(&VariableKind::Number) {
+        Diagnostic::new(line_index, format!("{action_name}: ${name} already holds a number"))
+            .at(segment)
+            .label("text and numbers can't share a variable, so pick another name")
+            .exit();
+    }
 
-                // Parse time
-                let time: u64 = if segments.len() > 4 {
-                    segments[4].parse().unwrap_or_else(|error| {
-                        println!("Line {line_index} ({action_name}): Invalid time {:?} ({error})", segments[4]);
-                        process::exit(1);
-                    })
-                } else {
-                    0
-                };
+    assigned_variables.insert(name.to_string(), VariableKind::Text);
+    name.to_string()
+}
 
-                // Add to actions
-                actions.push(Action::MouseMove { x, y, time, method });
-            }
-            "mousedown" | "mouseup" => {
-                // Validate arguments
-                if segments.len() < 2 {
-                    println!("Line {line_index} ({action_name}): No argument provided");
-                    process::exit(1);
-                }
-                if segments.len() > 2 {
-                    println!("Line {line_index} ({action_name}): Too many arguments provided (max. 1 argument)");
-                    process::exit(1);
-                }
+/// The names of the variables set while the script runs that hold numbers
+fn number_variables(assigned_variables: &HashMap<String, VariableKind>) -> impl Iterator<Item = &String> {
+    assigned_variables.iter().filter(|(_, kind)| **kind == VariableKind::Number).map(|(name, _)| name)
+}
 
-                // Parse button
-                let button_number: u8 = segments[1].parse().unwrap_or_else(|error| {
-                    println!("Line {line_index} ({action_name}): Invalid button {:?} ({error})", segments[1]);
-                    process::exit(1);
-                });
+/// Split text into literal pieces and the text variables set before it, so `Copied: $copied` fills in `$copied`
+///
+/// A `$` that isn't followed by the name of a text variable is kept as it is.
+fn parse_text(text: &str, assigned_variables: &HashMap<String, VariableKind>) -> Vec<TextPart> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut rest = text;
 
-                let button = match button_number {
-                    1 => Button::Left,
-                    2 => Button::Right,
-                    3 => Button::Middle,
-                    #[cfg(not(target_os = "macos"))]
-                    4 => Button::Back,
-                    #[cfg(not(target_os = "macos"))]
-                    5 => Button::Forward,
-                    _ => {
-                        println!("Line {line_index} ({action_name}): Invalid button {:?}", segments[1]);
-                        process::exit(1);
-                    }
-                };
+    while let Some(index) = rest.find('$') {
+        literal.push_str(&rest[..index]);
+        let after = &rest[index + 1..];
+        let length = after.find(|character: char| !character.is_ascii_alphanumeric() && character != '_').unwrap_or(after.len());
+        let name = &after[..length];
 
-                // Add to actions
-                match action_name {
-                    "mousedown" => actions.push(Action::MouseDown(button)),
-                    "mouseup" => actions.push(Action::MouseUp(button)),
-                    _ => unreachable!("Mouse action must be mousedown or mouseup")
-                }
+        if assigned_variables.get(name) == Some(&VariableKind::Text) {
+            if !literal.is_empty() {
+                parts.push(TextPart::Literal(std::mem::take(&mut literal)));
             }
-            "keydown" | "keyup" => {
-                // Validate arguments
-                if segments.len() < 2 {
-                    println!("Line {line_index} ({action_name}): No argument provided");
-                    process::exit(1);
-                }
-                if segments.len() > 2 {
-                    println!("Line {line_index} ({action_name}): Too many arguments provided (max. 1 argument)");
-                    process::exit(1);
-                }
+            parts.push(TextPart::Variable(name.to_string()));
+            rest = &after[length..];
+        } else {
+            literal.push('$');
+            rest = after;
+        }
+    }
 
-                // Parse key
-                let key = match segments[1].to_lowercase().as_str() {
-                    "alt" => Key::Alt,
-                    "backspace" => Key::Backspace,
-                    "capslock" => Key::CapsLock,
-                    "control" => Key::Control,
-                    "delete" => Key::Delete,
-                    "down" => Key::DownArrow,
-                    "end" => Key::End,
-                    "enter" => Key::Return,
-                    "escape" => Key::Escape,
-                    "f1" => Key::F1,
-                    "f2" => Key::F2,
-                    "f3" => Key::F3,
-                    "f4" => Key::F4,
-                    "f5" => Key::F5,
-                    "f6" => Key::F6,
-                    "f7" => Key::F7,
-                    "f8" => Key::F8,
-                    "f9" => Key::F9,
-                    "f10" => Key::F10,
-                    "f11" => Key::F11,
-                    "f12" => Key::F12,
-                    "f13" => Key::F13,
-                    "f14" => Key::F14,
-                    "f15" => Key::F15,
-                    "f16" => Key::F16,
-                    "f17" => Key::F17,
-                    "f18" => Key::F18,
-                    "f19" => Key::F19,
-                    "f20" => Key::F20,
-                    "home" => Key::Home,
-                    #[cfg(not(target_os = "macos"))]
-                    "insert" => Key::Insert,
-                    "left" => Key::LeftArrow,
-                    "pagedown" => Key::PageDown,
-                    "pageup" => Key::PageUp,
-                    "right" => Key::RightArrow,
-                    "shift" => Key::Shift,
-                    "space" => Key::Space,
-                    "super" => Key::Meta,
-                    "tab" => Key::Tab,
-                    "up" => Key::UpArrow,
-                    _ => {
-                        // Parse non-special keys
-                        let key: char = segments[1].to_lowercase().parse().unwrap_or_else(|error| {
-                            println!("Line {line_index} ({action_name}): Invalid key {:?} ({error})", segments[1]);
-                            process::exit(1);
-                        });
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        parts.push(TextPart::Literal(literal));
+    }
+    parts
+}
 
-                        // Disallow non-standard keys
-                        match key {
-                            'a'..='z' => Key::Unicode(key),
-                            '0'..='9' => Key::Unicode(key),
-                            '`' => Key::Unicode(key),
-                            '-' => Key::Unicode(key),
-                            '=' => Key::Unicode(key),
-                            '[' => Key::Unicode(key),
-                            ']' => Key::Unicode(key),
-                            '\\' => Key::Unicode(key),
-                            ';' => Key::Unicode(key),
-                            '\'' => Key::Unicode(key),
-                            ',' => Key::Unicode(key),
-                            '.' => Key::Unicode(key),
-                            '/' => Key::Unicode(key),
-                            _ => {
-                                println!("Line {line_index} ({action_name}): Invalid key {:?}", segments[1]);
-                                process::exit(1);
-                            }
-                        }
-                    }
-                };
+/// Fill in the text variables in a piece of text
+fn resolve_text(parts: &[TextPart], texts: &HashMap<String, String>) -> Result<String, String> {
+    parts.iter().map(|part| match part {
+        TextPart::Literal(text) => Ok(text.as_str()),
+        TextPart::Variable(name) => texts.get(name).map(String::as_str).ok_or(format!("${name} hasn't been set yet"))
+    }).collect()
+}
 
-                // Add to actions
-                match action_name {
-                    "keydown" => actions.push(Action::KeyDown(key)),
-                    "keyup" => actions.push(Action::KeyUp(key)),
-                    _ => unreachable!("Key action must be keydown or keyup")
-                }
-            }
-            "release" => {
-                // Validate arguments
-                if segments.len() < 2 {
-                    println!("Line {line_index} ({action_name}): No argument provided");
-                    process::exit(1);
-                }
-                if segments.len() > 2 {
-                    println!("Line {line_index} ({action_name}): Too many arguments provided (max. 1 argument)");
-                    process::exit(1);
-                }
+/// Calculate the position and size of a region of the screen, in physical pixels
+fn resolve_region(region: &[Number; 4], variables: &HashMap<String, i32>, logical_monitors: Option<&[Monitor]>) -> Result<(i32, i32, u32, u32), String> {
+    let [x, y, width, height] = [0, 1, 2, 3].map(|index| region[index].resolve(variables));
+    let (x, y, width, height) = (x?, y?, width?, height?);
+    if width <= 0 || height <= 0 {
+        return Err(format!("the size of the region has to be positive, not {width}x{height}"));
+    }
 
-                // Add to actions
-                match segments[1].to_lowercase().as_str() {
-                    "mouse" => actions.push(Action::Release(OutputType::Mouse)),
-                    "key" => actions.push(Action::Release(OutputType::Key)),
-                    "both" => actions.push(Action::Release(OutputType::Both)),
-                    _ => {
-                        println!("Line {line_index} ({action_name}): Invalid argument {:?}", segments[1]);
-                        process::exit(1);
-                    }
-                }
-            }
-            "text" => {
-                // Make sure text is provided
-                if segments.len() < 2 {
-                    println!("Line {line_index} ({action_name}): No text provided");
-                    process::exit(1);
-                }
+    // Both corners are converted like absolute mouse movements, so the region covers the same content at any scale
+    match logical_monitors {
+        Some(monitors) => {
+            let (left, top) = monitors::logical_to_physical(monitors, x, y);
+            let (right, bottom) = monitors::logical_to_physical(monitors, x.saturating_add(width), y.saturating_add(height));
+            Ok((left, top, (right - left).max(1) as u32, (bottom - top).max(1) as u32))
+        }
+        None => Ok((x, y, width as u32, height as u32))
+    }
+}
 
-                // Add to actions
-                actions.push(Action::Text(segments[1..].join(" ")));
+/// Get the physical positions of the points of a path, where relative points are from the point before them
+fn path_targets(start: (i32, i32), points: &[(i32, i32)], method: Coordinate, logical_monitors: Option<&[Monitor]>) -> Vec<(i32, i32)> {
+    let mut position = start;
+    points.iter().map(|&(x, y)| {
+        position = match (logical_monitors, method) {
+            (Some(monitors), Coordinate::Abs) => monitors::logical_to_physical(monitors, x, y),
+            (Some(monitors), Coordinate::Rel) => {
+                let (x, y) = monitors::logical_distance_to_physical(monitors, position, x, y);
+                (position.0 + x, position.1 + y)
             }
-            _ => {
-                println!("Line {line_index}: Invalid action: {action_name:?}");
-                process::exit(1);
+            (None, Coordinate::Abs) => (x, y),
+            (None, Coordinate::Rel) => (position.0 + x, position.1 + y)
+        };
+        position
+    }).collect()
+}
+
+/// Put the positions of named points in an action that takes positions, like `click left @submit`, in place of their
+/// names
+///
+/// Actions that take a method before the position get `abs` along with it, unless they already have one.
+fn expand_points<'a>(action: &'a str, points: &HashMap<String, NamedPoint>, line_index: i32) -> Cow<'a, str> {
+    let action_name = action.split_whitespace().next().unwrap_or_default();
+    let takes_method = matches!(action_name, "mousemove" | "click" | "doubleclick" | "tripleclick");
+    if !(takes_method || matches!(action_name, "probe" | "waitpixel" | "ifpixel")) || !action.contains('@') {
+        return Cow::Borrowed(action);
+    }
+
+    let mut words: Vec<&str> = Vec::new();
+    for word in action.split_whitespace() {
+        let Some(name) = word.strip_prefix('@') else {
+            words.push(word);
+            continue;
+        };
+        let Some(point) = points.get(name) else {
+            let names: Vec<String> = points.keys().map(|name| format!("@{name}")).collect();
+            Diagnostic::new(line_index, format!("Unknown point {word:?}"))
+                .at(word)
+                .label("points are defined with `point <name> = <x> <y>` before the lines that use them")
+                .suggest(word, names.iter().map(String::as_str))
+                .exit();
+        };
+        // The method comes before the first point, and the other points of a `mousemove` path use the same one
+        match words.iter().find(|word| matches!(**word, "abs" | "rel")) {
+            Some(&"rel") => {
+                Diagnostic::new(line_index, format!("{word} is an absolute position"))
+                    .at(word)
+                    .label("use `abs` before the point, or leave the method out")
+                    .exit();
             }
+            None if takes_method => words.push("abs"),
+            _ => {}
         }
+        words.push(&point.x);
+        words.push(&point.y);
     }
+    Cow::Owned(words.join(" "))
+}
 
-    // Return populated actions list
-    actions
+/// Turn a position on a monitor, like `mon2:100`, or a percentage of the screen, like `50%`, into an expression of the
+/// position on the whole screen, which uses the variables of the monitor for the axis
+///
+/// Percentages are of the primary monitor unless another is given, or of the window when targeting one.
+fn expand_position<'a>(argument: &'a str, axis: &str, variables: &HashMap<String, i32>) -> Result<Cow<'a, str>, String> {
+    let (monitor, position) = match argument.split_once(':').filter(|(monitor, _)| monitor.starts_with("mon")) {
+        Some((monitor, position)) => {
+            let number: usize = monitor["mon".len()..].parse().ok().filter(|&number| number > 0)
+                .ok_or_else(|| format!("invalid monitor {monitor:?}, expected one like mon2"))?;
+            (Some(number), position)
+        }
+        None => (None, argument)
+    };
+    let percent = position.strip_suffix('%');
+    if monitor.is_none() && percent.is_none() {
+        return Ok(Cow::Borrowed(argument));
+    }
+
+    // The size of the window or monitor along the axis, and where it starts on the whole screen
+    let size = if axis == "X" { "W" } else { "H" };
+    let (origin, length) = match monitor {
+        None if variables.contains_key(&format!("WINDOW_{size}")) => (0, variables[&format!("WINDOW_{size}")]),
+        _ => {
+            let number = monitor.unwrap_or(1);
+            let (Some(&origin), Some(&length)) = (variables.get(&format!("MONITOR{number}_{axis}")), variables.get(&format!("MONITOR{number}_{size}"))) else {
+                return Err(format!("there is no monitor {number}, as {} connected", match variables.get("MONITOR_COUNT") {
+                    Some(1) => "only 1 monitor is".to_string(),
+                    Some(0) | None => "no monitors are".to_string(),
+                    Some(count) => format!("only {count} monitors are")
+                }));
+            };
+            (origin, length)
+        }
+    };
+
+    match percent {
+        Some(percent) => {
+            let percent: f64 = percent.parse().ok().filter(|percent: &f64| percent.is_finite())
+                .ok_or_else(|| format!("invalid percentage {position:?}, expected a number followed by %, like 50%"))?;
+            // 100% is the last pixel, rather than the first one past the edge
+            let offset = ((f64::from(length) * percent / 100.0).round() as i32).min(length - 1);
+            Ok(Cow::Owned((origin + offset).to_string()))
+        }
+        None => Ok(Cow::Owned(format!("{origin}+({position})")))
+    }
 }
 
-fn execute_action(enigo: &mut Enigo, current_time: u64, action: Action, should_execute: bool, should_log: bool, held_outputs: (&mut Vec<Button>, &mut Vec<Key>)) -> Option<JoinHandle<()>> {
+/// Parse an absolute position on the X or Y axis, which can be on a monitor like `mon2:100` or a percentage like `50%`
+fn parse_position(argument: &str, axis: &str, variables: &HashMap<String, i32>, assigned_variables: &HashMap<String, VariableKind>) -> Result<Number, String> {
+    parse_number(&expand_position(argument, axis, variables)?, variables, assigned_variables)
+}
+
+/// Calculate an absolute position on the X or Y axis, which can be on a monitor like `mon2:100` or a percentage like `50%`
+fn resolve_position(argument: &str, axis: &str, variables: &HashMap<String, i32>) -> Result<i32, String> {
+    resolve_number(&expand_position(argument, axis, variables)?, variables)
+}
+
+/// Calculate a numeric argument, which is an expression of numbers and `$VARIABLES` that are set before the script starts
+fn resolve_number(argument: &str, variables: &HashMap<String, i32>) -> Result<i32, String> {
+    Expression::parse(argument)?.evaluate(variables)
+}
+
+fn execute_action(backend: &mut dyn Backend, options: &ExecutionOptions, current_time: u64, action: Action, held_outputs: (&mut Vec<Button>, &mut Vec<Key>, &mut Vec<KeyRepeat>), variables: &mut HashMap<String, i32>, texts: &mut HashMap<String, String>) -> Result<Option<JoinHandle<()>>, String> {
+    let should_execute = options.should_execute;
+    let logical_monitors = options.logical_monitors.as_deref();
+
     match action {
-        Action::MouseMove { x, y, time, method } => {
-            if should_log {
-                match method {
-                    Coordinate::Abs => println!("At {current_time}ms: Move mouse to {x}, {y} over {time}ms (absolute)"),
-                    Coordinate::Rel => println!("At {current_time}ms: Move mouse by {x}, {y} over {time}ms (relative)")
-                }
+        Action::MouseMove { x, y, time, method, human } => {
+            let x = x.resolve(variables).map_err(|error| format!("At {current_time}ms: Failed to move mouse: {error}"))?;
+            let y = y.resolve(variables).map_err(|error| format!("At {current_time}ms: Failed to move mouse: {error}"))?;
+            let motion = if human { ", like a hand" } else { "" };
+            match method {
+                Coordinate::Abs => debug!("At {current_time}ms: Move mouse to {x}, {y} over {time}ms (absolute{motion})"),
+                Coordinate::Rel => debug!("At {current_time}ms: Move mouse by {x}, {y} over {time}ms (relative{motion})")
             }
 
             if should_execute {
+                // Convert logical coordinates to physical pixels
+                let (x, y) = match (logical_monitors, method) {
+                    (Some(monitors), Coordinate::Abs) => monitors::logical_to_physical(monitors, x, y),
+                    _ => (x, y)
+                };
+
                 if time < 2 {
                     // Normal "snappy" mouse movement
-                    // Because of a bug in enigo, we can't just pass the method to the move_mouse() function
-                    match method {
-                        Coordinate::Abs => {
-                            let _ = enigo.move_mouse(x, y, method);
-                        },
-                        Coordinate::Rel => {
-                            // More details on why I'm doing this can be found on the relevant GitHub issue page
-                            // https://github.com/enigo-rs/enigo/issues/91
-                            // Basically, the relative mouse movement code uses incorrect pixel units.
-                            // The workaround for this is to first get the current mouse position,
-                            // calculate a new absolute position, and move the mouse there. This probably
-                            // introduces some overhead, but it'll just have to be acceptable until
-                            // the enigo maintainers push a fix.
-                            match enigo.location() {
-                                Ok(current_pos) => {
-                                    // No error occurred while trying to get the location
-                                    let _ = enigo.move_mouse(x + current_pos.0, y + current_pos.1, Coordinate::Abs);
-                                }
-                                Err(error) => {
-                                    // For some reason, we got an error trying to get the mouse position
-                                    println!("At {current_time}ms: Failed to move mouse: {error}");
-                                }
-                            }
+                    let (x, y) = match (logical_monitors, method) {
+                        (Some(monitors), Coordinate::Rel) => {
+                            let current_pos = backend.location().unwrap_or((0, 0));
+                            monitors::logical_distance_to_physical(monitors, current_pos, x, y)
                         }
+                        _ => (x, y)
+                    };
+
+                    if let Err(error) = backend.move_mouse(x, y, method) {
+                        speech::announce("Failed to move mouse");
+                        return Err(format!("At {current_time}ms: Failed to move mouse: {error}"));
                     }
                 } else {
+                    // The shape of a human movement is picked before the thread starts, so it follows the seed of the run
+                    let human = human.then(motion::Human::new);
+
                     // Create a new thread for handling timing of interpolated mouse movements
-                    return Some(thread::spawn(move || {
-                        // Create new enigo object for this thread to avoid dealing with cross-thread objects
+                    let logical_monitors = logical_monitors.map(|monitors| monitors.to_vec());
+                    let backend_config = options.backend_config.clone();
+                    return Ok(Some(thread::spawn(move || {
+                        // Create new backend object for this thread to avoid dealing with cross-thread objects
                         // There is probably a better way of doing this, but I'm not about to spend
                         // my entire week figuring out the best practice for this.
-                        let mut enigo = Enigo::new(&Settings::default()).unwrap_or_else(|error| {
-                            println!("Failed to initialize Enigo: {error}");
-                            process::exit(1);
-                        });
+                        let mut backend = backend::create(&backend_config).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Injection, error));
 
                         // Get start position
-                        let start_pos = match enigo.location() {
+                        let start_pos = match backend.location() {
                             Ok(pos) => pos,
                             Err(error) => {
                                 // For some reason, we got an error trying to get the mouse position
-                                println!("At {current_time}ms: Failed to move mouse: {error}");
-                                process::exit(1);
+                                speech::announce("Failed to move mouse");
+                                speech::wait();
+                                diagnostic::fail(ErrorKind::Injection, format!("At {current_time}ms: Failed to move mouse: {error}"));
                             }
                         };
 
                         // Get relative desired position regardless of movement method
                         let move_offset = match method {
                             Coordinate::Abs => (x - start_pos.0, y - start_pos.1),
-                            Coordinate::Rel => (x, y)
+                            Coordinate::Rel => match &logical_monitors {
+                                Some(monitors) => monitors::logical_distance_to_physical(monitors, start_pos, x, y),
+                                None => (x, y)
+                            }
                         };
 
                         // Gradually move mouse every millisecond
@@ -539,33 +5138,131 @@ fn execute_action(enigo: &mut Enigo, current_time: u64, action: Action, should_e
                             }
 
                             // Get absolute position for this iteration
-                            let x = start_pos.0 + move_offset.0 * iteration as i32 / time as i32;
-                            let y = start_pos.1 + move_offset.1 * iteration as i32 / time as i32;
+                            let (x, y) = match &human {
+                                Some(human) => {
+                                    let (x, y) = human.position(move_offset, iteration + 1, time);
+                                    (start_pos.0 + x, start_pos.1 + y)
+                                }
+                                None => (start_pos.0 + move_offset.0 * iteration as i32 / time as i32, start_pos.1 + move_offset.1 * iteration as i32 / time as i32)
+                            };
 
                             // Set mouse position
-                            let _ = enigo.move_mouse(x, y, Coordinate::Abs);
+                            let _ = backend.move_mouse(x, y, Coordinate::Abs);
                         }
-                    }))
+                    })))
                 }
             }
         }
-        Action::MouseDown(button) => {
-            if should_log {
-                println!("At {current_time}ms: Press mouse {button:?}");
+        Action::MousePath { points, time, method, human } => {
+            let points = points.iter().map(|(x, y)| Ok((x.resolve(variables)?, y.resolve(variables)?))).collect::<Result<Vec<_>, String>>()
+                .map_err(|error| format!("At {current_time}ms: Failed to move mouse: {error}"))?;
+            let path: Vec<String> = points.iter().map(|(x, y)| format!("{x}, {y}")).collect();
+            let motion = if human { ", like a hand" } else { "" };
+            match method {
+                Coordinate::Abs => debug!("At {current_time}ms: Move mouse through {} over {time}ms (absolute{motion})", path.join(" -> ")),
+                Coordinate::Rel => debug!("At {current_time}ms: Move mouse by {} over {time}ms (relative{motion})", path.join(" -> "))
+            }
+
+            if should_execute {
+                if time < 2 {
+                    // Jump from point to point
+                    let start_pos = backend.location().map_err(|error| format!("At {current_time}ms: Failed to move mouse: {error}"))?;
+                    for (x, y) in path_targets(start_pos, &points, method, logical_monitors) {
+                        if let Err(error) = backend.move_mouse(x, y, Coordinate::Abs) {
+                            speech::announce("Failed to move mouse");
+                            return Err(format!("At {current_time}ms: Failed to move mouse: {error}"));
+                        }
+                    }
+                } else {
+                    // The shape of every part of a human movement is picked before the thread starts, so it follows the seed of the run
+                    let humans: Vec<Option<motion::Human>> = points.iter().map(|_| human.then(motion::Human::new)).collect();
+
+                    // Move on a separate thread, like moving to a single point over time
+                    let logical_monitors = logical_monitors.map(|monitors| monitors.to_vec());
+                    let backend_config = options.backend_config.clone();
+                    return Ok(Some(thread::spawn(move || {
+                        let mut backend = backend::create(&backend_config).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Injection, error));
+                        let start_pos = backend.location().unwrap_or_else(|error| {
+                            speech::announce("Failed to move mouse");
+                            speech::wait();
+                            diagnostic::fail(ErrorKind::Injection, format!("At {current_time}ms: Failed to move mouse: {error}"));
+                        });
+                        let targets = path_targets(start_pos, &points, method, logical_monitors.as_deref());
+                        let waypoints = motion::spread_time(start_pos, &targets, time);
+
+                        // Gradually move mouse every millisecond, along the part of the path the time is in
+                        let start_time = Instant::now();
+                        let mut part_start = (0, start_pos);
+                        let mut index = 0;
+                        for iteration in 0..time {
+                            if start_time.elapsed() < Duration::from_millis(iteration) {
+                                spin_sleep::sleep(Duration::from_millis(iteration) - start_time.elapsed());
+                            }
+
+                            // Go on to the next part once the point at the end of this one has been reached
+                            while waypoints[index].0 <= iteration && index + 1 < waypoints.len() {
+                                part_start = (waypoints[index].0, (waypoints[index].1, waypoints[index].2));
+                                index += 1;
+                            }
+                            let (end_time, end_x, end_y) = waypoints[index];
+                            let (part_time, (part_x, part_y)) = (end_time - part_start.0, part_start.1);
+                            let offset = (end_x - part_x, end_y - part_y);
+                            let elapsed = iteration + 1 - part_start.0;
+
+                            let (x, y) = match &humans[index] {
+                                Some(human) => human.position(offset, elapsed, part_time),
+                                None => ((i64::from(offset.0) * elapsed as i64 / part_time as i64) as i32, (i64::from(offset.1) * elapsed as i64 / part_time as i64) as i32)
+                            };
+                            let _ = backend.move_mouse(part_x + x, part_y + y, Coordinate::Abs);
+                        }
+                    })));
+                }
+            }
+        }
+        Action::MouseTrace { file, points, speed } => {
+            let duration = points.last().map_or(0, |point| (point.time as f64 / speed).round() as u64);
+            debug!("At {current_time}ms: Move mouse along the path in {file} ({} points over {duration}ms)", points.len());
+
+            if should_execute {
+                // Follow the path on a separate thread, like moving the mouse over time
+                let logical_monitors = logical_monitors.map(|monitors| monitors.to_vec());
+                let backend_config = options.backend_config.clone();
+                return Ok(Some(thread::spawn(move || {
+                    let mut backend = backend::create(&backend_config).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Injection, error));
+
+                    // Schedule every point from the start, so time spent sending events doesn't add up over long paths
+                    let start_time = std::time::Instant::now();
+                    for point in points.iter() {
+                        if interrupt::requested() {
+                            break;
+                        }
+
+                        spin_sleep::sleep(std::time::Duration::from_secs_f64(point.time as f64 / speed / 1000.0).saturating_sub(start_time.elapsed()));
+                        let (x, y) = match &logical_monitors {
+                            Some(monitors) => monitors::logical_to_physical(monitors, point.x, point.y),
+                            None => (point.x, point.y)
+                        };
+                        if let Err(error) = backend.move_mouse(x, y, Coordinate::Abs) {
+                            error!("At {current_time}ms: Failed to move mouse along the path in {file}: {error}");
+                            break;
+                        }
+                    }
+                })));
             }
+        }
+        Action::MouseDown(button) => {
+            debug!("At {current_time}ms: Press mouse {button:?}");
 
             if should_execute {
-                let _ = enigo.button(button, Direction::Press);
+                let _ = backend.button(button, Direction::Press);
                 held_outputs.0.push(button);
             }
         }
         Action::MouseUp(button) => {
-            if should_log {
-                println!("At {current_time}ms: Release mouse {button:?}");
-            }
+            debug!("At {current_time}ms: Release mouse {button:?}");
 
             if should_execute {
-                let _ = enigo.button(button, Direction::Release);
+                let _ = backend.button(button, Direction::Release);
 
                 // Remove from held outputs
                 if let Some(index) = held_outputs.0.iter().position(|b| b == &button) {
@@ -573,28 +5270,68 @@ fn execute_action(enigo: &mut Enigo, current_time: u64, action: Action, should_e
                 }
             }
         }
-        Action::KeyDown(key) => {
-            if should_log {
-                println!("At {current_time}ms: Press key {key:?}");
+        Action::Scroll { amount, axis, time } => {
+            let amount = amount.resolve(variables).map_err(|error| format!("At {current_time}ms: Failed to scroll: {error}"))?;
+            let direction = match (axis, amount < 0) {
+                (Axis::Vertical, false) => "down",
+                (Axis::Vertical, true) => "up",
+                (Axis::Horizontal, false) => "right",
+                (Axis::Horizontal, true) => "left"
+            };
+            debug!("At {current_time}ms: Scroll {direction} by {} over {time}ms", amount.unsigned_abs());
+
+            if should_execute {
+                if time < 2 || amount.abs() < 2 {
+                    backend.scroll(amount, axis).map_err(|error| format!("At {current_time}ms: Failed to scroll: {error}"))?;
+                } else {
+                    // Scroll one notch at a time on a separate thread, like moving the mouse over time
+                    let backend_config = options.backend_config.clone();
+                    return Ok(Some(thread::spawn(move || {
+                        let mut backend = backend::create(&backend_config).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Injection, error));
+                        let notches = amount.unsigned_abs();
+                        let start_time = std::time::Instant::now();
+                        for notch in 0..notches {
+                            // Schedule every notch from the start, so the notches are spread evenly over the time
+                            let due = std::time::Duration::from_millis(time * u64::from(notch) / u64::from(notches - 1));
+                            spin_sleep::sleep(due.saturating_sub(start_time.elapsed()));
+                            if let Err(error) = backend.scroll(amount.signum(), axis) {
+                                warn!("At {current_time}ms: Failed to scroll: {error}");
+                                return;
+                            }
+                        }
+                    })));
+                }
+            }
+        }
+        Action::KeyDown(key, repeat) => {
+            match repeat {
+                Some(repeat) => debug!("At {current_time}ms: Press key {key:?}, repeating {} times per second after {}ms", repeat.rate, repeat.delay),
+                None => debug!("At {current_time}ms: Press key {key:?}")
             }
 
             if should_execute {
-                if let Err(error) = enigo.key(key, Direction::Press) {
-                    println!("Failed to press key {key:?}: {error}");
-                    return None;
+                // Pressing a key again restarts its repeating, like on a real keyboard
+                repeat::stop(held_outputs.2, Some(key));
+
+                if let Err(error) = backend.key(key, Direction::Press) {
+                    speech::announce("Failed to press key");
+                    return Err(format!("Failed to press key {key:?}: {error}"));
                 }
                 held_outputs.1.push(key);
+
+                if let Some(repeat) = repeat {
+                    held_outputs.2.push(KeyRepeat::start(&options.backend_config, key, repeat));
+                }
             }
         }
         Action::KeyUp(key) => {
-            if should_log {
-                println!("At {current_time}ms: Release key {key:?}");
-            }
+            debug!("At {current_time}ms: Release key {key:?}");
 
             if should_execute {
-                if let Err(error) = enigo.key(key, Direction::Release) {
-                    println!("Failed to release key {key:?}: {error}");
-                    return None;
+                repeat::stop(held_outputs.2, Some(key));
+                if let Err(error) = backend.key(key, Direction::Release) {
+                    speech::announce("Failed to release key");
+                    return Err(format!("Failed to release key {key:?}: {error}"));
                 }
 
                 // Remove from held outputs
@@ -604,53 +5341,320 @@ fn execute_action(enigo: &mut Enigo, current_time: u64, action: Action, should_e
             }
         }
         Action::Release(output_type) => {
-            if should_log {
-                match output_type {
-                    OutputType::Mouse => println!("At {current_time}ms: Release all mouse buttons"),
-                    OutputType::Key => println!("At {current_time}ms: Release all keys"),
-                    OutputType::Both => println!("At {current_time}ms: Release all mouse buttons and keys")
-                }
+            match output_type {
+                OutputType::Mouse => debug!("At {current_time}ms: Release all mouse buttons"),
+                OutputType::Key => debug!("At {current_time}ms: Release all keys"),
+                OutputType::Both => debug!("At {current_time}ms: Release all mouse buttons and keys")
             }
 
             if should_execute {
                 match output_type {
                     OutputType::Mouse => {
                         for button in &*held_outputs.0 {
-                            let _ = enigo.button(*button, Direction::Release);
+                            let _ = backend.button(*button, Direction::Release);
                         }
                         held_outputs.0.clear();
                     }
                     OutputType::Key => {
+                        repeat::stop(held_outputs.2, None);
                         for key in &*held_outputs.1 {
-                            let _ = enigo.key(*key, Direction::Release);
+                            let _ = backend.key(*key, Direction::Release);
                         }
                         held_outputs.1.clear();
                     }
                     OutputType::Both => {
                         for button in &*held_outputs.0 {
-                            let _ = enigo.button(*button, Direction::Release);
+                            let _ = backend.button(*button, Direction::Release);
                         }
                         held_outputs.0.clear();
 
+                        repeat::stop(held_outputs.2, None);
                         for key in &*held_outputs.1 {
-                            let _ = enigo.key(*key, Direction::Release);
+                            let _ = backend.key(*key, Direction::Release);
                         }
                         held_outputs.1.clear();
                     }
                 }
             }
         }
-        Action::Text(text) => {
-            if should_log {
-                println!("At {current_time}ms: Input text {text:?}");
+        Action::Text { text, typing } => {
+            let text = resolve_text(&text, texts).map_err(|error| format!("At {current_time}ms: Failed to input text: {error}"))?;
+            return type_text(backend, options, current_time, text, typing);
+        }
+        Action::TypeRandom { pattern, length, typing } => {
+            let length = length.resolve(variables).map_err(|error| format!("At {current_time}ms: Failed to input random text: {error}"))?;
+            let length = usize::try_from(length).map_err(|_| format!("At {current_time}ms: Failed to input random text: the length {length} is negative"))?;
+            return type_text(backend, options, current_time, pattern.generate(length), typing);
+        }
+        Action::Say(text) => {
+            let text = resolve_text(&text, texts).map_err(|error| format!("At {current_time}ms: Failed to say text: {error}"))?;
+            debug!("At {current_time}ms: Say {text:?}");
+
+            if should_execute {
+                speech::say(&text);
+            }
+        }
+        Action::Log(message) => {
+            let message = resolve_text(&message, texts).map_err(|error| format!("At {current_time}ms: Failed to log message: {error}"))?;
+            info!("At {current_time}ms: {message}");
+        }
+        Action::Turbo { input, rate, duration } => {
+            let taps = duration * rate as u64 / 1000;
+            debug!("At {current_time}ms: Tap {input:?} {taps} times at {rate}Hz over {duration}ms");
+
+            if should_execute {
+                // Tap on a separate thread, so the taps keep their rate regardless of the lines around them
+                let backend_config = options.backend_config.clone();
+                return Ok(Some(thread::spawn(move || {
+                    let mut backend = backend::create(&backend_config).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Injection, error));
+                    let hold_time = std::time::Duration::from_secs(1) / rate / 2;
+                    let press = |backend: &mut dyn Backend, direction| match input {
+                        TurboInput::Key(key) => backend.key(key, direction),
+                        TurboInput::Button(button) => backend.button(button, direction)
+                    };
+
+                    // Schedule every tap from the start, so time spent sending events doesn't add up over many taps
+                    let start_time = std::time::Instant::now();
+                    for tap in 0..taps {
+                        if interrupt::requested() {
+                            break;
+                        }
+
+                        // Release halfway through each period, as some applications ignore presses that are released right away
+                        let press_time = std::time::Duration::from_micros(tap * 1_000_000 / rate as u64);
+                        spin_sleep::sleep(press_time.saturating_sub(start_time.elapsed()));
+                        if let Err(error) = press(backend.as_mut(), Direction::Press) {
+                            error!("At {current_time}ms: Failed to tap {input:?}: {error}");
+                            break;
+                        }
+                        spin_sleep::sleep((press_time + hold_time).saturating_sub(start_time.elapsed()));
+                        let _ = press(backend.as_mut(), Direction::Release);
+                    }
+                })));
+            }
+        }
+        Action::HoldKey { key, duration, repeat } => {
+            match repeat {
+                Some(repeat) => debug!("At {current_time}ms: Hold key {key:?} for {duration}ms, repeating {} times per second after {}ms", repeat.rate, repeat.delay),
+                None => debug!("At {current_time}ms: Hold key {key:?} for {duration}ms")
+            }
+
+            if should_execute {
+                // Hold the key on a separate thread, like tapping with `turbo`, so the lines after it run as usual
+                let backend_config = options.backend_config.clone();
+                return Ok(Some(thread::spawn(move || {
+                    let mut backend = backend::create(&backend_config).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Injection, error));
+                    if let Err(error) = backend.key(key, Direction::Press) {
+                        error!("At {current_time}ms: Failed to hold key {key:?}: {error}");
+                        return;
+                    }
+                    let repeat = repeat.map(|repeat| KeyRepeat::start(&backend_config, key, repeat));
+
+                    // Check for aborting now and then, so the key isn't held for the rest of the duration
+                    let start_time = std::time::Instant::now();
+                    let duration = std::time::Duration::from_millis(duration);
+                    while start_time.elapsed() < duration && !interrupt::requested() {
+                        spin_sleep::sleep(duration.saturating_sub(start_time.elapsed()).min(CONTROL_CHECK_INTERVAL));
+                    }
+
+                    if let Some(repeat) = repeat {
+                        repeat.stop();
+                    }
+                    let _ = backend.key(key, Direction::Release);
+                })));
+            }
+        }
+        Action::WaitKey(_) | Action::Wait(_) | Action::WaitProcess { .. } | Action::WaitWindow { .. } | Action::WaitPixel { .. } | Action::WaitText { .. } | Action::WaitImage { .. } | Action::ClickImage { .. } | Action::Exec { .. } | Action::Abort { .. } => unreachable!("Waiting is handled by the runner, as it moves the schedule"),
+        Action::Cue(_) => unreachable!("Cues are handled by the runner, as they show up in the tray icon"),
+        Action::Probe(x, y) => {
+            let x = x.resolve(variables).map_err(|error| format!("At {current_time}ms: Failed to probe: {error}"))?;
+            let y = y.resolve(variables).map_err(|error| format!("At {current_time}ms: Failed to probe: {error}"))?;
+
+            // Nothing is changed by reading, so this also happens in a dry run, where the mouse stays where the user left it
+            let (physical_x, physical_y) = match logical_monitors {
+                Some(monitors) => monitors::logical_to_physical(monitors, x, y),
+                None => (x, y)
+            };
+            let color = screen::pixel(physical_x, physical_y).map_err(|error| format!("At {current_time}ms: Failed to probe {x}, {y}: {error}"))?;
+            let (mouse_x, mouse_y) = backend.location().map_err(|error| format!("At {current_time}ms: Failed to get mouse position: {error}"))?;
+            let (mouse_x, mouse_y) = match logical_monitors {
+                Some(monitors) => monitors::physical_to_logical(monitors, mouse_x, mouse_y),
+                None => (mouse_x, mouse_y)
+            };
+
+            info!("At {current_time}ms: Pixel at {x}, {y} is {color}, mouse is at {mouse_x}, {mouse_y}");
+        }
+        Action::GetMouse(x_name, y_name) => {
+            debug!("At {current_time}ms: Store mouse position in ${x_name} and ${y_name}");
+
+            // The position depends on where the events would have moved the mouse, so a dry run doesn't read it
+            let (x, y) = if should_execute {
+                let (x, y) = backend.location().map_err(|error| format!("At {current_time}ms: Failed to get mouse position: {error}"))?;
+                match logical_monitors {
+                    Some(monitors) => monitors::physical_to_logical(monitors, x, y),
+                    None => (x, y)
+                }
+            } else {
+                (0, 0)
+            };
+            trace!("Mouse is at {x}, {y}");
+
+            variables.insert(x_name, x);
+            variables.insert(y_name, y);
+        }
+        Action::GetClipboard(name) => {
+            debug!("At {current_time}ms: Store clipboard text in ${name}");
+
+            // The clipboard depends on what the events would have copied, so a dry run doesn't read it
+            let text = if should_execute {
+                clipboard::text().map_err(|error| format!("At {current_time}ms: Failed to get clipboard text: {error}"))?
+            } else {
+                String::new()
+            };
+            trace!("Clipboard holds {text:?}");
+
+            texts.insert(name, text);
+        }
+        Action::ReadText { region, name } => {
+            let (x, y, width, height) = resolve_region(&region, variables, logical_monitors).map_err(|error| format!("At {current_time}ms: Failed to read text: {error}"))?;
+            debug!("At {current_time}ms: Store text in {width}x{height} at {x}, {y} in ${name}");
+
+            // The screen depends on what the events would have done, so a dry run doesn't read it
+            let text = if should_execute {
+                ocr::read(Some((x, y, width, height))).map_err(|error| format!("At {current_time}ms: Failed to read text: {error}"))?
+            } else {
+                String::new()
+            };
+            trace!("Read {text:?}");
+
+            texts.insert(name, text);
+        }
+        Action::MoveWindow { title, x, y } => {
+            let x = x.resolve(variables).map_err(|error| format!("At {current_time}ms: Failed to move window: {error}"))?;
+            let y = y.resolve(variables).map_err(|error| format!("At {current_time}ms: Failed to move window: {error}"))?;
+            debug!("At {current_time}ms: Move window {title:?} to {x}, {y}");
+
+            if should_execute {
+                let window = find_window(&title).map_err(|error| format!("At {current_time}ms: Failed to move window: {error}"))?;
+                window::move_window(&window, x, y).map_err(|error| format!("At {current_time}ms: {error}"))?;
+            }
+        }
+        Action::ResizeWindow { title, width, height } => {
+            let width = width.resolve(variables).map_err(|error| format!("At {current_time}ms: Failed to resize window: {error}"))?;
+            let height = height.resolve(variables).map_err(|error| format!("At {current_time}ms: Failed to resize window: {error}"))?;
+            debug!("At {current_time}ms: Resize window {title:?} to {width}x{height}");
+
+            if width <= 0 || height <= 0 {
+                return Err(format!("At {current_time}ms: Failed to resize window: {width}x{height} isn't a valid size"));
+            }
+            if should_execute {
+                let window = find_window(&title).map_err(|error| format!("At {current_time}ms: Failed to resize window: {error}"))?;
+                window::resize_window(&window, width as u32, height as u32).map_err(|error| format!("At {current_time}ms: {error}"))?;
+            }
+        }
+        Action::WindowCommand(command, title) => {
+            debug!("At {current_time}ms: {command:?} window {title:?}");
+
+            if should_execute {
+                let window = find_window(&title).map_err(|error| format!("At {current_time}ms: Failed to {command} window: {error}"))?;
+                window::command(&window, command).map_err(|error| format!("At {current_time}ms: {error}"))?;
             }
+        }
+        Action::KillProcess(process) => {
+            debug!("At {current_time}ms: Kill {process}");
 
             if should_execute {
-                let _ = enigo.text(text.as_str());
+                // Killing a process that isn't running is fine, as it's often done just in case
+                match processes::kill(&process).map_err(|error| format!("At {current_time}ms: {error}"))? {
+                    Some(handle) => return Ok(Some(handle)),
+                    None => warn!("At {current_time}ms: Nothing to kill, as no {process} is running")
+                }
             }
         }
     }
 
     // Return None as no thread was created
-    None
+    Ok(None)
+}
+
+/// Type text, all at once or one character at a time with a delay in between
+fn type_text(backend: &mut dyn Backend, options: &ExecutionOptions, current_time: u64, text: String, typing: Typing) -> Result<Option<JoinHandle<()>>, String> {
+    let delay = typing.delay;
+    if delay == 0 {
+        debug!("At {current_time}ms: Input text {text:?}");
+    } else if typing.human {
+        debug!("At {current_time}ms: Input text {text:?} like a person, about {delay}ms between characters");
+    } else {
+        debug!("At {current_time}ms: Input text {text:?} with {delay}ms between characters");
+    }
+
+    if !options.should_execute {
+        return Ok(None);
+    }
+    if delay == 0 {
+        type_characters(backend, &text).map_err(|error| format!("At {current_time}ms: Failed to input text: {error}"))?;
+        return Ok(None);
+    }
+
+    // The keystrokes are planned before the thread starts, so the random choices follow the seed of the run
+    let keystrokes = if typing.human {
+        typing::human(&text, delay)
+    } else {
+        text.chars().enumerate().map(|(index, character)| (delay * index as u64, Keystroke::Character(character))).collect()
+    };
+
+    // Type on a separate thread, like turbo, so the characters keep their pace regardless of the lines around them
+    let backend_config = options.backend_config.clone();
+    Ok(Some(thread::spawn(move || {
+        let mut backend = backend::create(&backend_config).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Injection, error));
+
+        // Schedule every character from the start, so time spent sending events doesn't add up over long texts
+        let start_time = std::time::Instant::now();
+        for (time, keystroke) in keystrokes {
+            if interrupt::requested() {
+                break;
+            }
+
+            spin_sleep::sleep(std::time::Duration::from_millis(time).saturating_sub(start_time.elapsed()));
+            let result = match keystroke {
+                Keystroke::Character(character) => type_characters(backend.as_mut(), &character.to_string()),
+                Keystroke::Backspace => backend.key(Key::Backspace, Direction::Click).map_err(|error| error.to_string())
+            };
+            if let Err(error) = result {
+                error!("At {current_time}ms: Failed to input text: {error}");
+                break;
+            }
+        }
+    })))
+}
+
+/// Type text, pasting the characters the backend can't type instead, like emoji with the uinput backend
+fn type_characters(backend: &mut dyn Backend, text: &str) -> Result<(), String> {
+    // Every backend can type plain ASCII, so only other text is typed one character at a time to find the ones it can't
+    if text.is_ascii() {
+        return backend.text(text).map_err(|error| error.to_string());
+    }
+
+    let mut previous_clipboard = None;
+    let mut result = Ok(());
+    for character in text.chars() {
+        if backend.text(character.encode_utf8(&mut [0; 4])).is_ok() {
+            continue;
+        }
+
+        trace!("Pasting {character:?}, as the backend can't type it");
+        previous_clipboard.get_or_insert_with(|| clipboard::text().unwrap_or_default());
+        if let Err(error) = clipboard::paste(backend, &character.to_string()) {
+            result = Err(format!("couldn't type or paste {character:?}: {error}"));
+            break;
+        }
+    }
+
+    // Put back what was copied before, unless it wasn't text, as only text can be put back
+    if let Some(previous_clipboard) = previous_clipboard.filter(|text| !text.is_empty()) {
+        if let Err(error) = clipboard::set_text(&previous_clipboard) {
+            warn!("Failed to restore the clipboard after pasting: {error}");
+        }
+    }
+    result
 }
@@ -0,0 +1,141 @@
+use display_info::DisplayInfo;
+use std::collections::HashMap;
+
+/// A connected display, with its geometry in the same pixel units that are used for sending input
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub is_primary: bool
+}
+
+impl Monitor {
+    /// The scale factor between logical pixels and the pixels used for sending input
+    fn input_scale(&self) -> f32 {
+        // macOS already sends input in logical points, so there is nothing to scale
+        if cfg!(target_os = "macos") {
+            1.0
+        } else {
+            self.scale_factor
+        }
+    }
+
+    /// Check if a logical position is on this monitor
+    fn contains_logical(&self, x: i32, y: i32) -> bool {
+        let width = (self.width as f32 / self.input_scale()) as i32;
+        let height = (self.height as f32 / self.input_scale()) as i32;
+
+        x >= self.x && x < self.x + width && y >= self.y && y < self.y + height
+    }
+
+    /// Check if a physical position is on this monitor
+    pub fn contains_physical(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width as i32 && y >= self.y && y < self.y + self.height as i32
+    }
+}
+
+/// Get all connected monitors, with the primary monitor first and the rest ordered by position
+pub fn get_monitors() -> Vec<Monitor> {
+    let displays = DisplayInfo::all().unwrap_or_else(|error| {
+        log::warn!("Failed to get display information: {error}");
+        Vec::new()
+    });
+
+    let mut monitors: Vec<Monitor> = displays.into_iter().map(|display| {
+        // On Linux, the display geometry is reported in logical pixels, while input uses physical pixels
+        let geometry_scale = if cfg!(all(unix, not(target_os = "macos"))) {
+            display.scale_factor
+        } else {
+            1.0
+        };
+
+        Monitor {
+            name: display.friendly_name,
+            x: (display.x as f32 * geometry_scale) as i32,
+            y: (display.y as f32 * geometry_scale) as i32,
+            width: (display.width as f32 * geometry_scale) as u32,
+            height: (display.height as f32 * geometry_scale) as u32,
+            scale_factor: display.scale_factor,
+            is_primary: display.is_primary
+        }
+    }).collect();
+
+    monitors.sort_by_key(|monitor| (!monitor.is_primary, monitor.x, monitor.y));
+    monitors
+}
+
+/// Get the script variables describing the monitor geometry, e.g. `$MONITOR1_W`
+pub fn get_variables(monitors: &[Monitor]) -> HashMap<String, i32> {
+    let mut variables = HashMap::new();
+    variables.insert("MONITOR_COUNT".to_string(), monitors.len() as i32);
+
+    // The screen is the primary monitor, which comes first
+    if let Some(primary) = monitors.first() {
+        variables.insert("SCREEN_W".to_string(), primary.width as i32);
+        variables.insert("SCREEN_H".to_string(), primary.height as i32);
+    }
+
+    for (index, monitor) in monitors.iter().enumerate() {
+        let prefix = format!("MONITOR{}", index + 1);
+        variables.insert(format!("{prefix}_X"), monitor.x);
+        variables.insert(format!("{prefix}_Y"), monitor.y);
+        variables.insert(format!("{prefix}_W"), monitor.width as i32);
+        variables.insert(format!("{prefix}_H"), monitor.height as i32);
+    }
+
+    variables
+}
+
+/// Convert an absolute position in logical pixels to the pixels used for sending input
+///
+/// Logical positions are measured from the origin of the monitor they land on, so a script written for a 100% scaled
+/// display ends up in the same place on a 150% scaled one.
+pub fn logical_to_physical(monitors: &[Monitor], x: i32, y: i32) -> (i32, i32) {
+    let monitor = monitors.iter()
+        .find(|monitor| monitor.contains_logical(x, y))
+        .or_else(|| monitors.iter().find(|monitor| monitor.is_primary))
+        .or_else(|| monitors.first());
+
+    match monitor {
+        Some(monitor) => {
+            let scale = monitor.input_scale();
+            (
+                monitor.x + ((x - monitor.x) as f32 * scale).round() as i32,
+                monitor.y + ((y - monitor.y) as f32 * scale).round() as i32
+            )
+        }
+        None => (x, y)
+    }
+}
+
+/// Convert an absolute position in the pixels used for sending input to logical pixels, the opposite of [`logical_to_physical`]
+pub fn physical_to_logical(monitors: &[Monitor], x: i32, y: i32) -> (i32, i32) {
+    let monitor = monitors.iter()
+        .find(|monitor| monitor.contains_physical(x, y))
+        .or_else(|| monitors.iter().find(|monitor| monitor.is_primary))
+        .or_else(|| monitors.first());
+
+    match monitor {
+        Some(monitor) => {
+            let scale = monitor.input_scale();
+            (
+                monitor.x + ((x - monitor.x) as f32 / scale).round() as i32,
+                monitor.y + ((y - monitor.y) as f32 / scale).round() as i32
+            )
+        }
+        None => (x, y)
+    }
+}
+
+/// Convert a relative distance in logical pixels, starting at the given physical position, to physical pixels
+pub fn logical_distance_to_physical(monitors: &[Monitor], from: (i32, i32), x: i32, y: i32) -> (i32, i32) {
+    let scale = monitors.iter()
+        .find(|monitor| monitor.contains_physical(from.0, from.1))
+        .map_or(1.0, |monitor| monitor.input_scale());
+
+    ((x as f32 * scale).round() as i32, (y as f32 * scale).round() as i32)
+}
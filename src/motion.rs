@@ -0,0 +1,95 @@
+use crate::random;
+use std::f64::consts::TAU;
+use std::iter;
+
+/// How far past the target the mouse goes at most, as a part of the distance and in pixels
+const OVERSHOOT: f64 = 0.05;
+const MAX_OVERSHOOT: f64 = 20.0;
+
+/// How much of the time is spent going back from past the target to the target itself
+const CORRECTION: f64 = 0.2;
+
+/// How far the mouse shakes to the sides while moving, in pixels, and how many times a second
+const TREMOR: f64 = 1.2;
+const TREMOR_FREQUENCY: f64 = 9.0;
+
+/// The shape of a movement made like a hand moves the mouse, which is picked at random for every movement
+///
+/// The path bends a little to one side, speeds up and slows down, goes a bit past the target and then corrects back to
+/// it, with a pixel or so of tremor on the way that settles down before the end.
+#[derive(Debug, Clone, Copy)]
+pub struct Human {
+    /// How far the middle of the path is to the side, as a part of the distance
+    bend: f64,
+    /// How far past the target the mouse goes, as a part of the most it can
+    overshoot: f64,
+    tremor_phase: f64
+}
+
+impl Human {
+    /// Pick the shape of a movement
+    ///
+    /// The shape is picked before the movement starts, so the random choices follow the seed of the run.
+    pub fn new() -> Self {
+        Human {
+            bend: (random::below(301) as f64 - 150.0) / 1000.0,
+            overshoot: (50 + random::below(51)) as f64 / 100.0,
+            tremor_phase: random::below(1000) as f64 / 1000.0 * TAU
+        }
+    }
+
+    /// Get where the mouse is on the way to an offset from where it started, some milliseconds into a movement
+    ///
+    /// The position at the end of the time is always the offset itself.
+    pub fn position(&self, offset: (i32, i32), elapsed: u64, time: u64) -> (i32, i32) {
+        let (dx, dy) = (f64::from(offset.0), f64::from(offset.1));
+        let distance = dx.hypot(dy);
+        let progress = (elapsed as f64 / time.max(1) as f64).min(1.0);
+        if distance < 1.0 || progress >= 1.0 {
+            return offset;
+        }
+        let (direction, side) = ((dx / distance, dy / distance), (-dy / distance, dx / distance));
+
+        let past = (distance * OVERSHOOT).min(MAX_OVERSHOOT) * self.overshoot;
+        let along = if progress < 1.0 - CORRECTION {
+            // Towards the point past the target, bending to the side most in the middle
+            let eased = smoothstep(progress / (1.0 - CORRECTION));
+            let bend = distance * self.bend * 4.0 * eased * (1.0 - eased);
+            ((distance + past) * eased, bend)
+        } else {
+            // Back from past the target to the target itself
+            let eased = smoothstep((progress - (1.0 - CORRECTION)) / CORRECTION);
+            (distance + past * (1.0 - eased), 0.0)
+        };
+
+        let tremor = TREMOR * (1.0 - progress) * (TAU * TREMOR_FREQUENCY * elapsed as f64 / 1000.0 + self.tremor_phase).sin();
+        let sideways = along.1 + tremor;
+        (
+            (direction.0 * along.0 + side.0 * sideways).round() as i32,
+            (direction.1 * along.0 + side.1 * sideways).round() as i32
+        )
+    }
+}
+
+/// Ease from 0 to 1, starting and ending slowly
+fn smoothstep(progress: f64) -> f64 {
+    progress * progress * (3.0 - 2.0 * progress)
+}
+
+/// Spread the time of a movement through several points over the parts of the path, by how long each part is
+///
+/// Returns when the mouse reaches every point, in milliseconds from the start of the movement.
+pub fn spread_time(start: (i32, i32), points: &[(i32, i32)], time: u64) -> Vec<(u64, i32, i32)> {
+    let lengths: Vec<f64> = iter::once(start).chain(points.iter().copied()).zip(points)
+        .map(|(from, to)| f64::from(to.0 - from.0).hypot(f64::from(to.1 - from.1)))
+        .collect();
+    let total: f64 = lengths.iter().sum();
+
+    let mut travelled = 0.0;
+    points.iter().zip(lengths).enumerate().map(|(index, (&(x, y), length))| {
+        travelled += length;
+        // A path that doesn't go anywhere spends the same time on every point
+        let part = if total > 0.0 { travelled / total } else { (index + 1) as f64 / points.len() as f64 };
+        ((time as f64 * part).round() as u64, x, y)
+    }).collect()
+}
@@ -0,0 +1,43 @@
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+use x11 as platform;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "windows")]
+use win32 as platform;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub fn state() -> Result<(i32, i32, bool), String> {
+        Err("Reading the mouse is not supported on this platform".to_string())
+    }
+}
+
+use std::thread;
+use std::time::Duration;
+
+/// How often the mouse is checked while waiting for a click
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Get the position of the mouse, as the display sees it
+pub fn position() -> Result<(i32, i32), String> {
+    platform::state().map(|(x, y, _)| (x, y))
+}
+
+/// Wait until the left mouse button on the physical mouse is pressed, and get where the mouse was when it was
+///
+/// A button that's already held when waiting starts only counts once it's pressed again.
+pub fn wait_for_click() -> Result<(i32, i32), String> {
+    let mut ignored = platform::state()?.2;
+    loop {
+        let (x, y, pressed) = platform::state()?;
+        if pressed && !ignored {
+            return Ok((x, y));
+        }
+
+        ignored &= pressed;
+        thread::sleep(POLL_INTERVAL);
+    }
+}
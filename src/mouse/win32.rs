@@ -0,0 +1,15 @@
+use windows_sys::Win32::Foundation::POINT;
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_LBUTTON};
+use windows_sys::Win32::UI::WindowsAndMessaging::GetCursorPos;
+
+/// Get the position of the mouse on the screen, and whether the left button is held down
+pub fn state() -> Result<(i32, i32, bool), String> {
+    let mut cursor = POINT { x: 0, y: 0 };
+    // SAFETY: The point is valid for the duration of the call, and GetAsyncKeyState has no safety requirements
+    unsafe {
+        if GetCursorPos(&mut cursor) == 0 {
+            return Err("Failed to get the position of the mouse".to_string());
+        }
+        Ok((cursor.x, cursor.y, GetAsyncKeyState(VK_LBUTTON as i32) as u16 & 0x8000 != 0))
+    }
+}
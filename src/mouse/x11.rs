@@ -0,0 +1,23 @@
+use std::sync::OnceLock;
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::xproto::{ConnectionExt, KeyButMask};
+use x11rb::rust_connection::RustConnection;
+
+/// The connection to the X server, kept open as the mouse is checked many times in a row
+static CONNECTION: OnceLock<Result<(RustConnection, usize), String>> = OnceLock::new();
+
+/// Get the position of the mouse on the root window, and whether the left button is held down
+///
+/// This only works on X11, as Wayland doesn't let applications read the mouse outside of their windows.
+pub fn state() -> Result<(i32, i32, bool), String> {
+    let (connection, screen) = CONNECTION
+        .get_or_init(|| x11rb::connect(None).map_err(|error| format!("Failed to connect to X server: {error}")))
+        .as_ref()
+        .map_err(Clone::clone)?;
+    let pointer = connection.query_pointer(connection.setup().roots[*screen].root)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to read the mouse: {error}"))?;
+    Ok((pointer.root_x as i32, pointer.root_y as i32, pointer.mask.contains(KeyButMask::BUTTON1)))
+}
@@ -0,0 +1,63 @@
+use std::process::Command;
+use std::time::Duration;
+
+/// Show a desktop notification, waiting until it has been handed to the notification system
+pub fn send(summary: &str, body: &str) {
+    if let Err(error) = show(summary, body) {
+        log::warn!("Failed to send notification: {error}");
+    }
+}
+
+/// Format a duration for showing to the user, like `1m 5.2s`
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    if seconds < 60.0 {
+        format!("{seconds:.1}s")
+    } else {
+        format!("{}m {:.1}s", (seconds / 60.0) as u64, seconds % 60.0)
+    }
+}
+
+/// Run a notification command, turning a missing program or failure into an error
+fn run(command: &mut Command, program: &str) -> Result<(), String> {
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{program} exited with {status}")),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Err(format!("{program} was not found")),
+        Err(error) => Err(format!("Failed to run {program}: {error}"))
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn show(summary: &str, body: &str) -> Result<(), String> {
+    run(Command::new("notify-send").args(["--app-name=TATATA", "--", summary, body]), "notify-send")
+}
+
+#[cfg(target_os = "macos")]
+fn show(summary: &str, body: &str) -> Result<(), String> {
+    // The text is passed as arguments to the script so it doesn't have to be escaped for AppleScript
+    let script = ["-e", "on run argv", "-e", "display notification (item 2 of argv) with title (item 1 of argv)", "-e", "end run"];
+    run(Command::new("osascript").args(script).args([summary, body]), "osascript")
+}
+
+#[cfg(target_os = "windows")]
+fn show(summary: &str, body: &str) -> Result<(), String> {
+    // Toasts have to come from a registered application, so this borrows the ID of PowerShell
+    // The text is passed through the environment so it doesn't have to be escaped for PowerShell
+    let script = "\
+        $manager = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]; \
+        $template = $manager::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+        $texts = $template.GetElementsByTagName('text'); \
+        $texts.Item(0).AppendChild($template.CreateTextNode($env:TATATA_SUMMARY)) > $null; \
+        $texts.Item(1).AppendChild($template.CreateTextNode($env:TATATA_BODY)) > $null; \
+        $id = '{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\\WindowsPowerShell\\v1.0\\powershell.exe'; \
+        $manager::CreateToastNotifier($id).Show([Windows.UI.Notifications.ToastNotification]::new($template))";
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", script]).env("TATATA_SUMMARY", summary).env("TATATA_BODY", body);
+    run(&mut command, "PowerShell")
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn show(_summary: &str, _body: &str) -> Result<(), String> {
+    Err("Notifications are not supported on this platform".to_string())
+}
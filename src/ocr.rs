@@ -0,0 +1,46 @@
+use crate::screen::{self, Image};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Read the text in a region of the screen, or the whole screen, in physical pixels
+///
+/// The text is recognized by Tesseract, which has to be installed separately, so TATATA doesn't need its libraries to run
+/// scripts that don't read text.
+pub fn read(region: Option<(i32, i32, u32, u32)>) -> Result<String, String> {
+    let (x, y, width, height) = match region {
+        Some(region) => region,
+        None => screen::bounds()?
+    };
+    let image = screen::capture(x, y, width, height)?;
+
+    // The image is piped to Tesseract, so the screen never ends up in a file other users could read, and PPM is simple
+    // enough to write without an image library
+    let output = Command::new("tesseract").args(["stdin", "stdout"])
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()
+        .and_then(|mut child| {
+            // Tesseract only starts writing once it read the whole image, so the outputs can't fill up before this
+            let written = child.stdin.take().expect("stdin is piped").write_all(&ppm(&image));
+            // When Tesseract fails, what it printed explains why better than the image not being written
+            let output = child.wait_with_output()?;
+            written.or_else(|error| if output.status.success() { Err(error) } else { Ok(()) }).map(|_| output)
+        });
+
+    match output {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        Ok(output) => Err(format!("Tesseract exited with {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim())),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Err("Tesseract wasn't found, install it and add it to PATH to read text from the screen".to_string()),
+        Err(error) => Err(format!("Failed to run Tesseract: {error}"))
+    }
+}
+
+/// Check whether some text is in other text, ignoring case and differences in whitespace, like line breaks
+pub fn contains(haystack: &str, needle: &str) -> bool {
+    let normalize = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    normalize(haystack).contains(&normalize(needle))
+}
+
+fn ppm(image: &Image) -> Vec<u8> {
+    let mut data = format!("P6\n{} {}\n255\n", image.width, image.height).into_bytes();
+    data.extend(image.pixels.iter().flat_map(|color| [color.red, color.green, color.blue]));
+    data
+}
@@ -0,0 +1,238 @@
+use crate::bundle;
+use crate::inflate::inflate;
+use std::path::Path;
+
+/// A PNG image, with the pixels stored row by row from the top left as red, green, blue and alpha
+pub struct Png {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>
+}
+
+/// The bytes every PNG file starts with
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// How many bytes the pixels of an image can take up once they're decompressed, which is over 60 million pixels
+const MAX_SIZE: usize = 256 * 1024 * 1024;
+
+/// Read a PNG file
+///
+/// Every standard color type and bit depth is supported, but not interlaced images, which screenshot tools don't save.
+pub fn load(path: &Path) -> Result<Png, String> {
+    let data = bundle::read(path).map_err(|error| format!("couldn't read {}: {error}", path.display()))?;
+    decode(&data).map_err(|error| format!("couldn't decode {}: {error}", path.display()))
+}
+
+fn decode(data: &[u8]) -> Result<Png, String> {
+    let mut rest = data.strip_prefix(SIGNATURE).ok_or("it isn't a PNG file")?;
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+    while !rest.is_empty() {
+        if rest.len() < 12 {
+            return Err("the file is cut off".to_string());
+        }
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = &rest[4..8];
+        let chunk = rest.get(8..8 + length).ok_or("the file is cut off")?;
+        rest = rest.get(12 + length..).ok_or("the file is cut off")?;
+
+        match kind {
+            b"IHDR" => header = Some(Header::parse(chunk)?),
+            b"PLTE" => palette = chunk,
+            b"tRNS" => transparency = chunk,
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or("the file has no header")?;
+    let data = inflate_zlib(&compressed, header.data_size())?;
+    let rows = unfilter(&header, &data)?;
+    header.to_pixels(&rows, palette, transparency).map(|pixels| Png { width: header.width, height: header.height, pixels })
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8
+}
+
+impl Header {
+    fn parse(chunk: &[u8]) -> Result<Self, String> {
+        if chunk.len() != 13 {
+            return Err("the header is invalid".to_string());
+        }
+        let header = Header {
+            width: u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+            height: u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+            bit_depth: chunk[8],
+            color_type: chunk[9]
+        };
+
+        let valid_depths: &[u8] = match header.color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            color_type => return Err(format!("color type {color_type} doesn't exist"))
+        };
+        if !valid_depths.contains(&header.bit_depth) {
+            return Err(format!("a bit depth of {} isn't allowed with color type {}", header.bit_depth, header.color_type));
+        }
+        if chunk[12] != 0 {
+            return Err("interlaced images aren't supported".to_string());
+        }
+        if header.width == 0 || header.height == 0 {
+            return Err("the image is empty".to_string());
+        }
+        // The size comes from the file itself, so a small file could otherwise claim to decompress to gigabytes
+        if header.data_size() > MAX_SIZE {
+            return Err(format!("the image is {}x{}, which is too big", header.width, header.height));
+        }
+        Ok(header)
+    }
+
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1
+        }
+    }
+
+    /// The number of bytes to the same channel of the pixel before, which is at least 1 for images with small pixels
+    fn pixel_size(&self) -> usize {
+        (self.channels() * self.bit_depth as usize).div_ceil(8)
+    }
+
+    fn row_size(&self) -> usize {
+        (self.width as usize * self.channels() * self.bit_depth as usize).div_ceil(8)
+    }
+
+    /// The size of the decompressed data, where every row starts with the byte that says how it's filtered
+    fn data_size(&self) -> usize {
+        (self.row_size() + 1).saturating_mul(self.height as usize)
+    }
+
+    fn to_pixels(&self, rows: &[u8], palette: &[u8], transparency: &[u8]) -> Result<Vec<[u8; 4]>, String> {
+        let depth = self.bit_depth as usize;
+        let max = (1u32 << depth) - 1;
+
+        // Read a sample of the pixel at an index in a row, where samples of 16 bits are cut down to their high byte
+        let sample = |row: &[u8], index: usize| -> u8 {
+            match depth {
+                16 => row[index * 2],
+                8 => row[index],
+                _ => {
+                    let bit = index * depth;
+                    let value = (row[bit / 8] >> (8 - depth - bit % 8)) as u32 & max;
+                    if self.color_type == 3 { value as u8 } else { (value * 255 / max) as u8 }
+                }
+            }
+        };
+        // The full value of a sample, to compare against the transparent color of tRNS
+        let full_sample = |row: &[u8], index: usize| -> u16 {
+            match depth {
+                16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+                8 => row[index] as u16,
+                _ => ((row[index * depth / 8] >> (8 - depth - index * depth % 8)) as u32 & max) as u16
+            }
+        };
+        let transparent = |channel: usize| transparency.get(channel * 2..channel * 2 + 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+
+        let channels = self.channels();
+        let mut pixels = Vec::with_capacity(self.width as usize * self.height as usize);
+        for row in rows.chunks(self.row_size()) {
+            for x in 0..self.width as usize {
+                let first = x * channels;
+                let pixel = match self.color_type {
+                    0 => {
+                        let gray = sample(row, first);
+                        let alpha = if transparent(0) == Some(full_sample(row, first)) { 0 } else { 255 };
+                        [gray, gray, gray, alpha]
+                    }
+                    2 => {
+                        let color_key = [transparent(0), transparent(1), transparent(2)];
+                        let alpha = if (0..3).all(|channel| color_key[channel] == Some(full_sample(row, first + channel))) { 0 } else { 255 };
+                        [sample(row, first), sample(row, first + 1), sample(row, first + 2), alpha]
+                    }
+                    3 => {
+                        let index = sample(row, first) as usize;
+                        let color = palette.get(index * 3..index * 3 + 3).ok_or(format!("color {index} isn't in the palette"))?;
+                        [color[0], color[1], color[2], transparency.get(index).copied().unwrap_or(255)]
+                    }
+                    4 => {
+                        let gray = sample(row, first);
+                        [gray, gray, gray, sample(row, first + 1)]
+                    }
+                    _ => [sample(row, first), sample(row, first + 1), sample(row, first + 2), sample(row, first + 3)]
+                };
+                pixels.push(pixel);
+            }
+        }
+        Ok(pixels)
+    }
+}
+
+/// Undo the filter of every row, which stores the bytes of a row as differences from the pixels around them
+fn unfilter(header: &Header, data: &[u8]) -> Result<Vec<u8>, String> {
+    let row_size = header.row_size();
+    let pixel_size = header.pixel_size();
+    if data.len() < (row_size + 1) * header.height as usize {
+        return Err("the image data is cut off".to_string());
+    }
+
+    let mut rows = vec![0; row_size * header.height as usize];
+    for y in 0..header.height as usize {
+        let filter = data[y * (row_size + 1)];
+        let line = &data[y * (row_size + 1) + 1..(y + 1) * (row_size + 1)];
+        let (previous, current) = rows.split_at_mut(y * row_size);
+        let above = (y > 0).then(|| &previous[(y - 1) * row_size..]);
+        let current = &mut current[..row_size];
+
+        for index in 0..row_size {
+            let left = if index >= pixel_size { current[index - pixel_size] } else { 0 };
+            let up = above.map_or(0, |above| above[index]);
+            let up_left = if index >= pixel_size { above.map_or(0, |above| above[index - pixel_size]) } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                filter => return Err(format!("row {} has unknown filter {filter}", y + 1))
+            };
+            current[index] = line[index].wrapping_add(predicted);
+        }
+    }
+    Ok(rows)
+}
+
+/// Predict a byte from the one that's closest to the sum of the bytes to the left and above, minus the one between them
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let (distance_left, distance_up, distance_up_left) = ((estimate - left as i16).abs(), (estimate - up as i16).abs(), (estimate - up_left as i16).abs());
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Decompress zlib data, which is a DEFLATE stream with a header
+fn inflate_zlib(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let [method, flags, ..] = *data else {
+        return Err("the image data is empty".to_string());
+    };
+    if method & 0x0f != 8 || !(method as u16 * 256 + flags as u16).is_multiple_of(31) || flags & 0x20 != 0 {
+        return Err("the image data isn't compressed in a supported way".to_string());
+    }
+    inflate(&data[2..], limit)
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How long a process gets to exit by itself before it's forced to
+const GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// A process to kill, either by its ID or by the name of its executable
+#[derive(Debug, Clone)]
+pub enum Process {
+    Id(u32),
+    Name(String)
+}
+
+impl Process {
+    pub fn parse(text: &str) -> Self {
+        match text.parse() {
+            Ok(id) => Process::Id(id),
+            Err(_) => Process::Name(text.to_string())
+        }
+    }
+}
+
+impl fmt::Display for Process {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Process::Id(id) => write!(formatter, "process {id}"),
+            Process::Name(name) => write!(formatter, "process named {name:?}")
+        }
+    }
+}
+
+/// Check whether any matching process is running, other than TATATA itself
+pub fn exists(process: &Process) -> Result<bool, String> {
+    Ok(find(process)?.into_iter().any(|id| id != std::process::id()))
+}
+
+/// Ask the matching processes to exit, and force the ones that are still running after a grace period to
+///
+/// Returns the thread forcing the processes to exit, or nothing if no processes matched. TATATA itself never matches.
+pub fn kill(process: &Process) -> Result<Option<JoinHandle<()>>, String> {
+    let ids: Vec<u32> = find(process)?.into_iter().filter(|&id| id != std::process::id()).collect();
+    if ids.is_empty() {
+        return Ok(None);
+    }
+
+    for &id in &ids {
+        log::trace!("Asking process {id} to exit");
+        signal(id, false)?;
+    }
+
+    Ok(Some(thread::spawn(move || {
+        thread::sleep(GRACE_PERIOD);
+        for id in ids.into_iter().filter(|&id| is_running(id)) {
+            log::trace!("Process {id} didn't exit after {}s, forcing it to", GRACE_PERIOD.as_secs());
+            if let Err(error) = signal(id, true) {
+                log::warn!("Failed to force process {id} to exit: {error}");
+            }
+        }
+    })))
+}
+
+/// Find the IDs of the running processes that match
+#[cfg(target_os = "linux")]
+fn find(process: &Process) -> Result<Vec<u32>, String> {
+    let name = match process {
+        Process::Id(id) => return Ok(if is_running(*id) { vec![*id] } else { Vec::new() }),
+        Process::Name(name) => name
+    };
+
+    // The name in /proc/<id>/comm is cut off after 15 characters, so the name of the executable is checked as well
+    let entries = std::fs::read_dir("/proc").map_err(|error| format!("Failed to list processes: {error}"))?;
+    let ids = entries.filter_map(|entry| {
+        let entry = entry.ok()?;
+        let id: u32 = entry.file_name().to_str()?.parse().ok()?;
+        let comm = std::fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+        let executable = std::fs::read_link(entry.path().join("exe")).ok();
+        let matches = comm.trim_end() == name || executable.is_some_and(|path| path.file_name().is_some_and(|file_name| file_name == name.as_str()));
+        matches.then_some(id)
+    }).collect();
+
+    Ok(ids)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn find(process: &Process) -> Result<Vec<u32>, String> {
+    let name = match process {
+        Process::Id(id) => return Ok(if is_running(*id) { vec![*id] } else { Vec::new() }),
+        Process::Name(name) => name
+    };
+
+    // pgrep exits with 1 when nothing matches, which isn't a failure here
+    let output = std::process::Command::new("pgrep").arg("-x").arg(name).output()
+        .map_err(|error| format!("Failed to run pgrep: {error}"))?;
+    Ok(String::from_utf8_lossy(&output.stdout).lines().filter_map(|line| line.trim().parse().ok()).collect())
+}
+
+#[cfg(unix)]
+fn signal(id: u32, force: bool) -> Result<(), String> {
+    // SAFETY: kill has no safety requirements
+    if unsafe { libc::kill(id as libc::pid_t, if force { libc::SIGKILL } else { libc::SIGTERM }) } != 0 {
+        return Err(format!("Failed to kill process {id}: {}", std::io::Error::last_os_error()));
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_running(id: u32) -> bool {
+    // Sending no signal only checks whether the process exists, which it does even if it belongs to another user
+    // SAFETY: kill has no safety requirements
+    let result = unsafe { libc::kill(id as libc::pid_t, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(target_os = "windows")]
+fn find(process: &Process) -> Result<Vec<u32>, String> {
+    let filter = match process {
+        Process::Id(id) => format!("PID eq {id}"),
+        // Executables are named with their extension, which is easy to leave out
+        Process::Name(name) if !name.contains('.') => format!("IMAGENAME eq {name}.exe"),
+        Process::Name(name) => format!("IMAGENAME eq {name}")
+    };
+
+    // Every matching process is a line like "notepad.exe","1234",...
+    let output = std::process::Command::new("tasklist").args(["/FO", "CSV", "/NH", "/FI", &filter]).output()
+        .map_err(|error| format!("Failed to run tasklist: {error}"))?;
+    let ids = String::from_utf8_lossy(&output.stdout).lines()
+        .filter_map(|line| line.split(',').nth(1)?.trim_matches('"').parse().ok())
+        .collect();
+
+    Ok(ids)
+}
+
+#[cfg(target_os = "windows")]
+fn signal(id: u32, force: bool) -> Result<(), String> {
+    // Without /F, taskkill asks the windows of the process to close
+    let mut command = std::process::Command::new("taskkill");
+    command.args(["/PID", &id.to_string()]);
+    if force {
+        command.arg("/F");
+    }
+
+    let output = command.output().map_err(|error| format!("Failed to run taskkill: {error}"))?;
+    if !output.status.success() {
+        // Processes without windows can only be killed by force, which happens after the grace period
+        if !force {
+            log::trace!("Process {id} can't be asked to exit: {}", String::from_utf8_lossy(&output.stderr).trim());
+            return Ok(());
+        }
+        return Err(format!("Failed to kill process {id}: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn is_running(id: u32) -> bool {
+    find(&Process::Id(id)).is_ok_and(|ids| !ids.is_empty())
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn find(_process: &Process) -> Result<Vec<u32>, String> {
+    Err("Killing processes is not supported on this platform".to_string())
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn signal(_id: u32, _force: bool) -> Result<(), String> {
+    Err("Killing processes is not supported on this platform".to_string())
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn is_running(_id: u32) -> bool {
+    false
+}
@@ -0,0 +1,47 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Ask for the value of a variable on stdin
+///
+/// When someone is typing the answer, the question is printed to stderr so it doesn't end up in the output of a dry run
+/// when it's redirected, and invalid answers are asked again instead of failing. Otherwise the answer is read silently, so
+/// values can be piped in.
+pub fn ask(name: &str, description: &str) -> Result<i32, String> {
+    let interactive = io::stdin().is_terminal();
+
+    loop {
+        if interactive {
+            eprint!("{description} (${name}): ");
+            let _ = io::stderr().flush();
+        }
+
+        let mut answer = String::new();
+        let read = io::stdin().lock().read_line(&mut answer).map_err(|error| format!("Couldn't read the value of ${name}: {error}"))?;
+        if read == 0 {
+            if interactive {
+                eprintln!();
+            }
+            return Err(format!("No value given for ${name}, pass it with --var {name}=<value> to run without asking"));
+        }
+
+        match answer.trim().parse() {
+            Ok(value) => return Ok(value),
+            Err(error) if interactive => eprintln!("Invalid number ({error}), try again"),
+            Err(error) => return Err(format!("Invalid value {:?} for ${name} ({error})", answer.trim()))
+        }
+    }
+}
+
+/// Parse a `--var` argument, like `target_x=500`
+pub fn parse_assignment(text: &str) -> Result<(String, i32), String> {
+    let (name, value) = text.split_once('=').ok_or("expected <NAME>=<VALUE>, like target_x=500")?;
+    if !is_valid_name(name) {
+        return Err(format!("invalid variable name {name:?}, as names can only contain letters, digits and underscores, and can't start with a digit"));
+    }
+    let value = value.parse().map_err(|error| format!("invalid value {value:?} ({error})"))?;
+    Ok((name.to_string(), value))
+}
+
+/// Variable names are letters, digits and underscores, and don't start with a digit
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with(|character: char| character.is_ascii_digit()) && name.chars().all(|character| character.is_ascii_alphanumeric() || character == '_')
+}
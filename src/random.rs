@@ -0,0 +1,111 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The state of the generator, which is seeded the first time a number is needed
+static STATE: Mutex<Option<u64>> = Mutex::new(None);
+
+/// Get a random number below the given limit
+///
+/// The numbers are good enough for varying how a script plays back, but not for anything that has to be unpredictable.
+pub fn below(limit: usize) -> usize {
+    let mut state = STATE.lock().unwrap_or_else(|error| error.into_inner());
+    let state = state.get_or_insert_with(time_seed);
+
+    // SplitMix64, which is small and spreads even similar seeds out well
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut value = *state;
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^= value >> 31;
+
+    (value % limit as u64) as usize
+}
+
+/// Start the numbers from a seed, so runs with the same seed make the same choices
+pub fn set_seed(seed: u64) {
+    *STATE.lock().unwrap_or_else(|error| error.into_inner()) = Some(seed);
+}
+
+/// Mix the current time with the random keys the standard library uses for hash maps, so every run gets a different seed
+fn time_seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos()));
+    hasher.finish()
+}
+
+/// The words placeholder text is made of
+const LOREM_WORDS: &[&str] = &[
+    "lorem", "ipsum", "dolor", "sit", "amet", "consectetur", "adipiscing", "elit", "sed", "do", "eiusmod", "tempor",
+    "incididunt", "ut", "labore", "et", "dolore", "magna", "aliqua", "enim", "ad", "minim", "veniam", "quis", "nostrud",
+    "exercitation", "ullamco", "laboris", "nisi", "aliquip", "ex", "ea", "commodo", "consequat", "duis", "aute", "irure",
+    "in", "reprehenderit", "voluptate", "velit", "esse", "cillum", "fugiat", "nulla", "pariatur", "excepteur", "sint",
+    "occaecat", "cupidatat", "non", "proident", "sunt", "culpa", "qui", "officia", "deserunt", "mollit", "anim", "id", "est"
+];
+
+/// What random text is made of, like `alphanumeric` or `[a-f0-9]`
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Characters picked from a set
+    Characters(Vec<char>),
+    /// Placeholder words, like "Lorem ipsum dolor"
+    Lorem
+}
+
+impl Pattern {
+    /// Parse the name of a pattern, or a set of characters and ranges of characters in brackets, like `[a-z_]`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let range = |first: char, last: char| (first..=last).collect::<Vec<char>>();
+        match text {
+            "letters" => Ok(Pattern::Characters([range('a', 'z'), range('A', 'Z')].concat())),
+            "lowercase" => Ok(Pattern::Characters(range('a', 'z'))),
+            "uppercase" => Ok(Pattern::Characters(range('A', 'Z'))),
+            "digits" => Ok(Pattern::Characters(range('0', '9'))),
+            "alphanumeric" => Ok(Pattern::Characters([range('a', 'z'), range('A', 'Z'), range('0', '9')].concat())),
+            "lorem" => Ok(Pattern::Lorem),
+            _ => {
+                let Some(set) = text.strip_prefix('[').and_then(|text| text.strip_suffix(']')) else {
+                    return Err("expected letters, lowercase, uppercase, digits, alphanumeric, lorem, or characters in brackets like [a-z0-9]".to_string());
+                };
+
+                let set: Vec<char> = set.chars().collect();
+                let mut characters = Vec::new();
+                let mut index = 0;
+                while index < set.len() {
+                    // A dash between two characters is a range, while a dash at the start or end is itself
+                    if index + 2 < set.len() && set[index + 1] == '-' {
+                        if set[index] > set[index + 2] {
+                            return Err(format!("the range {}-{} is backwards", set[index], set[index + 2]));
+                        }
+                        characters.extend(range(set[index], set[index + 2]));
+                        index += 3;
+                    } else {
+                        characters.push(set[index]);
+                        index += 1;
+                    }
+                }
+
+                if characters.is_empty() {
+                    return Err("there are no characters in the brackets".to_string());
+                }
+                Ok(Pattern::Characters(characters))
+            }
+        }
+    }
+
+    /// Make random text, where the length is the number of characters, or the number of words for placeholder text
+    pub fn generate(&self, length: usize) -> String {
+        match self {
+            Pattern::Characters(characters) => (0..length).map(|_| characters[below(characters.len())]).collect(),
+            Pattern::Lorem => {
+                let words: Vec<&str> = (0..length).map(|_| LOREM_WORDS[below(LOREM_WORDS.len())]).collect();
+                let text = words.join(" ");
+
+                // Start with a capital letter, like a sentence
+                let mut characters = text.chars();
+                characters.next().map_or(String::new(), |first| first.to_uppercase().chain(characters).collect())
+            }
+        }
+    }
+}
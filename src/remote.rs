@@ -0,0 +1,324 @@
+use crate::diagnostic::ErrorKind;
+use crate::processes::{self, Process};
+use crate::sha256;
+use log::{info, warn};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+/// The version of the protocol, which the agent sends first so mismatched versions fail clearly
+const PROTOCOL: &str = "TATATA/2";
+
+/// The environment variable the token can be given in, so it doesn't show up in the list of processes
+const TOKEN_VARIABLE: &str = "TATATA_TOKEN";
+
+/// How long a client gets to authenticate and send its script, so a stuck client doesn't keep the agent busy
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The largest script or bundle a client can send
+const MAX_FILE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Get the token clients authenticate with, from `--token` or the `TATATA_TOKEN` environment variable
+pub fn token(given: Option<String>) -> Result<String, String> {
+    given.or_else(|| std::env::var(TOKEN_VARIABLE).ok()).filter(|token| !token.is_empty())
+        .ok_or(format!("No token given, pass --token or set the {TOKEN_VARIABLE} environment variable"))
+}
+
+/// Accept scripts from clients with the token, and run them one at a time, streaming their output back
+///
+/// Every script runs in a separate TATATA process with the options the client sent, like `tatata test` does.
+pub fn serve(address: &str, token: &str) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|error| format!("Couldn't listen on {address}: {error}"))?;
+    let executable = std::env::current_exe().map_err(|error| format!("Couldn't find the TATATA executable: {error}"))?;
+    info!("Listening on {}", listener.local_addr().map_or(address.to_string(), |address| address.to_string()));
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                warn!("Failed to accept a connection: {error}");
+                continue;
+            }
+        };
+        let peer = stream.peer_addr().map_or("an unknown address".to_string(), |address| address.to_string());
+        if let Err(error) = handle(stream, &peer, token, &executable) {
+            warn!("Run for {peer} failed: {error}");
+        }
+    }
+    Ok(())
+}
+
+/// Authenticate a client, receive its script, and run it
+fn handle(stream: TcpStream, peer: &str, token: &str, executable: &Path) -> Result<(), String> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT)).map_err(|error| error.to_string())?;
+    let mut writer = stream.try_clone().map_err(|error| error.to_string())?;
+    let mut reader = BufReader::new(stream);
+
+    // The client proves it knows the token by signing a challenge, so the token is never sent
+    let challenge = hex(&nonce().map_err(|error| format!("Couldn't make a challenge: {error}"))?);
+    writeln!(writer, "{PROTOCOL} {challenge}").map_err(|error| error.to_string())?;
+    let signature = read_line(&mut reader)?;
+    let expected = hex(&sha256::hmac(token.as_bytes(), challenge.as_bytes()));
+    if !signature.strip_prefix("AUTH ").is_some_and(|signature| constant_time_eq(signature.as_bytes(), expected.as_bytes())) {
+        let _ = writeln!(writer, "ERROR Wrong token");
+        return Err("the client sent the wrong token".to_string());
+    }
+    let mut incoming = Signer::new(token, &challenge, "client");
+    let mut outgoing = Signer::new(token, &challenge, "agent");
+    writeln!(writer, "{}", outgoing.sign("OK")).map_err(|error| error.to_string())?;
+
+    // Then it sends the script or bundle, and the options to run it with
+    let mut file = None;
+    let mut options = Vec::new();
+    loop {
+        let line = incoming.verify(&read_line(&mut reader)?)?;
+        let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+        match command {
+            "FILE" => {
+                let (name, hash) = argument.rsplit_once(' ').ok_or("the client sent an invalid file")?;
+                let (name, length) = name.rsplit_once(' ').ok_or("the client sent an invalid file")?;
+                let length: usize = length.parse().map_err(|_| "the client sent an invalid file length")?;
+                if length > MAX_FILE_SIZE {
+                    let _ = writeln!(writer, "{}", outgoing.sign(&format!("ERROR The file is larger than {}MB", MAX_FILE_SIZE / 1024 / 1024)));
+                    return Err(format!("the client sent a file of {length} bytes"));
+                }
+                let mut content = vec![0; length];
+                reader.read_exact(&mut content).map_err(|error| error.to_string())?;
+                // The content isn't signed itself, but its hash is part of the signed message
+                if hex(&sha256::hash(&content)) != hash {
+                    return Err("the file doesn't match the hash the client sent".to_string());
+                }
+
+                // Only the name of the file is kept, so it can't be written outside of the directory of the run
+                let name = Path::new(name).file_name().ok_or("the client sent a file without a name")?.to_os_string();
+                file = Some((name, content));
+            }
+            "OPTION" => options.push(argument.to_string()),
+            "RUN" => break,
+            _ => return Err(format!("the client sent an unknown command {command:?}"))
+        }
+    }
+    let (name, content) = file.ok_or("the client didn't send a script")?;
+
+    let directory = private_directory().map_err(|error| format!("Couldn't create a directory for the script: {error}"))?;
+    let path = directory.join(&name);
+    let written = fs::OpenOptions::new().write(true).create_new(true).open(&path).and_then(|mut file| file.write_all(&content));
+    let result = match written {
+        Ok(()) => {
+            info!("Running {} for {peer}", name.to_string_lossy());
+            run(&mut reader, &mut writer, &mut outgoing, executable, &path, &options)
+        }
+        Err(error) => Err(format!("Couldn't write {}: {error}", path.display()))
+    };
+    let _ = fs::remove_dir_all(&directory);
+    let _ = writer.shutdown(Shutdown::Both);
+    info!("Finished {} for {peer} with exit code {}", name.to_string_lossy(), result?);
+    Ok(())
+}
+
+/// Create a directory for a script the agent received, which only the user running the agent can use
+///
+/// The name is random, and creating it fails if it already exists, so another user can't create it first to read or
+/// replace the script.
+fn private_directory() -> io::Result<PathBuf> {
+    let directory = std::env::temp_dir().join(format!("tatata-agent-{}", hex(&nonce()?)));
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&directory)?;
+    Ok(directory)
+}
+
+/// Run a script, sending every line it prints to the client, and get its exit code
+///
+/// The script is aborted when the client disconnects, like when it's stopped with Ctrl+C.
+fn run(reader: &mut BufReader<TcpStream>, writer: &mut TcpStream, signer: &mut Signer, executable: &Path, path: &Path, options: &[String]) -> Result<i32, String> {
+    let mut child = Command::new(executable).args(options).arg(path)
+        .stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped())
+        .spawn().map_err(|error| format!("Failed to run {}: {error}", path.display()))?;
+
+    // Both outputs are read on their own threads, and sent to the client in the order they're printed
+    let (sender, receiver) = mpsc::channel();
+    let stdout = child.stdout.take().map(|output| forward(output, "OUT", sender.clone()));
+    let stderr = child.stderr.take().map(|output| forward(output, "ERR", sender));
+
+    // Nothing is sent by the client while the script runs, so reading only returns when it disconnects, or when the
+    // connection is shut down after the run, when the ID of the process could already belong to another one
+    let id = child.id();
+    let finished = Arc::new(AtomicBool::new(false));
+    let mut watcher = reader.get_ref().try_clone().map_err(|error| error.to_string())?;
+    watcher.set_read_timeout(None).map_err(|error| error.to_string())?;
+    let watcher_finished = Arc::clone(&finished);
+    thread::spawn(move || {
+        let _ = watcher.read(&mut [0]);
+        if !watcher_finished.load(Ordering::Relaxed) {
+            let _ = processes::kill(&Process::Id(id));
+        }
+    });
+
+    let mut connected = true;
+    for line in receiver {
+        connected = connected && writeln!(writer, "{}", signer.sign(&line)).is_ok();
+    }
+    for handle in [stdout, stderr].into_iter().flatten() {
+        let _ = handle.join();
+    }
+    let status = child.wait().map_err(|error| format!("Failed to wait for the script: {error}"));
+    finished.store(true, Ordering::Relaxed);
+    let status = status?;
+    // A script killed by a signal has no exit code, which is counted as being aborted
+    let code = status.code().unwrap_or(ErrorKind::Abort.exit_code());
+    if !connected || writeln!(writer, "{}", signer.sign(&format!("EXIT {code}"))).is_err() {
+        return Err("the client disconnected, so the script was aborted".to_string());
+    }
+    Ok(code)
+}
+
+/// Send the lines of an output of the script to the client, prefixed with which output they're from
+fn forward(output: impl Read + Send + 'static, prefix: &'static str, sender: mpsc::Sender<String>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut output = BufReader::new(output);
+        let mut line = Vec::new();
+        while output.read_until(b'\n', &mut line).is_ok_and(|length| length > 0) {
+            let text = String::from_utf8_lossy(&line);
+            if sender.send(format!("{prefix} {}", text.trim_end_matches(['\r', '\n']))).is_err() {
+                return;
+            }
+            line.clear();
+        }
+    })
+}
+
+/// Send a script or bundle to an agent, and print its output as it runs there
+///
+/// Returns the exit code of the script on the other machine.
+pub fn run_remote(address: &str, token: &str, file: &Path, options: &[String]) -> Result<i32, String> {
+    let content = fs::read(file).map_err(|error| format!("Couldn't read {}: {error}", file.display()))?;
+    let name = file.file_name().ok_or(format!("{} isn't a file", file.display()))?.to_string_lossy();
+    if let Some(option) = options.iter().find(|option| option.contains('\n')) {
+        return Err(format!("The option {option:?} contains a line break"));
+    }
+
+    let stream = TcpStream::connect(address).map_err(|error| format!("Couldn't connect to {address}: {error}"))?;
+    let mut writer = stream.try_clone().map_err(|error| error.to_string())?;
+    let mut reader = BufReader::new(stream);
+    let lost = |error: io::Error| format!("Lost the connection to {address}: {error}");
+
+    let greeting = read_line(&mut reader)?;
+    let challenge = greeting.strip_prefix(PROTOCOL).and_then(|rest| rest.strip_prefix(' '))
+        .ok_or(format!("{address} isn't a TATATA agent, or runs a different version"))?;
+    writeln!(writer, "AUTH {}", hex(&sha256::hmac(token.as_bytes(), challenge.as_bytes()))).map_err(lost)?;
+    let mut incoming = Signer::new(token, challenge, "agent");
+    let mut outgoing = Signer::new(token, challenge, "client");
+    // A wrong token is the only answer that isn't signed, as the agent has no key for this connection then
+    let response = read_line(&mut reader)?;
+    if let Some(error) = response.strip_prefix("ERROR ") {
+        return Err(format!("{address} refused the run: {error}"));
+    }
+    match incoming.verify(&response).map_err(|error| format!("{address} couldn't be trusted: {error}"))?.as_str() {
+        "OK" => {}
+        response => return Err(format!("{address} sent an unknown message {response:?}"))
+    }
+
+    writeln!(writer, "{}", outgoing.sign(&format!("FILE {name} {} {}", content.len(), hex(&sha256::hash(&content))))).map_err(lost)?;
+    writer.write_all(&content).map_err(lost)?;
+    for option in options {
+        writeln!(writer, "{}", outgoing.sign(&format!("OPTION {option}"))).map_err(lost)?;
+    }
+    writeln!(writer, "{}", outgoing.sign("RUN")).map_err(lost)?;
+    info!("Running {name} on {address}");
+
+    loop {
+        let line = incoming.verify(&read_line(&mut reader)?).map_err(|error| format!("{address} couldn't be trusted: {error}"))?;
+        let (kind, text) = line.split_once(' ').unwrap_or((&line, ""));
+        match kind {
+            "OUT" => println!("{text}"),
+            "ERR" => eprintln!("{text}"),
+            "EXIT" => return text.parse().map_err(|_| format!("{address} sent an invalid exit code {text:?}")),
+            "ERROR" => return Err(format!("{address} refused the run: {text}")),
+            _ => return Err(format!("{address} sent an unknown message {kind:?}"))
+        }
+    }
+}
+
+/// Read a line of the protocol, without its line break
+pub fn read_line(reader: &mut impl BufRead) -> Result<String, String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => Err("the connection was closed".to_string()),
+        Ok(_) => Ok(line.trim_end_matches(['\r', '\n']).to_string()),
+        // Reading fails with either of these when the timeout of the connection runs out, depending on the platform
+        Err(error) if matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => Err("no answer came in time".to_string()),
+        Err(error) => Err(format!("the connection was lost: {error}"))
+    }
+}
+
+/// Signs the messages of a connection after the client authenticated, so no one on the network can change them or send
+/// their own
+///
+/// Each direction has its own key, made from the token and the challenge of the connection, and the messages are
+/// numbered, so they can't be replayed in another connection, reordered, or sent back to the side that sent them.
+struct Signer {
+    key: [u8; 32],
+    /// The number of messages signed so far
+    sequence: u64
+}
+
+impl Signer {
+    fn new(token: &str, challenge: &str, sender: &str) -> Self {
+        Signer { key: sha256::hmac(token.as_bytes(), format!("{challenge} {sender}").as_bytes()), sequence: 0 }
+    }
+
+    fn signature(&mut self, message: &str) -> String {
+        let signature = hex(&sha256::hmac(&self.key, format!("{} {message}", self.sequence).as_bytes()));
+        self.sequence += 1;
+        signature
+    }
+
+    /// Add the signature to the end of a message
+    fn sign(&mut self, message: &str) -> String {
+        format!("{message} {}", self.signature(message))
+    }
+
+    /// Check the signature at the end of a received line, and get the message without it
+    fn verify(&mut self, line: &str) -> Result<String, String> {
+        let (message, signature) = line.rsplit_once(' ').ok_or("a message isn't signed")?;
+        if !constant_time_eq(signature.as_bytes(), self.signature(message).as_bytes()) {
+            return Err("a message has the wrong signature, so it was changed on the way".to_string());
+        }
+        Ok(message.to_string())
+    }
+}
+
+/// Make a challenge that's different for every connection, from the random generator of the operating system, so no
+/// one can guess the next one
+fn nonce() -> io::Result<[u8; 16]> {
+    let mut nonce = [0; 16];
+    #[cfg(unix)]
+    fs::File::open("/dev/urandom")?.read_exact(&mut nonce)?;
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::Security::Cryptography::{BCryptGenRandom, BCRYPT_USE_SYSTEM_PREFERRED_RNG};
+        // SAFETY: the buffer is valid for its length, and the system preferred generator takes no algorithm handle
+        let status = unsafe { BCryptGenRandom(std::ptr::null_mut(), nonce.as_mut_ptr(), nonce.len() as u32, BCRYPT_USE_SYSTEM_PREFERRED_RNG) };
+        if status != 0 {
+            return Err(io::Error::other(format!("BCryptGenRandom failed with {status:#x}")));
+        }
+    }
+    Ok(nonce)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Compare signatures without stopping at the first difference, so the time it takes doesn't reveal how much matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
@@ -0,0 +1,446 @@
+use crate::gif::{self, Frame};
+use crate::motion;
+use crate::png::Png;
+use crate::trace;
+use enigo::Button;
+use std::sync::Arc;
+
+/// Something a script does that shows up in a preview, at a time in milliseconds from the start of the script
+pub enum Event {
+    Move { x: i32, y: i32, relative: bool, duration: u64 },
+    /// A movement through several points, where relative points are from the point before them
+    Path { points: Vec<(i32, i32)>, relative: bool, duration: u64 },
+    Trace { points: Arc<Vec<trace::Point>>, speed: f64 },
+    MouseDown(Button),
+    MouseUp(Button),
+    /// A key pressed or released, by the name `keydown` uses for it
+    KeyDown(String),
+    KeyUp(String),
+    Text { text: String, delay: u64 }
+}
+
+/// How a preview looks
+pub struct Options {
+    /// A screenshot to draw over, which sets the size of the screen
+    pub background: Option<Png>,
+    /// The size of the screen when there's no screenshot
+    pub size: (u32, u32),
+    /// How much the screen is scaled in the preview
+    pub scale: f64,
+    pub fps: u32
+}
+
+/// How long the trail behind the cursor is, in milliseconds
+const TRAIL_LENGTH: u64 = 1000;
+
+/// How long a click ripples after the button is pressed, in milliseconds
+const CLICK_LENGTH: u64 = 400;
+
+/// How long the preview keeps showing the end of the script
+const END_PAUSE: u64 = 1000;
+
+/// The size of the letters of the caption, in pixels of the preview per pixel of the font
+const CAPTION_SCALE: usize = 2;
+
+/// The names of keys that are held for shortcuts, instead of typing something themselves
+const MODIFIERS: &[&str] = &["control", "shift", "alt", "super"];
+
+// The colors of the palette before the colors of the screenshot
+const BLACK: u8 = 0;
+const WHITE: u8 = 1;
+const TRAIL: u8 = 2;
+const CAPTION_BACKGROUND: u8 = 6;
+const CAPTION_DIM: u8 = 7;
+const SCREEN: u8 = 8;
+
+/// The colors used for drawing, followed by a cube of 6 levels of red, green and blue for the screenshot
+fn palette() -> [[u8; 3]; 256] {
+    let mut palette = [[0; 3]; 256];
+    palette[..9].copy_from_slice(&[
+        [0, 0, 0], [255, 255, 255], [255, 200, 0], [230, 50, 50], [60, 120, 255], [60, 200, 90], [24, 24, 24],
+        [150, 150, 150], [48, 52, 60]
+    ]);
+    for index in 0..216 {
+        palette[16 + index] = [(index / 36) as u8 * 51, (index / 6 % 6) as u8 * 51, (index % 6) as u8 * 51];
+    }
+    palette
+}
+
+/// Thresholds for dithering, which mix neighbouring colors of the palette in a pattern to show the colors between them
+const DITHER: [[i32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// The color of the palette for a pixel of the screenshot, dithered by where it is
+fn closest_color(x: usize, y: usize, [red, green, blue]: [usize; 3]) -> u8 {
+    let offset = (DITHER[y % 4][x % 4] * 51 + 25) / 16 - 26;
+    let level = |channel: usize| ((channel as i32 + offset).clamp(0, 255) + 25) as usize / 51;
+    (16 + level(red) * 36 + level(green) * 6 + level(blue)) as u8
+}
+
+/// The color a click of a mouse button is drawn in
+fn button_color(button: Button) -> u8 {
+    match button {
+        Button::Left => 3,
+        Button::Right => 4,
+        _ => 5
+    }
+}
+
+/// Where the cursor is over time, as the positions it has at points in time, moving in straight lines between them
+struct Path {
+    points: Vec<(u64, i32, i32)>
+}
+
+impl Path {
+    fn position(&self, time: u64) -> (i32, i32) {
+        let index = self.points.partition_point(|&(point_time, _, _)| point_time <= time).saturating_sub(1);
+        let (start_time, x, y) = self.points[index];
+        match self.points.get(index + 1) {
+            Some(&(end_time, end_x, end_y)) if end_time > start_time && time >= start_time => {
+                let progress = (time - start_time) as f64 / (end_time - start_time) as f64;
+                (x + ((end_x - x) as f64 * progress) as i32, y + ((end_y - y) as f64 * progress) as i32)
+            }
+            _ => (x, y)
+        }
+    }
+
+    /// Move the cursor from a point in time on, replacing where it was going to be after that
+    fn go(&mut self, time: u64, positions: impl IntoIterator<Item = (u64, i32, i32)>) {
+        let current = self.position(time);
+        self.points.retain(|&(point_time, _, _)| point_time <= time);
+        self.points.push((time, current.0, current.1));
+        self.points.extend(positions);
+    }
+}
+
+/// A press of a mouse button, and when it was released if it was
+struct Click {
+    time: u64,
+    release: Option<u64>,
+    button: Button,
+    position: (i32, i32)
+}
+
+/// What the script does over time, worked out from its events
+struct Timeline {
+    path: Path,
+    clicks: Vec<Click>,
+    /// The typed text and pressed shortcuts, with the time every piece is added to the caption
+    caption: Vec<(u64, String)>,
+    end: u64
+}
+
+impl Timeline {
+    fn new(events: &[(u64, Event)], center: (i32, i32)) -> Self {
+        let mut events: Vec<&(u64, Event)> = events.iter().collect();
+        events.sort_by_key(|(time, _)| *time);
+
+        let mut path = Path { points: vec![(0, center.0, center.1)] };
+        let mut clicks: Vec<Click> = Vec::new();
+        let mut caption = Vec::new();
+        let mut held: Vec<&str> = Vec::new();
+        let mut end = 0;
+        for (time, event) in events {
+            let time = *time;
+            match event {
+                Event::Move { x, y, relative, duration } => {
+                    let start = path.position(time);
+                    let target = if *relative { (start.0 + x, start.1 + y) } else { (*x, *y) };
+                    path.go(time, [(time + duration, target.0, target.1)]);
+                    end = end.max(time + duration);
+                }
+                Event::Path { points, relative, duration } => {
+                    let start = path.position(time);
+                    let targets: Vec<(i32, i32)> = if *relative {
+                        points.iter().scan(start, |position, (x, y)| {
+                            *position = (position.0 + x, position.1 + y);
+                            Some(*position)
+                        }).collect()
+                    } else {
+                        points.clone()
+                    };
+                    let positions = motion::spread_time(start, &targets, *duration).into_iter().map(|(point_time, x, y)| (time + point_time, x, y));
+                    path.go(time, positions);
+                    end = end.max(time + duration);
+                }
+                Event::Trace { points, speed } => {
+                    let positions: Vec<_> = points.iter().map(|point| (time + (point.time as f64 / speed).round() as u64, point.x, point.y)).collect();
+                    end = end.max(positions.last().map_or(time, |&(time, _, _)| time));
+                    path.go(time, positions);
+                }
+                Event::MouseDown(button) => clicks.push(Click { time, release: None, button: *button, position: path.position(time) }),
+                Event::MouseUp(button) => {
+                    if let Some(click) = clicks.iter_mut().rev().find(|click| click.button == *button && click.release.is_none()) {
+                        click.release = Some(time);
+                    }
+                }
+                Event::KeyDown(name) if MODIFIERS.contains(&name.as_str()) => {
+                    if !held.contains(&name.as_str()) {
+                        held.push(name);
+                    }
+                }
+                Event::KeyUp(name) => held.retain(|held| held != name),
+                Event::KeyDown(name) => caption.push((time, describe_key(name, &held))),
+                Event::Text { text, delay } => {
+                    for (index, character) in text.chars().enumerate() {
+                        let character = if character.is_control() { ' ' } else { character };
+                        caption.push((time + delay * index as u64, character.to_string()));
+                    }
+                    end = end.max(time + delay * text.chars().count().saturating_sub(1) as u64);
+                }
+            }
+            end = end.max(time);
+        }
+
+        Timeline { path, clicks, caption, end: end + END_PAUSE }
+    }
+}
+
+/// Write a key pressed by itself as what it types, and keys pressed along with modifiers as a shortcut, like `[control+c]`
+fn describe_key(name: &str, held: &[&str]) -> String {
+    let typed = match name {
+        "space" => Some(' '),
+        _ => {
+            let mut characters = name.chars();
+            characters.next().filter(|_| characters.next().is_none())
+        }
+    };
+    match (typed, held) {
+        (Some(character), []) => character.to_string(),
+        (Some(character), ["shift"]) => character.to_ascii_uppercase().to_string(),
+        (_, []) => format!("[{name}]"),
+        (_, held) => format!("[{}+{name}]", held.join("+"))
+    }
+}
+
+/// Simulate the events of a script, and draw them into an animated GIF
+pub fn render(events: &[(u64, Event)], options: Options) -> Result<Vec<u8>, String> {
+    let (screen_width, screen_height) = options.background.as_ref().map_or(options.size, |background| (background.width, background.height));
+    let width = ((screen_width as f64 * options.scale).round() as usize).max(1);
+    let height = ((screen_height as f64 * options.scale).round() as usize).max(1);
+    if width > u16::MAX as usize || height > u16::MAX as usize {
+        return Err(format!("The preview would be {width}x{height} pixels, which is too large for a GIF, pass a smaller --scale"));
+    }
+    let background = match &options.background {
+        Some(background) => scale_background(background, width, height),
+        None => vec![SCREEN; width * height]
+    };
+
+    let timeline = Timeline::new(events, (screen_width as i32 / 2, screen_height as i32 / 2));
+    let to_preview = |(x, y): (i32, i32)| ((x as f64 * options.scale) as i32, (y as f64 * options.scale) as i32);
+
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut last_state = None;
+    let mut frame_index: u64 = 0;
+    loop {
+        let time = frame_index * 1000 / options.fps as u64;
+        if time > timeline.end {
+            break;
+        }
+        frame_index += 1;
+        // Delays are in hundredths of a second, so they're rounded in a way that adds up to the right length
+        let delay = ((frame_index * 1000 / options.fps as u64).div_ceil(10) - time.div_ceil(10)) as u16;
+
+        // What's drawn only changes when something happens, so frames without changes just show the last one longer
+        let trail: Vec<(i32, i32)> = (0..=TRAIL_LENGTH / 20).map(|step| to_preview(timeline.path.position(time.saturating_sub(step * 20)))).collect();
+        let clicks: Vec<(usize, u64, bool)> = timeline.clicks.iter().enumerate()
+            .filter(|(_, click)| click.time <= time && (time - click.time < CLICK_LENGTH || click.release.is_none_or(|release| release > time)))
+            .map(|(index, click)| (index, (time - click.time).min(CLICK_LENGTH), click.release.is_none_or(|release| release > time)))
+            .collect();
+        let caption: String = timeline.caption.iter().filter(|(caption_time, _)| *caption_time <= time).map(|(_, text)| text.as_str()).collect();
+        let state = (trail, clicks, caption, time / 1000);
+        if last_state.as_ref() == Some(&state) {
+            if let Some(frame) = frames.last_mut() {
+                frame.delay = frame.delay.saturating_add(delay);
+            }
+            continue;
+        }
+
+        let (trail, clicks, caption, seconds) = &state;
+        let mut canvas = Canvas { pixels: background.clone(), width, height };
+        for pair in trail.windows(2) {
+            canvas.line(pair[0], pair[1], TRAIL);
+        }
+        for &(index, age, held) in clicks {
+            let click = &timeline.clicks[index];
+            let (x, y) = to_preview(click.position);
+            if age < CLICK_LENGTH {
+                canvas.ring(x, y, 4 + (age * 14 / CLICK_LENGTH) as i32, button_color(click.button));
+            }
+            if held {
+                canvas.disc(x, y, 4, button_color(click.button));
+            }
+        }
+        canvas.cursor(trail[0]);
+        canvas.caption(caption, &format!("{seconds}s"));
+
+        frames.push(Frame { pixels: canvas.pixels, delay });
+        last_state = Some(state);
+    }
+
+    Ok(gif::encode(width as u16, height as u16, &palette(), &frames))
+}
+
+/// Scale a screenshot to the size of the preview, averaging the pixels that end up in the same one
+fn scale_background(background: &Png, width: usize, height: usize) -> Vec<u8> {
+    let (source_width, source_height) = (background.width as usize, background.height as usize);
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let (top, bottom) = (y * source_height / height, ((y + 1) * source_height / height).max(y * source_height / height + 1));
+        for x in 0..width {
+            let (left, right) = (x * source_width / width, ((x + 1) * source_width / width).max(x * source_width / width + 1));
+            let mut sum = [0usize; 3];
+            for row in top..bottom.min(source_height) {
+                for pixel in &background.pixels[row * source_width + left..row * source_width + right.min(source_width)] {
+                    for channel in 0..3 {
+                        sum[channel] += pixel[channel] as usize;
+                    }
+                }
+            }
+            let count = ((bottom.min(source_height) - top) * (right.min(source_width) - left)).max(1);
+            pixels.push(closest_color(x, y, sum.map(|channel| channel / count)));
+        }
+    }
+    pixels
+}
+
+/// A frame being drawn
+struct Canvas {
+    pixels: Vec<u8>,
+    width: usize,
+    height: usize
+}
+
+impl Canvas {
+    fn set(&mut self, x: i32, y: i32, color: u8) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.pixels[y as usize * self.width + x as usize] = color;
+        }
+    }
+
+    /// Draw a line two pixels wide
+    fn line(&mut self, from: (i32, i32), to: (i32, i32), color: u8) {
+        let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).max(1);
+        for step in 0..=steps {
+            let x = from.0 + (to.0 - from.0) * step / steps;
+            let y = from.1 + (to.1 - from.1) * step / steps;
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                self.set(x + dx, y + dy, color);
+            }
+        }
+    }
+
+    fn ring(&mut self, x: i32, y: i32, radius: i32, color: u8) {
+        for dy in -radius - 1..=radius + 1 {
+            for dx in -radius - 1..=radius + 1 {
+                let distance = ((dx * dx + dy * dy) as f64).sqrt();
+                if (distance - radius as f64).abs() < 1.0 {
+                    self.set(x + dx, y + dy, color);
+                }
+            }
+        }
+    }
+
+    fn disc(&mut self, x: i32, y: i32, radius: i32, color: u8) {
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if dx * dx + dy * dy <= radius * radius {
+                    self.set(x + dx, y + dy, color);
+                }
+            }
+        }
+    }
+
+    /// Draw an arrow cursor with its tip at the position
+    fn cursor(&mut self, (x, y): (i32, i32)) {
+        for (row, line) in CURSOR.iter().enumerate() {
+            for (column, pixel) in line.chars().enumerate() {
+                match pixel {
+                    'X' => self.set(x + column as i32, y + row as i32, BLACK),
+                    '.' => self.set(x + column as i32, y + row as i32, WHITE),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Draw a bar along the bottom with the end of the caption on the left, and the time on the right
+    fn caption(&mut self, caption: &str, time: &str) {
+        let glyph_width = 6 * CAPTION_SCALE;
+        let bar_height = (8 * CAPTION_SCALE + 8).min(self.height);
+        let top = self.height - bar_height;
+        self.pixels[top * self.width..].fill(CAPTION_BACKGROUND);
+
+        let time_left = self.width.saturating_sub(time.len() * glyph_width + 4);
+        self.text(time_left, top + 4, time, CAPTION_DIM);
+        let room = time_left.saturating_sub(glyph_width + 4) / glyph_width;
+        let skip = caption.chars().count().saturating_sub(room);
+        let shown: String = caption.chars().skip(skip).collect();
+        self.text(4, top + 4, &shown, WHITE);
+    }
+
+    fn text(&mut self, left: usize, top: usize, text: &str, color: u8) {
+        for (index, character) in text.chars().enumerate() {
+            let glyph = FONT.get((character as usize).wrapping_sub(0x20)).unwrap_or(&FONT[b'?' as usize - 0x20]);
+            for (column, bits) in glyph.iter().enumerate() {
+                for row in 0..7 {
+                    if bits & (1 << row) == 0 {
+                        continue;
+                    }
+                    for (dx, dy) in (0..CAPTION_SCALE).flat_map(|dx| (0..CAPTION_SCALE).map(move |dy| (dx, dy))) {
+                        let x = left + index * 6 * CAPTION_SCALE + column * CAPTION_SCALE + dx;
+                        self.set(x as i32, (top + row * CAPTION_SCALE + dy) as i32, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An arrow cursor, with `X` for its outline and `.` for its inside
+const CURSOR: [&str; 17] = [
+    "X",
+    "XX",
+    "X.X",
+    "X..X",
+    "X...X",
+    "X....X",
+    "X.....X",
+    "X......X",
+    "X.......X",
+    "X........X",
+    "X.....XXXXX",
+    "X..X..X",
+    "X.X X..X",
+    "XX  X..X",
+    "X    X..X",
+    "     X..X",
+    "      XX"
+];
+
+/// A font of 5 by 7 pixels for the printable ASCII characters, as columns from the left with the top row in the lowest bit
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5f, 0x00, 0x00], [0x00, 0x07, 0x00, 0x07, 0x00], [0x14, 0x7f, 0x14, 0x7f, 0x14],
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62], [0x36, 0x49, 0x55, 0x22, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1c, 0x22, 0x41, 0x00], [0x00, 0x41, 0x22, 0x1c, 0x00], [0x08, 0x2a, 0x1c, 0x2a, 0x08], [0x08, 0x08, 0x3e, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], [0x00, 0x60, 0x60, 0x00, 0x00], [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], [0x00, 0x42, 0x7f, 0x40, 0x00], [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4b, 0x31],
+    [0x18, 0x14, 0x12, 0x7f, 0x10], [0x27, 0x45, 0x45, 0x45, 0x39], [0x3c, 0x4a, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1e], [0x00, 0x36, 0x36, 0x00, 0x00], [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14], [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3e], [0x7e, 0x11, 0x11, 0x11, 0x7e], [0x7f, 0x49, 0x49, 0x49, 0x36], [0x3e, 0x41, 0x41, 0x41, 0x22],
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], [0x7f, 0x49, 0x49, 0x49, 0x41], [0x7f, 0x09, 0x09, 0x09, 0x01], [0x3e, 0x41, 0x49, 0x49, 0x7a],
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], [0x00, 0x41, 0x7f, 0x41, 0x00], [0x20, 0x40, 0x41, 0x3f, 0x01], [0x7f, 0x08, 0x14, 0x22, 0x41],
+    [0x7f, 0x40, 0x40, 0x40, 0x40], [0x7f, 0x02, 0x0c, 0x02, 0x7f], [0x7f, 0x04, 0x08, 0x10, 0x7f], [0x3e, 0x41, 0x41, 0x41, 0x3e],
+    [0x7f, 0x09, 0x09, 0x09, 0x06], [0x3e, 0x41, 0x51, 0x21, 0x5e], [0x7f, 0x09, 0x19, 0x29, 0x46], [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7f, 0x01, 0x01], [0x3f, 0x40, 0x40, 0x40, 0x3f], [0x1f, 0x20, 0x40, 0x20, 0x1f], [0x3f, 0x40, 0x38, 0x40, 0x3f],
+    [0x63, 0x14, 0x08, 0x14, 0x63], [0x07, 0x08, 0x70, 0x08, 0x07], [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7f, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7f, 0x00], [0x04, 0x02, 0x01, 0x02, 0x04], [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00], [0x20, 0x54, 0x54, 0x54, 0x78], [0x7f, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7f], [0x38, 0x54, 0x54, 0x54, 0x18], [0x08, 0x7e, 0x09, 0x01, 0x02], [0x0c, 0x52, 0x52, 0x52, 0x3e],
+    [0x7f, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7d, 0x40, 0x00], [0x20, 0x40, 0x44, 0x3d, 0x00], [0x7f, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7f, 0x40, 0x00], [0x7c, 0x04, 0x18, 0x04, 0x78], [0x7c, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7c, 0x14, 0x14, 0x14, 0x08], [0x08, 0x14, 0x14, 0x18, 0x7c], [0x7c, 0x08, 0x04, 0x04, 0x08], [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3f, 0x44, 0x40, 0x20], [0x3c, 0x40, 0x40, 0x20, 0x7c], [0x1c, 0x20, 0x40, 0x20, 0x1c], [0x3c, 0x40, 0x30, 0x40, 0x3c],
+    [0x44, 0x28, 0x10, 0x28, 0x44], [0x0c, 0x50, 0x50, 0x50, 0x3c], [0x44, 0x64, 0x54, 0x4c, 0x44], [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7f, 0x00, 0x00], [0x00, 0x41, 0x36, 0x08, 0x00], [0x08, 0x04, 0x08, 0x10, 0x08]
+];
@@ -0,0 +1,161 @@
+use crate::interrupt;
+use crate::remote::read_line;
+use log::{debug, info, warn};
+use std::io::{BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The version of the protocol, which every instance sends first so mismatched versions fail clearly
+const PROTOCOL: &str = "TATATA-SYNC/1";
+
+/// How many times an instance asks for the time of the coordinator, keeping the answer that came back the fastest
+const CLOCK_SAMPLES: u32 = 10;
+
+/// How long after everyone is ready the run starts, which is enough for the start time to reach every instance
+const START_DELAY: Duration = Duration::from_secs(1);
+
+/// How often an instance tries to reach a coordinator that isn't listening yet
+const CONNECT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the coordinator checks for new connections
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long an instance that's connecting gets to get ready, which takes a few round trips, before it's given up on
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Wait for other instances to connect, and tell them when to start, returning when the run starts here as well
+///
+/// Times are sent in microseconds since the coordinator started listening, so the clocks of the machines don't have to
+/// be set to the same time.
+pub fn coordinate(address: &str, peers: usize) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|error| format!("Couldn't listen on {address}: {error}"))?;
+    let origin = Instant::now();
+    info!("Waiting for {peers} other instance(s) to connect to {}", listener.local_addr().map_or(address.to_string(), |address| address.to_string()));
+
+    // The listener is polled, so the run can be aborted while waiting
+    listener.set_nonblocking(true).map_err(|error| error.to_string())?;
+    let mut connections = Vec::new();
+    while connections.len() < peers {
+        let (stream, peer) = match listener.accept() {
+            Ok(connection) => connection,
+            Err(error) if error.kind() == ErrorKind::WouldBlock && !interrupt::requested() => {
+                thread::sleep(ACCEPT_INTERVAL);
+                continue;
+            }
+            Err(_) if interrupt::requested() => return Err("Aborted while waiting for the other instances".to_string()),
+            Err(error) => return Err(format!("Failed to accept a connection: {error}"))
+        };
+        match handshake(stream, origin) {
+            Ok(writer) => connections.push((peer, writer)),
+            Err(error) => {
+                warn!("Ignoring {peer}: {error}");
+                continue;
+            }
+        }
+        info!("{peer} is ready, {} more to go", peers - connections.len());
+    }
+
+    // An instance that left after getting ready doesn't keep the others from starting
+    let start = origin.elapsed() + START_DELAY;
+    for (peer, connection) in &mut connections {
+        if let Err(error) = writeln!(connection, "START {}", start.as_micros()) {
+            warn!("Lost the connection to {peer}, so it won't start with the others: {error}");
+        }
+    }
+    wait_until(origin + start);
+    Ok(())
+}
+
+/// Check that a new connection is another instance, and answer its questions about the time until it's ready
+///
+/// The instance has to be ready within [`HANDSHAKE_TIMEOUT`], and can only ask for the time as often as instances do, so
+/// one that stops answering or keeps asking can't keep the others waiting.
+fn handshake(stream: TcpStream, origin: Instant) -> Result<TcpStream, String> {
+    let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+    stream.set_nonblocking(false).and_then(|_| stream.set_nodelay(true)).map_err(|error| error.to_string())?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT)).map_err(|error| error.to_string())?;
+    let mut writer = stream.try_clone().map_err(|error| error.to_string())?;
+    let mut reader = BufReader::new(stream);
+    let next_line = |reader: &mut BufReader<TcpStream>| {
+        if interrupt::requested() {
+            return Err("the run was aborted".to_string());
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err("it took too long to get ready".to_string());
+        }
+        reader.get_ref().set_read_timeout(Some(remaining)).map_err(|error| error.to_string())?;
+        read_line(reader)
+    };
+    if next_line(&mut reader)? != PROTOCOL {
+        return Err("it isn't a TATATA instance, or runs a different version".to_string());
+    }
+    writeln!(writer, "{PROTOCOL}").map_err(|error| error.to_string())?;
+
+    // Answer with the time until the instance knows the clock well enough
+    for _ in 0..=CLOCK_SAMPLES {
+        match next_line(&mut reader)?.as_str() {
+            "TIME" => writeln!(writer, "{}", origin.elapsed().as_micros()).map_err(|error| error.to_string())?,
+            "READY" => return Ok(writer),
+            line => return Err(format!("it sent an unknown message {line:?}"))
+        }
+    }
+    Err(format!("it asked for the time more than {CLOCK_SAMPLES} times"))
+}
+
+/// Connect to the coordinator, and return at the moment it says the run starts
+pub fn join(address: &str) -> Result<(), String> {
+    let stream = connect(address)?;
+    let mut writer = stream.try_clone().map_err(|error| error.to_string())?;
+    let mut reader = BufReader::new(stream);
+    writeln!(writer, "{PROTOCOL}").map_err(|error| error.to_string())?;
+    if read_line(&mut reader)? != PROTOCOL {
+        return Err(format!("{address} isn't a TATATA instance, or runs a different version"));
+    }
+
+    // The coordinator's clock read halfway through the round trip with the least delay is the closest to the truth
+    let mut closest: Option<(Duration, Instant, u64)> = None;
+    for _ in 0..CLOCK_SAMPLES {
+        let sent = Instant::now();
+        writeln!(writer, "TIME").map_err(|error| error.to_string())?;
+        let time: u64 = read_line(&mut reader)?.parse().map_err(|_| format!("{address} sent an invalid time"))?;
+        let round_trip = sent.elapsed();
+        if closest.is_none_or(|(closest, _, _)| round_trip < closest) {
+            closest = Some((round_trip, sent + round_trip / 2, time));
+        }
+    }
+    let (round_trip, local, remote) = closest.expect("the clock is sampled at least once");
+    writeln!(writer, "READY").map_err(|error| error.to_string())?;
+    info!("Connected to {address}, waiting for the other instances");
+    debug!("The clock of {address} is known to within {}us", round_trip.as_micros() / 2);
+
+    let line = read_line(&mut reader)?;
+    let start: u64 = line.strip_prefix("START ").and_then(|start| start.parse().ok())
+        .ok_or(format!("{address} sent an unknown message {line:?}"))?;
+    wait_until(local + Duration::from_micros(start.saturating_sub(remote)));
+    Ok(())
+}
+
+/// Connect to the coordinator, waiting for it if it isn't listening yet, as instances can be started in any order
+fn connect(address: &str) -> Result<TcpStream, String> {
+    let mut waiting = false;
+    loop {
+        match TcpStream::connect(address) {
+            // The clock is measured by small messages going back and forth, which mustn't wait to be sent in bulk
+            Ok(stream) => return stream.set_nodelay(true).map(|_| stream).map_err(|error| error.to_string()),
+            Err(_) if interrupt::requested() => return Err("Aborted while waiting for the coordinator".to_string()),
+            Err(error) => {
+                if !waiting {
+                    info!("Waiting for {address} to start listening ({error})");
+                    waiting = true;
+                }
+                thread::sleep(CONNECT_INTERVAL);
+            }
+        }
+    }
+}
+
+fn wait_until(start: Instant) {
+    spin_sleep::sleep(start.saturating_duration_since(Instant::now()));
+}
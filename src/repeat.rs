@@ -0,0 +1,78 @@
+use crate::backend::{self, BackendConfig};
+use crate::diagnostic::{self, ErrorKind};
+use crate::interrupt;
+use enigo::{Direction, Key};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The time a key is held before it starts repeating, if the script doesn't say
+pub const DEFAULT_DELAY: u64 = 500;
+
+/// How often a held key is pressed again
+#[derive(Debug, Clone, Copy)]
+pub struct RepeatRate {
+    /// Presses per second
+    pub rate: u32,
+    /// The time before the first repeat, in milliseconds
+    pub delay: u64
+}
+
+/// A held key that is pressed again at a fixed rate, like the operating system does when a key on a real keyboard is held
+pub struct KeyRepeat {
+    pub key: Key,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>
+}
+
+impl KeyRepeat {
+    /// Start repeating a key that has just been pressed
+    pub fn start(backend_config: &BackendConfig, key: Key, repeat: RepeatRate) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let backend_config = backend_config.clone();
+        let thread_stop = stop.clone();
+
+        let handle = thread::spawn(move || {
+            let mut backend = backend::create(&backend_config).unwrap_or_else(|error| diagnostic::fail(ErrorKind::Injection, error));
+            let period = Duration::from_secs(1) / repeat.rate;
+
+            // Schedule every repeat from the press, so time spent sending events doesn't add up
+            let start_time = Instant::now();
+            let mut next_repeat = Duration::from_millis(repeat.delay);
+            while !thread_stop.load(Ordering::Relaxed) && !interrupt::requested() {
+                let elapsed = start_time.elapsed();
+                if elapsed < next_repeat {
+                    // Parking lets stopping wake the thread up right away, instead of waiting for the next repeat
+                    thread::park_timeout(next_repeat - elapsed);
+                    continue;
+                }
+
+                if let Err(error) = backend.key(key, Direction::Press) {
+                    log::error!("Failed to repeat key {key:?}: {error}");
+                    return;
+                }
+                next_repeat += period;
+            }
+        });
+
+        KeyRepeat { key, stop, handle }
+    }
+
+    /// Stop repeating, waiting until the last repeat has been sent so it can't arrive after the key is released
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.thread().unpark();
+        let _ = self.handle.join();
+    }
+}
+
+/// Stop all repeats of a key, or of every key if none is given
+pub fn stop(repeats: &mut Vec<KeyRepeat>, key: Option<Key>) {
+    let (stopping, remaining) = repeats.drain(..).partition(|repeat| key.is_none_or(|key| repeat.key == key));
+    *repeats = remaining;
+
+    for repeat in stopping {
+        repeat.stop();
+    }
+}
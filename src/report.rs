@@ -0,0 +1,132 @@
+use crate::diagnostic;
+use crate::notification;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// What happened during a run, collected while it runs and written as an HTML page for reviewing it afterwards
+pub struct Report {
+    path: PathBuf,
+    rows: Vec<Row>
+}
+
+enum Row {
+    /// The start of the setup, the main lines or the teardown
+    Section(&'static str),
+    /// A line that ran, or that failed outside of running its actions, like when checking the condition of a loop
+    Line {
+        line: i32,
+        /// When the line was scheduled to run, in milliseconds after the start of its section
+        time: Option<u64>,
+        /// How long after its scheduled time the line ran
+        lateness: Option<Duration>,
+        errors: Vec<String>
+    }
+}
+
+/// How a run ended
+pub enum Outcome {
+    Finished,
+    Aborted(i32),
+    /// The script stopped itself with `abort` on a line
+    Stopped(i32)
+}
+
+impl Report {
+    pub fn new(path: PathBuf) -> Self {
+        Report { path, rows: Vec::new() }
+    }
+
+    /// Start a new section, so the times after it aren't mixed up with the times before it
+    pub fn section(&mut self, name: &'static str) {
+        self.rows.push(Row::Section(name));
+    }
+
+    pub fn line(&mut self, line: i32, time: u64, lateness: Duration) {
+        self.rows.push(Row::Line { line, time: Some(time), lateness: Some(lateness), errors: Vec::new() });
+    }
+
+    /// Add a failed action to the line it's on, or a new row if the line isn't the one that ran last
+    pub fn failure(&mut self, line: i32, error: &str) {
+        match self.rows.last_mut() {
+            Some(Row::Line { line: last, errors, .. }) if *last == line => errors.push(error.to_string()),
+            _ => self.rows.push(Row::Line { line, time: None, lateness: None, errors: vec![error.to_string()] })
+        }
+    }
+
+    /// Write the report, warning instead of failing the run if it can't be written
+    pub fn write(&self, script_name: &str, outcome: Outcome, duration: Duration) {
+        if let Err(error) = std::fs::write(&self.path, self.render(script_name, outcome, duration)) {
+            log::warn!("Failed to write report to {}: {error}", self.path.display());
+        }
+    }
+
+    fn render(&self, script_name: &str, outcome: Outcome, duration: Duration) -> String {
+        let latenesses: Vec<Duration> = self.rows.iter().filter_map(|row| match row {
+            Row::Line { lateness, .. } => *lateness,
+            Row::Section(_) => None
+        }).collect();
+        let failures: usize = self.rows.iter().map(|row| match row {
+            Row::Line { errors, .. } => errors.len(),
+            Row::Section(_) => 0
+        }).sum();
+        let result = match outcome {
+            Outcome::Aborted(line) => format!("Aborted before line {line}"),
+            Outcome::Stopped(line) => format!("Stopped by the script on line {line}"),
+            Outcome::Finished if failures > 0 => format!("Finished with {failures} failed action(s)"),
+            Outcome::Finished => "Finished".to_string()
+        };
+        let max_lateness = latenesses.iter().max().copied().unwrap_or_default();
+        let average_lateness = latenesses.iter().sum::<Duration>().checked_div(latenesses.len() as u32).unwrap_or_default();
+
+        let mut html = String::new();
+        let _ = writeln!(html, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>TATATA report: {}</title>", escape(script_name));
+        html.push_str("<style>\n\
+            body { font-family: sans-serif; margin: 2em; }\n\
+            table { border-collapse: collapse; }\n\
+            th, td { padding: 0.3em 0.8em; text-align: left; vertical-align: top; border-bottom: 1px solid #ddd; }\n\
+            td.number { text-align: right; font-variant-numeric: tabular-nums; }\n\
+            code { white-space: pre; }\n\
+            tr.section th { padding-top: 1.2em; }\n\
+            tr.failed { background: #fde8e8; }\n\
+            .error { color: #b00020; }\n\
+            </style>\n</head>\n<body>\n");
+        let _ = writeln!(html, "<h1>{}</h1>\n<table>", escape(script_name));
+        let _ = writeln!(html, "<tr><th>Result</th><td>{}</td></tr>", escape(&result));
+        let _ = writeln!(html, "<tr><th>Duration</th><td>{}</td></tr>", notification::format_duration(duration));
+        let _ = writeln!(html, "<tr><th>Lines run</th><td>{}</td></tr>", latenesses.len());
+        let _ = writeln!(html, "<tr><th>Failed actions</th><td>{failures}</td></tr>");
+        let _ = writeln!(html, "<tr><th>Lateness</th><td>{} on average, {} at most</td></tr>\n</table>", milliseconds(average_lateness), milliseconds(max_lateness));
+
+        html.push_str("<h2>Timeline</h2>\n<table>\n<tr><th>Time</th><th>Lateness</th><th>Line</th><th>Source</th></tr>\n");
+        for row in &self.rows {
+            match row {
+                Row::Section(name) => {
+                    let _ = writeln!(html, "<tr class=\"section\"><th colspan=\"4\">{name}</th></tr>");
+                }
+                Row::Line { line, time, lateness, errors } => {
+                    let class = if errors.is_empty() { "" } else { " class=\"failed\"" };
+                    let time = time.map_or(String::new(), |time| format!("{time}ms"));
+                    let lateness = lateness.map_or(String::new(), milliseconds);
+                    let source = diagnostic::source_line(*line).unwrap_or_default().trim();
+                    let _ = write!(html, "<tr{class}><td class=\"number\">{time}</td><td class=\"number\">{lateness}</td><td class=\"number\">{line}</td><td><code>{}</code>", escape(source));
+                    for error in errors {
+                        let _ = write!(html, "<div class=\"error\">{}</div>", escape(error));
+                    }
+                    html.push_str("</td></tr>\n");
+                }
+            }
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+}
+
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Escape text for putting it in HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,80 @@
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+use x11 as platform;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "windows")]
+use win32 as platform;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub fn pixel(_x: i32, _y: i32) -> Result<super::Color, String> {
+        Err("Reading the screen is not supported on this platform".to_string())
+    }
+
+    pub fn bounds() -> Result<(i32, i32, u32, u32), String> {
+        Err("Reading the screen is not supported on this platform".to_string())
+    }
+
+    pub fn capture(_x: i32, _y: i32, _width: u32, _height: u32) -> Result<super::Image, String> {
+        Err("Reading the screen is not supported on this platform".to_string())
+    }
+}
+
+use std::fmt;
+
+/// A color on the screen, without transparency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8
+}
+
+impl Color {
+    /// Parse a color written like `#112233`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let hex = text.strip_prefix('#').ok_or("colors start with #")?;
+        if hex.len() != 6 || !hex.chars().all(|character| character.is_ascii_hexdigit()) {
+            return Err("colors have 6 hexadecimal digits, like #112233".to_string());
+        }
+
+        let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).expect("Hex digits were checked above");
+        Ok(Color { red: channel(0), green: channel(2), blue: channel(4) })
+    }
+
+    /// Whether every channel of the color is at most `tolerance` away from that of another color
+    pub fn is_close_to(self, other: Color, tolerance: u8) -> bool {
+        [(self.red, other.red), (self.green, other.green), (self.blue, other.blue)].iter().all(|(a, b)| a.abs_diff(*b) <= tolerance)
+    }
+}
+
+/// A picture of part of the screen, with the pixels stored row by row from the top left
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Color>
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+/// Get the color of the pixel at a position on the screen, in physical pixels
+pub fn pixel(x: i32, y: i32) -> Result<Color, String> {
+    platform::pixel(x, y)
+}
+
+/// Get the position and size of the whole screen, spanning all monitors, in physical pixels
+pub fn bounds() -> Result<(i32, i32, u32, u32), String> {
+    platform::bounds()
+}
+
+/// Read a region of the screen, in physical pixels
+pub fn capture(x: i32, y: i32, width: u32, height: u32) -> Result<Image, String> {
+    platform::capture(x, y, width, height)
+}
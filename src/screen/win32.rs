@@ -0,0 +1,78 @@
+use super::{Color, Image};
+use std::mem;
+use std::ptr::null_mut;
+use windows_sys::Win32::Graphics::Gdi::{
+    BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC, GetDIBits, GetPixel, ReleaseDC,
+    SelectObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, CAPTUREBLT, CLR_INVALID, DIB_RGB_COLORS, SRCCOPY
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN};
+
+/// Read a pixel from the screen
+pub fn pixel(x: i32, y: i32) -> Result<Color, String> {
+    // SAFETY: The device context of the screen is released right after reading from it
+    let color = unsafe {
+        let context = GetDC(null_mut());
+        if context.is_null() {
+            return Err("Failed to get the device context of the screen".to_string());
+        }
+        let color = GetPixel(context, x, y);
+        ReleaseDC(null_mut(), context);
+        color
+    };
+
+    if color == CLR_INVALID {
+        return Err(format!("{x}, {y} is outside the screen"));
+    }
+
+    // COLORREF values are laid out as 0x00bbggrr
+    Ok(Color { red: color as u8, green: (color >> 8) as u8, blue: (color >> 16) as u8 })
+}
+
+/// Get the area covered by all monitors, which starts left of or above 0, 0 when a monitor is placed there
+pub fn bounds() -> Result<(i32, i32, u32, u32), String> {
+    // SAFETY: GetSystemMetrics has no preconditions
+    let (x, y, width, height) = unsafe {
+        (GetSystemMetrics(SM_XVIRTUALSCREEN), GetSystemMetrics(SM_YVIRTUALSCREEN), GetSystemMetrics(SM_CXVIRTUALSCREEN), GetSystemMetrics(SM_CYVIRTUALSCREEN))
+    };
+    Ok((x, y, width as u32, height as u32))
+}
+
+/// Copy a region of the screen into a bitmap, and read it as 32-bit pixels from top to bottom
+pub fn capture(x: i32, y: i32, width: u32, height: u32) -> Result<Image, String> {
+    // SAFETY: BITMAPINFO only holds numbers, so all zeroes is a valid value
+    let mut info: BITMAPINFO = unsafe { mem::zeroed() };
+    info.bmiHeader.biSize = mem::size_of::<BITMAPINFOHEADER>() as u32;
+    info.bmiHeader.biWidth = width as i32;
+    info.bmiHeader.biHeight = -(height as i32);
+    info.bmiHeader.biPlanes = 1;
+    info.bmiHeader.biBitCount = 32;
+    info.bmiHeader.biCompression = BI_RGB;
+    let mut data = vec![0u8; width as usize * height as usize * 4];
+
+    // SAFETY: Every device context and bitmap is released before returning, and the buffer fits the requested lines
+    let (copied, lines) = unsafe {
+        let screen = GetDC(null_mut());
+        if screen.is_null() {
+            return Err("Failed to get the device context of the screen".to_string());
+        }
+        let memory = CreateCompatibleDC(screen);
+        let bitmap = CreateCompatibleBitmap(screen, width as i32, height as i32);
+        let previous = SelectObject(memory, bitmap);
+        let copied = BitBlt(memory, 0, 0, width as i32, height as i32, screen, x, y, SRCCOPY | CAPTUREBLT);
+        SelectObject(memory, previous);
+        let lines = GetDIBits(memory, bitmap, 0, height, data.as_mut_ptr().cast(), &mut info, DIB_RGB_COLORS);
+
+        DeleteObject(bitmap);
+        DeleteDC(memory);
+        ReleaseDC(null_mut(), screen);
+        (copied, lines)
+    };
+
+    if copied == 0 || lines != height as i32 {
+        return Err(format!("Failed to read {width}x{height} at {x}, {y} from the screen"));
+    }
+
+    // The pixels are laid out as blue, green, red and an unused byte
+    let pixels = data.chunks(4).map(|bytes| Color { red: bytes[2], green: bytes[1], blue: bytes[0] }).collect();
+    Ok(Image { width, height, pixels })
+}
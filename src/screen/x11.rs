@@ -0,0 +1,92 @@
+use super::{Color, Image};
+use std::sync::OnceLock;
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::xproto::{ConnectionExt, ImageFormat, ImageOrder, Screen};
+use x11rb::rust_connection::RustConnection;
+
+/// The connection to the X server, kept open as pixels are often read many times in a row
+static CONNECTION: OnceLock<Result<(RustConnection, usize), String>> = OnceLock::new();
+
+fn connection() -> Result<(&'static RustConnection, &'static Screen), String> {
+    let (connection, screen) = CONNECTION
+        .get_or_init(|| x11rb::connect(None).map_err(|error| format!("Failed to connect to X server: {error}")))
+        .as_ref()
+        .map_err(Clone::clone)?;
+    Ok((connection, &connection.setup().roots[*screen]))
+}
+
+/// Read a pixel from the root window
+///
+/// This only works on X11, as Wayland doesn't let applications read the screen without asking for permission.
+pub fn pixel(x: i32, y: i32) -> Result<Color, String> {
+    let (_, root) = connection()?;
+    if x < 0 || y < 0 || x >= root.width_in_pixels as i32 || y >= root.height_in_pixels as i32 {
+        return Err(format!("{x}, {y} is outside the screen"));
+    }
+
+    Ok(capture(x, y, 1, 1)?.pixels[0])
+}
+
+pub fn bounds() -> Result<(i32, i32, u32, u32), String> {
+    let (_, root) = connection()?;
+    Ok((0, 0, root.width_in_pixels as u32, root.height_in_pixels as u32))
+}
+
+/// Read a region of the root window, which has to be on the screen
+pub fn capture(x: i32, y: i32, width: u32, height: u32) -> Result<Image, String> {
+    let (connection, root) = connection()?;
+    let setup = connection.setup();
+    if x < 0 || y < 0 || x as i64 + width as i64 > root.width_in_pixels as i64 || y as i64 + height as i64 > root.height_in_pixels as i64 {
+        return Err(format!("{width}x{height} at {x}, {y} doesn't fit on the screen"));
+    }
+
+    let image = connection.get_image(ImageFormat::Z_PIXMAP, root.root, x as i16, y as i16, width as u16, height as u16, !0)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to read the screen: {error}"))?;
+
+    // The layout of the pixels depends on the depth and visual of the screen, and every row is padded
+    let format = setup.pixmap_formats.iter().find(|format| format.depth == image.depth);
+    let bits_per_pixel = format.map_or(32, |format| format.bits_per_pixel) as usize;
+    let scanline_pad = format.map_or(32, |format| format.scanline_pad) as usize;
+    let row_length = (width as usize * bits_per_pixel).div_ceil(scanline_pad) * scanline_pad / 8;
+    let visual = root.allowed_depths.iter()
+        .flat_map(|depth| &depth.visuals)
+        .find(|visual| visual.visual_id == image.visual)
+        .ok_or("The screen uses an unknown visual")?;
+    if bits_per_pixel < 8 {
+        return Err("The screen uses an unsupported pixel format".to_string());
+    }
+    if image.data.len() < row_length * height as usize {
+        return Err("The screen returned an incomplete image".to_string());
+    }
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for row in image.data.chunks(row_length).take(height as usize) {
+        for bytes in row.chunks(bits_per_pixel / 8).take(width as usize) {
+            let value = match setup.image_byte_order {
+                ImageOrder::MSB_FIRST => bytes.iter().fold(0u32, |value, &byte| value << 8 | byte as u32),
+                _ => bytes.iter().rev().fold(0u32, |value, &byte| value << 8 | byte as u32)
+            };
+            pixels.push(Color {
+                red: channel(value, visual.red_mask),
+                green: channel(value, visual.green_mask),
+                blue: channel(value, visual.blue_mask)
+            });
+        }
+    }
+
+    Ok(Image { width, height, pixels })
+}
+
+/// Get a color channel from a pixel value, scaled to 8 bits
+fn channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+
+    let shift = mask.trailing_zeros();
+    let max = mask >> shift;
+    (((value & mask) >> shift) * 255 / max) as u8
+}
@@ -0,0 +1,101 @@
+use crate::backend::{self, Backend, BackendConfig, BackendKind};
+use crate::keyboard::Owner;
+use crate::{monitors, mouse};
+use enigo::{Coordinate, Direction, Key};
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait for the mouse to move, when its position is read from the display
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Check that TATATA can connect to the display and send events through the given backend
+///
+/// Prints the result of every check, and returns whether all of them passed.
+pub fn run(backend_kind: BackendKind) -> bool {
+    let mut passed = true;
+
+    // Check the display connection
+    let monitors = monitors::get_monitors();
+    match monitors.first() {
+        Some(primary) => report(Status::Ok, &format!("Connected to display with {} monitor(s), primary is {}x{}", monitors.len(), primary.width, primary.height)),
+        None => {
+            report(Status::Fail, "Failed to find any monitors. Is the display running, and is the display name correct?");
+            passed = false;
+        }
+    }
+
+    // Check the backend
+    let mut backend = match backend::create(&BackendConfig { kind: backend_kind, window: None, owner: Owner::next() }) {
+        Ok(backend) => {
+            report(Status::Ok, &format!("Initialized {backend_kind:?} backend"));
+            backend
+        }
+        Err(error) => {
+            report(Status::Fail, &format!("Failed to initialize {backend_kind:?} backend: {error}"));
+            return false;
+        }
+    };
+
+    // Check that the mouse can be moved, by moving it and reading the position back. The uinput and portal backends
+    // only remember where they moved the mouse to, so the position is read from the display instead, which Wayland
+    // doesn't allow
+    let target = monitors.first().map_or((100, 100), |primary| (primary.x + primary.width as i32 / 2, primary.y + primary.height as i32 / 2));
+    let native = backend_kind == BackendKind::Native;
+    let read_back = |backend: &dyn Backend| if native { backend.location().map_err(|error| error.to_string()) } else { mouse::position() };
+    let original = read_back(&*backend).ok();
+    match backend.move_mouse(target.0, target.1, Coordinate::Abs) {
+        Ok(_) => {
+            // Events from the other backends go through the kernel or the compositor, so they can arrive a bit later
+            if !native {
+                thread::sleep(SETTLE_TIME);
+            }
+            match read_back(&*backend) {
+                Ok(position) if position == target => report(Status::Ok, &format!("Moved mouse to {}, {}", target.0, target.1)),
+                Ok(position) => {
+                    report(Status::Fail, &format!("Moved mouse to {}, {}, but it ended up at {}, {}", target.0, target.1, position.0, position.1));
+                    passed = false;
+                }
+                Err(error) if !native => report(Status::Unknown, &format!("Moved mouse to {}, {}, but where it ended up isn't verifiable: {error}", target.0, target.1)),
+                Err(error) => {
+                    report(Status::Fail, &format!("Moved mouse to {}, {}, but couldn't read it back: {error}", target.0, target.1));
+                    passed = false;
+                }
+            }
+        }
+        Err(error) => {
+            report(Status::Fail, &format!("Failed to move mouse: {error}"));
+            passed = false;
+        }
+    }
+    if let Some(original) = original {
+        let _ = backend.move_mouse(original.0, original.1, Coordinate::Abs);
+    }
+
+    // Check that key events can be sent, using a key that doesn't do anything on its own
+    match backend.key(Key::Shift, Direction::Click) {
+        Ok(_) => report(Status::Ok, "Sent key events"),
+        Err(error) => {
+            report(Status::Fail, &format!("Failed to send key events: {error}"));
+            passed = false;
+        }
+    }
+
+    passed
+}
+
+/// The result of a check
+enum Status {
+    Ok,
+    Fail,
+    /// The check couldn't tell whether it worked, which doesn't count as failing
+    Unknown
+}
+
+fn report(status: Status, message: &str) {
+    let status = match status {
+        Status::Ok => " OK ",
+        Status::Fail => "FAIL",
+        Status::Unknown => " ?? "
+    };
+    println!("[{status}] {message}");
+}
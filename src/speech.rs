@@ -0,0 +1,118 @@
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+/// Whether progress milestones and errors should be spoken
+static ANNOUNCE: AtomicBool = AtomicBool::new(false);
+
+/// The queue of the thread that speaks messages one at a time, so they don't talk over each other
+static SPEAKER: OnceLock<Mutex<Sender<Message>>> = OnceLock::new();
+
+enum Message {
+    Say(String),
+    /// Reply once everything before it has been spoken
+    Flush(Sender<()>)
+}
+
+/// Speak progress milestones and errors passed to `announce()`
+pub fn enable_announcements() {
+    ANNOUNCE.store(true, Ordering::Relaxed);
+}
+
+/// Speak a progress milestone or error, if announcements are enabled
+pub fn announce(text: &str) {
+    if ANNOUNCE.load(Ordering::Relaxed) {
+        say(text);
+    }
+}
+
+/// Speak some text through the text-to-speech engine of the platform, without waiting for it to finish
+pub fn say(text: &str) {
+    send(Message::Say(text.to_string()));
+}
+
+/// Wait until everything that has been queued so far has been spoken
+pub fn wait() {
+    // Nothing has been spoken if the speaker thread was never started
+    if SPEAKER.get().is_none() {
+        return;
+    }
+
+    let (reply, done) = mpsc::channel();
+    send(Message::Flush(reply));
+    let _ = done.recv();
+}
+
+fn send(message: Message) {
+    let speaker = SPEAKER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut warned = false;
+            for message in receiver {
+                match message {
+                    Message::Say(text) => {
+                        if let Err(error) = speak(&text) {
+                            // Only warn once, as the engine is probably missing rather than failing on this text
+                            if !warned {
+                                log::warn!("Failed to speak {text:?}: {error}");
+                                warned = true;
+                            }
+                        }
+                    }
+                    Message::Flush(reply) => {
+                        let _ = reply.send(());
+                    }
+                }
+            }
+        });
+        Mutex::new(sender)
+    });
+
+    let _ = speaker.lock().unwrap_or_else(|error| error.into_inner()).send(message);
+}
+
+/// Speak some text and wait for it to finish
+#[cfg(target_os = "linux")]
+fn speak(text: &str) -> Result<(), String> {
+    // Try Speech Dispatcher first, as it uses the voice the user has configured, then fall back to eSpeak
+    let engines: [(&str, &[&str]); 3] = [("spd-say", &["--wait"]), ("espeak-ng", &[]), ("espeak", &[])];
+    for (program, arguments) in engines {
+        match Command::new(program).args(arguments).arg("--").arg(text).status() {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => return Err(format!("{program} exited with {status}")),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(format!("Failed to run {program}: {error}"))
+        }
+    }
+
+    Err("No text-to-speech engine found, install speech-dispatcher or espeak-ng".to_string())
+}
+
+/// Speak some text and wait for it to finish
+#[cfg(target_os = "macos")]
+fn speak(text: &str) -> Result<(), String> {
+    match Command::new("say").arg(text).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("say exited with {status}")),
+        Err(error) => Err(format!("Failed to run say: {error}"))
+    }
+}
+
+/// Speak some text and wait for it to finish
+#[cfg(target_os = "windows")]
+fn speak(text: &str) -> Result<(), String> {
+    // The text is passed through the environment so it doesn't have to be escaped for PowerShell
+    let script = "Add-Type -AssemblyName System.Speech; (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:TATATA_SPEECH)";
+    match Command::new("powershell").args(["-NoProfile", "-NonInteractive", "-Command", script]).env("TATATA_SPEECH", text).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("PowerShell exited with {status}")),
+        Err(error) => Err(format!("Failed to run PowerShell: {error}"))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn speak(_text: &str) -> Result<(), String> {
+    Err("Text-to-speech is not supported on this platform".to_string())
+}
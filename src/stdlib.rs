@@ -0,0 +1,33 @@
+/// The names of the macros in the standard library, which scripts can use after `@include std`
+pub const NAMES: &[&str] = &["std.copy", "std.cut", "std.paste", "std.select_all", "std.undo", "std.redo", "std.save", "std.find", "std.alt_tab", "std.close_window"];
+
+/// The key that most shortcuts are held with, which is Command on macOS and Control everywhere else
+const PRIMARY: &str = if cfg!(target_os = "macos") { "super" } else { "control" };
+
+/// Get the actions a macro of the standard library stands for on this platform, like `std.copy` pressing Ctrl+C
+pub fn expand(name: &str) -> Option<String> {
+    let actions = match name {
+        "std.copy" => shortcut(&[PRIMARY], "c"),
+        "std.cut" => shortcut(&[PRIMARY], "x"),
+        "std.paste" => shortcut(&[PRIMARY], "v"),
+        "std.select_all" => shortcut(&[PRIMARY], "a"),
+        "std.undo" => shortcut(&[PRIMARY], "z"),
+        "std.redo" if cfg!(windows) => shortcut(&["control"], "y"),
+        "std.redo" => shortcut(&[PRIMARY, "shift"], "z"),
+        "std.save" => shortcut(&[PRIMARY], "s"),
+        "std.find" => shortcut(&[PRIMARY], "f"),
+        "std.alt_tab" if cfg!(target_os = "macos") => shortcut(&["super"], "tab"),
+        "std.alt_tab" => shortcut(&["alt"], "tab"),
+        "std.close_window" if cfg!(target_os = "macos") => shortcut(&["super"], "w"),
+        "std.close_window" => shortcut(&["alt"], "f4"),
+        _ => return None
+    };
+    Some(actions)
+}
+
+/// Write the actions for pressing a key while holding modifiers, releasing everything in reverse order
+fn shortcut(modifiers: &[&str], key: &str) -> String {
+    let presses = modifiers.iter().chain([&key]).map(|key| format!("keydown {key}"));
+    let releases = modifiers.iter().chain([&key]).rev().map(|key| format!("keyup {key}"));
+    presses.chain(releases).collect::<Vec<String>>().join("; ")
+}
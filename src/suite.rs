@@ -0,0 +1,88 @@
+use crate::bundle;
+use crate::diagnostic::ErrorKind;
+use crate::interrupt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+/// Run every script in a directory and its subdirectories, one after the other, and report which of them failed
+///
+/// Every script runs in a separate TATATA process, so a script that fails to parse doesn't stop the rest. The arguments
+/// are passed to TATATA for every script. Returns the exit code of the first script that failed, or 0 if they all passed.
+pub fn run(directory: &Path, arguments: &[String]) -> Result<i32, String> {
+    let mut paths = Vec::new();
+    scan(directory, &mut paths)?;
+    if paths.is_empty() {
+        return Err(format!("No scripts found in {}", directory.display()));
+    }
+
+    let executable = std::env::current_exe().map_err(|error| format!("Couldn't find the TATATA executable: {error}"))?;
+
+    // Ctrl+C also reaches the script that's running, so the suite only has to stop starting new ones
+    interrupt::install();
+
+    let mut failures: Vec<(&PathBuf, i32)> = Vec::new();
+    let mut passed = 0;
+    for path in &paths {
+        log::info!("Running {}", path.display());
+        let start = Instant::now();
+        let status = Command::new(&executable).args(arguments).arg(path).status()
+            .map_err(|error| format!("Failed to run {}: {error}", path.display()))?;
+        let seconds = start.elapsed().as_secs_f64();
+
+        // A script killed by a signal has no exit code, which is counted as being aborted
+        let code = status.code().unwrap_or(ErrorKind::Abort.exit_code());
+        if code == ErrorKind::Abort.exit_code() || interrupt::requested() {
+            log::error!("Aborted while running {}, skipping the remaining scripts", path.display());
+            summarize(passed, &failures);
+            return Ok(ErrorKind::Abort.exit_code());
+        }
+
+        if code == 0 {
+            log::info!("PASS {} ({seconds:.1}s)", path.display());
+            passed += 1;
+        } else {
+            log::error!("FAIL {} ({seconds:.1}s): {}", path.display(), describe(code));
+            failures.push((path, code));
+        }
+    }
+
+    summarize(passed, &failures);
+    Ok(failures.first().map_or(0, |(_, code)| *code))
+}
+
+/// Find all scripts in a directory and its subdirectories, in alphabetical order
+fn scan(directory: &Path, paths: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = std::fs::read_dir(directory).map_err(|error| format!("Couldn't read {}: {error}", directory.display()))?;
+    let mut entries: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            scan(&path, paths)?;
+        } else if path.extension().is_some_and(|extension| extension == "tatata" || extension == bundle::EXTENSION) {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Explain what went wrong from the exit code of a script
+fn describe(code: i32) -> String {
+    match code {
+        1 => "the run couldn't start".to_string(),
+        2 => "invalid command line arguments".to_string(),
+        3 => "the script is invalid".to_string(),
+        4 => "events couldn't be sent".to_string(),
+        5 => "an assertion failed".to_string(),
+        _ => format!("exited with code {code}")
+    }
+}
+
+fn summarize(passed: usize, failures: &[(&PathBuf, i32)]) {
+    log::info!("{passed} passed, {} failed", failures.len());
+    for (path, code) in failures {
+        log::info!("  {}: {}", path.display(), describe(*code));
+    }
+}
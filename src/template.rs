@@ -0,0 +1,52 @@
+use crate::png;
+use crate::screen::{Color, Image};
+use std::fmt;
+use std::path::Path;
+
+/// How far every channel of a pixel on the screen can be from the template, so slightly different rendering still matches
+const TOLERANCE: u8 = 24;
+
+/// An image to look for on the screen, where transparent pixels match anything
+pub struct Template {
+    pub width: u32,
+    pub height: u32,
+    /// The positions and colors of the pixels that aren't transparent
+    pixels: Vec<(u32, u32, Color)>
+}
+
+impl Template {
+    /// Read a template from a PNG file, like a screenshot of a button
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let image = png::load(path)?;
+        let pixels: Vec<(u32, u32, Color)> = image.pixels.iter().enumerate()
+            .filter(|(_, [_, _, _, alpha])| *alpha >= 128)
+            .map(|(index, &[red, green, blue, _])| (index as u32 % image.width, index as u32 / image.width, Color { red, green, blue }))
+            .collect();
+        if pixels.is_empty() {
+            return Err(format!("{} is completely transparent", path.display()));
+        }
+
+        Ok(Template { width: image.width, height: image.height, pixels })
+    }
+
+    /// Find the first place the template is in an image, searching row by row, and get its top left corner
+    pub fn find(&self, image: &Image) -> Option<(u32, u32)> {
+        let close = |a: u8, b: u8| a.abs_diff(b) <= TOLERANCE;
+        let matches = |left: u32, top: u32| self.pixels.iter().all(|&(x, y, color)| {
+            let pixel = image.pixels[((top + y) * image.width + left + x) as usize];
+            close(pixel.red, color.red) && close(pixel.green, color.green) && close(pixel.blue, color.blue)
+        });
+
+        let (Some(last_left), Some(last_top)) = (image.width.checked_sub(self.width), image.height.checked_sub(self.height)) else {
+            return None;
+        };
+        (0..=last_top).flat_map(|top| (0..=last_left).map(move |left| (left, top))).find(|&(left, top)| matches(left, top))
+    }
+}
+
+// The pixels are left out, as there are far too many to show
+impl fmt::Debug for Template {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Template({}x{})", self.width, self.height)
+    }
+}
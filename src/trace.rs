@@ -0,0 +1,208 @@
+use crate::bundle;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+
+/// A point of a recorded mouse path, with its time in milliseconds after the first point
+#[derive(Debug, Clone, Copy)]
+pub struct Point {
+    pub time: u64,
+    pub x: i32,
+    pub y: i32
+}
+
+/// Read a recorded mouse path
+///
+/// CSV files have a `time,x,y` line for every point, optionally after a header line naming the columns in another
+/// order. JSON files have an array of `{"time": ..., "x": ..., "y": ...}` objects, or of `[time, x, y]` arrays. Times are
+/// in milliseconds, and can count from anywhere, like the time the recording was made.
+pub fn load(path: &Path) -> Result<Vec<Point>, String> {
+    let text = bundle::read_to_string(path).map_err(|error| format!("couldn't read {}: {error}", path.display()))?;
+    let samples = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case("csv") => parse_csv(&text)?,
+        Some(extension) if extension.eq_ignore_ascii_case("json") => parse_json(&text)?,
+        _ => return Err(format!("{} isn't a .csv or .json file", path.display()))
+    };
+
+    let Some(&(start, _, _)) = samples.first() else {
+        return Err(format!("{} has no points", path.display()));
+    };
+    let mut points = Vec::with_capacity(samples.len());
+    let mut previous = start;
+    for (index, (time, x, y)) in samples.into_iter().enumerate() {
+        if time < previous {
+            return Err(format!("point {} is at {time}ms, which is before the point before it", index + 1));
+        }
+        previous = time;
+        points.push(Point { time: (time - start).round() as u64, x: x.round() as i32, y: y.round() as i32 });
+    }
+    Ok(points)
+}
+
+fn parse_csv(text: &str) -> Result<Vec<(f64, f64, f64)>, String> {
+    let mut columns = [0, 1, 2];
+    let mut samples = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // A header line says which column is which
+        if index == 0 && fields.iter().any(|field| field.parse::<f64>().is_err()) {
+            for (column, name) in columns.iter_mut().zip(["time", "x", "y"]) {
+                *column = fields.iter().position(|field| field.eq_ignore_ascii_case(name))
+                    .ok_or(format!("the header has no {name:?} column"))?;
+            }
+            continue;
+        }
+
+        let [time, x, y] = columns.map(|column| fields.get(column).and_then(|field| field.parse::<f64>().ok()));
+        match (time, x, y) {
+            (Some(time), Some(x), Some(y)) => samples.push((time, x, y)),
+            _ => return Err(format!("line {} isn't a time, an X and a Y position", index + 1))
+        }
+    }
+    Ok(samples)
+}
+
+fn parse_json(text: &str) -> Result<Vec<(f64, f64, f64)>, String> {
+    let mut parser = JsonParser { characters: text.chars().peekable() };
+    let value = parser.value()?;
+    if parser.next_token().is_some() {
+        return Err("unexpected text after the points".to_string());
+    }
+
+    let Json::Array(points) = value else {
+        return Err("expected an array of points".to_string());
+    };
+    points.into_iter().enumerate().map(|(index, point)| {
+        let fields = match point {
+            Json::Array(values) if values.len() == 3 => [&values[0], &values[1], &values[2]].map(Json::number),
+            Json::Object(fields) => ["time", "x", "y"].map(|name| fields.iter().find(|(key, _)| key == name).and_then(|(_, value)| value.number())),
+            _ => [None; 3]
+        };
+        match fields {
+            [Some(time), Some(x), Some(y)] => Ok((time, x, y)),
+            _ => Err(format!("point {} isn't a time, an X and a Y position", index + 1))
+        }
+    }).collect()
+}
+
+/// The parts of JSON that mouse paths are made of, with everything else kept as `Other`
+enum Json {
+    Number(f64),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+    Other
+}
+
+impl Json {
+    fn number(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    characters: Peekable<Chars<'a>>
+}
+
+impl JsonParser<'_> {
+    /// Skip whitespace, and look at the character after it
+    fn next_token(&mut self) -> Option<char> {
+        while self.characters.next_if(|character| character.is_whitespace()).is_some() {}
+        self.characters.peek().copied()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next_token() {
+            Some(character) if character == expected => {
+                self.characters.next();
+                Ok(())
+            }
+            Some(character) => Err(format!("expected {expected:?}, found {character:?}")),
+            None => Err(format!("expected {expected:?}, found the end of the file"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        match self.next_token() {
+            Some('[') => {
+                self.characters.next();
+                let mut values = Vec::new();
+                if self.next_token() == Some(']') {
+                    self.characters.next();
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    match self.next_token() {
+                        Some(',') => self.characters.next(),
+                        _ => break
+                    };
+                }
+                self.expect(']')?;
+                Ok(Json::Array(values))
+            }
+            Some('{') => {
+                self.characters.next();
+                let mut fields = Vec::new();
+                if self.next_token() == Some('}') {
+                    self.characters.next();
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    if self.next_token() != Some('"') {
+                        return Err("expected the name of a field in quotes".to_string());
+                    }
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    fields.push((key, self.value()?));
+                    match self.next_token() {
+                        Some(',') => self.characters.next(),
+                        _ => break
+                    };
+                }
+                self.expect('}')?;
+                Ok(Json::Object(fields))
+            }
+            Some('"') => self.string().map(|_| Json::Other),
+            Some(character) if character == '-' || character.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(character) = self.characters.next_if(|character| matches!(character, '0'..='9' | '-' | '+' | '.' | 'e' | 'E')) {
+                    number.push(character);
+                }
+                number.parse().map(Json::Number).map_err(|_| format!("invalid number {number:?}"))
+            }
+            Some(character) if character.is_ascii_alphabetic() => {
+                let mut word = String::new();
+                while let Some(character) = self.characters.next_if(char::is_ascii_alphabetic) {
+                    word.push(character);
+                }
+                match word.as_str() {
+                    "true" | "false" | "null" => Ok(Json::Other),
+                    _ => Err(format!("unexpected {word:?}"))
+                }
+            }
+            Some(character) => Err(format!("unexpected {character:?}")),
+            None => Err("expected a value, found the end of the file".to_string())
+        }
+    }
+
+    /// Read a string in quotes, where escaped characters are kept as the character after the backslash
+    fn string(&mut self) -> Result<String, String> {
+        self.characters.next();
+        let mut string = String::new();
+        loop {
+            match self.characters.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.extend(self.characters.next()),
+                Some(character) => string.push(character),
+                None => return Err("a string is never closed".to_string())
+            }
+        }
+    }
+}
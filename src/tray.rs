@@ -0,0 +1,32 @@
+#[cfg(target_os = "linux")]
+mod sni;
+#[cfg(target_os = "linux")]
+use sni as platform;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "windows")]
+use win32 as platform;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub fn start(_script_name: String) -> Result<(), String> {
+        Err("The tray icon is not supported on this platform".to_string())
+    }
+
+    pub fn stop() {}
+}
+
+/// How often the tray icon checks whether the state of the run has changed
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+const UPDATE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Show a tray icon with the progress of the run, and menu items for pausing, resuming and aborting it
+pub fn start(script_name: &str) -> Result<(), String> {
+    platform::start(script_name.to_string())
+}
+
+/// Remove the tray icon, so it doesn't linger after exiting
+pub fn stop() {
+    platform::stop();
+}
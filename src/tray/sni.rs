@@ -0,0 +1,184 @@
+use crate::dbus::{Connection, MethodCall, Value};
+use crate::{control, interrupt};
+use std::thread;
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+const MENU_INTERFACE: &str = "com.canonical.dbusmenu";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+// The IDs of the menu items, where 0 is the root of the menu
+const MENU_STATUS: i32 = 1;
+const MENU_SEPARATOR: i32 = 2;
+const MENU_PAUSE: i32 = 3;
+const MENU_ABORT: i32 = 4;
+
+/// Register a StatusNotifierItem with the tray of the desktop, and serve it on a separate thread
+pub fn start(script_name: String) -> Result<(), String> {
+    let mut connection = Connection::session()?;
+
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    connection.call("org.freedesktop.DBus", "/org/freedesktop/DBus", "org.freedesktop.DBus", "RequestName", &[Value::String(name.clone()), Value::Uint32(0)])?;
+    connection.call("org.kde.StatusNotifierWatcher", "/StatusNotifierWatcher", "org.kde.StatusNotifierWatcher", "RegisterStatusNotifierItem", &[Value::String(name)])
+        .map_err(|error| format!("No system tray is running: {error}"))?;
+
+    // The tray starts asking for properties right away, and those calls are queued until the thread handles them
+    thread::spawn(move || serve(connection, &script_name));
+    Ok(())
+}
+
+/// The item goes away with the connection when the process exits
+pub fn stop() {}
+
+fn serve(mut connection: Connection, script_name: &str) {
+    let mut revision = 1;
+    let mut last_state = (control::progress(), control::cue(), control::is_paused(), control::held());
+
+    loop {
+        match connection.next_method_call(super::UPDATE_INTERVAL) {
+            Ok(Some(call)) => {
+                let result = match handle(&call, script_name, revision) {
+                    Some(body) => connection.reply(&call, &body),
+                    None => connection.reply_error(&call, "org.freedesktop.DBus.Error.UnknownMethod", &format!("Unknown method {}.{}", call.interface, call.member))
+                };
+                if result.is_err() {
+                    return;
+                }
+            }
+            Ok(None) => {}
+            Err(_) => return
+        }
+
+        // Tell the tray to fetch the tooltip and menu again when they have changed
+        let state = (control::progress(), control::cue(), control::is_paused(), control::held());
+        if state != last_state {
+            revision += 1;
+            let results = [
+                connection.emit(ITEM_PATH, ITEM_INTERFACE, "NewToolTip", &[]),
+                connection.emit(ITEM_PATH, ITEM_INTERFACE, "NewIcon", &[]),
+                connection.emit(MENU_PATH, MENU_INTERFACE, "LayoutUpdated", &[Value::Uint32(revision), Value::Int32(0)])
+            ];
+            if results.iter().any(Result::is_err) {
+                return;
+            }
+            last_state = state;
+        }
+    }
+}
+
+/// Handle a method call, returning the values to reply with, or nothing if the method doesn't exist
+fn handle(call: &MethodCall, script_name: &str, revision: u32) -> Option<Vec<Value>> {
+    let argument = |index: usize| call.body.get(index);
+
+    let body = match (call.path.as_str(), call.interface.as_str(), call.member.as_str()) {
+        (_, "org.freedesktop.DBus.Peer", "Ping") => vec![],
+        (path, PROPERTIES_INTERFACE, "Get") => {
+            let name = argument(1)?.as_str()?;
+            let value = properties(path, script_name).into_iter().find(|(key, _)| *key == name)?.1;
+            vec![Value::Variant(Box::new(value))]
+        }
+        (path, PROPERTIES_INTERFACE, "GetAll") => vec![Value::dict(properties(path, script_name))],
+        (ITEM_PATH, ITEM_INTERFACE, "Activate" | "SecondaryActivate" | "ContextMenu" | "Scroll") => vec![],
+        (MENU_PATH, MENU_INTERFACE, "GetLayout") => {
+            let items = menu_items(script_name).into_iter().map(|(id, properties)| {
+                Value::Variant(Box::new(Value::Struct(vec![Value::Int32(id), Value::dict(properties), Value::Array("v".to_string(), vec![])])))
+            }).collect();
+            let root = Value::Struct(vec![
+                Value::Int32(0),
+                Value::dict(vec![("children-display", Value::String("submenu".to_string()))]),
+                Value::Array("v".to_string(), items)
+            ]);
+            vec![Value::Uint32(revision), root]
+        }
+        (MENU_PATH, MENU_INTERFACE, "GetGroupProperties") => {
+            let ids = argument(0)?.as_slice()?;
+            let items = menu_items(script_name).into_iter()
+                .filter(|(id, _)| ids.is_empty() || ids.contains(&Value::Int32(*id)))
+                .map(|(id, properties)| Value::Struct(vec![Value::Int32(id), Value::dict(properties)]))
+                .collect();
+            vec![Value::Array("(ia{sv})".to_string(), items)]
+        }
+        (MENU_PATH, MENU_INTERFACE, "GetProperty") => {
+            let (id, name) = (argument(0)?.as_i32()?, argument(1)?.as_str()?);
+            let (_, properties) = menu_items(script_name).into_iter().find(|(item_id, _)| *item_id == id)?;
+            let value = properties.into_iter().find(|(key, _)| *key == name)?.1;
+            vec![Value::Variant(Box::new(value))]
+        }
+        (MENU_PATH, MENU_INTERFACE, "Event") => {
+            menu_event(argument(0)?.as_i32()?, argument(1)?.as_str()?);
+            vec![]
+        }
+        (MENU_PATH, MENU_INTERFACE, "EventGroup") => {
+            for event in argument(0)?.as_slice()? {
+                let fields = event.as_slice()?;
+                menu_event(fields.first()?.as_i32()?, fields.get(1)?.as_str()?);
+            }
+            vec![Value::Array("i".to_string(), vec![])]
+        }
+        (MENU_PATH, MENU_INTERFACE, "AboutToShow") => vec![Value::Bool(false)],
+        (MENU_PATH, MENU_INTERFACE, "AboutToShowGroup") => vec![Value::Array("i".to_string(), vec![]), Value::Array("i".to_string(), vec![])],
+        _ => return None
+    };
+
+    Some(body)
+}
+
+/// Get the properties of the item or the menu
+fn properties(path: &str, script_name: &str) -> Vec<(&'static str, Value)> {
+    match path {
+        ITEM_PATH => {
+            let icon = if control::is_paused() { "media-playback-pause" } else { "media-playback-start" };
+            let tooltip = Value::Struct(vec![
+                Value::String(String::new()),
+                Value::Array("(iiay)".to_string(), vec![]),
+                Value::String("TATATA".to_string()),
+                Value::String(control::describe(script_name))
+            ]);
+
+            vec![
+                ("Category", Value::String("ApplicationStatus".to_string())),
+                ("Id", Value::String("tatata".to_string())),
+                ("Title", Value::String("TATATA".to_string())),
+                ("Status", Value::String("Active".to_string())),
+                ("IconName", Value::String(icon.to_string())),
+                ("ToolTip", tooltip),
+                ("ItemIsMenu", Value::Bool(true)),
+                ("Menu", Value::ObjectPath(MENU_PATH.to_string()))
+            ]
+        }
+        MENU_PATH => vec![
+            ("Version", Value::Uint32(3)),
+            ("TextDirection", Value::String("ltr".to_string())),
+            ("Status", Value::String("normal".to_string())),
+            ("IconThemePath", Value::Array("s".to_string(), vec![]))
+        ],
+        _ => vec![]
+    }
+}
+
+/// Get the ID and properties of every item in the menu
+fn menu_items(script_name: &str) -> Vec<(i32, Vec<(&'static str, Value)>)> {
+    let pause_label = if control::is_paused() { "Resume" } else { "Pause" };
+
+    vec![
+        (MENU_STATUS, vec![("label", Value::String(control::describe(script_name))), ("enabled", Value::Bool(false))]),
+        (MENU_SEPARATOR, vec![("type", Value::String("separator".to_string()))]),
+        (MENU_PAUSE, vec![("label", Value::String(pause_label.to_string()))]),
+        (MENU_ABORT, vec![("label", Value::String("Abort".to_string()))])
+    ]
+}
+
+fn menu_event(id: i32, event: &str) {
+    if event != "clicked" {
+        return;
+    }
+
+    match id {
+        MENU_PAUSE => control::toggle_pause(),
+        MENU_ABORT => {
+            interrupt::request();
+        }
+        _ => {}
+    }
+}
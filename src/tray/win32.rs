@@ -0,0 +1,156 @@
+use crate::{control, interrupt};
+use std::ffi::c_void;
+use std::ptr::{null, null_mut};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{mpsc, OnceLock};
+use std::thread;
+use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, POINT, WPARAM};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::UI::Shell::{Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu, DispatchMessageW, GetCursorPos, GetMessageW,
+    LoadIconW, PostMessageW, RegisterClassW, SetForegroundWindow, SetTimer, TrackPopupMenu, TranslateMessage, HWND_MESSAGE,
+    IDI_APPLICATION, MF_GRAYED, MF_SEPARATOR, MF_STRING, MSG, TPM_RETURNCMD, TPM_RIGHTBUTTON, WM_APP, WM_LBUTTONUP, WM_NULL,
+    WM_RBUTTONUP, WM_TIMER, WNDCLASSW
+};
+
+/// The message the icon sends to the window when it's clicked
+const WM_TRAY: u32 = WM_APP + 1;
+
+// The IDs of the menu items
+const MENU_PAUSE: usize = 1;
+const MENU_ABORT: usize = 2;
+
+/// The hidden window that owns the icon
+static WINDOW: AtomicPtr<c_void> = AtomicPtr::new(null_mut());
+
+static SCRIPT_NAME: OnceLock<String> = OnceLock::new();
+
+/// Add a notification area icon, and handle its messages on a separate thread
+pub fn start(script_name: String) -> Result<(), String> {
+    let _ = SCRIPT_NAME.set(script_name);
+
+    // The window has to be created on the thread that handles its messages, so wait for that thread to report back
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // SAFETY: All pointers passed to the window functions are valid, and the strings are null terminated
+        unsafe {
+            let instance = GetModuleHandleW(null());
+            let class_name = wide("TatataTray");
+            let class = WNDCLASSW {
+                lpfnWndProc: Some(window_procedure),
+                hInstance: instance,
+                lpszClassName: class_name.as_ptr(),
+                ..std::mem::zeroed()
+            };
+            RegisterClassW(&class);
+
+            let window = CreateWindowExW(0, class_name.as_ptr(), class_name.as_ptr(), 0, 0, 0, 0, 0, HWND_MESSAGE, null_mut(), instance, null());
+            if window.is_null() {
+                let _ = sender.send(Err("Failed to create a window for the tray icon".to_string()));
+                return;
+            }
+
+            let mut data = icon_data(window);
+            data.uFlags |= NIF_ICON | NIF_MESSAGE;
+            data.uCallbackMessage = WM_TRAY;
+            data.hIcon = LoadIconW(null_mut(), IDI_APPLICATION);
+            if Shell_NotifyIconW(NIM_ADD, &data) == 0 {
+                let _ = sender.send(Err("Failed to add the tray icon".to_string()));
+                return;
+            }
+
+            WINDOW.store(window, Ordering::Relaxed);
+            SetTimer(window, 1, super::UPDATE_INTERVAL.as_millis() as u32, None);
+            let _ = sender.send(Ok(()));
+
+            let mut message: MSG = std::mem::zeroed();
+            while GetMessageW(&mut message, null_mut(), 0, 0) > 0 {
+                TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        }
+    });
+
+    receiver.recv().unwrap_or_else(|_| Err("The tray icon thread stopped unexpectedly".to_string()))
+}
+
+/// Remove the icon, as Windows leaves it in the notification area until it's hovered otherwise
+pub fn stop() {
+    let window = WINDOW.swap(null_mut(), Ordering::Relaxed);
+    if !window.is_null() {
+        // SAFETY: The data is valid for the duration of the call
+        unsafe { Shell_NotifyIconW(NIM_DELETE, &icon_data(window)) };
+    }
+}
+
+/// Get the data identifying the icon, along with its current tooltip
+fn icon_data(window: HWND) -> NOTIFYICONDATAW {
+    // SAFETY: NOTIFYICONDATAW is a plain C struct, for which all zeroes is a valid value
+    let mut data: NOTIFYICONDATAW = unsafe { std::mem::zeroed() };
+    data.cbSize = size_of::<NOTIFYICONDATAW>() as u32;
+    data.hWnd = window;
+    data.uID = 1;
+    data.uFlags = NIF_TIP;
+
+    // Leave room for the null terminator
+    let tooltip = wide(&control::describe(SCRIPT_NAME.get().map_or("", String::as_str)));
+    let length = tooltip.len().min(data.szTip.len()) - 1;
+    data.szTip[..length].copy_from_slice(&tooltip[..length]);
+
+    data
+}
+
+unsafe extern "system" fn window_procedure(window: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match message {
+        WM_TIMER => {
+            // SAFETY: The data is valid for the duration of the call
+            unsafe { Shell_NotifyIconW(NIM_MODIFY, &icon_data(window)) };
+            0
+        }
+        WM_TRAY if matches!(lparam as u32, WM_LBUTTONUP | WM_RBUTTONUP) => {
+            // SAFETY: The window is valid, as it's the one receiving this message
+            unsafe { show_menu(window) };
+            0
+        }
+        // SAFETY: The arguments are passed on unchanged from Windows
+        _ => unsafe { DefWindowProcW(window, message, wparam, lparam) }
+    }
+}
+
+/// Show the menu at the cursor, and handle the item that was picked
+unsafe fn show_menu(window: HWND) {
+    let status = wide(&control::describe(SCRIPT_NAME.get().map_or("", String::as_str)));
+    let pause = wide(if control::is_paused() { "Resume" } else { "Pause" });
+    let abort = wide("Abort");
+
+    // SAFETY: The menu and window are valid, and the strings outlive the menu
+    unsafe {
+        let menu = CreatePopupMenu();
+        AppendMenuW(menu, MF_STRING | MF_GRAYED, 0, status.as_ptr());
+        AppendMenuW(menu, MF_SEPARATOR, 0, null());
+        AppendMenuW(menu, MF_STRING, MENU_PAUSE, pause.as_ptr());
+        AppendMenuW(menu, MF_STRING, MENU_ABORT, abort.as_ptr());
+
+        // The window has to be in the foreground for the menu to close when clicking elsewhere
+        let mut cursor = POINT { x: 0, y: 0 };
+        GetCursorPos(&mut cursor);
+        SetForegroundWindow(window);
+        let picked = TrackPopupMenu(menu, TPM_RETURNCMD | TPM_RIGHTBUTTON, cursor.x, cursor.y, 0, window, null());
+        PostMessageW(window, WM_NULL, 0, 0);
+        DestroyMenu(menu);
+
+        match picked as usize {
+            MENU_PAUSE => control::toggle_pause(),
+            MENU_ABORT => {
+                interrupt::request();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Convert a string to a null terminated UTF-16 string
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(std::iter::once(0)).collect()
+}
@@ -0,0 +1,60 @@
+use crate::random;
+
+/// How often a letter is mistyped, as one in this many
+const TYPO_CHANCE: usize = 40;
+
+/// The rows of a QWERTY keyboard, which a typo picks a key next to the right one from
+const ROWS: [&str; 3] = ["qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+/// Something typed by human typing, at a time in milliseconds from the start of the text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Keystroke {
+    Character(char),
+    Backspace
+}
+
+/// Plan how a person would type the text, about the delay apart on average
+///
+/// Letters come in bursts within a word, with a longer pause before the next word, and now and then a letter is mistyped
+/// as a key next to it and corrected with backspace after a moment.
+pub fn human(text: &str, delay: u64) -> Vec<(u64, Keystroke)> {
+    let mut keystrokes = Vec::new();
+    let mut time = 0;
+    let mut previous: Option<char> = None;
+    for character in text.chars() {
+        if previous.is_some() {
+            time += gap(delay, previous.is_some_and(|previous| !previous.is_alphanumeric()) && character.is_alphanumeric());
+        }
+
+        // Only letters inside a word are mistyped, as the start of a word is typed with more care
+        if previous.is_some_and(char::is_alphanumeric) && random::below(TYPO_CHANCE) == 0 {
+            if let Some(typo) = neighbour(character) {
+                keystrokes.push((time, Keystroke::Character(typo)));
+                // Noticing the typo takes a moment, and fixing it is quicker than typing
+                time += gap(delay * 3, false);
+                keystrokes.push((time, Keystroke::Backspace));
+                time += gap(delay, false);
+            }
+        }
+
+        keystrokes.push((time, Keystroke::Character(character)));
+        previous = Some(character);
+    }
+    keystrokes
+}
+
+/// A time between keystrokes varying around the delay, from half to one and a half times it, and longer between words
+fn gap(delay: u64, new_word: bool) -> u64 {
+    let varied = delay / 2 + random::below(delay as usize + 1) as u64;
+    if new_word { varied + delay + random::below(delay as usize * 2 + 1) as u64 } else { varied }
+}
+
+/// A key next to the letter on the same row, in the same case, or nothing when it isn't a letter
+fn neighbour(character: char) -> Option<char> {
+    let lowercase = character.to_ascii_lowercase();
+    let row = ROWS.iter().find(|row| row.contains(lowercase))?.as_bytes();
+    let index = row.iter().position(|&key| key as char == lowercase)?;
+    let neighbours: Vec<u8> = [index.checked_sub(1), Some(index + 1)].into_iter().flatten().filter_map(|index| row.get(index).copied()).collect();
+    let typo = neighbours[random::below(neighbours.len())] as char;
+    Some(if character.is_ascii_uppercase() { typo.to_ascii_uppercase() } else { typo })
+}
@@ -0,0 +1,126 @@
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+use x11 as platform;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "windows")]
+use win32 as platform;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub fn list() -> Result<Vec<super::Window>, String> {
+        Err("Working with windows is not supported on this platform".to_string())
+    }
+
+    pub fn active() -> Result<Option<u64>, String> {
+        Err("Working with windows is not supported on this platform".to_string())
+    }
+
+    pub fn size(_window: &super::Window) -> Result<(u32, u32), String> {
+        Err("Working with windows is not supported on this platform".to_string())
+    }
+
+    pub fn move_window(_window: &super::Window, _x: i32, _y: i32) -> Result<(), String> {
+        Err("Working with windows is not supported on this platform".to_string())
+    }
+
+    pub fn resize_window(_window: &super::Window, _width: u32, _height: u32) -> Result<(), String> {
+        Err("Working with windows is not supported on this platform".to_string())
+    }
+
+    pub fn command(_window: &super::Window, _command: super::Command) -> Result<(), String> {
+        Err("Working with windows is not supported on this platform".to_string())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+pub use platform::Target;
+#[cfg(target_os = "linux")]
+pub use x11::keysym;
+#[cfg(target_os = "windows")]
+pub use win32::virtual_key;
+
+use std::fmt;
+use std::sync::Mutex;
+
+/// The cursor position in the target window, shared so every thread's target continues from the same place
+static POSITION: Mutex<(i32, i32)> = Mutex::new((0, 0));
+
+/// A top-level window
+#[derive(Debug, Clone)]
+pub struct Window {
+    /// The platform-specific window handle
+    pub id: u64,
+    pub title: String
+}
+
+/// Something the window manager can be asked to do with a window
+#[derive(Debug, Clone, Copy)]
+pub enum Command {
+    Maximize,
+    Minimize,
+    Close
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(match self {
+            Command::Maximize => "maximize",
+            Command::Minimize => "minimize",
+            Command::Close => "close"
+        })
+    }
+}
+
+/// Get all visible top-level windows
+pub fn list() -> Result<Vec<Window>, String> {
+    platform::list()
+}
+
+/// Find the first window whose title contains the given text, ignoring case
+pub fn find(title: &str) -> Result<Option<Window>, String> {
+    let title = title.to_lowercase();
+    Ok(list()?.into_iter().find(|window| window.title.to_lowercase().contains(&title)))
+}
+
+/// Get the window that has the keyboard focus, if it's one of the windows `list` returns
+pub fn active() -> Result<Option<Window>, String> {
+    let Some(id) = platform::active()? else {
+        return Ok(None);
+    };
+    Ok(list()?.into_iter().find(|window| window.id == id))
+}
+
+/// Get the width and height of the part of a window that mouse positions are relative to when targeting it
+pub fn size(window: &Window) -> Result<(u32, u32), String> {
+    platform::size(window)
+}
+
+/// Move a window so its top-left corner is at a position on the screen
+pub fn move_window(window: &Window, x: i32, y: i32) -> Result<(), String> {
+    platform::move_window(window, x, y)
+}
+
+/// Change the width and height of a window
+pub fn resize_window(window: &Window, width: u32, height: u32) -> Result<(), String> {
+    platform::resize_window(window, width, height)
+}
+
+/// Maximize, minimize or close a window
+///
+/// Closing a window asks the application to close it, so it can still ask to save unsaved changes.
+pub fn command(window: &Window, command: Command) -> Result<(), String> {
+    platform::command(window, command)
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn position() -> (i32, i32) {
+    *POSITION.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn set_position(position: (i32, i32)) {
+    *POSITION.lock().unwrap_or_else(|error| error.into_inner()) = position;
+}
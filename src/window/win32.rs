@@ -0,0 +1,375 @@
+use super::{Command, Window};
+use crate::keyboard::Owner;
+use enigo::{Axis, Button, Coordinate, Direction, InputError, InputResult, Key, Keyboard, Mouse};
+use windows_sys::core::BOOL;
+use windows_sys::Win32::Foundation::{HWND, LPARAM, POINT, RECT, WPARAM};
+use windows_sys::Win32::Graphics::Gdi::{ClientToScreen, MapWindowPoints};
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{MapVirtualKeyW, VkKeyScanW, MAPVK_VK_TO_VSC};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    ChildWindowFromPointEx, EnumWindows, GetClientRect, GetForegroundWindow, GetGUIThreadInfo, GetWindowTextW,
+    GetWindowThreadProcessId, IsWindowVisible, PostMessageW, SetWindowPos, ShowWindowAsync, CWP_SKIPINVISIBLE,
+    GUITHREADINFO, MK_LBUTTON, MK_MBUTTON, MK_RBUTTON, MK_XBUTTON1, MK_XBUTTON2, SET_WINDOW_POS_FLAGS, SWP_NOACTIVATE,
+    SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SW_MAXIMIZE, SW_MINIMIZE, WM_CHAR, WM_CLOSE, WM_KEYDOWN, WM_KEYUP,
+    WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL,
+    WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP
+};
+
+/// The scroll distance of one wheel notch
+const WHEEL_DELTA: i32 = 120;
+
+unsafe extern "system" fn collect_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    // SAFETY: lparam is the pointer to the vector passed to EnumWindows in list()
+    let windows = unsafe { &mut *(lparam as *mut Vec<Window>) };
+
+    let mut buffer = [0u16; 512];
+    // SAFETY: hwnd is a valid window handle passed by EnumWindows, and the buffer length is correct
+    let length = unsafe {
+        if IsWindowVisible(hwnd) == 0 {
+            return 1;
+        }
+        GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32)
+    };
+
+    if length > 0 {
+        windows.push(Window { id: hwnd as u64, title: String::from_utf16_lossy(&buffer[..length as usize]) });
+    }
+
+    // Continue enumerating
+    1
+}
+
+pub fn list() -> Result<Vec<Window>, String> {
+    let mut windows: Vec<Window> = Vec::new();
+
+    // SAFETY: The callback only uses lparam as the vector, which outlives the call
+    unsafe {
+        EnumWindows(Some(collect_window), &mut windows as *mut Vec<Window> as LPARAM);
+    }
+
+    Ok(windows)
+}
+
+/// Get the window in the foreground, which is the one that gets keyboard input
+pub fn active() -> Result<Option<u64>, String> {
+    // SAFETY: GetForegroundWindow has no safety requirements
+    let hwnd = unsafe { GetForegroundWindow() };
+    Ok((!hwnd.is_null()).then_some(hwnd as u64))
+}
+
+/// Get the size of the client area of a window, which is the part without its title bar and borders
+pub fn size(window: &Window) -> Result<(u32, u32), String> {
+    let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+    // SAFETY: GetClientRect fails without side effects if the window handle is no longer valid
+    if unsafe { GetClientRect(window.id as HWND, &mut rect) } == 0 {
+        return Err(format!("Failed to get the size of {:?}", window.title));
+    }
+
+    Ok(((rect.right - rect.left) as u32, (rect.bottom - rect.top) as u32))
+}
+
+/// Move a window, where the position is that of its outer edge, including its title bar and borders
+pub fn move_window(window: &Window, x: i32, y: i32) -> Result<(), String> {
+    set_window_pos(window, (x, y, 0, 0), SWP_NOSIZE)
+}
+
+/// Resize a window, where the size includes its title bar and borders
+pub fn resize_window(window: &Window, width: u32, height: u32) -> Result<(), String> {
+    set_window_pos(window, (0, 0, width as i32, height as i32), SWP_NOMOVE)
+}
+
+fn set_window_pos(window: &Window, (x, y, width, height): (i32, i32, i32, i32), flags: SET_WINDOW_POS_FLAGS) -> Result<(), String> {
+    // SAFETY: SetWindowPos fails without side effects if the window handle is no longer valid
+    if unsafe { SetWindowPos(window.id as HWND, std::ptr::null_mut(), x, y, width, height, flags | SWP_NOZORDER | SWP_NOACTIVATE) } == 0 {
+        return Err(format!("Failed to change the geometry of {:?}", window.title));
+    }
+
+    Ok(())
+}
+
+/// Maximize, minimize or close a window, without waiting for its application to respond
+pub fn command(window: &Window, command: Command) -> Result<(), String> {
+    let hwnd = window.id as HWND;
+
+    // SAFETY: ShowWindowAsync and PostMessageW fail without side effects if the window handle is no longer valid
+    let result = unsafe {
+        match command {
+            Command::Maximize => ShowWindowAsync(hwnd, SW_MAXIMIZE),
+            Command::Minimize => ShowWindowAsync(hwnd, SW_MINIMIZE),
+            Command::Close => PostMessageW(hwnd, WM_CLOSE, 0, 0)
+        }
+    };
+    if result == 0 {
+        return Err(format!("Failed to {command} {:?}", window.title));
+    }
+
+    Ok(())
+}
+
+/// Sends events directly to a window with PostMessage, without moving the real cursor or changing focus
+///
+/// Mouse coordinates are relative to the client area of the window. Note that some applications ignore events sent this
+/// way.
+pub struct Target {
+    window: HWND,
+    position: (i32, i32),
+    buttons: usize,
+    owner: Owner
+}
+
+impl Target {
+    pub fn new(window: &Window, owner: Owner) -> Result<Self, String> {
+        Ok(Target {
+            window: window.id as HWND,
+            position: super::position(),
+            buttons: 0,
+            owner
+        })
+    }
+
+    /// Get the control that has keyboard focus in the target window
+    fn focused_control(&self) -> HWND {
+        // SAFETY: GUITHREADINFO is a plain C struct for which all zeroes is a valid value, and cbSize is set as required
+        unsafe {
+            let thread = GetWindowThreadProcessId(self.window, std::ptr::null_mut());
+            let mut info: GUITHREADINFO = std::mem::zeroed();
+            info.cbSize = size_of::<GUITHREADINFO>() as u32;
+
+            if GetGUIThreadInfo(thread, &mut info) != 0 && !info.hwndFocus.is_null() {
+                info.hwndFocus
+            } else {
+                self.window
+            }
+        }
+    }
+
+    /// Get the control at the cursor position, and the cursor position relative to it
+    fn control_at_cursor(&self) -> (HWND, POINT) {
+        let mut point = POINT { x: self.position.0, y: self.position.1 };
+
+        // SAFETY: The window handle is valid, and the point is a valid pointer for the duration of the calls
+        unsafe {
+            let child = ChildWindowFromPointEx(self.window, point, CWP_SKIPINVISIBLE);
+            if child.is_null() || child == self.window {
+                return (self.window, point);
+            }
+
+            MapWindowPoints(self.window, child, &mut point, 1);
+            (child, point)
+        }
+    }
+
+    fn post(&self, window: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) -> InputResult<()> {
+        // SAFETY: PostMessageW doesn't dereference any of the arguments
+        if unsafe { PostMessageW(window, message, wparam, lparam) } == 0 {
+            Err(InputError::Simulate("failed to post message to window"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn post_key(&self, virtual_key: u16, press: bool) -> InputResult<()> {
+        // Build the lParam the same way the system does: repeat count, scan code, and transition state
+        // SAFETY: MapVirtualKeyW has no safety requirements
+        let scan_code = unsafe { MapVirtualKeyW(virtual_key as u32, MAPVK_VK_TO_VSC) } as isize;
+        let lparam = if press { 1 | (scan_code << 16) } else { 1 | (scan_code << 16) | (0b11 << 30) };
+
+        // Alt is sent as a system key
+        let message = match (virtual_key == VK_MENU, press) {
+            (true, true) => WM_SYSKEYDOWN,
+            (true, false) => WM_SYSKEYUP,
+            (false, true) => WM_KEYDOWN,
+            (false, false) => WM_KEYUP
+        };
+
+        self.post(self.focused_control(), message, virtual_key as WPARAM, lparam)
+    }
+
+    fn post_mouse(&self, message: u32, extra_wparam: usize) -> InputResult<()> {
+        let (control, point) = self.control_at_cursor();
+        let lparam = ((point.y as u16 as isize) << 16) | point.x as u16 as isize;
+        self.post(control, message, self.buttons | extra_wparam, lparam)
+    }
+}
+
+const VK_MENU: u16 = 0x12;
+
+fn directions(direction: Direction) -> &'static [bool] {
+    match direction {
+        Direction::Press => &[true],
+        Direction::Release => &[false],
+        Direction::Click => &[true, false]
+    }
+}
+
+impl Keyboard for Target {
+    fn fast_text(&mut self, text: &str) -> InputResult<Option<()>> {
+        let control = self.focused_control();
+        for unit in text.encode_utf16() {
+            self.post(control, WM_CHAR, unit as WPARAM, 1)?;
+        }
+
+        Ok(Some(()))
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> InputResult<()> {
+        crate::keyboard::with_shift(self.owner, key, crate::keyboard::needs_shift(key), direction, |key, direction| {
+            let virtual_key = virtual_key(key).ok_or(InputError::InvalidInput("key is not supported when targeting a window"))?;
+            for &press in directions(direction) {
+                self.post_key(virtual_key, press)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn raw(&mut self, keycode: u16, direction: Direction) -> InputResult<()> {
+        for &press in directions(direction) {
+            self.post_key(keycode, press)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Mouse for Target {
+    fn button(&mut self, button: Button, direction: Direction) -> InputResult<()> {
+        let (down, up, mask, extra_wparam) = match button {
+            Button::Left => (WM_LBUTTONDOWN, WM_LBUTTONUP, MK_LBUTTON, 0),
+            Button::Right => (WM_RBUTTONDOWN, WM_RBUTTONUP, MK_RBUTTON, 0),
+            Button::Middle => (WM_MBUTTONDOWN, WM_MBUTTONUP, MK_MBUTTON, 0),
+            Button::Back => (WM_XBUTTONDOWN, WM_XBUTTONUP, MK_XBUTTON1, 1 << 16),
+            Button::Forward => (WM_XBUTTONDOWN, WM_XBUTTONUP, MK_XBUTTON2, 2 << 16),
+            // Scroll buttons only do something when pressed
+            Button::ScrollUp if direction != Direction::Release => return self.scroll(-1, Axis::Vertical),
+            Button::ScrollDown if direction != Direction::Release => return self.scroll(1, Axis::Vertical),
+            Button::ScrollLeft if direction != Direction::Release => return self.scroll(-1, Axis::Horizontal),
+            Button::ScrollRight if direction != Direction::Release => return self.scroll(1, Axis::Horizontal),
+            Button::ScrollUp | Button::ScrollDown | Button::ScrollLeft | Button::ScrollRight => return Ok(())
+        };
+
+        for &press in directions(direction) {
+            if press {
+                self.buttons |= mask as usize;
+                self.post_mouse(down, extra_wparam)?;
+            } else {
+                self.buttons &= !(mask as usize);
+                self.post_mouse(up, extra_wparam)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> InputResult<()> {
+        self.position = match coordinate {
+            Coordinate::Abs => (x, y),
+            Coordinate::Rel => (self.position.0 + x, self.position.1 + y)
+        };
+        super::set_position(self.position);
+
+        self.post_mouse(WM_MOUSEMOVE, 0)
+    }
+
+    fn scroll(&mut self, length: i32, axis: Axis) -> InputResult<()> {
+        // Wheel messages take screen coordinates, and positive deltas scroll up/right
+        let mut point = POINT { x: self.position.0, y: self.position.1 };
+        // SAFETY: The window handle is valid, and the point is a valid pointer for the duration of the call
+        unsafe {
+            ClientToScreen(self.window, &mut point);
+        }
+
+        let (message, delta) = match axis {
+            Axis::Vertical => (WM_MOUSEWHEEL, -length * WHEEL_DELTA),
+            Axis::Horizontal => (WM_MOUSEHWHEEL, length * WHEEL_DELTA)
+        };
+        let wparam = ((delta as u16 as usize) << 16) | self.buttons;
+        let lparam = ((point.y as u16 as isize) << 16) | point.x as u16 as isize;
+
+        self.post(self.window, message, wparam, lparam)
+    }
+
+    fn main_display(&self) -> InputResult<(i32, i32)> {
+        let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+        // SAFETY: The window handle is valid, and the rect is a valid pointer for the duration of the call
+        if unsafe { GetClientRect(self.window, &mut rect) } == 0 {
+            return Err(InputError::Simulate("failed to get window size"));
+        }
+
+        Ok((rect.right - rect.left, rect.bottom - rect.top))
+    }
+
+    fn location(&self) -> InputResult<(i32, i32)> {
+        Ok(self.position)
+    }
+}
+
+/// Get the virtual key code for a key
+pub fn virtual_key(key: Key) -> Option<u16> {
+    let virtual_key = match key {
+        Key::Alt => VK_MENU,
+        Key::Apps => 0x5d,
+        Key::Backspace => 0x08,
+        Key::CapsLock => 0x14,
+        Key::Control => 0x11,
+        Key::Delete => 0x2e,
+        Key::DownArrow => 0x28,
+        Key::End => 0x23,
+        Key::Escape => 0x1b,
+        Key::F1 => 0x70,
+        Key::F2 => 0x71,
+        Key::F3 => 0x72,
+        Key::F4 => 0x73,
+        Key::F5 => 0x74,
+        Key::F6 => 0x75,
+        Key::F7 => 0x76,
+        Key::F8 => 0x77,
+        Key::F9 => 0x78,
+        Key::F10 => 0x79,
+        Key::F11 => 0x7a,
+        Key::F12 => 0x7b,
+        Key::F13 => 0x7c,
+        Key::F14 => 0x7d,
+        Key::F15 => 0x7e,
+        Key::F16 => 0x7f,
+        Key::F17 => 0x80,
+        Key::F18 => 0x81,
+        Key::F19 => 0x82,
+        Key::F20 => 0x83,
+        Key::Home => 0x24,
+        Key::Insert => 0x2d,
+        Key::LeftArrow => 0x25,
+        Key::MediaNextTrack => 0xb0,
+        Key::MediaPlayPause => 0xb3,
+        Key::MediaPrevTrack => 0xb1,
+        Key::MediaStop => 0xb2,
+        Key::Meta => 0x5b,
+        Key::Numlock => 0x90,
+        Key::PageDown => 0x22,
+        Key::PageUp => 0x21,
+        Key::Pause => 0x13,
+        Key::Return => 0x0d,
+        Key::RightArrow => 0x27,
+        Key::Scroll => 0x91,
+        Key::Shift => 0x10,
+        Key::Snapshot => 0x2c,
+        Key::Space => 0x20,
+        Key::Tab => 0x09,
+        Key::UpArrow => 0x26,
+        Key::VolumeDown => 0xae,
+        Key::VolumeMute => 0xad,
+        Key::VolumeUp => 0xaf,
+        Key::Unicode(character) => {
+            // The low byte is the virtual key, or -1 if the character can't be typed on the current layout
+            let mut units = [0u16; 2];
+            character.encode_utf16(&mut units);
+            // SAFETY: VkKeyScanW has no safety requirements
+            let result = unsafe { VkKeyScanW(units[0]) };
+            if result == -1 {
+                return None;
+            }
+            (result & 0xff) as u16
+        }
+        _ => return None
+    };
+
+    Some(virtual_key)
+}
@@ -0,0 +1,448 @@
+use super::{Command, Window};
+use crate::keyboard::Owner;
+use enigo::{Axis, Button, Coordinate, Direction, InputError, InputResult, Key, Keyboard, Mouse};
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::xproto::{self, AtomEnum, ClientMessageEvent, ConfigureWindowAux, ConnectionExt, EventMask, KeyButMask};
+use x11rb::rust_connection::RustConnection;
+
+/// Connect to the X server, returning the connection and the root window
+fn connect() -> Result<(RustConnection, u32), String> {
+    let (connection, screen) = x11rb::connect(None).map_err(|error| format!("Failed to connect to X server: {error}"))?;
+    let root = connection.setup().roots[screen].root;
+    Ok((connection, root))
+}
+
+fn intern_atom(connection: &RustConnection, name: &str) -> Result<u32, String> {
+    connection.intern_atom(false, name.as_bytes())
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map(|reply| reply.atom)
+        .map_err(|error| format!("Failed to get X atom {name}: {error}"))
+}
+
+/// Get the title of a window, preferring the UTF-8 title set by modern applications
+fn window_title(connection: &RustConnection, window: u32, net_wm_name: u32, utf8_string: u32) -> Option<String> {
+    for (property, type_) in [(net_wm_name, utf8_string), (AtomEnum::WM_NAME.into(), AtomEnum::STRING.into())] {
+        let reply = connection.get_property(false, window, property, type_, 0, 1024).ok()?.reply().ok()?;
+        if !reply.value.is_empty() {
+            return Some(String::from_utf8_lossy(&reply.value).into_owned());
+        }
+    }
+
+    None
+}
+
+pub fn list() -> Result<Vec<Window>, String> {
+    let (connection, root) = connect()?;
+    let client_list = intern_atom(&connection, "_NET_CLIENT_LIST")?;
+    let net_wm_name = intern_atom(&connection, "_NET_WM_NAME")?;
+    let utf8_string = intern_atom(&connection, "UTF8_STRING")?;
+
+    let reply = connection.get_property(false, root, client_list, AtomEnum::WINDOW, 0, u32::MAX)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to list windows (is a window manager running?): {error}"))?;
+
+    let windows = reply.value32().into_iter().flatten().filter_map(|id| {
+        window_title(&connection, id, net_wm_name, utf8_string).map(|title| Window { id: id as u64, title })
+    }).collect();
+
+    Ok(windows)
+}
+
+/// Get the window the window manager says is active, as described by EWMH
+pub fn active() -> Result<Option<u64>, String> {
+    let (connection, root) = connect()?;
+    let active_window = intern_atom(&connection, "_NET_ACTIVE_WINDOW")?;
+
+    let reply = connection.get_property(false, root, active_window, AtomEnum::WINDOW, 0, 1)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to get the active window (is a window manager running?): {error}"))?;
+
+    Ok(reply.value32().and_then(|mut ids| ids.next()).filter(|&id| id != 0).map(u64::from))
+}
+
+/// Get the size of a window itself, without its title bar and borders
+pub fn size(window: &Window) -> Result<(u32, u32), String> {
+    let (connection, _) = connect()?;
+    let reply = connection.get_geometry(window.id as u32)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+        .map_err(|error| format!("Failed to get the size of {:?}: {error}", window.title))?;
+    Ok((u32::from(reply.width), u32::from(reply.height)))
+}
+
+/// Move a window, where the position is that of the window itself rather than its title bar and borders
+pub fn move_window(window: &Window, x: i32, y: i32) -> Result<(), String> {
+    configure(window, &ConfigureWindowAux::new().x(x).y(y))
+}
+
+/// Resize a window, where the size is that of the window itself without its title bar and borders
+pub fn resize_window(window: &Window, width: u32, height: u32) -> Result<(), String> {
+    configure(window, &ConfigureWindowAux::new().width(width).height(height))
+}
+
+/// Ask the window manager to change the geometry of a window
+fn configure(window: &Window, changes: &ConfigureWindowAux) -> Result<(), String> {
+    let (connection, _) = connect()?;
+    connection.configure_window(window.id as u32, changes)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.check())
+        .map_err(|error| format!("Failed to change the geometry of {:?}: {error}", window.title))
+}
+
+/// Ask the window manager to maximize, minimize or close a window, through the messages described by EWMH and ICCCM
+pub fn command(window: &Window, command: Command) -> Result<(), String> {
+    let (connection, root) = connect()?;
+
+    let (message_type, data) = match command {
+        // Add both maximized states, as a normal application
+        Command::Maximize => (intern_atom(&connection, "_NET_WM_STATE")?, [
+            1,
+            intern_atom(&connection, "_NET_WM_STATE_MAXIMIZED_VERT")?,
+            intern_atom(&connection, "_NET_WM_STATE_MAXIMIZED_HORZ")?,
+            1,
+            0
+        ]),
+        // Change to the iconic state, which is what X calls minimized
+        Command::Minimize => (intern_atom(&connection, "WM_CHANGE_STATE")?, [3, 0, 0, 0, 0]),
+        // Close at the current time, as a normal application
+        Command::Close => (intern_atom(&connection, "_NET_CLOSE_WINDOW")?, [x11rb::CURRENT_TIME, 1, 0, 0, 0])
+    };
+
+    let event = ClientMessageEvent::new(32, window.id as u32, message_type, data);
+    connection.send_event(false, root, EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY, event)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.check())
+        .map_err(|error| format!("Failed to {command} {:?}: {error}", window.title))
+}
+
+/// Sends events directly to a window with XSendEvent, without moving the real cursor or changing focus
+///
+/// Mouse coordinates are relative to the window. Note that some applications ignore events sent this way.
+pub struct Target {
+    connection: RustConnection,
+    root: u32,
+    window: u32,
+    min_keycode: u8,
+    keysyms_per_keycode: u8,
+    keysyms: Vec<u32>,
+    position: (i32, i32),
+    state: u16
+}
+
+impl Target {
+    /// Shift is only part of the state of the events sent to the window, so it isn't shared with anything, and the owner
+    /// of the keys pressed along with it isn't needed
+    pub fn new(window: &Window, _owner: Owner) -> Result<Self, String> {
+        let (connection, root) = connect()?;
+
+        // Get the keyboard mapping, used to find the key code for each key
+        let setup = connection.setup();
+        let min_keycode = setup.min_keycode;
+        let count = setup.max_keycode - min_keycode + 1;
+        let mapping = connection.get_keyboard_mapping(min_keycode, count)
+            .map_err(ReplyError::from)
+            .and_then(|cookie| cookie.reply())
+            .map_err(|error| format!("Failed to get keyboard mapping: {error}"))?;
+
+        Ok(Target {
+            connection,
+            root,
+            window: window.id as u32,
+            min_keycode,
+            keysyms_per_keycode: mapping.keysyms_per_keycode,
+            keysyms: mapping.keysyms,
+            position: super::position(),
+            state: 0
+        })
+    }
+
+    /// Find the key code for a keysym, and whether shift needs to be held to get it
+    fn keycode(&self, keysym: u32) -> Option<(u8, bool)> {
+        let index = self.keysyms.iter().position(|&candidate| candidate == keysym)?;
+        let per_keycode = self.keysyms_per_keycode as usize;
+        Some((self.min_keycode + (index / per_keycode) as u8, index % per_keycode == 1))
+    }
+
+    /// Find the innermost window at a position in the target window, and the position relative to it
+    fn child_at(&self, x: i32, y: i32) -> (u32, i16, i16) {
+        let (mut window, mut x, mut y) = (self.window, x as i16, y as i16);
+
+        for _ in 0..16 {
+            let child = match self.connection.translate_coordinates(window, window, x, y).ok().and_then(|cookie| cookie.reply().ok()) {
+                Some(reply) if reply.child != x11rb::NONE => reply.child,
+                _ => break
+            };
+            let Some(reply) = self.connection.translate_coordinates(window, child, x, y).ok().and_then(|cookie| cookie.reply().ok()) else {
+                break;
+            };
+
+            (window, x, y) = (child, reply.dst_x, reply.dst_y);
+        }
+
+        (window, x, y)
+    }
+
+    /// Get the position of the cursor relative to the root window
+    fn root_position(&self) -> (i16, i16) {
+        self.connection.translate_coordinates(self.window, self.root, self.position.0 as i16, self.position.1 as i16)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map_or((0, 0), |reply| (reply.dst_x, reply.dst_y))
+    }
+
+    fn send_key(&mut self, keycode: u8, press: bool, extra_state: u16) -> InputResult<()> {
+        let (root_x, root_y) = self.root_position();
+        let event = xproto::KeyPressEvent {
+            response_type: if press { xproto::KEY_PRESS_EVENT } else { xproto::KEY_RELEASE_EVENT },
+            detail: keycode,
+            sequence: 0,
+            time: x11rb::CURRENT_TIME,
+            root: self.root,
+            event: self.window,
+            child: x11rb::NONE,
+            root_x,
+            root_y,
+            event_x: self.position.0 as i16,
+            event_y: self.position.1 as i16,
+            state: KeyButMask::from(self.state | extra_state),
+            same_screen: true
+        };
+        let mask = if press { EventMask::KEY_PRESS } else { EventMask::KEY_RELEASE };
+
+        self.send(self.window, mask, event)
+    }
+
+    fn send_button(&mut self, button: u8, press: bool) -> InputResult<()> {
+        let (root_x, root_y) = self.root_position();
+        let (child, event_x, event_y) = self.child_at(self.position.0, self.position.1);
+        let event = xproto::ButtonPressEvent {
+            response_type: if press { xproto::BUTTON_PRESS_EVENT } else { xproto::BUTTON_RELEASE_EVENT },
+            detail: button,
+            sequence: 0,
+            time: x11rb::CURRENT_TIME,
+            root: self.root,
+            event: child,
+            child: x11rb::NONE,
+            root_x,
+            root_y,
+            event_x,
+            event_y,
+            state: KeyButMask::from(self.state),
+            same_screen: true
+        };
+        let mask = if press { EventMask::BUTTON_PRESS } else { EventMask::BUTTON_RELEASE };
+
+        self.send(child, mask, event)
+    }
+
+    fn send<E: Into<[u8; 32]>>(&self, window: u32, mask: EventMask, event: E) -> InputResult<()> {
+        self.connection.send_event(true, window, mask, event)
+            .map_err(|_| InputError::Simulate("failed to send event to window"))?;
+        self.connection.flush().map_err(|_| InputError::Simulate("failed to send event to window"))
+    }
+}
+
+fn directions(direction: Direction) -> &'static [bool] {
+    match direction {
+        Direction::Press => &[true],
+        Direction::Release => &[false],
+        Direction::Click => &[true, false]
+    }
+}
+
+impl Keyboard for Target {
+    fn fast_text(&mut self, _text: &str) -> InputResult<Option<()>> {
+        // There is no fast way of entering text, so it will be entered one key at a time
+        Ok(None)
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> InputResult<()> {
+        let keysym = keysym(key).ok_or(InputError::InvalidInput("key is not supported when targeting a window"))?;
+        let (keycode, shift) = self.keycode(keysym).ok_or(InputError::Mapping(format!("no key code for keysym {keysym:#x}")))?;
+        let extra_state = if shift { u16::from(KeyButMask::SHIFT) } else { 0 };
+        let modifier = match key {
+            Key::Shift => u16::from(KeyButMask::SHIFT),
+            Key::Control => u16::from(KeyButMask::CONTROL),
+            Key::Alt => u16::from(KeyButMask::MOD1),
+            Key::Meta => u16::from(KeyButMask::MOD4),
+            _ => 0
+        };
+
+        for &press in directions(direction) {
+            self.send_key(keycode, press, extra_state)?;
+
+            // Modifiers apply to the events after the modifier was pressed
+            if press {
+                self.state |= modifier;
+            } else {
+                self.state &= !modifier;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn raw(&mut self, keycode: u16, direction: Direction) -> InputResult<()> {
+        for &press in directions(direction) {
+            self.send_key(keycode as u8, press, 0)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Mouse for Target {
+    fn button(&mut self, button: Button, direction: Direction) -> InputResult<()> {
+        let (number, mask) = match button {
+            Button::Left => (1, KeyButMask::BUTTON1),
+            Button::Middle => (2, KeyButMask::BUTTON2),
+            Button::Right => (3, KeyButMask::BUTTON3),
+            Button::ScrollUp => (4, KeyButMask::BUTTON4),
+            Button::ScrollDown => (5, KeyButMask::BUTTON5),
+            Button::ScrollLeft => (6, KeyButMask::from(0u16)),
+            Button::ScrollRight => (7, KeyButMask::from(0u16)),
+            Button::Back => (8, KeyButMask::from(0u16)),
+            Button::Forward => (9, KeyButMask::from(0u16))
+        };
+
+        for &press in directions(direction) {
+            self.send_button(number, press)?;
+
+            if press {
+                self.state |= u16::from(mask);
+            } else {
+                self.state &= !u16::from(mask);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> InputResult<()> {
+        self.position = match coordinate {
+            Coordinate::Abs => (x, y),
+            Coordinate::Rel => (self.position.0 + x, self.position.1 + y)
+        };
+        super::set_position(self.position);
+
+        let (root_x, root_y) = self.root_position();
+        let (child, event_x, event_y) = self.child_at(self.position.0, self.position.1);
+        let event = xproto::MotionNotifyEvent {
+            response_type: xproto::MOTION_NOTIFY_EVENT,
+            detail: xproto::Motion::NORMAL,
+            sequence: 0,
+            time: x11rb::CURRENT_TIME,
+            root: self.root,
+            event: child,
+            child: x11rb::NONE,
+            root_x,
+            root_y,
+            event_x,
+            event_y,
+            state: KeyButMask::from(self.state),
+            same_screen: true
+        };
+
+        self.send(child, EventMask::POINTER_MOTION, event)
+    }
+
+    fn scroll(&mut self, length: i32, axis: Axis) -> InputResult<()> {
+        let button = match (axis, length < 0) {
+            (Axis::Vertical, true) => 4,
+            (Axis::Vertical, false) => 5,
+            (Axis::Horizontal, true) => 6,
+            (Axis::Horizontal, false) => 7
+        };
+
+        for _ in 0..length.abs() {
+            self.send_button(button, true)?;
+            self.send_button(button, false)?;
+        }
+
+        Ok(())
+    }
+
+    fn main_display(&self) -> InputResult<(i32, i32)> {
+        let geometry = self.connection.get_geometry(self.window)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .ok_or(InputError::Simulate("failed to get window size"))?;
+        Ok((geometry.width as i32, geometry.height as i32))
+    }
+
+    fn location(&self) -> InputResult<(i32, i32)> {
+        Ok(self.position)
+    }
+}
+
+/// Get the X keysym for a key
+pub fn keysym(key: Key) -> Option<u32> {
+    let keysym = match key {
+        Key::Alt => 0xffe9,
+        Key::Backspace => 0xff08,
+        Key::CapsLock => 0xffe5,
+        Key::Control => 0xffe3,
+        Key::Delete => 0xffff,
+        Key::DownArrow => 0xff54,
+        Key::End => 0xff57,
+        Key::Escape => 0xff1b,
+        Key::F1 => 0xffbe,
+        Key::F2 => 0xffbf,
+        Key::F3 => 0xffc0,
+        Key::F4 => 0xffc1,
+        Key::F5 => 0xffc2,
+        Key::F6 => 0xffc3,
+        Key::F7 => 0xffc4,
+        Key::F8 => 0xffc5,
+        Key::F9 => 0xffc6,
+        Key::F10 => 0xffc7,
+        Key::F11 => 0xffc8,
+        Key::F12 => 0xffc9,
+        Key::F13 => 0xffca,
+        Key::F14 => 0xffcb,
+        Key::F15 => 0xffcc,
+        Key::F16 => 0xffcd,
+        Key::F17 => 0xffce,
+        Key::F18 => 0xffcf,
+        Key::F19 => 0xffd0,
+        Key::F20 => 0xffd1,
+        Key::Home => 0xff50,
+        Key::Insert => 0xff63,
+        Key::LeftArrow => 0xff51,
+        // enigo calls the menu key left menu on Linux
+        Key::LMenu => 0xff67,
+        Key::MediaNextTrack => 0x1008_ff17,
+        Key::MediaPlayPause => 0x1008_ff14,
+        Key::MediaPrevTrack => 0x1008_ff16,
+        Key::MediaStop => 0x1008_ff15,
+        Key::Meta => 0xffeb,
+        Key::Numlock => 0xff7f,
+        Key::PageDown => 0xff56,
+        Key::PageUp => 0xff55,
+        Key::Pause => 0xff13,
+        Key::Print => 0xff61,
+        Key::Return => 0xff0d,
+        Key::RightArrow => 0xff53,
+        Key::ScrollLock => 0xff14,
+        Key::Shift => 0xffe1,
+        Key::Space => 0x20,
+        Key::Tab => 0xff09,
+        Key::UpArrow => 0xff52,
+        Key::VolumeDown => 0x1008_ff11,
+        Key::VolumeMute => 0x1008_ff12,
+        Key::VolumeUp => 0x1008_ff13,
+        Key::Other(keysym) => keysym,
+        Key::Unicode('\n') => 0xff0d,
+        Key::Unicode('\t') => 0xff09,
+        // Latin-1 characters have the same keysym as their code point, other characters are offset
+        Key::Unicode(character) if (character as u32) < 0x100 => character as u32,
+        Key::Unicode(character) => 0x0100_0000 + character as u32,
+        _ => return None
+    };
+
+    Some(keysym)
+}